@@ -0,0 +1,69 @@
+// ScriptView Capture
+// Watches the caption overlay of streaming sites and sends each caption to
+// ScriptView's WebSocket server. Change PORT if you changed "Extension port"
+// in ScriptView's settings.
+
+const PORT = 7273;
+const RECONNECT_MS = 5000;
+
+// Elements holding the caption lines on each site
+const CAPTION_SELECTORS = {
+  "www.netflix.com": ".player-timedtext-text-container",
+  "www.youtube.com": ".caption-visual-line",
+};
+
+let socket = null;
+let lastText = "";
+
+function connect() {
+  socket = new WebSocket(`ws://127.0.0.1:${PORT}`);
+  socket.addEventListener("close", () => {
+    socket = null;
+    setTimeout(connect, RECONNECT_MS);
+  });
+}
+
+function send(message) {
+  if (socket && socket.readyState === WebSocket.OPEN) {
+    socket.send(JSON.stringify(message));
+  }
+}
+
+function captionText() {
+  const selector = CAPTION_SELECTORS[location.hostname];
+  if (!selector) {
+    return "";
+  }
+  return Array.from(document.querySelectorAll(selector))
+    .map((line) => line.innerText.trim())
+    .filter((line) => line !== "")
+    .join("\n");
+}
+
+function check() {
+  const video = document.querySelector("video");
+  const text = captionText();
+  if (!video || text === lastText) {
+    return;
+  }
+  lastText = text;
+  if (text === "") {
+    send({ type: "clear", time: video.currentTime });
+  } else {
+    send({
+      type: "caption",
+      text,
+      time: video.currentTime,
+      url: location.origin + location.pathname + location.search,
+      title: document.title,
+      duration: Number.isFinite(video.duration) ? video.duration : null,
+    });
+  }
+}
+
+connect();
+new MutationObserver(check).observe(document.body, {
+  childList: true,
+  subtree: true,
+  characterData: true,
+});
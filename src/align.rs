@@ -0,0 +1,50 @@
+//! Matching lines from different sources by media time: a secondary track's
+//! lines to the primary ones they translate, or a subtitle file's cues to
+//! the lines captured while the same video played.
+
+use crate::SubtitleEntry;
+
+/// Of `candidates` (index, start time), the one starting nearest to `time`,
+/// if it's within `max_offset` seconds.
+pub fn nearest(time: f64, candidates: impl Iterator<Item = (usize, f64)>, max_offset: f64) -> Option<usize> {
+    candidates
+        .map(|(index, start)| (index, (start - time).abs()))
+        .filter(|(_, offset)| *offset <= max_offset)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// The cue of `cues` on screen at `time` (the latest to start, if they
+/// overlap), or else the one starting nearest to it within `max_offset`.
+pub fn cue_at(time: f64, cues: &[SubtitleEntry], max_offset: f64) -> Option<usize> {
+    let showing = cues
+        .iter()
+        .enumerate()
+        .filter(|(_, cue)| cue.start_time <= time && cue.end_time.is_some_and(|end| time < end))
+        .max_by(|(_, a), (_, b)| a.start_time.total_cmp(&b.start_time))
+        .map(|(index, _)| index);
+    showing.or_else(|| nearest(time, cues.iter().map(|cue| cue.start_time).enumerate(), max_offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_time: f64, end_time: f64) -> SubtitleEntry {
+        SubtitleEntry {
+            start_time,
+            end_time: Some(end_time),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cue_at() {
+        let cues = vec![cue(1.0, 4.0), cue(3.0, 5.0), cue(10.0, 12.0)];
+        assert_eq!(cue_at(2.0, &cues, 1.0), Some(0));
+        assert_eq!(cue_at(3.5, &cues, 1.0), Some(1));
+        // Captured lines often start a moment before the cue
+        assert_eq!(cue_at(9.2, &cues, 1.0), Some(2));
+        assert_eq!(cue_at(7.0, &cues, 1.0), None);
+    }
+}
@@ -0,0 +1,330 @@
+//! Card creation through AnkiConnect, the Anki add-on that serves a JSON API
+//! on `localhost:8765`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver};
+
+use serde_json::{json, Value};
+
+use crate::starred::StarredLine;
+
+pub const DEFAULT_URL: &str = "http://localhost:8765";
+const API_VERSION: u32 = 6;
+/// Notes sent per request, so progress can be shown for large batches.
+const BATCH_SIZE: usize = 20;
+
+/// Where and how cards are created.
+#[derive(Debug, Clone)]
+pub struct NoteTemplate {
+    pub deck: String,
+    pub note_type: String,
+    pub sentence_field: String,
+    /// Field for the media title and timestamp; empty leaves it out.
+    pub source_field: String,
+    /// Field searched for the sentence before adding it; empty skips the check.
+    pub dedup_field: String,
+    /// Field for the line's audio, cut from the media; empty adds no audio.
+    pub audio_field: String,
+    /// Command that speaks the sentence when the media can't be cut, such as
+    /// a stream; `None` leaves those cards without audio.
+    pub tts_command: Option<String>,
+    /// Where cut and spoken audio is kept, normally [`cache_dir`].
+    pub cache_dir: PathBuf,
+}
+
+/// What became of one line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Added as the note with this id.
+    Added(u64),
+    /// Skipped because this note already has the sentence.
+    Duplicate(u64),
+    Failed(String),
+}
+
+impl NoteTemplate {
+    fn note(&self, line: &StarredLine, audio: Option<&Path>) -> Value {
+        let mut fields = serde_json::Map::new();
+        fields.insert(self.sentence_field.clone(), json!(line.text));
+        if !self.source_field.is_empty() {
+            fields.insert(self.source_field.clone(), json!(line.source()));
+        }
+        let mut note = json!({
+            "deckName": self.deck,
+            "modelName": self.note_type,
+            "fields": fields,
+            "tags": ["scriptview"],
+        });
+        if let Some(audio) = audio {
+            let filename = audio.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            note["audio"] = json!([{
+                "path": audio.to_string_lossy(),
+                "filename": format!("scriptview-{}", filename),
+                "fields": [self.audio_field],
+            }]);
+        }
+        note
+    }
+
+    /// Audio for a card: the line cut from its media file, or else spoken by
+    /// the text to speech command if one is set. Nothing is downloaded, so
+    /// there's no Forvo pronunciation to fall back on.
+    fn audio(&self, line: &StarredLine) -> Result<Option<PathBuf>, String> {
+        if self.audio_field.is_empty() {
+            return Ok(None);
+        }
+        let key = format!("{:x}", md5::compute(format!("{:?}:{}:{}", line.path, line.start_time, line.text)));
+        let clip = line
+            .path
+            .as_deref()
+            .filter(|path| Path::new(path).is_file())
+            .and_then(|path| cut_clip(path, line, &self.cache_dir.join(format!("{}.mp3", key))).ok());
+        if clip.is_some() {
+            return Ok(clip);
+        }
+        let Some(command) = &self.tts_command else {
+            return Ok(None);
+        };
+        let file = self.cache_dir.join(format!("{}-tts.wav", key));
+        std::fs::create_dir_all(&self.cache_dir).map_err(|e| e.to_string())?;
+        crate::tts::synthesize(command, &line.text, &file).map_err(|e| format!("Text to speech: {}", e))?;
+        Ok(Some(file))
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(format!("{}/.cache/scriptview/cards", home_dir))
+}
+
+/// Cut the line's audio, with the same padding as its waveform, with ffmpeg.
+fn cut_clip(media: &str, line: &StarredLine, file: &Path) -> Result<PathBuf, String> {
+    if file.exists() {
+        return Ok(file.to_path_buf());
+    }
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let (start, end) = crate::waveform::clip_range(line.start_time, line.end_time);
+    let status = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-ss", &format!("{:.3}", start), "-t", &format!("{:.3}", end - start), "-i"])
+        .arg(media)
+        .args(["-vn", "-ac", "1"])
+        .arg(file)
+        .status()
+        .map_err(|e| format!("Could not run ffmpeg: {}", e))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(file);
+        return Err("ffmpeg could not cut the audio".to_string());
+    }
+    Ok(file.to_path_buf())
+}
+
+/// An Anki search for notes whose `field` is exactly `text`.
+fn search_query(field: &str, text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        // Quotes end the term and `*`/`_` are wildcards
+        if matches!(c, '\\' | '"' | '*' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!("\"{}:{}\"", field, escaped)
+}
+
+/// One `multi` request running `action` once per set of params, so a failure
+/// only affects that line. Versioned actions report `{result, error}` each.
+fn multi_request(action: &str, params: impl Iterator<Item = Value>) -> Value {
+    let actions: Vec<Value> = params
+        .map(|params| json!({ "action": action, "version": API_VERSION, "params": params }))
+        .collect();
+    json!({ "action": "multi", "version": API_VERSION, "params": { "actions": actions } })
+}
+
+fn add_notes_request(template: &NoteTemplate, lines: &[(StarredLine, Option<PathBuf>)]) -> Value {
+    multi_request(
+        "addNote",
+        lines.iter().map(|(line, audio)| json!({ "note": template.note(line, audio.as_deref()) })),
+    )
+}
+
+fn find_notes_request(field: &str, lines: &[StarredLine]) -> Value {
+    multi_request("findNotes", lines.iter().map(|line| json!({ "query": search_query(field, &line.text) })))
+}
+
+/// Per-action results of a `multi` response.
+fn parse_multi_response(response: &Value, count: usize) -> Result<Vec<Result<Value, String>>, String> {
+    if let Some(error) = response["error"].as_str() {
+        return Err(error.to_string());
+    }
+    let results = response["result"].as_array().ok_or("unexpected response from AnkiConnect")?;
+    if results.len() != count {
+        return Err(format!("AnkiConnect answered {} of {} notes", results.len(), count));
+    }
+    Ok(results
+        .iter()
+        .map(|result| match result["error"].as_str() {
+            Some(error) => Err(error.to_string()),
+            None => Ok(result["result"].clone()),
+        })
+        .collect())
+}
+
+fn post(url: &str, request: &Value, count: usize) -> Result<Vec<Result<Value, String>>, String> {
+    let body = ureq::post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&request.to_string())
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let response: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    parse_multi_response(&response, count)
+}
+
+/// Look for existing notes, then add the lines that have none with their audio.
+fn export_batch(url: &str, template: &NoteTemplate, lines: &[StarredLine]) -> Result<Vec<Outcome>, String> {
+    let mut outcomes: Vec<Option<Outcome>> = vec![None; lines.len()];
+    if !template.dedup_field.is_empty() {
+        let found = post(url, &find_notes_request(&template.dedup_field, lines), lines.len())?;
+        for (outcome, result) in outcomes.iter_mut().zip(found) {
+            *outcome = match result {
+                Ok(ids) => ids.get(0).and_then(Value::as_u64).map(Outcome::Duplicate),
+                Err(e) => Some(Outcome::Failed(e)),
+            };
+        }
+    }
+    let mut new_lines = Vec::new();
+    for (line, outcome) in lines.iter().zip(outcomes.iter_mut()) {
+        if outcome.is_none() {
+            match template.audio(line) {
+                Ok(audio) => new_lines.push((line.clone(), audio)),
+                Err(e) => *outcome = Some(Outcome::Failed(e)),
+            }
+        }
+    }
+    if !new_lines.is_empty() {
+        let mut added = post(url, &add_notes_request(template, &new_lines), new_lines.len())?.into_iter();
+        for outcome in outcomes.iter_mut().filter(|outcome| outcome.is_none()) {
+            *outcome = Some(match added.next() {
+                Some(Ok(id)) => id.as_u64().map_or_else(|| Outcome::Failed("note was not added".to_string()), Outcome::Added),
+                Some(Err(e)) => Outcome::Failed(e),
+                None => Outcome::Failed("note was not added".to_string()),
+            });
+        }
+    }
+    Ok(outcomes.into_iter().flatten().collect())
+}
+
+/// Add a card for every line on a background thread. The outcome of each line
+/// arrives on the receiver as its batch finishes; the channel closes when all
+/// are done.
+pub fn spawn_export(url: String, template: NoteTemplate, lines: Vec<StarredLine>) -> Receiver<(StarredLine, Outcome)> {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        for batch in lines.chunks(BATCH_SIZE) {
+            let outcomes = match export_batch(&url, &template, batch) {
+                Ok(outcomes) => outcomes,
+                Err(e) => vec![Outcome::Failed(e); batch.len()],
+            };
+            for (line, outcome) in batch.iter().zip(outcomes) {
+                if tx.send((line.clone(), outcome)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> NoteTemplate {
+        NoteTemplate {
+            deck: "Mining".to_string(),
+            note_type: "Basic".to_string(),
+            sentence_field: "Front".to_string(),
+            source_field: "Back".to_string(),
+            dedup_field: "Front".to_string(),
+            audio_field: "Audio".to_string(),
+            tts_command: None,
+            cache_dir: std::env::temp_dir().join(format!("scriptview-cards-{}", std::process::id())),
+        }
+    }
+
+    #[test]
+    fn test_add_notes_request() {
+        let line = StarredLine {
+            text: "猫がいる".to_string(),
+            start_time: 5.0,
+            end_time: None,
+            path: None,
+            title: Some("Show".to_string()),
+        };
+        let request = add_notes_request(&template(), &[(line, Some(PathBuf::from("/tmp/cards/abc.mp3")))]);
+        assert_eq!(request["action"], "multi");
+        let note = &request["params"]["actions"][0]["params"]["note"];
+        assert_eq!(note["deckName"], "Mining");
+        assert_eq!(note["fields"]["Front"], "猫がいる");
+        assert_eq!(note["fields"]["Back"], "Show (0:05.0)");
+        assert_eq!(note["audio"][0]["filename"], "scriptview-abc.mp3");
+        assert_eq!(note["audio"][0]["fields"][0], "Audio");
+    }
+
+    #[test]
+    fn test_parse_multi_response() {
+        let response = json!({
+            "result": [
+                { "result": 1496198395707u64, "error": null },
+                { "result": null, "error": "cannot create note because it is a duplicate" },
+            ],
+            "error": null,
+        });
+        let results = parse_multi_response(&response, 2).unwrap();
+        assert_eq!(results[0], Ok(json!(1496198395707u64)));
+        assert_eq!(results[1], Err("cannot create note because it is a duplicate".to_string()));
+        assert!(parse_multi_response(&json!({ "result": null, "error": "deck not found" }), 1).is_err());
+        assert!(parse_multi_response(&response, 3).is_err());
+    }
+
+    #[test]
+    fn test_search_query_escapes_wildcards() {
+        assert_eq!(search_query("Front", "猫がいる"), r#""Front:猫がいる""#);
+        assert_eq!(search_query("Sentence", r#"say "hi" *now*_"#), r#""Sentence:say \"hi\" \*now\*\_""#);
+    }
+
+    #[test]
+    fn test_find_notes_request() {
+        let line = StarredLine {
+            text: "猫".to_string(),
+            start_time: 0.0,
+            end_time: None,
+            path: None,
+            title: None,
+        };
+        let request = find_notes_request("Front", &[line]);
+        assert_eq!(request["params"]["actions"][0]["action"], "findNotes");
+        assert_eq!(request["params"]["actions"][0]["params"]["query"], r#""Front:猫""#);
+    }
+
+    #[test]
+    fn test_audio_falls_back_to_text_to_speech() {
+        let line = StarredLine {
+            text: "猫".to_string(),
+            start_time: 0.0,
+            end_time: None,
+            path: Some("https://example.com/stream.m3u8".to_string()),
+            title: None,
+        };
+        assert_eq!(template().audio(&line), Ok(None));
+        let failing = NoteTemplate {
+            tts_command: Some("scriptview-no-such-tts {file} {text}".to_string()),
+            ..template()
+        };
+        assert!(failing.audio(&line).unwrap_err().starts_with("Text to speech"));
+        let _ = std::fs::remove_dir_all(failing.cache_dir);
+    }
+}
@@ -0,0 +1,244 @@
+//! Advanced SubStation Alpha (.ass) import and export. Styling is kept on each
+//! entry so an imported file can be written back without losing it, while the
+//! viewer shows plain text.
+
+use serde::{Deserialize, Serialize};
+
+use crate::SubtitleEntry;
+
+const EVENT_FORMAT: &str = "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+/// Header used when exporting a transcript that didn't come from an ASS file.
+const DEFAULT_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,64,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,1,2,40,40,40,1
+";
+
+/// The per-line fields of an ASS `Dialogue` event besides its times.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssFields {
+    pub layer: i32,
+    pub style: String,
+    /// The `Name` (actor) field.
+    pub actor: String,
+    pub margin_l: i32,
+    pub margin_r: i32,
+    pub margin_v: i32,
+    pub effect: String,
+    /// Text with override tags and `\N` line breaks intact.
+    pub raw_text: String,
+}
+
+/// A parsed ASS file: everything before the events, kept verbatim, and the
+/// dialogue lines.
+#[derive(Debug)]
+pub struct AssDocument {
+    pub header: String,
+    pub entries: Vec<SubtitleEntry>,
+}
+
+fn parse_time(text: &str) -> Option<f64> {
+    let mut parts = text.trim().splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+fn format_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Display text: override blocks removed and ASS escapes turned into plain
+/// line breaks and spaces.
+pub fn plain_text(raw: &str) -> String {
+    let mut text = String::with_capacity(raw.len());
+    let mut in_override = false;
+    for c in raw.chars() {
+        match c {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if !in_override => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " ")
+}
+
+pub fn parse(content: &str) -> Result<AssDocument, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let events_start = content
+        .lines()
+        .position(|line| line.trim().eq_ignore_ascii_case("[Events]"))
+        .ok_or("no [Events] section")?;
+    let lines: Vec<&str> = content.lines().collect();
+    let header = lines[..events_start].join("\n").trim_end().to_string() + "\n";
+
+    let mut format: Vec<String> = Vec::new();
+    let mut entries = Vec::new();
+    for line in &lines[events_start + 1..] {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some(fields) = line.strip_prefix("Format:") {
+            format = fields.split(',').map(|f| f.trim().to_ascii_lowercase()).collect();
+            continue;
+        }
+        let Some(values) = line.strip_prefix("Dialogue:") else {
+            continue;
+        };
+        if format.is_empty() {
+            return Err("Dialogue line before the Format line".to_string());
+        }
+        // Text is last and may itself contain commas
+        let values: Vec<&str> = values.trim_start().splitn(format.len(), ',').collect();
+        let field = |name: &str| {
+            format
+                .iter()
+                .position(|f| f == name)
+                .and_then(|i| values.get(i).copied())
+                .unwrap_or("")
+        };
+        let number = |name: &str| field(name).trim().parse().unwrap_or(0);
+        let (Some(start), Some(end)) = (parse_time(field("start")), parse_time(field("end"))) else {
+            continue;
+        };
+        let raw_text = field("text").to_string();
+        entries.push(SubtitleEntry {
+            text: plain_text(&raw_text),
+            start_time: start,
+            end_time: Some(end),
+            ass: Some(AssFields {
+                layer: number("layer"),
+                style: field("style").trim().to_string(),
+                actor: field("name").trim().to_string(),
+                margin_l: number("marginl"),
+                margin_r: number("marginr"),
+                margin_v: number("marginv"),
+                effect: field("effect").trim().to_string(),
+                raw_text,
+            }),
+            ..Default::default()
+        });
+    }
+    Ok(AssDocument { header, entries })
+}
+
+/// ASS text for `entries`, under `header` (or a default one). Entries without
+/// ASS fields use the `Default` style.
+pub fn to_ass(header: Option<&str>, entries: &[SubtitleEntry]) -> String {
+    let mut ass = header.unwrap_or(DEFAULT_HEADER).trim_end().to_string();
+    ass.push_str("\n\n[Events]\n");
+    ass.push_str(EVENT_FORMAT);
+    ass.push('\n');
+    for entry in entries {
+        let end = entry.end_time.unwrap_or(entry.start_time);
+        let fields = entry.ass.clone().unwrap_or_else(|| AssFields {
+            layer: 0,
+            style: "Default".to_string(),
+            actor: String::new(),
+            margin_l: 0,
+            margin_r: 0,
+            margin_v: 0,
+            effect: String::new(),
+            raw_text: entry.text.trim().replace('\n', "\\N"),
+        });
+        ass.push_str(&format!(
+            "Dialogue: {},{},{},{},{},{},{},{},{},{}\n",
+            fields.layer,
+            format_time(entry.start_time),
+            format_time(end),
+            fields.style,
+            fields.actor,
+            fields.margin_l,
+            fields.margin_r,
+            fields.margin_v,
+            fields.effect,
+            fields.raw_text
+        ));
+    }
+    ass
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\u{feff}[Script Info]
+Title: Sample
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize
+Style: Sign,Arial,40
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,note to self
+Dialogue: 1,0:00:01.50,0:00:04.00,Sign,Narrator,10,20,30,,{\\an8\\i1}Hello,\\Nworld
+Dialogue: 0,1:02:03.04,1:02:05.00,Default,,0,0,0,,Plain
+";
+
+    #[test]
+    fn test_parse_keeps_fields() {
+        let doc = parse(SAMPLE).unwrap();
+        assert!(doc.header.starts_with("[Script Info]"));
+        assert!(doc.header.contains("Style: Sign,Arial,40"));
+        assert_eq!(doc.entries.len(), 2);
+
+        let first = &doc.entries[0];
+        assert_eq!(first.text, "Hello,\nworld");
+        assert_eq!(first.start_time, 1.5);
+        assert_eq!(first.end_time, Some(4.0));
+        let ass = first.ass.as_ref().unwrap();
+        assert_eq!(ass.style, "Sign");
+        assert_eq!(ass.actor, "Narrator");
+        assert_eq!((ass.layer, ass.margin_l, ass.margin_r, ass.margin_v), (1, 10, 20, 30));
+        assert_eq!(ass.raw_text, "{\\an8\\i1}Hello,\\Nworld");
+        assert_eq!(doc.entries[1].start_time, 3723.04);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let doc = parse(SAMPLE).unwrap();
+        let written = to_ass(Some(&doc.header), &doc.entries);
+        assert!(written.contains("Dialogue: 1,0:00:01.50,0:00:04.00,Sign,Narrator,10,20,30,,{\\an8\\i1}Hello,\\Nworld\n"));
+        assert!(written.contains("Dialogue: 0,1:02:03.04,1:02:05.00,Default,,0,0,0,,Plain\n"));
+        let again = parse(&written).unwrap();
+        assert_eq!(again.header, doc.header);
+        assert_eq!(
+            again.entries.iter().map(|e| &e.ass).collect::<Vec<_>>(),
+            doc.entries.iter().map(|e| &e.ass).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_plain_entries_get_default_style() {
+        let entry = SubtitleEntry {
+            text: "two\nlines".to_string(),
+            start_time: 2.0,
+            ..Default::default()
+        };
+        let written = to_ass(None, &[entry]);
+        assert!(written.contains("[V4+ Styles]"));
+        assert!(written.ends_with("Dialogue: 0,0:00:02.00,0:00:02.00,Default,,0,0,0,,two\\Nlines\n"));
+    }
+
+    #[test]
+    fn test_missing_events_is_error() {
+        assert!(parse("[Script Info]\nTitle: x\n").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Pronunciation audio for single words, from a local audio collection (such
+//! as a Forvo dump) or a download URL, played through mpv. Also replays the
+//! stretch of a media file a line was spoken in.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver};
+
+use serde::{Deserialize, Serialize};
+
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "ogg", "opus", "m4a", "aac", "wav", "flac"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    /// A directory tree of `<word>.<ext>` files.
+    #[default]
+    LocalDir,
+    /// A URL template where `{word}` is replaced by the word.
+    Url,
+}
+
+impl AudioSource {
+    pub const ALL: [AudioSource; 2] = [AudioSource::LocalDir, AudioSource::Url];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioSource::LocalDir => "Local directory",
+            AudioSource::Url => "Download URL",
+        }
+    }
+}
+
+fn cache_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(format!("{}/.cache/scriptview/audio", home_dir))
+}
+
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn fill_template(template: &str, word: &str) -> String {
+    template.replace("{word}", &percent_encode(word))
+}
+
+/// First audio file named after `word` anywhere under `dir`.
+fn find_local(dir: &Path, word: &str) -> Option<PathBuf> {
+    let mut subdirs = Vec::new();
+    for entry in std::fs::read_dir(dir).ok()?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if path.file_stem().is_some_and(|stem| stem == word)
+            && path
+                .extension()
+                .is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|a| ext.eq_ignore_ascii_case(a)))
+        {
+            return Some(path);
+        }
+    }
+    subdirs.into_iter().find_map(|subdir| find_local(&subdir, word))
+}
+
+fn download(url: &str) -> Result<PathBuf, String> {
+    let path = cache_dir().join(format!("{:x}", md5::compute(url)));
+    if path.exists() {
+        return Ok(path);
+    }
+    let response = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let mut data = Vec::new();
+    std::io::Read::read_to_end(&mut response.into_reader(), &mut data).map_err(|e| e.to_string())?;
+    if data.is_empty() {
+        return Err("empty response".to_string());
+    }
+    std::fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    std::fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Play `path` with mpv, returning once it's done.
+fn play(path: &Path) -> Result<(), String> {
+    let status = Command::new("mpv")
+        .args(["--no-video", "--really-quiet"])
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Could not start mpv: {}", e))?;
+    if !status.success() {
+        return Err(format!("mpv couldn't play {} ({})", path.display(), status));
+    }
+    Ok(())
+}
+
+/// Find or download the audio for `word` on a background thread, then play it.
+pub fn spawn_pronounce(source: AudioSource, location: String, word: String) -> Receiver<Result<(), String>> {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        let file = match source {
+            AudioSource::LocalDir => find_local(Path::new(&location), &word)
+                .ok_or_else(|| format!("No audio for \"{}\" in {}", word, location)),
+            AudioSource::Url => download(&fill_template(&location, &word)),
+        };
+        let _ = tx.send(file.and_then(|path| play(&path)));
+    });
+    rx
+}
+
+/// mpv arguments that play `media` from `start` to `end` without video.
+fn clip_args(media: &str, start: f64, end: f64) -> Vec<String> {
+    vec![
+        "--no-video".to_string(),
+        "--really-quiet".to_string(),
+        "--no-resume-playback".to_string(),
+        format!("--start={:.3}", start),
+        format!("--end={:.3}", end),
+        // A file name starting with `-` isn't an option
+        "--".to_string(),
+        media.to_string(),
+    ]
+}
+
+/// Play the audio of `media` between `start` and `end` (padded like the
+/// waveform) on a background thread. Streams are left for mpv to fetch.
+pub fn spawn_play_clip(media: String, start: f64, end: Option<f64>) -> Receiver<Result<(), String>> {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        let result = if !media.contains("://") && !Path::new(&media).is_file() {
+            Err(format!("Media file not found: {}", media))
+        } else {
+            let (start, end) = crate::waveform::clip_range(start, end);
+            match Command::new("mpv").args(clip_args(&media, start, end)).status() {
+                Ok(status) if status.success() => Ok(()),
+                Ok(status) => Err(format!("mpv couldn't play {} ({})", media, status)),
+                Err(e) => Err(format!("Could not start mpv: {}", e)),
+            }
+        };
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template_encodes_word() {
+        assert_eq!(
+            fill_template("https://example.com/audio?kanji={word}", "猫"),
+            "https://example.com/audio?kanji=%E7%8C%AB"
+        );
+        assert_eq!(fill_template("http://x/{word}.mp3", "a b"), "http://x/a%20b.mp3");
+    }
+
+    #[test]
+    fn test_clip_args() {
+        assert_eq!(
+            clip_args("/videos/show.mkv", 9.5, 12.25)[3..],
+            ["--start=9.500", "--end=12.250", "--", "/videos/show.mkv"]
+        );
+        assert_eq!(clip_args("-rare name.mkv", 0.0, 1.0)[5..], ["--", "-rare name.mkv"]);
+    }
+
+    #[test]
+    fn test_find_local_searches_subdirectories() {
+        let dir = std::env::temp_dir().join(format!("scriptview-audio-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("speaker")).unwrap();
+        std::fs::write(dir.join("speaker/猫.opus"), b"").unwrap();
+        std::fs::write(dir.join("猫.txt"), b"").unwrap();
+
+        assert_eq!(find_local(&dir, "猫"), Some(dir.join("speaker/猫.opus")));
+        assert_eq!(find_local(&dir, "犬"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
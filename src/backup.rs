@@ -0,0 +1,275 @@
+//! Backing up everything the viewer keeps: settings and layouts, session
+//! stats, known words, the review queue, starred lines, the raw stream, and
+//! the sessions themselves. The archive is one gzipped JSON file holding each
+//! file under `~/.config/scriptview` and `~/.local/share/scriptview`, plus
+//! the capture file with its session archives and summaries from beside it,
+//! so it can be carried to another machine and restored there with
+//! `--restore`. With the history encrypted, the whole archive is sealed with
+//! the history key.
+
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::crypt::Key;
+
+const FORMAT: &str = "scriptview-backup";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    format: String,
+    version: u32,
+    /// When it was made, as a Unix timestamp.
+    created: i64,
+    files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedFile {
+    /// `config/settings.json`, `data/raw/2024-05-01.jsonl` and so on.
+    path: String,
+    /// The content in base64, since the SQLite storage isn't text.
+    data: String,
+}
+
+/// A folder backed up, by the name it has in the archive.
+pub struct Root {
+    pub name: &'static str,
+    pub dir: String,
+    /// Only these files directly in `dir`, or everything under it if `None`.
+    pub files: Option<Vec<String>>,
+}
+
+impl Root {
+    pub fn all(name: &'static str, dir: String) -> Self {
+        Self { name, dir, files: None }
+    }
+}
+
+/// The folders backed up: ScriptView's own, and from the folder the capture
+/// file is in, the capture file, its session archives and summaries.
+pub fn roots(capture_file: &str) -> Vec<Root> {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    let capture = Path::new(capture_file);
+    let sessions = capture
+        .parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    let names = std::fs::read_dir(&sessions)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let archives: Vec<String> = crate::session::list_sessions(capture_file)
+        .iter()
+        .filter_map(|path| Some(Path::new(path).file_name()?.to_string_lossy().into_owned()))
+        .collect();
+    let files = names
+        .into_iter()
+        .filter(|name| archives.contains(name) || crate::summary::is_summary(capture_file, name))
+        .collect();
+    vec![
+        Root::all("config", crate::settings::config_dir()),
+        Root::all("data", format!("{}/.local/share/scriptview", home_dir)),
+        Root {
+            name: "sessions",
+            dir: sessions,
+            files: Some(files),
+        },
+    ]
+}
+
+/// `<dir>/scriptview-backup-YYYY-MM-DD.json.gz`, with `.enc` after it if
+/// it's to be sealed.
+pub fn default_path(dir: &str, sealed: bool) -> String {
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    format!("{}/scriptview-backup-{}.json.gz{}", dir, date, if sealed { ".enc" } else { "" })
+}
+
+fn collect(dir: &Path, name: &str, files: &mut Vec<ArchivedFile>) -> Result<(), std::io::Error> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = format!("{}/{}", name, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            collect(&entry.path(), &path, files)?;
+        } else {
+            let data = base64::engine::general_purpose::STANDARD.encode(std::fs::read(entry.path())?);
+            files.push(ArchivedFile { path, data });
+        }
+    }
+    Ok(())
+}
+
+/// Write every file of `roots` to an archive at `path`, sealed if there's
+/// a key, returning how many there were.
+pub fn create(path: &str, roots: &[Root], key: Option<&Key>) -> Result<usize, String> {
+    let mut files = Vec::new();
+    for root in roots {
+        let dir = Path::new(&root.dir);
+        let Some(names) = &root.files else {
+            if dir.is_dir() {
+                collect(dir, root.name, &mut files).map_err(|e| format!("Could not read {}: {}", root.dir, e))?;
+            }
+            continue;
+        };
+        for name in names {
+            let file = dir.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            let data = std::fs::read(&file).map_err(|e| format!("Could not read {}: {}", file.display(), e))?;
+            files.push(ArchivedFile {
+                path: format!("{}/{}", root.name, name),
+                data: base64::engine::general_purpose::STANDARD.encode(data),
+            });
+        }
+    }
+    let archive = Archive {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created: chrono::Utc::now().timestamp(),
+        files,
+    };
+    if let Some(dir) = Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    serde_json::to_writer(&mut encoder, &archive).map_err(|e| e.to_string())?;
+    let mut data = encoder.finish().map_err(|e| e.to_string())?;
+    if let Some(key) = key {
+        data = key.seal_file(&data);
+    }
+    let mut file = std::fs::File::create(path).map_err(|e| format!("Could not create {}: {}", path, e))?;
+    file.write_all(&data).map_err(|e| format!("Could not write {}: {}", path, e))?;
+    Ok(archive.files.len())
+}
+
+/// Where an archived file goes, or `None` if its path would leave the
+/// folders it belongs in.
+fn destination(path: &str, roots: &[Root]) -> Option<String> {
+    let (name, rest) = path.split_once('/')?;
+    let root = roots.iter().find(|root| root.name == name)?;
+    let mut components = Path::new(rest).components();
+    let safe = components.all(|component| matches!(component, Component::Normal(_)));
+    // Sessions are single files beside the capture file
+    let flat = root.files.is_none() || !rest.contains('/');
+    (safe && flat).then(|| format!("{}/{}", root.dir, rest))
+}
+
+/// Whether the archive at `path` needs a passphrase to restore.
+pub fn is_encrypted(path: &str) -> bool {
+    std::fs::read(path).is_ok_and(|data| crate::crypt::is_sealed_file(&data))
+}
+
+/// Write the files of the archive at `path` into `roots`, replacing those of
+/// the same name, and return them. Files not in the archive are left alone.
+pub fn restore(path: &str, roots: &[Root], passphrase: Option<&str>) -> Result<Vec<String>, String> {
+    let mut data = std::fs::read(path).map_err(|e| format!("Could not open {}: {}", path, e))?;
+    if crate::crypt::is_sealed_file(&data) {
+        let passphrase = passphrase.ok_or_else(|| format!("{} is encrypted", path))?;
+        data = crate::crypt::open_file(&data, passphrase)?;
+    }
+    let mut json = String::new();
+    GzDecoder::new(data.as_slice())
+        .read_to_string(&mut json)
+        .map_err(|e| format!("{} is not a ScriptView backup: {}", path, e))?;
+    let archive: Archive = serde_json::from_str(&json).map_err(|e| format!("{} is not a ScriptView backup: {}", path, e))?;
+    if archive.format != FORMAT {
+        return Err(format!("{} is not a ScriptView backup", path));
+    }
+    if archive.version > VERSION {
+        return Err(format!("{} is from a newer ScriptView (backup version {})", path, archive.version));
+    }
+    // Check everything before writing anything, so a bad archive changes nothing
+    let mut writes = Vec::new();
+    for file in &archive.files {
+        let destination = destination(&file.path, roots).ok_or_else(|| format!("Unsafe path in backup: {}", file.path))?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&file.data)
+            .map_err(|e| format!("{} is damaged: {}", file.path, e))?;
+        writes.push((destination, data));
+    }
+    for (destination, data) in &writes {
+        if let Some(dir) = Path::new(destination).parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(destination, data).map_err(|e| format!("Could not write {}: {}", destination, e))?;
+    }
+    Ok(writes.into_iter().map(|(destination, _)| destination).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_restore() {
+        let base = std::env::temp_dir().join(format!("scriptview-backup-{}", std::process::id()));
+        let base = base.to_string_lossy().into_owned();
+        let roots = |side: &str, sessions: Option<Vec<String>>| {
+            vec![
+                Root::all("config", format!("{}/{}/config", base, side)),
+                Root::all("data", format!("{}/{}/data", base, side)),
+                Root {
+                    name: "sessions",
+                    dir: format!("{}/{}/tmp", base, side),
+                    files: sessions,
+                },
+            ]
+        };
+        let from = roots("from", Some(vec!["mpv-subtitles.json".to_string(), "gone.json".to_string()]));
+        std::fs::create_dir_all(format!("{}/raw", from[1].dir)).unwrap();
+        std::fs::create_dir_all(&from[0].dir).unwrap();
+        std::fs::create_dir_all(&from[2].dir).unwrap();
+        std::fs::write(format!("{}/settings.json", from[0].dir), "{\"font_size\": 20}").unwrap();
+        std::fs::write(format!("{}/raw/2024-05-01.jsonl", from[1].dir), "{\"text\":\"猫\"}\n").unwrap();
+        std::fs::write(format!("{}/raw.sqlite", from[1].dir), [0u8, 159, 146, 150]).unwrap();
+        std::fs::write(format!("{}/mpv-subtitles.json", from[2].dir), "[]").unwrap();
+        std::fs::write(format!("{}/unrelated.txt", from[2].dir), "not mine").unwrap();
+
+        let archive = format!("{}/backup.json.gz", base);
+        assert_eq!(create(&archive, &from, None).unwrap(), 4);
+        assert!(!is_encrypted(&archive));
+        let to = roots("to", Some(Vec::new()));
+        assert_eq!(restore(&archive, &to, None).unwrap().len(), 4);
+        let raw = std::fs::read_to_string(format!("{}/raw/2024-05-01.jsonl", to[1].dir)).unwrap();
+        assert_eq!(raw, "{\"text\":\"猫\"}\n");
+        assert_eq!(std::fs::read(format!("{}/raw.sqlite", to[1].dir)).unwrap(), [0u8, 159, 146, 150]);
+        assert_eq!(std::fs::read_to_string(format!("{}/mpv-subtitles.json", to[2].dir)).unwrap(), "[]");
+        assert!(!Path::new(&format!("{}/unrelated.txt", to[2].dir)).exists());
+
+        assert!(restore(&format!("{}/settings.json", from[0].dir), &to, None).is_err());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn test_destination() {
+        let roots = vec![
+            Root::all("config", "/home/me/.config/scriptview".to_string()),
+            Root {
+                name: "sessions",
+                dir: "/tmp".to_string(),
+                files: Some(Vec::new()),
+            },
+        ];
+        assert_eq!(
+            destination("config/settings.json", &roots).as_deref(),
+            Some("/home/me/.config/scriptview/settings.json")
+        );
+        assert_eq!(destination("config/../../.bashrc", &roots), None);
+        assert_eq!(destination("config//etc/passwd", &roots), None);
+        assert_eq!(destination("data/stats.json", &roots), None);
+        assert_eq!(destination("sessions/mpv-subtitles.json", &roots).as_deref(), Some("/tmp/mpv-subtitles.json"));
+        assert_eq!(destination("sessions/nested/file.json", &roots), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Right-to-left text. egui lays text out left to right one glyph per
+//! character, so Arabic letters are swapped for their joined presentation
+//! forms, lines are wrapped in logical order and each line is then reordered
+//! for display with the Unicode bidirectional algorithm.
+
+use serde::{Deserialize, Serialize};
+use unicode_bidi::{Direction, Level, ParagraphBidiInfo};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextDirection {
+    /// Right to left when a line's first letter is from a right-to-left script.
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    pub const ALL: [TextDirection; 3] = [TextDirection::Auto, TextDirection::LeftToRight, TextDirection::RightToLeft];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextDirection::Auto => "Automatic",
+            TextDirection::LeftToRight => "Left to right",
+            TextDirection::RightToLeft => "Right to left",
+        }
+    }
+
+    /// Whether `text` should be laid out right to left.
+    pub fn is_rtl(self, text: &str) -> bool {
+        match self {
+            TextDirection::Auto => unicode_bidi::get_base_direction(text) == Direction::Rtl,
+            TextDirection::LeftToRight => false,
+            TextDirection::RightToLeft => true,
+        }
+    }
+}
+
+/// Presentation forms of the Arabic letters U+0621..=U+064A: the isolated form
+/// (final, initial and medial follow it in Unicode) and whether the letter
+/// joins to the one after it. A zero form means the letter is left as it is.
+const ARABIC_FORMS: [(u32, bool); 42] = [
+    (0xFE80, false), // ء
+    (0xFE81, false), // آ
+    (0xFE83, false), // أ
+    (0xFE85, false), // ؤ
+    (0xFE87, false), // إ
+    (0xFE89, true),  // ئ
+    (0xFE8D, false), // ا
+    (0xFE8F, true),  // ب
+    (0xFE93, false), // ة
+    (0xFE95, true),  // ت
+    (0xFE99, true),  // ث
+    (0xFE9D, true),  // ج
+    (0xFEA1, true),  // ح
+    (0xFEA5, true),  // خ
+    (0xFEA9, false), // د
+    (0xFEAB, false), // ذ
+    (0xFEAD, false), // ر
+    (0xFEAF, false), // ز
+    (0xFEB1, true),  // س
+    (0xFEB5, true),  // ش
+    (0xFEB9, true),  // ص
+    (0xFEBD, true),  // ض
+    (0xFEC1, true),  // ط
+    (0xFEC5, true),  // ظ
+    (0xFEC9, true),  // ع
+    (0xFECD, true),  // غ
+    (0, false),      // U+063B..=U+063F: rare letters without presentation forms
+    (0, false),
+    (0, false),
+    (0, false),
+    (0, false),
+    (0, true),       // ـ tatweel joins on both sides but has no forms
+    (0xFED1, true),  // ف
+    (0xFED5, true),  // ق
+    (0xFED9, true),  // ك
+    (0xFEDD, true),  // ل
+    (0xFEE1, true),  // م
+    (0xFEE5, true),  // ن
+    (0xFEE9, true),  // ه
+    (0xFEED, false), // و
+    (0xFEEF, false), // ى
+    (0xFEF1, true),  // ي
+];
+
+/// Lam followed by these alefs becomes one ligature (isolated form; final is next).
+const LAM_ALEF: [(char, u32); 4] = [('آ', 0xFEF5), ('أ', 0xFEF7), ('إ', 0xFEF9), ('ا', 0xFEFB)];
+
+fn arabic_letter(c: char) -> Option<(u32, bool)> {
+    let index = (c as u32).checked_sub(0x0621)? as usize;
+    ARABIC_FORMS.get(index).copied()
+}
+
+/// Harakat and other marks that sit on a letter without breaking joining.
+fn is_transparent(c: char) -> bool {
+    matches!(c as u32, 0x064B..=0x065F | 0x0670)
+}
+
+/// Replace Arabic letters with the presentation form their neighbours call
+/// for, including lam-alef ligatures. Other text passes through unchanged.
+pub fn shape_arabic(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let neighbour = |from: usize, forward: bool| {
+        let mut i = from;
+        loop {
+            i = if forward { i + 1 } else { i.checked_sub(1)? };
+            let c = *chars.get(i)?;
+            if !is_transparent(c) {
+                return Some((i, c));
+            }
+        }
+    };
+    let mut shaped = String::with_capacity(text.len());
+    let mut skip = None;
+    for (i, &c) in chars.iter().enumerate() {
+        if skip == Some(i) {
+            continue;
+        }
+        let Some((base, _)) = arabic_letter(c).filter(|(base, _)| *base != 0) else {
+            shaped.push(c);
+            continue;
+        };
+        let joins_previous = neighbour(i, false)
+            .and_then(|(_, p)| arabic_letter(p))
+            .is_some_and(|(_, joins_next)| joins_next);
+        let next = neighbour(i, true);
+        if c == 'ل' {
+            if let Some((alef_at, ligature)) =
+                next.and_then(|(j, n)| LAM_ALEF.iter().find(|(alef, _)| *alef == n).map(|(_, l)| (j, *l)))
+            {
+                // Marks between lam and alef are dropped with the merge
+                shaped.extend(char::from_u32(ligature + joins_previous as u32));
+                skip = Some(alef_at);
+                continue;
+            }
+        }
+        let joins_next = arabic_letter(c).is_some_and(|(_, joins)| joins)
+            && next.and_then(|(_, n)| arabic_letter(n)).is_some();
+        let form = match (joins_previous, joins_next) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (true, true) => 3,
+        };
+        // Letters that only join backwards have just isolated and final forms
+        let form = if arabic_letter(c).is_some_and(|(_, joins)| joins) { form } else { form.min(1) };
+        shaped.extend(char::from_u32(base + form));
+    }
+    shaped
+}
+
+/// Break `text` into lines no wider than `max_width`, at spaces where
+/// possible, in logical (reading) order.
+pub fn wrap_logical(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_inclusive(' ') {
+        let candidate = format!("{}{}", line, word);
+        if !line.is_empty() && measure(candidate.trim_end()) > max_width {
+            lines.push(line.trim_end().to_string());
+            line = word.to_string();
+        } else {
+            line = candidate;
+        }
+    }
+    if !line.trim().is_empty() || lines.is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// One line rearranged into the left-to-right order egui draws in.
+pub fn visual_order(line: &str, rtl: bool) -> String {
+    let level = if rtl { Level::rtl() } else { Level::ltr() };
+    let info = ParagraphBidiInfo::new(line, Some(level));
+    info.reorder_line(0..line.len()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_direction() {
+        assert!(TextDirection::Auto.is_rtl("שלום world"));
+        assert!(!TextDirection::Auto.is_rtl("hello שלום"));
+        assert!(TextDirection::RightToLeft.is_rtl("hello"));
+    }
+
+    #[test]
+    fn test_shape_arabic_joins_letters() {
+        // بيت: initial beh, medial yeh, final teh
+        assert_eq!(shape_arabic("بيت"), "\u{FE91}\u{FEF4}\u{FE96}");
+        // دار: dal and alef don't join forwards, so every letter stands alone
+        assert_eq!(shape_arabic("دار"), "\u{FEA9}\u{FE8D}\u{FEAD}");
+        assert_eq!(shape_arabic("a ب"), "a \u{FE8F}");
+    }
+
+    #[test]
+    fn test_shape_arabic_lam_alef() {
+        assert_eq!(shape_arabic("لا"), "\u{FEFB}");
+        // سلام: initial seen, final lam-alef ligature, isolated meem
+        assert_eq!(shape_arabic("سلام"), "\u{FEB3}\u{FEFC}\u{FEE1}");
+    }
+
+    #[test]
+    fn test_visual_order() {
+        assert_eq!(visual_order("אבג", true), "גבא");
+        assert_eq!(visual_order("אב 12 ג", true), "ג 12 בא");
+        assert_eq!(visual_order("abc אב", false), "abc בא");
+    }
+
+    #[test]
+    fn test_wrap_logical() {
+        let lines = wrap_logical("one two three four", 9.0, |s| s.chars().count() as f32);
+        assert_eq!(lines, vec!["one two", "three", "four"]);
+        assert_eq!(wrap_logical("", 5.0, |s| s.len() as f32), vec![""]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Captions from streaming sites, sent by the companion browser extension in
+//! `browser-extension/` over a WebSocket on localhost.
+//!
+//! The extension scrapes the caption overlay of Netflix and YouTube and sends
+//! one JSON message per change: `{"type": "caption", "text", "time", "url",
+//! "title", "duration"}` when a caption appears and `{"type": "clear",
+//! "time"}` when it goes away. Captions are written to a capture file of
+//! their own, so they take the same path through the viewer as mpv's.
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite;
+
+use crate::capture::CaptureWriter;
+use crate::runtime::{StopSignal, Stopped};
+use crate::SubtitleEntry;
+
+pub const DEFAULT_PORT: u16 = 7273;
+pub const OUTPUT_FILE: &str = "/tmp/browser-subtitles.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Caption {
+        text: String,
+        /// Playback position of the video in seconds
+        time: f64,
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        duration: Option<f64>,
+    },
+    Clear {
+        time: f64,
+    },
+}
+
+/// Caption text as scraped from the page: whitespace within lines collapsed
+/// and empty lines dropped.
+fn normalize_text(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A short link to `time` seconds into the YouTube video at `url`, or `None`
+/// if it isn't one.
+pub fn youtube_link(url: &str, time: f64) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.strip_prefix("www.").or_else(|| rest.strip_prefix("m.")).unwrap_or(rest);
+    let id = if let Some(path) = rest.strip_prefix("youtu.be/") {
+        path.split(['?', '#', '/']).next()
+    } else if let Some(query) = rest.strip_prefix("youtube.com/watch?") {
+        query.split(['&', '#']).find_map(|pair| pair.strip_prefix("v="))
+    } else {
+        None
+    }?;
+    if id.is_empty() {
+        return None;
+    }
+    Some(format!("https://youtu.be/{}?t={}", id, time.max(0.0).floor() as u64))
+}
+
+/// Carry out one message from the extension.
+fn handle_message(writer: &Mutex<CaptureWriter>, message: &str) -> Result<(), String> {
+    let message: Message = serde_json::from_str(message).map_err(|e| e.to_string())?;
+    let mut writer = writer.lock().unwrap();
+    let result = match message {
+        Message::Caption {
+            text,
+            time,
+            url,
+            title,
+            duration,
+        } => {
+            let text = normalize_text(&text);
+            if text.is_empty() {
+                return Ok(());
+            }
+            writer.push(SubtitleEntry {
+                text,
+                start_time: time,
+                end_time: None,
+                timestamp: chrono::Local::now().timestamp(),
+                path: Some(url),
+                duration: duration.filter(|d| d.is_finite()),
+                title: title.filter(|t| !t.is_empty()),
+                ..Default::default()
+            })
+        }
+        Message::Clear { time } => writer.end_last(time),
+    };
+    result.map_err(|e| format!("Could not write {}: {}", OUTPUT_FILE, e))
+}
+
+async fn serve_connection(stream: TcpStream, writer: Arc<Mutex<CaptureWriter>>, mut stopped: Stopped) {
+    let mut socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Warning: Browser extension handshake failed: {}", e);
+            return;
+        }
+    };
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = stopped.wait() => break,
+        };
+        match message {
+            Some(Ok(tungstenite::Message::Text(text))) => {
+                if let Err(e) = handle_message(&writer, &text) {
+                    eprintln!("Warning: Ignoring message from browser extension: {}", e);
+                }
+            }
+            Some(Ok(tungstenite::Message::Close(_)) | Err(_)) | None => break,
+            Some(Ok(_)) => {}
+        }
+    }
+}
+
+/// The WebSocket server, running until dropped.
+pub struct Server {
+    pub port: u16,
+    stop: Option<StopSignal>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `port` on localhost (0 picks a free port) and start an empty
+    /// capture file at `output_file`.
+    pub fn start(port: u16, output_file: &str) -> Result<Self, std::io::Error> {
+        let listener = crate::runtime::bind(port)?;
+        let port = listener.local_addr()?.port();
+        let writer = Arc::new(Mutex::new(CaptureWriter::create(output_file)?));
+        let (stop, mut stopped) = crate::runtime::stop_signal();
+
+        let accept = crate::runtime::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = stopped.wait() => break,
+                };
+                match accepted {
+                    Ok((stream, _)) => {
+                        crate::runtime::spawn(serve_connection(stream, writer.clone(), stopped.clone()));
+                    }
+                    Err(e) => eprintln!("Warning: Browser extension connection failed: {}", e),
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            stop: Some(stop),
+            accept: Some(accept),
+        })
+    }
+
+    /// Stop listening, waiting for the port to be let go so it can be bound
+    /// again right away.
+    pub fn stop(&mut self) {
+        self.stop = None;
+        let Some(accept) = self.accept.take() else {
+            return;
+        };
+        if !crate::runtime::join_within(accept, crate::runtime::STOP_TIMEOUT) {
+            eprintln!("Warning: Browser extension server didn't stop in time");
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(normalize_text("  Where   are\n\n you going? \n"), "Where are\nyou going?");
+        assert_eq!(normalize_text(" \n "), "");
+    }
+
+    #[test]
+    fn test_youtube_link() {
+        assert_eq!(
+            youtube_link("https://www.youtube.com/watch?list=x&v=abc#t", 83.9).as_deref(),
+            Some("https://youtu.be/abc?t=83")
+        );
+        assert_eq!(youtube_link("https://youtu.be/abc?si=y", 5.0).as_deref(), Some("https://youtu.be/abc?t=5"));
+        assert_eq!(youtube_link("https://www.netflix.com/watch/123", 5.0), None);
+        assert_eq!(youtube_link("/videos/show.mkv", 5.0), None);
+    }
+
+    #[test]
+    fn test_extension_captions_reach_capture_file() {
+        let output_file = std::env::temp_dir().join(format!("scriptview-browser-{}.json", std::process::id()));
+        let output_file = output_file.to_string_lossy().into_owned();
+        let server = Server::start(0, &output_file).unwrap();
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://127.0.0.1:{}", server.port)).unwrap();
+        let url = "https://www.youtube.com/watch?v=abc";
+        for message in [
+            serde_json::json!({"type": "caption", "text": "猫が\n  いる", "time": 10.5, "url": url, "title": "Cats"}),
+            serde_json::json!({"type": "clear", "time": 12.0}),
+            serde_json::json!({"type": "dance"}),
+            serde_json::json!({"type": "caption", "text": "犬", "time": 13.0, "url": url}),
+        ] {
+            socket.send(tungstenite::Message::Text(message.to_string())).unwrap();
+        }
+        socket.close(None).unwrap();
+        // Closing completes once the server has read everything before it
+        while socket.read().is_ok() {}
+
+        let entries = crate::session::read_entries(&output_file).unwrap();
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["猫が\nいる", "犬"]);
+        assert_eq!(entries[0].end_time, Some(12.0));
+        assert_eq!(entries[0].title.as_deref(), Some("Cats"));
+        assert_eq!(entries[1].path.as_deref(), Some(url));
+
+        // Stopping lets go of the port, so restarting can bind it again
+        let port = server.port;
+        drop(server);
+        Server::start(port, &output_file).unwrap();
+        std::fs::remove_file(output_file).unwrap();
+    }
+}
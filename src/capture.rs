@@ -0,0 +1,93 @@
+//! Capture files written from inside the viewer, for sources that have no
+//! player script of their own. They follow the scripts' truncate mode: the
+//! last 50 lines, starting over when the media changes.
+
+use crate::SubtitleEntry;
+
+const MAX_ENTRIES: usize = 50;
+
+pub struct CaptureWriter {
+    path: String,
+    entries: Vec<SubtitleEntry>,
+}
+
+impl CaptureWriter {
+    /// Start an empty capture file at `path`, like a script does on load.
+    pub fn create(path: &str) -> Result<Self, std::io::Error> {
+        let writer = Self {
+            path: path.to_string(),
+            entries: Vec::new(),
+        };
+        writer.write()?;
+        Ok(writer)
+    }
+
+    /// Append a line, unless it repeats the last one (captions redrawn in
+    /// place). A line from other media clears the history first.
+    pub fn push(&mut self, entry: SubtitleEntry) -> Result<(), std::io::Error> {
+        if let Some(last) = self.entries.last_mut() {
+            if last.path != entry.path {
+                self.entries.clear();
+            } else if last.text == entry.text {
+                return Ok(());
+            } else if last.end_time.is_none() {
+                last.end_time = Some(entry.start_time).filter(|&end| end >= last.start_time);
+            }
+        }
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.write()
+    }
+
+    /// The last line left the screen at `time`.
+    pub fn end_last(&mut self, time: f64) -> Result<(), std::io::Error> {
+        match self.entries.last_mut() {
+            Some(last) if last.end_time.is_none() && time >= last.start_time => {
+                last.end_time = Some(time);
+                self.write()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&self) -> Result<(), std::io::Error> {
+        std::fs::write(&self.path, serde_json::to_string(&self.entries)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, start_time: f64, path: &str) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            path: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        let path = std::env::temp_dir().join(format!("scriptview-capture-{}.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let mut writer = CaptureWriter::create(&path).unwrap();
+        writer.push(entry("猫だ", 1.0, "a")).unwrap();
+        writer.push(entry("猫だ", 1.5, "a")).unwrap();
+        writer.push(entry("犬", 4.0, "a")).unwrap();
+        writer.end_last(6.0).unwrap();
+
+        let entries = crate::session::read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].end_time, Some(4.0));
+        assert_eq!(entries[1].end_time, Some(6.0));
+
+        writer.push(entry("鳥", 0.5, "b")).unwrap();
+        let entries = crate::session::read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+//! Self-test mode for a finished transcript: one side of every line, the
+//! original or its translation, is masked until clicked.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClozeSide {
+    Original,
+    Translation,
+}
+
+impl ClozeSide {
+    pub const ALL: [ClozeSide; 2] = [ClozeSide::Original, ClozeSide::Translation];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ClozeSide::Original => "Hide the original lines",
+            ClozeSide::Translation => "Hide the translations",
+        }
+    }
+}
+
+/// `text` with every character but spaces and line breaks blanked out,
+/// keeping its length as a hint.
+pub fn mask(text: &str) -> String {
+    text.chars().map(|c| if c.is_whitespace() { c } else { '▁' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask("猫がいる"), "▁▁▁▁");
+        assert_eq!(mask("It's a cat\nreally"), "▁▁▁▁ ▁ ▁▁▁\n▁▁▁▁▁▁");
+    }
+}
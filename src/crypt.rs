@@ -0,0 +1,225 @@
+//! Encryption at rest for the history, for shared machines: the raw stream,
+//! capture file archives, session summaries and backups, sealed with
+//! ChaCha20-Poly1305 under a key
+//! derived from a passphrase with PBKDF2. The salt and a check value live in
+//! `key.json` beside the raw stream, so the passphrase itself is never
+//! stored.
+//!
+//! Raw stream lines are sealed one by one (`enc:` and base64 in place of the
+//! JSON) so days can still be appended to; whole files start with a header
+//! holding the salt, so they can be opened with nothing but the passphrase,
+//! on another machine too.
+
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+/// OWASP's recommendation for PBKDF2-HMAC-SHA256.
+pub const ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const LINE_PREFIX: &str = "enc:";
+/// Starts every sealed file.
+const MAGIC: &[u8] = b"SVENC1";
+/// Sealed into `key.json` to tell a wrong passphrase from a right one.
+const CHECK: &[u8] = b"scriptview";
+
+pub fn key_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/key.json", home_dir)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    /// Base64, like `check`.
+    salt: String,
+    iterations: u32,
+    check: String,
+}
+
+/// A key derived from the passphrase, kept only in memory.
+#[derive(Clone)]
+pub struct Key {
+    bytes: [u8; 32],
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+fn base64() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+impl Key {
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN], iterations: u32) -> Self {
+        let mut bytes = [0; 32];
+        let rounds = std::num::NonZeroU32::new(iterations.max(1)).unwrap();
+        ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, rounds, &salt, passphrase.as_bytes(), &mut bytes);
+        Self {
+            bytes,
+            salt,
+            iterations,
+        }
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.bytes).unwrap())
+    }
+
+    /// A random nonce followed by the ciphertext and its tag.
+    fn seal(&self, plain: &[u8]) -> Vec<u8> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).expect("no system random number generator");
+        let mut sealed = plain.to_vec();
+        self.aead()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .expect("plaintext too long");
+        [nonce.as_slice(), &sealed].concat()
+    }
+
+    fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut buffer = ciphertext.to_vec();
+        let plain = self.aead().open_in_place(nonce, Aad::empty(), &mut buffer).ok()?;
+        Some(plain.to_vec())
+    }
+
+    /// A key with a fixed salt and one round, for tests elsewhere.
+    #[cfg(test)]
+    pub fn for_tests(passphrase: &str) -> Self {
+        Self::derive(passphrase, [7; SALT_LEN], 1)
+    }
+
+    /// A raw stream line in place of its JSON.
+    pub fn seal_line(&self, json: &str) -> String {
+        format!("{}{}", LINE_PREFIX, base64().encode(self.seal(json.as_bytes())))
+    }
+
+    /// A whole file, openable with the passphrase alone.
+    pub fn seal_file(&self, plain: &[u8]) -> Vec<u8> {
+        [MAGIC, &self.salt, &self.iterations.to_le_bytes(), &self.seal(plain)].concat()
+    }
+
+    /// Open a file sealed with this key, without deriving it again.
+    pub fn open_file(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let header = [MAGIC, &self.salt, &self.iterations.to_le_bytes()].concat();
+        self.open(data.strip_prefix(header.as_slice())?)
+    }
+}
+
+/// The JSON of a raw stream line, opening it if it's sealed. `None` for a
+/// sealed line without the key to open it.
+pub fn open_line(key: Option<&Key>, line: &str) -> Option<String> {
+    match line.strip_prefix(LINE_PREFIX) {
+        Some(sealed) => {
+            let plain = key?.open(&base64().decode(sealed).ok()?)?;
+            String::from_utf8(plain).ok()
+        }
+        None => Some(line.to_string()),
+    }
+}
+
+pub fn is_sealed_file(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Open a file made with [`Key::seal_file`].
+pub fn open_file(data: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let header = MAGIC.len() + SALT_LEN + 4;
+    if !is_sealed_file(data) || data.len() < header {
+        return Err("Not an encrypted ScriptView file".to_string());
+    }
+    let salt = data[MAGIC.len()..MAGIC.len() + SALT_LEN].try_into().unwrap();
+    let iterations = u32::from_le_bytes(data[header - 4..header].try_into().unwrap());
+    Key::derive(passphrase, salt, iterations)
+        .open(&data[header..])
+        .ok_or_else(|| "Wrong passphrase, or the file is damaged".to_string())
+}
+
+/// The key for the history: the passphrase checked against `key.json`, or
+/// if there's none yet, a new one made for it.
+pub fn unlock(passphrase: &str) -> Result<Key, String> {
+    unlock_at(&key_path(), passphrase, ITERATIONS)
+}
+
+fn unlock_at(path: &str, passphrase: &str, iterations: u32) -> Result<Key, String> {
+    if passphrase.is_empty() {
+        return Err("The passphrase is empty".to_string());
+    }
+    if let Ok(content) = std::fs::read_to_string(path) {
+        let file: KeyFile = serde_json::from_str(&content).map_err(|e| format!("{} is damaged: {}", path, e))?;
+        let salt = base64()
+            .decode(&file.salt)
+            .ok()
+            .and_then(|salt| salt.try_into().ok())
+            .ok_or_else(|| format!("{} is damaged", path))?;
+        let key = Key::derive(passphrase, salt, file.iterations);
+        let check = base64().decode(&file.check).ok().and_then(|check| key.open(&check));
+        return match check {
+            Some(check) if check == CHECK => Ok(key),
+            _ => Err("Wrong passphrase".to_string()),
+        };
+    }
+    let mut salt = [0; SALT_LEN];
+    SystemRandom::new().fill(&mut salt).map_err(|_| "No system random number generator")?;
+    let key = Key::derive(passphrase, salt, iterations);
+    let file = KeyFile {
+        salt: base64().encode(salt),
+        iterations,
+        check: base64().encode(key.seal(CHECK)),
+    };
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("Could not write {}: {}", path, e))?;
+    Ok(key)
+}
+
+/// The passphrase for command line use: `SCRIPTVIEW_PASSPHRASE`, or asked
+/// for on the terminal.
+pub fn read_passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var("SCRIPTVIEW_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    eprint!("Passphrase: ");
+    let mut passphrase = String::new();
+    std::io::stdin().read_line(&mut passphrase).map_err(|e| e.to_string())?;
+    Ok(passphrase.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlock_and_seal() {
+        let path = std::env::temp_dir().join(format!("scriptview-key-{}.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let key = unlock_at(&path, "hunter2", 1000).unwrap();
+        assert_eq!(unlock_at(&path, "hunter3", 1000).unwrap_err(), "Wrong passphrase");
+        let again = unlock_at(&path, "hunter2", 1000).unwrap();
+
+        let line = key.seal_line("{\"text\":\"猫\"}");
+        assert!(!line.contains('猫') && line.starts_with("enc:"));
+        assert_eq!(open_line(Some(&again), &line).as_deref(), Some("{\"text\":\"猫\"}"));
+        assert_eq!(open_line(None, &line), None);
+        assert_eq!(open_line(None, "{}").as_deref(), Some("{}"));
+
+        let file = key.seal_file("# Session".as_bytes());
+        assert!(is_sealed_file(&file));
+        assert_eq!(open_file(&file, "hunter2").unwrap(), b"# Session");
+        assert_eq!(again.open_file(&file).unwrap(), b"# Session");
+        assert!(open_file(&file, "hunter3").is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! Working out why a file couldn't be written or watched, so the error
+//! panel can say what to do about it instead of just "Permission denied".
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// A failure and what might fix it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub problem: String,
+    pub hints: Vec<String>,
+}
+
+/// The closest of `path` and its ancestors that exists.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|ancestor| ancestor.exists()).map(Path::to_path_buf)
+}
+
+/// Our user ID, from `/proc/self/status`.
+#[cfg(unix)]
+fn current_uid() -> Option<u32> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("Uid:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Hints about who owns `path` and whether it can be written.
+fn permission_hints(path: &Path, hints: &mut Vec<String>) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Some(uid) = current_uid().filter(|&uid| uid != 0 && uid != metadata.uid()) {
+            hints.push(format!(
+                "{} belongs to user ID {}, not you ({}); `sudo chown -R $USER {}` gives it back",
+                path.display(),
+                metadata.uid(),
+                uid,
+                path.display()
+            ));
+            return;
+        }
+        if metadata.mode() & 0o200 == 0 {
+            hints.push(format!("{} is read-only; `chmod u+w {}` makes it writable", path.display(), path.display()));
+        }
+    }
+    #[cfg(not(unix))]
+    if metadata.permissions().readonly() {
+        hints.push(format!("{} is read-only", path.display()));
+    }
+}
+
+/// Hints about confinement that can deny access regardless of permissions.
+fn sandbox_hints(hints: &mut Vec<String>) {
+    if std::env::var_os("FLATPAK_ID").is_some() || Path::new("/.flatpak-info").exists() {
+        hints.push(
+            "ScriptView is running as a Flatpak; allow it into the directory with `flatpak override --user --filesystem=~/.config/mpv`"
+                .to_string(),
+        );
+    }
+    if std::env::var_os("SNAP").is_some() {
+        hints.push("ScriptView is running as a Snap, which can only reach some of your home directory".to_string());
+    }
+    let selinux_enforcing = std::fs::read_to_string("/sys/fs/selinux/enforce").is_ok_and(|mode| mode.trim() == "1");
+    if selinux_enforcing {
+        hints.push("SELinux is enforcing; `sudo ausearch -m avc -ts recent` shows whether it blocked this".to_string());
+    }
+}
+
+/// Why writing `path` failed with `error`.
+pub fn write_failed(path: &Path, error: &std::io::Error) -> Diagnosis {
+    let mut hints = Vec::new();
+    match existing_ancestor(path) {
+        Some(ancestor) if ancestor != path && !ancestor.is_dir() => {
+            hints.push(format!("{} is a file, but a directory is needed there", ancestor.display()));
+        }
+        Some(ancestor) => {
+            if let Some(missing) = path.parent().filter(|parent| !parent.exists()) {
+                hints.push(format!(
+                    "{} doesn't exist and couldn't be created inside {}",
+                    missing.display(),
+                    ancestor.display()
+                ));
+            }
+            permission_hints(&ancestor, &mut hints);
+        }
+        None => {}
+    }
+    match error.kind() {
+        ErrorKind::ReadOnlyFilesystem => hints.push("The file system is mounted read-only".to_string()),
+        ErrorKind::StorageFull => hints.push("The disk is full".to_string()),
+        ErrorKind::PermissionDenied => sandbox_hints(&mut hints),
+        _ => {}
+    }
+    Diagnosis {
+        problem: format!("Could not write {}: {}", path.display(), error),
+        hints,
+    }
+}
+
+/// Why watching the capture file at `path` failed with `error`.
+pub fn watch_failed(path: &Path, error: &notify::Error) -> Diagnosis {
+    let mut hints = Vec::new();
+    match &error.kind {
+        notify::ErrorKind::MaxFilesWatch => hints.push(
+            "The limit on watched files is reached; raise it with `sudo sysctl fs.inotify.max_user_watches=524288`"
+                .to_string(),
+        ),
+        notify::ErrorKind::Io(e) if e.kind() == ErrorKind::PermissionDenied => {
+            if let Some(parent) = path.parent() {
+                permission_hints(parent, &mut hints);
+            }
+            sandbox_hints(&mut hints);
+        }
+        _ => {}
+    }
+    Diagnosis {
+        problem: format!("Could not watch {} for new lines: {}", path.display(), error),
+        hints,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_in_the_way_of_a_directory() {
+        let dir = std::env::temp_dir().join(format!("scriptview-diagnose-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scripts"), "").unwrap();
+        let path = dir.join("scripts/subtitle-monitor.lua");
+        let error = std::fs::write(&path, "").unwrap_err();
+        let diagnosis = write_failed(&path, &error);
+        assert!(diagnosis.problem.contains("subtitle-monitor.lua"));
+        assert!(diagnosis.hints[0].contains("is a file, but a directory is needed"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_watch_limit_is_explained() {
+        let path = Path::new("/tmp/mpv-subtitles.json");
+        let diagnosis = watch_failed(path, &notify::Error::new(notify::ErrorKind::MaxFilesWatch));
+        assert!(diagnosis.hints[0].contains("max_user_watches"));
+    }
+}
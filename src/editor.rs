@@ -0,0 +1,266 @@
+//! Editing the lines in view in an external editor. They're written to a
+//! Markdown file, one `[#id time] text` marker line each (a line's further
+//! lines follow it as they are), and the file is read back on every save:
+//! changed text replaces the line's text in the view and removed markers
+//! move the line to the trash. Like deleting, editing never touches the
+//! capture file; edits last until the viewer closes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::ids::EntryId;
+use crate::SubtitleEntry;
+
+const HEADER: &str = "<!-- Edit the text after each [#id time] marker and save; ScriptView picks up every save. \
+Remove a marker and its text to delete the line. Keep the markers as they are. -->";
+
+/// `<temp dir>/scriptview-edit-<pid>.md`
+pub fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!("scriptview-edit-{}.md", std::process::id()))
+}
+
+pub fn to_markdown(title: &str, entries: &[SubtitleEntry]) -> String {
+    let mut text = format!("# {}\n\n{}\n\n", title, HEADER);
+    for entry in entries {
+        text.push_str(&format!("[#{} {}] {}\n", entry.id, crate::format_timestamp(entry.start_time), entry.text));
+    }
+    text
+}
+
+/// The text of every marker in `markdown`, by ID.
+fn parse(markdown: &str) -> HashMap<EntryId, String> {
+    let mut lines: HashMap<EntryId, String> = HashMap::new();
+    let mut current = None;
+    for line in markdown.lines() {
+        let marker = line
+            .strip_prefix("[#")
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(marker, text)| Some((marker.split_whitespace().next()?.parse::<EntryId>().ok()?, text)));
+        match marker {
+            Some((id, text)) => {
+                lines.insert(id, text.strip_prefix(' ').unwrap_or(text).to_string());
+                current = Some(id);
+            }
+            None => {
+                if let Some(text) = current.and_then(|id| lines.get_mut(&id)) {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+            }
+        }
+    }
+    for text in lines.values_mut() {
+        text.truncate(text.trim_end().len());
+    }
+    lines
+}
+
+/// What a save changed in the lines that were written out.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    /// Each written line's text as the file has it now.
+    pub texts: Vec<(EntryId, String)>,
+    pub deleted: Vec<EntryId>,
+}
+
+pub fn changes(markdown: &str, written: &[SubtitleEntry]) -> Changes {
+    let mut edited = parse(markdown);
+    let mut changes = Changes::default();
+    for entry in written {
+        match edited.remove(&entry.id) {
+            Some(text) => changes.texts.push((entry.id, text)),
+            None => changes.deleted.push(entry.id),
+        }
+    }
+    changes
+}
+
+/// Text replaced from the editor, put back over every fresh read of the
+/// capture file.
+#[derive(Debug, Default)]
+pub struct Edits {
+    texts: HashMap<EntryId, String>,
+}
+
+impl Edits {
+    /// Replace a line's text, or drop the replacement when the text is as
+    /// the capture file has it.
+    pub fn set(&mut self, id: EntryId, text: String, original: &str) {
+        if text == original {
+            self.texts.remove(&id);
+        } else {
+            self.texts.insert(id, text);
+        }
+    }
+
+    pub fn apply(&self, entries: &mut [SubtitleEntry]) {
+        if self.texts.is_empty() {
+            return;
+        }
+        for entry in entries {
+            if let Some(text) = self.texts.get(&entry.id) {
+                entry.text = text.clone();
+            }
+        }
+    }
+}
+
+/// A file open in the editor, and the lines it was made from.
+pub struct Session {
+    pub path: PathBuf,
+    pub written: Vec<SubtitleEntry>,
+    modified: Option<SystemTime>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl Session {
+    /// Write `entries` to `path` and open it in the editor.
+    pub fn start(path: PathBuf, title: &str, entries: Vec<SubtitleEntry>, command: &str) -> Result<Self, String> {
+        std::fs::write(&path, to_markdown(title, &entries)).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        open(command, &path)?;
+        Ok(Self {
+            modified: modified(&path),
+            path,
+            written: entries,
+        })
+    }
+
+    /// The file's content, if it was saved since the last call.
+    pub fn saved(&mut self) -> Option<String> {
+        let now = modified(&self.path);
+        if now == self.modified {
+            return None;
+        }
+        self.modified = now;
+        std::fs::read_to_string(&self.path).ok()
+    }
+}
+
+/// Terminal emulators that run the command given after them.
+const TERMINALS: [&str; 12] = [
+    "alacritty",
+    "foot",
+    "gnome-terminal",
+    "kitty",
+    "konsole",
+    "st",
+    "terminator",
+    "tilix",
+    "urxvt",
+    "wezterm",
+    "xfce4-terminal",
+    "xterm",
+];
+
+/// The program and arguments to edit `file` with: the `command` from
+/// settings with `{file}` filled in (a terminal on its own runs `$EDITOR`,
+/// which is usually a terminal editor), else `$VISUAL`, else whatever the
+/// system opens Markdown files with.
+fn editor_args(command: &str, file: &Path, env: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let file = file.to_string_lossy();
+    let set = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+    if let Some(program) = command.split_whitespace().next() {
+        let mut args: Vec<String> = command.split_whitespace().map(|arg| arg.replace("{file}", &file)).collect();
+        if !command.contains("{file}") {
+            let name = Path::new(program).file_name().map(|name| name.to_string_lossy());
+            if name.is_some_and(|name| TERMINALS.contains(&name.as_ref())) {
+                let editor = set("EDITOR").unwrap_or_else(|| "vi".to_string());
+                args.extend(editor.split_whitespace().map(str::to_string));
+            }
+            args.push(file.into_owned());
+        }
+        return args;
+    }
+    if let Some(editor) = set("VISUAL") {
+        let mut args: Vec<String> = editor.split_whitespace().map(str::to_string).collect();
+        args.push(file.into_owned());
+        return args;
+    }
+    let opener = if cfg!(target_os = "macos") {
+        vec!["open"]
+    } else if cfg!(windows) {
+        vec!["cmd", "/C", "start", ""]
+    } else {
+        vec!["xdg-open"]
+    };
+    opener.into_iter().map(str::to_string).chain([file.into_owned()]).collect()
+}
+
+fn open(command: &str, file: &Path) -> Result<(), String> {
+    let args = editor_args(command, file, |name| std::env::var(name).ok());
+    let (program, args) = args.split_first().ok_or("No editor set")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .spawn()
+        .map_err(|e| format!("Could not run {}: {}", program, e))?;
+    // Saves are picked up while it runs; this only reaps it
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(id: EntryId, text: &str) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time: id as f64,
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let written = vec![line(1, "猫がいる"), line(2, "二行目\nです"), line(3, "犬だ")];
+        let markdown = to_markdown("Show", &written);
+        assert!(markdown.contains("[#2 0:02.0] 二行目\nです\n"));
+        assert_eq!(
+            changes(&markdown, &written).texts,
+            vec![(1, "猫がいる".to_string()), (2, "二行目\nです".to_string()), (3, "犬だ".to_string())]
+        );
+
+        let edited = markdown.replace("猫がいる", "猫が居る").replace("[#3 0:03.0] 犬だ\n", "");
+        let changes = changes(&edited, &written);
+        assert_eq!(changes.texts[0], (1, "猫が居る".to_string()));
+        assert_eq!(changes.deleted, vec![3]);
+
+        let mut edits = Edits::default();
+        edits.set(1, "猫が居る".to_string(), "猫がいる");
+        edits.set(2, "二行目\nです".to_string(), "二行目\nです");
+        let mut view = written.clone();
+        edits.apply(&mut view);
+        assert_eq!(view[0].text, "猫が居る");
+        assert_eq!(view[1].text, "二行目\nです");
+        edits.set(1, "猫がいる".to_string(), "猫がいる");
+        let mut view = written.clone();
+        edits.apply(&mut view);
+        assert_eq!(view[0].text, "猫がいる");
+    }
+
+    #[test]
+    fn test_editor_args() {
+        let file = Path::new("/tmp/edit.md");
+        let env = |name: &str| match name {
+            "VISUAL" => Some("code --wait".to_string()),
+            "EDITOR" => Some("nvim".to_string()),
+            _ => None,
+        };
+        assert_eq!(editor_args("", file, env), vec!["code", "--wait", "/tmp/edit.md"]);
+        assert_eq!(editor_args("kitty vim {file}", file, env), vec!["kitty", "vim", "/tmp/edit.md"]);
+        assert_eq!(editor_args("gedit", file, env), vec!["gedit", "/tmp/edit.md"]);
+        // $EDITOR runs in a terminal, and only in one
+        assert_eq!(editor_args("/usr/bin/kitty", file, env), vec!["/usr/bin/kitty", "nvim", "/tmp/edit.md"]);
+        assert_eq!(editor_args("xterm -e", file, |_| None), vec!["xterm", "-e", "vi", "/tmp/edit.md"]);
+        let editor_only = |name: &str| (name == "EDITOR").then(|| "nvim".to_string());
+        let opened = editor_args("", file, editor_only);
+        assert!(!opened.contains(&"nvim".to_string()));
+        assert_eq!(opened.last().map(String::as_str), Some("/tmp/edit.md"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Text clean-up for imported subtitle files: byte order marks, legacy
+//! encodings and HTML entities.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    /// A byte order mark if there is one, else UTF-8 if the file is valid
+    /// UTF-8, else a statistical guess.
+    #[default]
+    Auto,
+    Utf8,
+    ShiftJis,
+    EucJp,
+    Gbk,
+    Big5,
+    EucKr,
+    Windows1251,
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 9] = [
+        TextEncoding::Auto,
+        TextEncoding::Utf8,
+        TextEncoding::ShiftJis,
+        TextEncoding::EucJp,
+        TextEncoding::Gbk,
+        TextEncoding::Big5,
+        TextEncoding::EucKr,
+        TextEncoding::Windows1251,
+        TextEncoding::Windows1252,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextEncoding::Auto => "Detect",
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::ShiftJis => "Shift_JIS",
+            TextEncoding::EucJp => "EUC-JP",
+            TextEncoding::Gbk => "GBK",
+            TextEncoding::Big5 => "Big5",
+            TextEncoding::EucKr => "EUC-KR",
+            TextEncoding::Windows1251 => "Windows-1251",
+            TextEncoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    fn encoding(self) -> Option<&'static encoding_rs::Encoding> {
+        match self {
+            TextEncoding::Auto => None,
+            TextEncoding::Utf8 => Some(encoding_rs::UTF_8),
+            TextEncoding::ShiftJis => Some(encoding_rs::SHIFT_JIS),
+            TextEncoding::EucJp => Some(encoding_rs::EUC_JP),
+            TextEncoding::Gbk => Some(encoding_rs::GBK),
+            TextEncoding::Big5 => Some(encoding_rs::BIG5),
+            TextEncoding::EucKr => Some(encoding_rs::EUC_KR),
+            TextEncoding::Windows1251 => Some(encoding_rs::WINDOWS_1251),
+            TextEncoding::Windows1252 => Some(encoding_rs::WINDOWS_1252),
+        }
+    }
+}
+
+/// Decode a subtitle file, returning the text and the name of the encoding
+/// used. A byte order mark always wins and is removed.
+pub fn decode(bytes: &[u8], choice: TextEncoding) -> (String, &'static str) {
+    let encoding = choice.encoding().unwrap_or_else(|| {
+        if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+            encoding
+        } else if std::str::from_utf8(bytes).is_ok() {
+            encoding_rs::UTF_8
+        } else {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(bytes, true);
+            detector.guess(None, true)
+        }
+    });
+    let (text, used, _) = encoding.decode(bytes);
+    (text.into_owned(), used.name())
+}
+
+/// Replace `&amp;`-style named and numeric character references. Unknown
+/// ones are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let replacement = rest
+            .find(';')
+            .filter(|&semi| semi <= 10)
+            .and_then(|semi| entity(&rest[1..semi]).map(|c| (c, semi)));
+        match replacement {
+            Some((c, semi)) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lrm" => '\u{200e}',
+        "rlm" => '\u{200f}',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_strips_bom() {
+        let (text, name) = decode(b"\xef\xbb\xbfhello", TextEncoding::Auto);
+        assert_eq!((text.as_str(), name), ("hello", "UTF-8"));
+    }
+
+    #[test]
+    fn test_decode_detects_shift_jis() {
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode("これは日本語の字幕です。猫が好きです。");
+        let (text, name) = decode(&bytes, TextEncoding::Auto);
+        assert_eq!(name, "Shift_JIS");
+        assert_eq!(text, "これは日本語の字幕です。猫が好きです。");
+    }
+
+    #[test]
+    fn test_decode_override() {
+        let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode("Привет");
+        let (text, name) = decode(&bytes, TextEncoding::Windows1251);
+        assert_eq!((text.as_str(), name), ("Привет", "windows-1251"));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry &lt;3 &#12354;&#x3042;"), "Tom & Jerry <3 ああ");
+        assert_eq!(decode_entities("R&D &bogus; a&b"), "R&D &bogus; a&b");
+    }
+}
@@ -0,0 +1,152 @@
+//! Example sentence lookup, from a local Tatoeba dump or the Immersion Kit API.
+
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver};
+
+use serde::{Deserialize, Serialize};
+
+const IMMERSION_KIT_URL: &str = "https://api.immersionkit.com/look_up_dictionary";
+const MAX_EXAMPLES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleSource {
+    /// `sentences.csv` from the Tatoeba downloads page (`id<TAB>lang<TAB>text`).
+    #[default]
+    Tatoeba,
+    ImmersionKit,
+}
+
+impl ExampleSource {
+    pub const ALL: [ExampleSource; 2] = [ExampleSource::Tatoeba, ExampleSource::ImmersionKit];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExampleSource::Tatoeba => "Tatoeba (local file)",
+            ExampleSource::ImmersionKit => "Immersion Kit (online)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    pub sentence: String,
+    pub translation: Option<String>,
+    /// Where the sentence is from, e.g. the show name for Immersion Kit.
+    pub origin: Option<String>,
+}
+
+/// Split a Tatoeba `sentences.csv` line into language code and text.
+fn parse_tatoeba_line(line: &str) -> Option<(&str, &str)> {
+    let mut fields = line.splitn(3, '\t');
+    let _id = fields.next()?;
+    let lang = fields.next()?;
+    let text = fields.next()?;
+    Some((lang, text))
+}
+
+fn search_tatoeba(path: &str, language: &str, word: &str) -> Result<Vec<Example>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut examples = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let Some((lang, text)) = parse_tatoeba_line(&line) else {
+            continue;
+        };
+        if (language.is_empty() || lang == language) && text.contains(word) {
+            examples.push(Example {
+                sentence: text.to_string(),
+                translation: None,
+                origin: None,
+            });
+            if examples.len() >= MAX_EXAMPLES {
+                break;
+            }
+        }
+    }
+    Ok(examples)
+}
+
+fn parse_immersion_kit(response: &serde_json::Value) -> Vec<Example> {
+    let examples = response["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|data| data["examples"].as_array())
+        .flatten();
+    examples
+        .filter_map(|example| {
+            Some(Example {
+                sentence: example["sentence"].as_str()?.to_string(),
+                translation: example["translation"].as_str().map(str::to_string),
+                origin: example["deck_name"].as_str().map(str::to_string),
+            })
+        })
+        .take(MAX_EXAMPLES)
+        .collect()
+}
+
+fn search_immersion_kit(word: &str) -> Result<Vec<Example>, String> {
+    let body = ureq::get(IMMERSION_KIT_URL)
+        .query("keyword", word)
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let response: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    Ok(parse_immersion_kit(&response))
+}
+
+/// Run the lookup on a background thread; the result arrives on the receiver.
+pub fn spawn_lookup(
+    source: ExampleSource,
+    tatoeba_file: String,
+    language: String,
+    word: String,
+) -> Receiver<Result<Vec<Example>, String>> {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        let result = match source {
+            ExampleSource::Tatoeba => search_tatoeba(&tatoeba_file, &language, &word),
+            ExampleSource::ImmersionKit => search_immersion_kit(&word),
+        };
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tatoeba_line() {
+        assert_eq!(parse_tatoeba_line("4704\tjpn\t今日は\tいい天気"), Some(("jpn", "今日は\tいい天気")));
+        assert_eq!(parse_tatoeba_line("4704\tjpn"), None);
+    }
+
+    #[test]
+    fn test_parse_immersion_kit() {
+        let response = serde_json::json!({
+            "data": [{
+                "examples": [
+                    {"sentence": "猫が好き", "translation": "I like cats", "deck_name": "Some Show"},
+                    {"translation": "missing sentence"}
+                ]
+            }]
+        });
+        assert_eq!(
+            parse_immersion_kit(&response),
+            vec![Example {
+                sentence: "猫が好き".to_string(),
+                translation: Some("I like cats".to_string()),
+                origin: Some("Some Show".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_immersion_kit_unexpected_shape() {
+        assert!(parse_immersion_kit(&serde_json::json!({"error": "nope"})).is_empty());
+    }
+}
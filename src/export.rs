@@ -0,0 +1,541 @@
+//! Transcript export to subtitle files, to bilingual study sheets when
+//! lines have translations, and to notes for Obsidian and Logseq vaults.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ass;
+use crate::session;
+use crate::stats::csv_field;
+use crate::translation;
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    #[default]
+    Srt,
+    Ass,
+    /// SRT with each cue's translation on the line below it.
+    BilingualSrt,
+    /// Two columns, original and translation.
+    Csv,
+    /// A readable transcript with a timestamp per line.
+    Markdown,
+    /// A note with YAML frontmatter and a bullet per line.
+    Obsidian,
+    /// A page with Logseq properties and a block per line.
+    Logseq,
+}
+
+impl Format {
+    pub const ALL: [Format; 7] = [
+        Format::Srt,
+        Format::Ass,
+        Format::BilingualSrt,
+        Format::Csv,
+        Format::Markdown,
+        Format::Obsidian,
+        Format::Logseq,
+    ];
+
+    /// The formats that make notes for a vault.
+    pub const NOTES: [Format; 2] = [Format::Obsidian, Format::Logseq];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Srt => "SRT",
+            Format::Ass => "ASS",
+            Format::BilingualSrt => "bilingual SRT",
+            Format::Csv => "bilingual CSV",
+            Format::Markdown => "Markdown",
+            Format::Obsidian => "Obsidian note",
+            Format::Logseq => "Logseq page",
+        }
+    }
+
+    /// Everything after the stem of the file name. Notes saved to a vault
+    /// are plain `.md`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Srt => "srt",
+            Format::Ass => "ass",
+            Format::BilingualSrt => "bilingual.srt",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+            Format::Obsidian => "obsidian.md",
+            Format::Logseq => "logseq.md",
+        }
+    }
+
+    /// Whether the format pairs lines with their translations.
+    pub fn is_bilingual(self) -> bool {
+        matches!(self, Format::BilingualSrt | Format::Csv)
+    }
+}
+
+/// Exporting without being asked: when a session ends, and every so often
+/// while one goes on, so long streams are archived as they play.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoExport {
+    pub on_session_end: bool,
+    /// Minutes between exports during a session; 0 for none.
+    pub every_minutes: u32,
+    pub format: Format,
+    /// Where the files go; empty for the export directory.
+    pub dir: String,
+}
+
+impl Default for AutoExport {
+    fn default() -> Self {
+        Self {
+            on_session_end: false,
+            every_minutes: 0,
+            format: Format::Srt,
+            dir: String::new(),
+        }
+    }
+}
+
+/// How missing end times are filled in before export.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CueTiming {
+    /// Fill in missing end times at all; when off, such cues get `max_duration`.
+    pub synthesize: bool,
+    /// Seconds left between a cue's end and the next cue's start.
+    pub gap: f64,
+    pub min_duration: f64,
+    pub max_duration: f64,
+}
+
+impl Default for CueTiming {
+    fn default() -> Self {
+        Self {
+            synthesize: true,
+            gap: 0.1,
+            min_duration: 1.0,
+            max_duration: 7.0,
+        }
+    }
+}
+
+/// Give every cue an end time: the next cue's start minus `gap`, clamped to
+/// the duration limits. Cues that already have one are left alone.
+pub fn synthesize_end_times(entries: &mut [SubtitleEntry], timing: &CueTiming) {
+    for i in 0..entries.len() {
+        if entries[i].end_time.is_some() {
+            continue;
+        }
+        let start = entries[i].start_time;
+        let next_start = entries[i + 1..]
+            .iter()
+            .map(|next| next.start_time)
+            .find(|&next| next > start);
+        let duration = match next_start {
+            Some(next) if timing.synthesize => (next - timing.gap - start).clamp(timing.min_duration, timing.max_duration),
+            _ => timing.max_duration,
+        };
+        entries[i].end_time = Some(start + duration);
+    }
+}
+
+fn srt_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// SRT text for entries that all have end times.
+pub fn to_srt(entries: &[SubtitleEntry]) -> String {
+    let mut srt = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let end = entry.end_time.unwrap_or(entry.start_time);
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            srt_time(entry.start_time),
+            srt_time(end),
+            entry.text.trim()
+        ));
+    }
+    srt
+}
+
+/// SRT with translations under the lines they translate.
+pub fn to_bilingual_srt(entries: &[SubtitleEntry]) -> String {
+    let cues: Vec<SubtitleEntry> = entries
+        .iter()
+        .map(|entry| match &entry.translation {
+            Some(translation) => SubtitleEntry {
+                text: format!("{}\n{}", entry.text.trim(), translation.trim()),
+                ..entry.clone()
+            },
+            None => entry.clone(),
+        })
+        .collect();
+    to_srt(&cues)
+}
+
+/// An `original,translation` row per line.
+pub fn to_csv(entries: &[SubtitleEntry]) -> String {
+    let mut csv = String::from("original,translation\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{}\n",
+            csv_field(entry.text.trim()),
+            csv_field(entry.translation.as_deref().unwrap_or("").trim())
+        ));
+    }
+    csv
+}
+
+/// A heading and a `` `[hh:mm:ss]` text `` line per cue.
+pub fn to_markdown(title: &str, entries: &[SubtitleEntry]) -> String {
+    let mut markdown = format!("# {}\n\n", title);
+    for entry in entries {
+        let text = entry.text.trim().replace('\n', " ");
+        markdown.push_str(&format!("`[{}]` {}  \n", crate::format_timestamp(entry.start_time), text));
+    }
+    markdown
+}
+
+/// What a note says about where its lines are from: the media title (else
+/// `title`), the day they were watched and the media file.
+fn note_fields(title: &str, entries: &[SubtitleEntry]) -> (String, String, String) {
+    let first = entries.first();
+    let name = first.and_then(|entry| entry.title.clone()).unwrap_or_else(|| title.to_string());
+    let date = first
+        .and_then(|entry| chrono::DateTime::from_timestamp(entry.timestamp, 0))
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let source = first.and_then(|entry| entry.path.clone()).unwrap_or_default();
+    (name, date, source)
+}
+
+/// YAML frontmatter (title, date, source, tags) and a
+/// `` - `[m:ss.s]` text `` bullet per cue.
+pub fn to_obsidian(title: &str, entries: &[SubtitleEntry]) -> String {
+    let (name, date, source) = note_fields(title, entries);
+    // JSON strings are valid YAML, quotes and all
+    let quote = |value: &str| serde_json::Value::from(value).to_string();
+    let mut note = format!(
+        "---\ntitle: {}\ndate: {}\nsource: {}\ntags: [scriptview, transcript]\n---\n\n",
+        quote(&name),
+        date,
+        quote(&source)
+    );
+    for entry in entries {
+        let text = entry.text.trim().replace('\n', " ");
+        note.push_str(&format!("- `[{}]` {}\n", crate::format_timestamp(entry.start_time), text));
+    }
+    note
+}
+
+/// Page properties (title, date, source, tags) in the first block and a
+/// block per cue.
+pub fn to_logseq(title: &str, entries: &[SubtitleEntry]) -> String {
+    let (name, date, source) = note_fields(title, entries);
+    let mut page = format!("title:: {}\ndate:: {}\n", name.replace('\n', " "), date);
+    if !source.is_empty() {
+        page.push_str(&format!("source:: {}\n", source));
+    }
+    page.push_str("tags:: scriptview, transcript\n\n");
+    for entry in entries {
+        let text = entry.text.trim().replace('\n', " ");
+        page.push_str(&format!("- `{}` {}\n", crate::format_timestamp(entry.start_time), text));
+    }
+    page
+}
+
+/// File name (without extension) for one file's transcript.
+fn export_stem(group: &session::MediaGroup) -> String {
+    let name = group
+        .path
+        .as_deref()
+        .and_then(|path| std::path::Path::new(path).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("transcript-{}", chrono::Local::now().format("%Y-%m-%d")));
+    name.replace(['/', '\\'], "_")
+}
+
+fn render(format: Format, title: &str, ass_header: Option<&str>, cues: &[SubtitleEntry]) -> String {
+    match format {
+        Format::Srt => to_srt(cues),
+        Format::Ass => ass::to_ass(ass_header, cues),
+        Format::BilingualSrt => to_bilingual_srt(cues),
+        Format::Csv => to_csv(cues),
+        Format::Markdown => to_markdown(title, cues),
+        Format::Obsidian => to_obsidian(title, cues),
+        Format::Logseq => to_logseq(title, cues),
+    }
+}
+
+/// The lines to export in `format`: with translations attached if it's a
+/// bilingual one.
+fn prepare(entries: &[SubtitleEntry], format: Format) -> Vec<SubtitleEntry> {
+    if format.is_bilingual() {
+        translation::attach(entries)
+    } else {
+        entries.to_vec()
+    }
+}
+
+/// Write one file per media file in a captured transcript's timeline,
+/// returning the paths written.
+pub fn export_transcript(
+    entries: &[SubtitleEntry],
+    format: Format,
+    dir: &str,
+    timing: &CueTiming,
+) -> Result<Vec<String>, std::io::Error> {
+    write_transcript(entries, format, dir, timing, |stem| format!("{}/{}.{}", dir, stem, format.extension()))
+}
+
+/// Export `entries` as `auto` says, into its directory or else
+/// `export_dir`. Nothing is written without lines.
+pub fn auto_export(
+    entries: &[SubtitleEntry],
+    auto: &AutoExport,
+    export_dir: &str,
+    timing: &CueTiming,
+) -> Result<Vec<String>, std::io::Error> {
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dir = if auto.dir.trim().is_empty() { export_dir } else { &auto.dir };
+    export_transcript(entries, auto.format, dir, timing)
+}
+
+/// The shutdown step for closing the viewer, which ends its session too:
+/// export as on any other session end, if that's on.
+pub fn export_on_close(
+    entries: &[SubtitleEntry],
+    auto: &AutoExport,
+    export_dir: &str,
+    timing: &CueTiming,
+) -> Result<(), String> {
+    if !auto.on_session_end {
+        return Ok(());
+    }
+    auto_export(entries, auto, export_dir, timing).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Save a note per media file into a vault, as `<vault>/<stem>.md`. A note
+/// already there is never replaced, since it may have been edited: the new
+/// one is `<stem> YYYY-MM-DD.md` instead, numbered if that's taken too, and
+/// comes back with `true`.
+pub fn save_to_vault(
+    entries: &[SubtitleEntry],
+    format: Format,
+    vault: &str,
+    timing: &CueTiming,
+) -> Result<Vec<(String, bool)>, std::io::Error> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut renamed = Vec::new();
+    let paths = write_transcript(entries, format, vault, timing, |stem| {
+        let path = vault_path(vault, stem, &today);
+        renamed.push(path != format!("{}/{}.md", vault, stem));
+        path
+    })?;
+    Ok(paths.into_iter().zip(renamed).collect())
+}
+
+/// The first of `<stem>.md`, `<stem> <date>.md`, `<stem> <date> 2.md` and
+/// so on that isn't in `vault` yet.
+fn vault_path(vault: &str, stem: &str, date: &str) -> String {
+    let dated = format!("{} {}", stem, date);
+    let numbered = (2..).map(|n| format!("{} {}", dated, n));
+    [stem.to_string(), dated.clone()]
+        .into_iter()
+        .chain(numbered)
+        .map(|name| format!("{}/{}.md", vault, name))
+        .find(|path| !std::path::Path::new(path).exists())
+        .unwrap()
+}
+
+fn write_transcript(
+    entries: &[SubtitleEntry],
+    format: Format,
+    dir: &str,
+    timing: &CueTiming,
+    mut path_for: impl FnMut(&str) -> String,
+) -> Result<Vec<String>, std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+    let mut timeline = session::timeline(&prepare(entries, format));
+    let mut written = Vec::new();
+    for group in session::media_groups(&timeline) {
+        let cues = &mut timeline[group.range.clone()];
+        synthesize_end_times(cues, timing);
+        let stem = export_stem(&group);
+        let path = path_for(&stem);
+        std::fs::write(&path, render(format, &stem, None, cues))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Write an imported file back out as it is, keeping its ASS header if it had one.
+pub fn export_file(
+    entries: &[SubtitleEntry],
+    format: Format,
+    ass_header: Option<&str>,
+    path: &str,
+    timing: &CueTiming,
+) -> Result<(), std::io::Error> {
+    let mut cues = prepare(entries, format);
+    synthesize_end_times(&mut cues, timing);
+    let title = std::path::Path::new(path).file_stem().unwrap_or_default().to_string_lossy();
+    std::fs::write(path, render(format, &title, ass_header, &cues))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_time: f64, end_time: Option<f64>) -> SubtitleEntry {
+        SubtitleEntry {
+            text: format!("at {}", start_time),
+            start_time,
+            end_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_synthesize_end_times_clamps() {
+        let mut entries = vec![cue(0.0, None), cue(3.0, None), cue(3.5, None), cue(30.0, Some(31.0)), cue(40.0, None)];
+        synthesize_end_times(&mut entries, &CueTiming::default());
+        let ends: Vec<f64> = entries.iter().map(|e| e.end_time.unwrap()).collect();
+        assert_eq!(ends, vec![2.9, 4.0, 10.5, 31.0, 47.0]);
+    }
+
+    #[test]
+    fn test_synthesize_disabled_uses_max_duration() {
+        let mut entries = vec![cue(0.0, None), cue(3.0, None)];
+        let timing = CueTiming {
+            synthesize: false,
+            ..Default::default()
+        };
+        synthesize_end_times(&mut entries, &timing);
+        assert_eq!(entries[0].end_time, Some(7.0));
+    }
+
+    #[test]
+    fn test_to_srt() {
+        let mut entry = cue(3661.5, Some(3663.25));
+        entry.text = "こんにちは\n".to_string();
+        assert_eq!(to_srt(&[entry]), "1\n01:01:01,500 --> 01:01:03,250\nこんにちは\n\n");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let mut entry = cue(62.0, Some(63.0));
+        entry.text = "猫だ\nよ".to_string();
+        assert_eq!(to_markdown("show", &[entry]), "# show\n\n`[1:02.0]` 猫だ よ  \n");
+    }
+
+    #[test]
+    fn test_notes() {
+        let mut entry = cue(62.0, Some(63.0));
+        entry.text = "猫だ\nよ".to_string();
+        entry.title = Some("猫の \"日\"".to_string());
+        entry.path = Some("/videos/cats-01.mkv".to_string());
+        let note = to_obsidian("cats-01", &[entry.clone()]);
+        assert!(note.starts_with("---\ntitle: \"猫の \\\"日\\\"\"\ndate: "));
+        assert!(note.ends_with("source: \"/videos/cats-01.mkv\"\ntags: [scriptview, transcript]\n---\n\n- `[1:02.0]` 猫だ よ\n"));
+        let page = to_logseq("cats-01", &[entry]);
+        assert!(page.starts_with("title:: 猫の \"日\"\ndate:: "));
+        assert!(page.ends_with("source:: /videos/cats-01.mkv\ntags:: scriptview, transcript\n\n- `1:02.0` 猫だ よ\n"));
+    }
+
+    #[test]
+    fn test_export_on_close() {
+        let dir = std::env::temp_dir().join(format!("scriptview-close-export-{}", std::process::id()));
+        let auto = AutoExport {
+            on_session_end: true,
+            dir: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let mut shutdown = crate::shutdown::ShutdownCoordinator::<Vec<SubtitleEntry>>::default();
+        shutdown.register("auto-export", move |entries| {
+            export_on_close(entries, &auto, "/nonexistent", &CueTiming::default())
+        });
+        let mut entry = cue(1.0, Some(2.0));
+        entry.path = Some("/videos/cats-01.mkv".to_string());
+        assert!(shutdown.run(&mut vec![entry]).is_empty());
+        assert!(dir.join("cats-01.srt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_to_vault_keeps_notes() {
+        let vault = std::env::temp_dir().join(format!("scriptview-vault-{}", std::process::id()));
+        let vault = vault.to_string_lossy().into_owned();
+        let mut entry = cue(1.0, Some(2.0));
+        entry.path = Some("/videos/cats-01.mkv".to_string());
+        let entries = [entry];
+        let save = || save_to_vault(&entries, Format::Obsidian, &vault, &CueTiming::default()).unwrap();
+
+        assert_eq!(save(), vec![(format!("{}/cats-01.md", vault), false)]);
+        std::fs::write(format!("{}/cats-01.md", vault), "My notes").unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d");
+        assert_eq!(save(), vec![(format!("{}/cats-01 {}.md", vault, today), true)]);
+        assert_eq!(save(), vec![(format!("{}/cats-01 {} 2.md", vault, today), true)]);
+        assert_eq!(std::fs::read_to_string(format!("{}/cats-01.md", vault)).unwrap(), "My notes");
+        let _ = std::fs::remove_dir_all(&vault);
+    }
+
+    /// Export `testdata/export/session.json` in every format and compare it
+    /// with the files next to it. Run with `SCRIPTVIEW_UPDATE_GOLDEN=1` to
+    /// write them afresh after an intended change, then review the diff.
+    #[test]
+    fn test_golden_files() {
+        let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/export");
+        let entries = session::read_entries(&format!("{}/session.json", golden)).unwrap();
+        let dir = std::env::temp_dir().join(format!("scriptview-golden-{}", std::process::id()));
+        let dir = dir.to_string_lossy().into_owned();
+        let update = std::env::var_os("SCRIPTVIEW_UPDATE_GOLDEN").is_some();
+        let mut mismatched = Vec::new();
+        for format in Format::ALL {
+            let written = export_transcript(&entries, format, &dir, &CueTiming::default()).unwrap();
+            assert_eq!(written.len(), 2, "{} should have a file per video", format.label());
+            for path in written {
+                let name = std::path::Path::new(&path).file_name().unwrap().to_string_lossy().into_owned();
+                let actual = std::fs::read_to_string(&path).unwrap();
+                let expected_path = format!("{}/{}", golden, name);
+                if update {
+                    std::fs::write(&expected_path, &actual).unwrap();
+                } else if std::fs::read_to_string(&expected_path).ok().as_deref() != Some(actual.as_str()) {
+                    eprintln!("{} differs from the golden file; it was:\n{}", name, actual);
+                    mismatched.push(name);
+                }
+            }
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(mismatched.is_empty(), "exports differ from testdata/export: {:?}", mismatched);
+    }
+
+    #[test]
+    fn test_bilingual_layouts() {
+        let mut cat = cue(1.0, Some(2.0));
+        cat.text = "猫だ".to_string();
+        cat.translation = Some("It's a cat, \"really\"".to_string());
+        let mut dog = cue(3.0, Some(4.0));
+        dog.text = "犬".to_string();
+        let cues = [cat, dog];
+        assert_eq!(
+            to_bilingual_srt(&cues),
+            "1\n00:00:01,000 --> 00:00:02,000\n猫だ\nIt's a cat, \"really\"\n\n2\n00:00:03,000 --> 00:00:04,000\n犬\n\n"
+        );
+        assert_eq!(
+            to_csv(&cues),
+            "original,translation\n猫だ,\"It's a cat, \"\"really\"\"\"\n犬,\n"
+        );
+    }
+}
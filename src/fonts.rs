@@ -0,0 +1,49 @@
+//! Extra fonts. egui's built-in fonts only cover Latin, Greek and Cyrillic,
+//! so scripts such as Japanese, Arabic or Hebrew need a font file from the
+//! system.
+
+use eframe::egui;
+
+fn read_font(path: &str) -> Result<egui::FontData, String> {
+    std::fs::read(path)
+        .map(egui::FontData::from_owned)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Install egui's default fonts, with `primary` (if given) in front of them
+/// for proportional text and `fallback` (if set) behind them for any
+/// characters they lack. Fonts that can't be read are skipped and reported.
+pub fn install(ctx: &egui::Context, fallback: &str, primary: Option<&str>) -> Result<(), String> {
+    let mut fonts = egui::FontDefinitions::default();
+    let mut errors = Vec::new();
+    if let Some(primary) = primary.filter(|p| !p.is_empty()) {
+        match read_font(primary) {
+            Ok(data) => {
+                fonts.font_data.insert("primary".to_string(), data);
+                fonts
+                    .families
+                    .entry(egui::FontFamily::Proportional)
+                    .or_default()
+                    .insert(0, "primary".to_string());
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    if !fallback.is_empty() {
+        match read_font(fallback) {
+            Ok(data) => {
+                fonts.font_data.insert("fallback".to_string(), data);
+                for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+                    fonts.families.entry(family).or_default().push("fallback".to_string());
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    ctx.set_fonts(fonts);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
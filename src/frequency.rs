@@ -0,0 +1,230 @@
+//! Word frequency lists, used to tint subtitle words by how common they are.
+//!
+//! Lines are split into tokens with the list itself as the dictionary: words
+//! in space-separated scripts are looked up whole, while runs of CJK text are
+//! segmented by greedy longest match against the list.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use eframe::egui::Color32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Band {
+    Common,
+    Frequent,
+    Uncommon,
+    Rare,
+    Unknown,
+}
+
+impl Band {
+    pub const ALL: [Band; 5] = [Band::Common, Band::Frequent, Band::Uncommon, Band::Rare, Band::Unknown];
+
+    pub fn from_rank(rank: Option<usize>) -> Self {
+        match rank {
+            Some(r) if r <= 1500 => Band::Common,
+            Some(r) if r <= 5000 => Band::Frequent,
+            Some(r) if r <= 15000 => Band::Uncommon,
+            Some(_) => Band::Rare,
+            None => Band::Unknown,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Band::Common => "common",
+            Band::Frequent => "top 5k",
+            Band::Uncommon => "top 15k",
+            Band::Rare => "rare",
+            Band::Unknown => "not listed",
+        }
+    }
+
+    /// Tint for the band; `None` leaves common words in the normal text color.
+    pub fn color(self) -> Option<Color32> {
+        match self {
+            Band::Common => None,
+            Band::Frequent => Some(Color32::from_rgb(120, 180, 255)),
+            Band::Uncommon => Some(Color32::from_rgb(230, 200, 90)),
+            Band::Rare => Some(Color32::from_rgb(240, 140, 60)),
+            Band::Unknown => Some(Color32::from_rgb(230, 90, 90)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub range: Range<usize>,
+    /// `None` for whitespace and punctuation.
+    pub band: Option<Band>,
+}
+
+#[derive(Debug, Default)]
+pub struct FrequencyList {
+    ranks: HashMap<String, usize>,
+    /// Longest entry in characters, bounding the CJK longest-match search.
+    max_chars: usize,
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+    )
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
+
+impl FrequencyList {
+    /// Parse a list with one word per line, most frequent first. Extra
+    /// numeric columns (`word 1234` or `12\tword`) are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut list = Self::default();
+        for line in content.lines() {
+            let Some(word) = line
+                .split_whitespace()
+                .find(|field| !field.chars().all(|c| c.is_ascii_digit() || c == '.'))
+            else {
+                continue;
+            };
+            let word = word.to_lowercase();
+            let rank = list.ranks.len() + 1;
+            list.max_chars = list.max_chars.max(word.chars().count());
+            list.ranks.entry(word).or_insert(rank);
+        }
+        list
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn rank(&self, word: &str) -> Option<usize> {
+        self.ranks.get(&word.to_lowercase()).copied()
+    }
+
+    /// Split `text` into tokens covering all of it.
+    pub fn tokenize(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let byte_at = |i: usize| chars.get(i).map_or(text.len(), |(b, _)| *b);
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i].1;
+            let start = i;
+            if is_cjk(c) {
+                while i < chars.len() && is_cjk(chars[i].1) {
+                    i += 1;
+                }
+                self.segment_cjk(text, &chars, start, i, &mut tokens);
+                continue;
+            }
+            let word = is_word_char(c);
+            while i < chars.len() && !is_cjk(chars[i].1) && is_word_char(chars[i].1) == word {
+                i += 1;
+            }
+            let range = byte_at(start)..byte_at(i);
+            let band = word.then(|| Band::from_rank(self.rank(&text[range.clone()])));
+            tokens.push(Token { range, band });
+        }
+        tokens
+    }
+
+    fn segment_cjk(&self, text: &str, chars: &[(usize, char)], start: usize, end: usize, tokens: &mut Vec<Token>) {
+        let byte_at = |i: usize| chars.get(i).map_or(text.len(), |(b, _)| *b);
+        let mut i = start;
+        while i < end {
+            let longest = (i + 1..=end.min(i + self.max_chars))
+                .rev()
+                .find_map(|j| self.rank(&text[byte_at(i)..byte_at(j)]).map(|rank| (j, rank)));
+            let (j, rank) = match longest {
+                Some((j, rank)) => (j, Some(rank)),
+                None => (i + 1, None),
+            };
+            tokens.push(Token {
+                range: byte_at(i)..byte_at(j),
+                band: Some(Band::from_rank(rank)),
+            });
+            i = j;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words<'a>(text: &'a str, tokens: &[Token]) -> Vec<(&'a str, Option<Band>)> {
+        tokens.iter().map(|t| (&text[t.range.clone()], t.band)).collect()
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let list = FrequencyList::parse("the\nof 123\n3\tand\n\n");
+        assert_eq!(list.rank("the"), Some(1));
+        assert_eq!(list.rank("of"), Some(2));
+        assert_eq!(list.rank("AND"), Some(3));
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn test_band_thresholds() {
+        assert_eq!(Band::from_rank(Some(1)), Band::Common);
+        assert_eq!(Band::from_rank(Some(1501)), Band::Frequent);
+        assert_eq!(Band::from_rank(Some(15000)), Band::Uncommon);
+        assert_eq!(Band::from_rank(Some(15001)), Band::Rare);
+        assert_eq!(Band::from_rank(None), Band::Unknown);
+    }
+
+    #[test]
+    fn test_tokenize_space_separated() {
+        let list = FrequencyList::parse("the\ncat");
+        let text = "The cat, purring.";
+        assert_eq!(
+            words(text, &list.tokenize(text)),
+            vec![
+                ("The", Some(Band::Common)),
+                (" ", None),
+                ("cat", Some(Band::Common)),
+                (", ", None),
+                ("purring", Some(Band::Unknown)),
+                (".", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_cjk_longest_match() {
+        let list = FrequencyList::parse("は\n今日\n今\nいい天気");
+        let text = "今日はいい天気だ";
+        assert_eq!(
+            words(text, &list.tokenize(text)),
+            vec![
+                ("今日", Some(Band::Common)),
+                ("は", Some(Band::Common)),
+                ("いい天気", Some(Band::Common)),
+                ("だ", Some(Band::Unknown)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_covers_text() {
+        let list = FrequencyList::default();
+        let text = "混ぜた text 123!";
+        let tokens = list.tokenize(text);
+        let joined: String = tokens.iter().map(|t| &text[t.range.clone()]).collect();
+        assert_eq!(joined, text);
+    }
+}
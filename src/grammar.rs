@@ -0,0 +1,377 @@
+//! User-supplied grammar patterns, matched against subtitle lines.
+//!
+//! Patterns are written the way textbooks list them, e.g. `〜ばかりでなく` or
+//! `〜ば〜ほど`: each `〜` (or `~`) stands for "any text", so a pattern is a
+//! sequence of literal segments that must appear in order within the line.
+//! A segment has to stand on its own: in spaced scripts it can't be part of
+//! a longer word, and a lone kana such as `ば` or `て` has to end a run of
+//! kana, so it isn't found at the start of `ばかり` or `てる`.
+//!
+//! The file is a JSON list, or the same list in YAML when it ends in
+//! `.yaml` or `.yml`. Only the YAML such a list needs is read: items of
+//! `key: value` lines with plain or quoted values on one line, and comments.
+
+use std::ops::Range;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::frequency::is_cjk;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrammarPattern {
+    pub pattern: String,
+    pub meaning: String,
+    #[serde(default)]
+    pub example: Option<String>,
+}
+
+impl GrammarPattern {
+    pub fn tooltip(&self) -> String {
+        match &self.example {
+            Some(example) => format!("{}\n{}\n\n{}", self.pattern, self.meaning, example),
+            None => format!("{}\n{}", self.pattern, self.meaning),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GrammarDb {
+    patterns: Vec<(Vec<String>, GrammarPattern)>,
+}
+
+/// A stretch of a line, tagged with the pattern it belongs to (if any).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub range: Range<usize>,
+    pub pattern: Option<usize>,
+}
+
+fn split_segments(pattern: &str) -> Vec<String> {
+    pattern
+        .split(['〜', '~', '～'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+/// Whether the segment at `range` of `text` stands on its own rather than
+/// being part of a longer word.
+fn at_boundary(text: &str, range: &Range<usize>) -> bool {
+    let segment = &text[range.clone()];
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    let spaced = |c: char| c.is_alphanumeric() && !is_cjk(c);
+    if segment.chars().next().is_some_and(spaced) && before.is_some_and(spaced) {
+        return false;
+    }
+    if segment.chars().next_back().is_some_and(spaced) && after.is_some_and(spaced) {
+        return false;
+    }
+    let mut chars = segment.chars();
+    let lone_kana = chars.next().is_some_and(is_kana) && chars.next().is_none();
+    !(lone_kana && after.is_some_and(is_kana))
+}
+
+/// The first place from byte `from` on where `segment` stands on its own.
+fn find_segment(text: &str, segment: &str, from: usize) -> Option<Range<usize>> {
+    text[from..]
+        .match_indices(segment)
+        .map(|(offset, _)| from + offset..from + offset + segment.len())
+        .find(|range| at_boundary(text, range))
+}
+
+/// Byte ranges of `segments` found in order in `text`, starting from the
+/// leftmost occurrence of the first segment that allows a complete match.
+fn match_segments(text: &str, segments: &[String]) -> Option<Vec<Range<usize>>> {
+    let first = segments.first()?;
+    let mut from = 0;
+    while let Some(range) = find_segment(text, first, from) {
+        from = range.start + first.len();
+        let mut pos = range.end;
+        let mut ranges = Vec::with_capacity(segments.len());
+        ranges.push(range);
+        let complete = segments[1..].iter().all(|segment| match find_segment(text, segment, pos) {
+            Some(range) => {
+                pos = range.end;
+                ranges.push(range);
+                true
+            }
+            None => false,
+        });
+        if complete {
+            return Some(ranges);
+        }
+    }
+    None
+}
+
+/// A YAML list of mappings, as JSON.
+fn parse_yaml(content: &str) -> Result<Value, String> {
+    let mut items: Vec<Map<String, Value>> = Vec::new();
+    // Where the keys of the current item start
+    let mut key_column = None;
+    for (number, line) in content.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {}", number + 1, message);
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') || text == "---" {
+            continue;
+        }
+        let column = line.len() - line.trim_start().len();
+        let entry = match text.strip_prefix('-').filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+            Some(rest) => {
+                items.push(Map::new());
+                let key = rest.trim_start();
+                key_column = Some(column + 1 + rest.len() - key.len());
+                if key.is_empty() {
+                    continue;
+                }
+                key
+            }
+            None if key_column == Some(column) => text,
+            None => return Err(error("expected `- ` starting an item, or a key lined up with the item's")),
+        };
+        let (key, value) = match entry.split_once(": ") {
+            Some((key, value)) => (key, value),
+            None => match entry.strip_suffix(':') {
+                Some(key) => (key, ""),
+                None => return Err(error("expected `key: value`")),
+            },
+        };
+        let value = yaml_scalar(value).map_err(|e| error(&e))?;
+        items.last_mut().unwrap().insert(key.trim().to_string(), value);
+    }
+    Ok(Value::Array(items.into_iter().map(Value::Object).collect()))
+}
+
+/// A one-line YAML value: quoted, plain up to a comment, or null.
+fn yaml_scalar(value: &str) -> Result<Value, String> {
+    let value = value.trim();
+    let (text, rest) = match value.chars().next() {
+        Some('"') => {
+            let mut text = String::new();
+            let mut chars = value[1..].chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some(c @ ('"' | '\\' | '/')) => text.push(c),
+                        Some(c) => return Err(format!("unknown escape `\\{}`", c)),
+                        None => return Err("unclosed quote".to_string()),
+                    },
+                    Some(c) => text.push(c),
+                    None => return Err("unclosed quote".to_string()),
+                }
+            }
+            (text, chars.as_str())
+        }
+        Some('\'') => {
+            let mut text = String::new();
+            let mut rest = &value[1..];
+            loop {
+                let Some(end) = rest.find('\'') else {
+                    return Err("unclosed quote".to_string());
+                };
+                text.push_str(&rest[..end]);
+                rest = &rest[end + 1..];
+                // Doubled, it's a quote
+                match rest.strip_prefix('\'') {
+                    Some(after) => {
+                        text.push('\'');
+                        rest = after;
+                    }
+                    None => break,
+                }
+            }
+            (text, rest)
+        }
+        Some('|' | '>') => return Err("block text (`|` and `>`) isn't read; put the value on one line".to_string()),
+        _ => {
+            let plain = value.split(" #").next().unwrap_or_default().trim_end();
+            return Ok(match plain {
+                "" | "~" | "null" => Value::Null,
+                plain => Value::String(plain.to_string()),
+            });
+        }
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("text after the closing quote".to_string());
+    }
+    Ok(Value::String(text))
+}
+
+impl GrammarDb {
+    pub fn from_patterns(patterns: Vec<GrammarPattern>) -> Self {
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|p| (split_segments(&p.pattern), p))
+                .filter(|(segments, _)| !segments.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let patterns: Vec<GrammarPattern> = if path.ends_with(".yaml") || path.ends_with(".yml") {
+            serde_json::from_value(parse_yaml(&content)?).map_err(|e| e.to_string())?
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())?
+        };
+        Ok(Self::from_patterns(patterns))
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn pattern(&self, index: usize) -> &GrammarPattern {
+        &self.patterns[index].1
+    }
+
+    /// Split `text` into spans, marking the segments of matched patterns.
+    /// Longer patterns win when matches overlap.
+    pub fn annotate(&self, text: &str) -> Vec<Span> {
+        let mut claimed: Vec<(Range<usize>, usize)> = Vec::new();
+        let mut order: Vec<usize> = (0..self.patterns.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.patterns[i].0.iter().map(String::len).sum::<usize>()));
+        for index in order {
+            let Some(ranges) = match_segments(text, &self.patterns[index].0) else {
+                continue;
+            };
+            let overlaps = ranges
+                .iter()
+                .any(|r| claimed.iter().any(|(c, _)| r.start < c.end && c.start < r.end));
+            if !overlaps {
+                claimed.extend(ranges.into_iter().map(|r| (r, index)));
+            }
+        }
+        claimed.sort_by_key(|(r, _)| r.start);
+
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        for (range, index) in claimed {
+            if range.start > pos {
+                spans.push(Span { range: pos..range.start, pattern: None });
+            }
+            pos = range.end;
+            spans.push(Span { range, pattern: Some(index) });
+        }
+        if pos < text.len() {
+            spans.push(Span { range: pos..text.len(), pattern: None });
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(patterns: &[&str]) -> GrammarDb {
+        GrammarDb::from_patterns(
+            patterns
+                .iter()
+                .map(|p| GrammarPattern {
+                    pattern: p.to_string(),
+                    meaning: String::new(),
+                    example: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_split_segments() {
+        assert_eq!(split_segments("〜ばかりでなく"), vec!["ばかりでなく"]);
+        assert_eq!(split_segments("〜ば〜ほど"), vec!["ば", "ほど"]);
+        assert_eq!(split_segments("not only ~ but also"), vec!["not only", "but also"]);
+    }
+
+    #[test]
+    fn test_annotate_single_segment() {
+        let text = "彼は歌ばかりでなく踊りも上手だ";
+        let spans = db(&["〜ばかりでなく"]).annotate(text);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(&text[spans[1].range.clone()], "ばかりでなく");
+        assert_eq!(spans[1].pattern, Some(0));
+        assert_eq!(spans[0].pattern, None);
+    }
+
+    #[test]
+    fn test_annotate_segments_in_order() {
+        let text = "読めば読むほど面白い";
+        let spans = db(&["〜ば〜ほど"]).annotate(text);
+        let marked: Vec<&str> = spans
+            .iter()
+            .filter(|s| s.pattern.is_some())
+            .map(|s| &text[s.range.clone()])
+            .collect();
+        assert_eq!(marked, vec!["ば", "ほど"]);
+    }
+
+    #[test]
+    fn test_annotate_requires_order() {
+        let spans = db(&["〜ば〜ほど"]).annotate("ほどほどにすれば");
+        assert!(spans.iter().all(|s| s.pattern.is_none()));
+    }
+
+    #[test]
+    fn test_longer_pattern_wins_overlap() {
+        let text = "食べたばかりだ";
+        let spans = db(&["〜ばかり", "〜たばかり"]).annotate(text);
+        let marked: Vec<_> = spans.iter().filter(|s| s.pattern.is_some()).collect();
+        assert_eq!(marked.len(), 1);
+        assert_eq!(marked[0].pattern, Some(1));
+    }
+
+    #[test]
+    fn test_segments_stand_on_their_own() {
+        let marked = |pattern: &str, text: &str| {
+            let spans = db(&[pattern]).annotate(text);
+            spans.iter().filter(|s| s.pattern.is_some()).map(|s| text[s.range.clone()].to_string()).collect::<Vec<_>>()
+        };
+        assert!(marked("〜ば", "ばかりだ").is_empty());
+        assert_eq!(marked("〜ば", "ばかり言えば"), vec!["ば"]);
+        assert!(marked("〜て", "見てる").is_empty());
+        assert_eq!(marked("〜て", "待って！"), vec!["て"]);
+        assert!(marked("~ so ~", "it also works").is_empty());
+        assert_eq!(marked("~ so ~", "so it works"), vec!["so"]);
+    }
+
+    #[test]
+    fn test_load_yaml() {
+        let db = GrammarDb::load(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/grammar/patterns.yaml")).unwrap();
+        assert_eq!(db.len(), 3);
+        assert_eq!(db.pattern(0).meaning, "not only … but also");
+        assert_eq!(db.pattern(0).example.as_deref(), Some("彼は歌ばかりでなく踊りも上手だ"));
+        assert_eq!(db.pattern(1).pattern, "〜ば〜ほど");
+        assert_eq!(db.pattern(1).meaning, "the more … the more, or 'the -er'");
+        assert_eq!(db.pattern(2).meaning, "English, \"quoted\"");
+        assert_eq!(db.pattern(2).example, None);
+        let spans = db.annotate("読めば読むほど");
+        assert!(spans.iter().any(|span| span.pattern == Some(1)));
+    }
+
+    #[test]
+    fn test_yaml_errors_name_the_line() {
+        assert_eq!(parse_yaml("- pattern: 〜ば\nmeaning: if").unwrap_err().split(':').next(), Some("line 2"));
+        assert!(parse_yaml("- pattern: |\n    〜ば").unwrap_err().starts_with("line 1"));
+        assert!(parse_yaml("- meaning: \"if\n").is_err());
+        assert_eq!(parse_yaml("# nothing yet\n").unwrap(), Value::Array(Vec::new()));
+    }
+
+    #[test]
+    fn test_annotate_no_patterns() {
+        let spans = GrammarDb::default().annotate("hello");
+        assert_eq!(spans, vec![Span { range: 0..5, pattern: None }]);
+    }
+}
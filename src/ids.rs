@@ -0,0 +1,86 @@
+//! Stable entry IDs. The capture file is read whole on every change, so the
+//! same line comes back as a fresh value each time; `EntryIds` recognises it
+//! and gives it the ID it got the first time, while lines never seen before
+//! get the next number. Actions on a row refer to its entry by ID rather than
+//! position, which shifts as old lines drop off the front of the file.
+
+use std::collections::HashMap;
+
+use crate::SubtitleEntry;
+
+pub type EntryId = u64;
+
+/// Arrival time, media time, text, file and which repeat of those it is.
+type Key = (i64, u64, String, Option<String>, usize);
+
+/// Above this many remembered entries, those not in the latest read are forgotten.
+const MAX_KNOWN: usize = 10_000;
+
+#[derive(Debug, Default)]
+pub struct EntryIds {
+    last: EntryId,
+    known: HashMap<Key, EntryId>,
+}
+
+impl EntryIds {
+    /// Set the `id` of each entry, reusing the IDs of entries seen before.
+    pub fn assign(&mut self, entries: &mut [SubtitleEntry]) {
+        let mut occurrences: HashMap<(i64, u64, &str, Option<&str>), usize> = HashMap::new();
+        let mut keys = Vec::with_capacity(entries.len());
+        for entry in entries.iter() {
+            let seen = occurrences
+                .entry((entry.timestamp, entry.start_time.to_bits(), &entry.text, entry.path.as_deref()))
+                .or_default();
+            keys.push((
+                entry.timestamp,
+                entry.start_time.to_bits(),
+                entry.text.clone(),
+                entry.path.clone(),
+                *seen,
+            ));
+            *seen += 1;
+        }
+        for (entry, key) in entries.iter_mut().zip(&keys) {
+            entry.id = match self.known.get(key) {
+                Some(&id) => id,
+                None => {
+                    self.last += 1;
+                    self.known.insert(key.clone(), self.last);
+                    self.last
+                }
+            };
+        }
+        if self.known.len() > MAX_KNOWN {
+            let current: std::collections::HashSet<&Key> = keys.iter().collect();
+            self.known.retain(|key, _| current.contains(key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, timestamp: i64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ids_survive_reloads() {
+        let mut ids = EntryIds::default();
+        let mut first = vec![line("猫", 1), line("犬", 2), line("犬", 2)];
+        ids.assign(&mut first);
+        let first_ids: Vec<EntryId> = first.iter().map(|e| e.id).collect();
+        assert_eq!(first_ids, vec![1, 2, 3]);
+
+        // The oldest line dropped off the front and a new one arrived
+        let mut second = vec![line("犬", 2), line("犬", 2), line("鳥", 3)];
+        ids.assign(&mut second);
+        let second_ids: Vec<EntryId> = second.iter().map(|e| e.id).collect();
+        assert_eq!(second_ids, vec![2, 3, 4]);
+    }
+}
@@ -0,0 +1,51 @@
+//! Watch words: subtitles mentioning one are highlighted and announced with a
+//! desktop notification.
+
+use std::process::Command;
+
+/// Split a comma-separated list of watch words, ignoring blanks.
+pub fn parse(list: &str) -> Vec<String> {
+    list.split([',', '\n'])
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The first watch word found in `text`, ignoring case.
+pub fn find<'a>(text: &str, words: &'a [String]) -> Option<&'a str> {
+    let text = text.to_lowercase();
+    words.iter().find(|word| text.contains(word.as_str())).map(String::as_str)
+}
+
+/// Show a desktop notification through `notify-send`, on a thread that
+/// waits for it.
+pub fn notify(word: &str, text: &str) {
+    let mut command = Command::new("notify-send");
+    // A line starting with `-` isn't an option
+    command.args(["--app-name=ScriptView", "--", &format!("“{}” mentioned", word), text]);
+    std::thread::spawn(move || match command.status() {
+        Ok(status) if !status.success() => eprintln!("Warning: notify-send failed ({})", status),
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: Could not run notify-send: {}", e),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(" Tanaka, 猫 ,,\nbaseball "), vec!["tanaka", "猫", "baseball"]);
+        assert!(parse(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_find_ignores_case() {
+        let words = parse("tanaka, 猫");
+        assert_eq!(find("Where is TANAKA-san?", &words), Some("tanaka"));
+        assert_eq!(find("黒い猫", &words), Some("猫"));
+        assert_eq!(find("nothing here", &words), None);
+    }
+}
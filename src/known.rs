@@ -0,0 +1,94 @@
+//! Words the user has marked as known, kept per profile since each profile
+//! is usually a different language.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frequency::{Band, FrequencyList};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KnownWords {
+    /// Known words by profile name, lowercased.
+    pub profiles: BTreeMap<String, BTreeSet<String>>,
+}
+
+pub fn known_words_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/known-words.json", home_dir)
+}
+
+impl KnownWords {
+    pub fn load() -> Self {
+        std::fs::read_to_string(known_words_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let path = known_words_path();
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn is_known(&self, profile: &str, word: &str) -> bool {
+        self.profiles
+            .get(profile)
+            .is_some_and(|words| words.contains(&word.to_lowercase()))
+    }
+
+    /// Mark `word` known, or unknown again if it already was. Returns whether
+    /// it is now known.
+    pub fn toggle(&mut self, profile: &str, word: &str) -> bool {
+        let words = self.profiles.entry(profile.to_string()).or_default();
+        let word = word.to_lowercase();
+        if words.remove(&word) {
+            false
+        } else {
+            words.insert(word);
+            true
+        }
+    }
+
+    /// The distinct words in `text` that are neither marked known nor among
+    /// the frequency list's common words.
+    pub fn unknown_words<'a>(&self, profile: &str, text: &'a str, frequency: &FrequencyList) -> Vec<&'a str> {
+        let mut unknown: Vec<&str> = Vec::new();
+        for token in frequency.tokenize(text) {
+            let word = &text[token.range];
+            match token.band {
+                None | Some(Band::Common) => {}
+                Some(_) if self.is_known(profile, word) || unknown.contains(&word) => {}
+                Some(_) => unknown.push(word),
+            }
+        }
+        unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_is_per_profile() {
+        let mut known = KnownWords::default();
+        assert!(known.toggle("Japanese", "猫"));
+        assert!(known.is_known("Japanese", "猫"));
+        assert!(!known.is_known("German", "猫"));
+        assert!(!known.toggle("Japanese", "猫"));
+        assert!(!known.is_known("Japanese", "猫"));
+    }
+
+    #[test]
+    fn test_unknown_words_skip_known_and_common() {
+        let frequency = FrequencyList::parse("the\ncat\n");
+        let mut known = KnownWords::default();
+        known.toggle("Default", "Sat");
+        let unknown = known.unknown_words("Default", "The cat sat on the mat, the mat!", &frequency);
+        assert_eq!(unknown, vec!["on", "mat"]);
+    }
+}
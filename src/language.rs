@@ -0,0 +1,114 @@
+//! Telling what language a session is in from the scripts its first lines
+//! are written in, to suggest switching to the profile for it. Only
+//! languages with a script of their own can be told apart this way, so
+//! lines in Latin script go unguessed.
+
+use crate::settings::Profile;
+
+/// How many lines a session needs before its language is guessed.
+pub const SAMPLE_LINES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    /// ISO 639-3, as Tatoeba uses it.
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+const fn language(code: &'static str, name: &'static str) -> Language {
+    Language { code, name }
+}
+
+const JAPANESE: Language = language("jpn", "Japanese");
+const KOREAN: Language = language("kor", "Korean");
+const CHINESE: Language = language("cmn", "Chinese");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Kana,
+    Han,
+    /// A script only one of the languages told apart here is written in.
+    Own(Language),
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match u32::from(c) {
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Own(KOREAN),
+        0x0370..=0x03FF => Script::Own(language("ell", "Greek")),
+        0x0400..=0x04FF => Script::Own(language("rus", "Russian")),
+        0x0590..=0x05FF => Script::Own(language("heb", "Hebrew")),
+        0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Own(language("ara", "Arabic")),
+        0x0900..=0x097F => Script::Own(language("hin", "Hindi")),
+        0x0E00..=0x0E7F => Script::Own(language("tha", "Thai")),
+        _ => Script::Other,
+    }
+}
+
+/// The language most of the letters in `lines` are written in, if it can
+/// be told from their script. Japanese is told from Chinese by its kana.
+pub fn detect(lines: &[&str]) -> Option<Language> {
+    let scripts: Vec<Script> = lines
+        .iter()
+        .flat_map(|line| line.chars())
+        .filter(|c| c.is_alphabetic())
+        .map(script)
+        .collect();
+    let count = |wanted: Script| scripts.iter().filter(|&&script| script == wanted).count();
+    let letters = scripts.len();
+    let (kana, han) = (count(Script::Kana), count(Script::Han));
+    // Even kanji-heavy Japanese has particles and endings in kana
+    if kana > 0 && (kana + han) * 2 > letters && kana * 10 >= kana + han {
+        return Some(JAPANESE);
+    }
+    if han * 2 > letters {
+        return Some(CHINESE);
+    }
+    scripts
+        .iter()
+        .filter_map(|script| match script {
+            Script::Own(language) => Some(*language),
+            _ => None,
+        })
+        .find(|&language| count(Script::Own(language)) * 2 > letters)
+}
+
+impl Language {
+    /// Whether `profile` is for this language: its Tatoeba language is this
+    /// one, or its name says so.
+    pub fn matches(&self, profile: &Profile) -> bool {
+        profile.tatoeba_language.eq_ignore_ascii_case(self.code)
+            || profile.name.to_lowercase().contains(&self.name.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&["猫がいる", "東京駅", "行きましょう"]), Some(JAPANESE));
+        assert_eq!(detect(&["我们走吧", "东京站"]), Some(CHINESE));
+        assert_eq!(detect(&["고양이가 있어요"]), Some(KOREAN));
+        assert_eq!(detect(&["Где кошка?", "OK"]).map(|language| language.code), Some("rus"));
+        assert_eq!(detect(&["Where's the cat?", "Où est le chat ?"]), None);
+        assert_eq!(detect(&["♪", "123"]), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut profile = Profile {
+            name: "日本語 (Japanese)".to_string(),
+            ..Default::default()
+        };
+        assert!(JAPANESE.matches(&profile));
+        profile.name = "Anime".to_string();
+        assert!(!JAPANESE.matches(&profile));
+        profile.tatoeba_language = "jpn".to_string();
+        assert!(JAPANESE.matches(&profile));
+        assert!(!KOREAN.matches(&profile));
+    }
+}
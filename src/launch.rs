@@ -0,0 +1,245 @@
+//! How the viewer starts: command line flags, falling back to the startup
+//! settings. Meant for launching it alongside mpv (the Lua script's
+//! `viewer_command` option) without taking focus from the video.
+
+use crate::settings::Settings;
+use crate::storage::StorageKind;
+
+pub const USAGE: &str = "Usage: scriptview [options]
+
+  --minimized      Start with the window minimized
+  --overlay        Start with the mini overlay open
+  --layout NAME    Start in the saved layout NAME (a window docked at the
+                   edge of the screen, say)
+  --no-focus       Don't take keyboard focus when the window opens
+  --renderer NAME  glow (OpenGL, the default), wgpu (if built with the
+                   wgpu feature) or software (OpenGL drawn on the CPU,
+                   for remote desktops); falls back to the next one that
+                   works
+  --migrate-storage KIND
+                   Copy the raw stream into jsonl (files) or sqlite
+                   storage, switch to it and exit
+  --backup FILE    Save settings, stats, known words, starred lines and
+                   the raw stream to FILE and exit
+  --restore FILE   Restore a backup made with --backup and exit (with
+                   the viewer closed, or it saves over what's restored)
+  --decrypt FILE   Print an encrypted session summary or capture archive
+  -h, --help       Show this help";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Renderer {
+    #[default]
+    Glow,
+    Wgpu,
+    /// OpenGL through Mesa's software rasterizer.
+    Software,
+}
+
+impl Renderer {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "glow" | "opengl" => Some(Renderer::Glow),
+            "wgpu" => Some(Renderer::Wgpu),
+            "software" => Some(Renderer::Software),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Renderer::Glow => "OpenGL",
+            Renderer::Wgpu => "wgpu",
+            Renderer::Software => "software OpenGL",
+        }
+    }
+
+    /// This renderer and the ones to try after it if it can't start, each
+    /// less demanding of the graphics setup than the last.
+    pub fn fallbacks(self) -> Vec<Renderer> {
+        let order = [Renderer::Wgpu, Renderer::Glow, Renderer::Software];
+        let from = order.iter().position(|&renderer| renderer == self).unwrap_or_default();
+        order[from..]
+            .iter()
+            .copied()
+            .filter(|&renderer| renderer != Renderer::Wgpu || cfg!(feature = "wgpu"))
+            .collect()
+    }
+
+    /// Check ahead of eframe that this renderer can start. The release build
+    /// aborts on a panic, so a renderer whose failure panics inside
+    /// `run_native` never gets to hand over to the next one. Only wgpu needs
+    /// this: it's given a graphics adapter and device here the way eframe
+    /// asks for them. A window surface the adapter can't draw to still
+    /// panics, since there's no window to check against yet. OpenGL reports
+    /// its failures as errors.
+    pub fn probe(self) -> Result<(), String> {
+        #[cfg(feature = "wgpu")]
+        if self == Renderer::Wgpu {
+            use eframe::wgpu;
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends: eframe::egui_wgpu::WgpuConfiguration::default().supported_backends,
+                ..Default::default()
+            });
+            return crate::runtime::runtime().block_on(async {
+                let adapter = instance
+                    .request_adapter(&wgpu::RequestAdapterOptions::default())
+                    .await
+                    .ok_or("no graphics adapter")?;
+                adapter.request_device(&wgpu::DeviceDescriptor::default(), None).await.map_err(|e| e.to_string())?;
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// eframe's options for starting with this renderer.
+    pub fn configure(self, options: &mut eframe::NativeOptions) {
+        #[cfg(feature = "wgpu")]
+        if self == Renderer::Wgpu {
+            options.renderer = eframe::Renderer::Wgpu;
+            return;
+        }
+        options.renderer = eframe::Renderer::Glow;
+        if self == Renderer::Software {
+            // Read by Mesa when the GL context is created
+            std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub minimized: bool,
+    pub overlay: bool,
+    pub layout: Option<String>,
+    pub no_focus: bool,
+    pub renderer: Renderer,
+    /// Storage to copy the raw stream into instead of starting.
+    pub migrate_storage: Option<StorageKind>,
+    /// Archive to back up to instead of starting.
+    pub backup: Option<String>,
+    /// Archive to restore instead of starting.
+    pub restore: Option<String>,
+    /// Sealed file to print instead of starting.
+    pub decrypt: Option<String>,
+    pub help: bool,
+}
+
+fn parse_renderer(name: Option<&str>) -> Result<Renderer, String> {
+    let name = name.ok_or("--renderer needs glow, wgpu or software")?;
+    let renderer = Renderer::parse(name).ok_or_else(|| format!("Unknown renderer: {}", name))?;
+    if renderer == Renderer::Wgpu && !cfg!(feature = "wgpu") {
+        return Err("This build has no wgpu renderer; build with --features wgpu".to_string());
+    }
+    Ok(renderer)
+}
+
+fn parse_storage(name: Option<&str>) -> Result<StorageKind, String> {
+    let name = name.ok_or("--migrate-storage needs jsonl or sqlite")?;
+    StorageKind::parse(name).ok_or_else(|| format!("Unknown storage: {}", name))
+}
+
+impl LaunchOptions {
+    /// Options from the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--minimized" => options.minimized = true,
+                "--overlay" => options.overlay = true,
+                "--no-focus" => options.no_focus = true,
+                "-h" | "--help" => options.help = true,
+                "--layout" => match args.next() {
+                    Some(name) => options.layout = Some(name),
+                    None => return Err("--layout needs a layout name".to_string()),
+                },
+                "--renderer" => options.renderer = parse_renderer(args.next().as_deref())?,
+                "--migrate-storage" => options.migrate_storage = Some(parse_storage(args.next().as_deref())?),
+                "--backup" => options.backup = Some(args.next().ok_or("--backup needs a file name")?),
+                "--restore" => options.restore = Some(args.next().ok_or("--restore needs a backup file")?),
+                "--decrypt" => options.decrypt = Some(args.next().ok_or("--decrypt needs a file name")?),
+                _ => {
+                    if let Some(name) = arg.strip_prefix("--layout=") {
+                        options.layout = Some(name.to_string());
+                    } else if let Some(name) = arg.strip_prefix("--renderer=") {
+                        options.renderer = parse_renderer(Some(name))?;
+                    } else if let Some(name) = arg.strip_prefix("--migrate-storage=") {
+                        options.migrate_storage = Some(parse_storage(Some(name))?);
+                    } else {
+                        return Err(format!("Unknown option: {}", arg));
+                    }
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// These options with the startup settings filled in where no flag was given.
+    pub fn with_settings(mut self, settings: &Settings) -> Self {
+        self.minimized |= settings.start_minimized;
+        self.overlay |= settings.start_in_overlay;
+        if self.layout.is_none() && !settings.start_layout.is_empty() {
+            self.layout = Some(settings.start_layout.clone());
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = LaunchOptions::parse(args(&["--minimized", "--layout", "docked", "--no-focus"])).unwrap();
+        assert!(options.minimized && options.no_focus && !options.overlay);
+        assert_eq!(options.layout.as_deref(), Some("docked"));
+        assert_eq!(
+            LaunchOptions::parse(args(&["--layout=review"])).unwrap().layout.as_deref(),
+            Some("review")
+        );
+        assert!(LaunchOptions::parse(args(&["--layout"])).is_err());
+        assert!(LaunchOptions::parse(args(&["--fullscreen"])).is_err());
+        assert_eq!(
+            LaunchOptions::parse(args(&["--renderer", "software"])).unwrap().renderer,
+            Renderer::Software
+        );
+        assert!(LaunchOptions::parse(args(&["--renderer=vulkan"])).is_err());
+        assert_eq!(
+            LaunchOptions::parse(args(&["--migrate-storage", "sqlite"])).unwrap().migrate_storage,
+            Some(StorageKind::Sqlite)
+        );
+        assert!(LaunchOptions::parse(args(&["--migrate-storage"])).is_err());
+        assert_eq!(
+            LaunchOptions::parse(args(&["--restore", "old.json.gz"])).unwrap().restore.as_deref(),
+            Some("old.json.gz")
+        );
+        assert!(LaunchOptions::parse(args(&["--backup"])).is_err());
+    }
+
+    #[test]
+    fn test_renderer_fallbacks() {
+        assert_eq!(Renderer::Glow.fallbacks(), vec![Renderer::Glow, Renderer::Software]);
+        assert_eq!(Renderer::Software.fallbacks(), vec![Renderer::Software]);
+        assert!(Renderer::Wgpu.fallbacks().ends_with(&[Renderer::Glow, Renderer::Software]));
+    }
+
+    #[test]
+    fn test_flags_override_settings() {
+        let settings = Settings {
+            start_in_overlay: true,
+            start_layout: "mining".to_string(),
+            ..Default::default()
+        };
+        let options = LaunchOptions::parse(args(&["--layout", "review"])).unwrap().with_settings(&settings);
+        assert!(options.overlay && !options.minimized);
+        assert_eq!(options.layout.as_deref(), Some("review"));
+        let options = LaunchOptions::default().with_settings(&settings);
+        assert_eq!(options.layout.as_deref(), Some("mining"));
+    }
+}
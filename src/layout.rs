@@ -0,0 +1,74 @@
+//! Named window layouts ("overlay", "review", "mining", …): where the window
+//! is and how big, which views and panels are showing, and the text size,
+//! saved together so switching tasks is one click.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub name: String,
+    /// Inner size of the window in points, if it was known when saved.
+    pub size: Option<[f32; 2]>,
+    /// Outer position of the window on screen, if the platform reports it.
+    pub position: Option<[f32; 2]>,
+    pub always_on_top: bool,
+    pub timeline_view: bool,
+    pub show_overlay: bool,
+    pub show_waveforms: bool,
+    pub reading_ruler: bool,
+    pub show_review: bool,
+    pub show_stats: bool,
+    pub font_size: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            size: None,
+            position: None,
+            always_on_top: false,
+            timeline_view: false,
+            show_overlay: false,
+            show_waveforms: false,
+            reading_ruler: false,
+            show_review: false,
+            show_stats: false,
+            font_size: 14.0,
+        }
+    }
+}
+
+/// Add `layout` to `layouts`, replacing one with the same name (ignoring
+/// case and surrounding spaces).
+pub fn store(layouts: &mut Vec<Layout>, mut layout: Layout) {
+    layout.name = layout.name.trim().to_string();
+    match layouts.iter_mut().find(|saved| saved.name.eq_ignore_ascii_case(&layout.name)) {
+        Some(saved) => *saved = layout,
+        None => layouts.push(layout),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_replaces_by_name() {
+        let mut layouts = Vec::new();
+        store(&mut layouts, Layout { name: "Review".to_string(), ..Default::default() });
+        store(&mut layouts, Layout { name: "overlay".to_string(), ..Default::default() });
+        store(
+            &mut layouts,
+            Layout {
+                name: " review ".to_string(),
+                font_size: 20.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(layouts.len(), 2);
+        assert_eq!(layouts[0].name, "review");
+        assert_eq!(layouts[0].font_size, 20.0);
+    }
+}
@@ -0,0 +1,256 @@
+//! A source fed newline-delimited JSON on stdin or a TCP port, for tools
+//! with no player behind them (text hookers, chat logs, live captioning).
+//!
+//! Each line is an object like `{"text": "...", "start_time": 1.5,
+//! "end_time": 3.0, "path": "...", "title": "...", "translation": "...",
+//! "confidence": 0.8, "source": "asr"}` where only `text` is required, or
+//! just a JSON string. Without `start_time` a line is placed at
+//! the seconds since the source started, so timelines still make sense.
+
+use std::io::{BufRead, IsTerminal};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Instant;
+
+use serde::Deserialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+
+use crate::capture::CaptureWriter;
+use crate::runtime::{StopSignal, Stopped};
+use crate::source::Source;
+use crate::SubtitleEntry;
+
+pub const DEFAULT_PORT: u16 = 7274;
+pub const OUTPUT_FILE: &str = "/tmp/scriptview-lines.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Text(String),
+    Entry {
+        text: String,
+        #[serde(default)]
+        start_time: Option<f64>,
+        #[serde(default)]
+        end_time: Option<f64>,
+        #[serde(default)]
+        path: Option<String>,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        translation: Option<String>,
+        /// From speech recognition or OCR, 0 to 1.
+        #[serde(default)]
+        confidence: Option<f32>,
+        /// "ocr" or "asr"; anything else counts as plain JSON lines.
+        #[serde(default)]
+        source: Option<String>,
+    },
+}
+
+/// The entry for one line of input, or `None` for a blank one. Lines
+/// without a start time get `default_start`.
+pub fn parse_line(line: &str, default_start: impl FnOnce() -> f64) -> Result<Option<SubtitleEntry>, String> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let line: Line = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let (text, start_time, end_time, path, title, translation, confidence, source) = match line {
+        Line::Text(text) => (text, None, None, None, None, None, None, None),
+        Line::Entry {
+            text,
+            start_time,
+            end_time,
+            path,
+            title,
+            translation,
+            confidence,
+            source,
+        } => (text, start_time, end_time, path, title, translation, confidence, source),
+    };
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(SubtitleEntry {
+        text: text.trim().to_string(),
+        start_time: start_time.unwrap_or_else(default_start),
+        end_time,
+        timestamp: chrono::Local::now().timestamp(),
+        path,
+        title,
+        translation,
+        confidence: confidence.map(|confidence| confidence.clamp(0.0, 1.0)),
+        source: source.as_deref().and_then(Source::from_name),
+        ..Default::default()
+    }))
+}
+
+/// The capture shared by the TCP connections and stdin.
+struct Target {
+    writer: Mutex<CaptureWriter>,
+    started: Instant,
+}
+
+impl Target {
+    fn handle_line(&self, line: &str) -> Result<(), String> {
+        let Some(entry) = parse_line(line, || self.started.elapsed().as_secs_f64())? else {
+            return Ok(());
+        };
+        self.writer
+            .lock()
+            .unwrap()
+            .push(entry)
+            .map_err(|e| format!("Could not write capture file: {}", e))
+    }
+
+}
+
+async fn serve_connection(stream: TcpStream, target: Arc<Target>, mut stopped: Stopped) {
+    let mut lines = tokio::io::BufReader::new(stream).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => {
+                    if let Err(e) = target.handle_line(&line) {
+                        eprintln!("Warning: Ignoring line: {}", e);
+                    }
+                }
+                Ok(None) | Err(_) => break,
+            },
+            _ = stopped.wait() => break,
+        }
+    }
+}
+
+/// Where stdin lines go. Stdin can only be read once, so one thread reads it
+/// for the whole run and hands lines to whichever server is running. (It's
+/// a thread of its own since reading stdin blocks either way.)
+static STDIN_TARGET: Mutex<Option<Arc<Target>>> = Mutex::new(None);
+static STDIN_READER: Once = Once::new();
+
+fn read_stdin() {
+    if std::io::stdin().is_terminal() {
+        return;
+    }
+    STDIN_READER.call_once(|| {
+        std::thread::spawn(|| {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                let target = STDIN_TARGET.lock().unwrap().clone();
+                if let Some(target) = target {
+                    if let Err(e) = target.handle_line(&line) {
+                        eprintln!("Warning: Ignoring line from stdin: {}", e);
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// The TCP listener and stdin hookup, running until dropped.
+pub struct Server {
+    pub port: u16,
+    /// Whether stdin is a pipe rather than a terminal.
+    pub stdin: bool,
+    stop: Option<StopSignal>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `port` on localhost (0 picks a free port), read stdin if
+    /// it's piped, and start an empty capture file at `output_file`.
+    pub fn start(port: u16, output_file: &str) -> Result<Self, std::io::Error> {
+        let listener = crate::runtime::bind(port)?;
+        let port = listener.local_addr()?.port();
+        let target = Arc::new(Target {
+            writer: Mutex::new(CaptureWriter::create(output_file)?),
+            started: Instant::now(),
+        });
+        let (stop, mut stopped) = crate::runtime::stop_signal();
+
+        *STDIN_TARGET.lock().unwrap() = Some(target.clone());
+        read_stdin();
+
+        let accept = crate::runtime::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = stopped.wait() => break,
+                };
+                match accepted {
+                    Ok((stream, _)) => {
+                        crate::runtime::spawn(serve_connection(stream, target.clone(), stopped.clone()));
+                    }
+                    Err(e) => eprintln!("Warning: Line source connection failed: {}", e),
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            stdin: !std::io::stdin().is_terminal(),
+            stop: Some(stop),
+            accept: Some(accept),
+        })
+    }
+
+    /// Stop listening and reading stdin, waiting for the port to be let go
+    /// so it can be bound again right away.
+    pub fn stop(&mut self) {
+        *STDIN_TARGET.lock().unwrap() = None;
+        self.stop = None;
+        let Some(accept) = self.accept.take() else {
+            return;
+        };
+        if !crate::runtime::join_within(accept, crate::runtime::STOP_TIMEOUT) {
+            eprintln!("Warning: Line source server didn't stop in time");
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tcp_lines_reach_capture_file() {
+        let output_file = std::env::temp_dir().join(format!("scriptview-lines-{}.json", std::process::id()));
+        let output_file = output_file.to_string_lossy().into_owned();
+        let server = Server::start(0, &output_file).unwrap();
+
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        writeln!(stream, r#"{{"text": "猫だ", "start_time": 2.0, "confidence": 0.4, "source": "ocr"}}"#).unwrap();
+        writeln!(stream, "not json").unwrap();
+        writeln!(stream).unwrap();
+        writeln!(stream, r#""犬もいる""#).unwrap();
+        drop(stream);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let entries = loop {
+            let entries = crate::session::read_entries(&output_file).unwrap_or_default();
+            if entries.len() == 2 || Instant::now() > deadline {
+                break entries;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["猫だ", "犬もいる"]);
+        assert_eq!(entries[0].start_time, 2.0);
+        assert_eq!(entries[0].source, Some(Source::Ocr));
+        assert_eq!((entries[0].confidence, entries[1].confidence), (Some(0.4), None));
+        // Placed at the time since the source started
+        assert!(entries[1].start_time < 2.0);
+        let port = server.port;
+        drop(server);
+        Server::start(port, &output_file).unwrap();
+        std::fs::remove_file(output_file).unwrap();
+    }
+}
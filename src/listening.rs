@@ -0,0 +1,72 @@
+//! Listening-first mode: each line that arrives is masked for a while, or
+//! until it's revealed, so it's parsed by ear before it's read.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::ids::EntryId;
+
+#[derive(Debug, Default)]
+pub struct Delayed {
+    /// The masked lines and when each is due to show; `None` waits to be
+    /// revealed.
+    hidden: HashMap<EntryId, Option<Instant>>,
+}
+
+impl Delayed {
+    /// Mask a line that just arrived for `delay`, or with no delay until
+    /// it's revealed.
+    pub fn hold(&mut self, id: EntryId, now: Instant, delay: Duration) {
+        self.hidden.insert(id, (!delay.is_zero()).then(|| now + delay));
+    }
+
+    pub fn is_hidden(&self, id: EntryId) -> bool {
+        self.hidden.contains_key(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hidden.is_empty()
+    }
+
+    pub fn reveal(&mut self, id: EntryId) {
+        self.hidden.remove(&id);
+    }
+
+    pub fn reveal_all(&mut self) {
+        self.hidden.clear();
+    }
+
+    /// Unmask the lines whose time is up, returning how long until the next
+    /// one is.
+    pub fn tick(&mut self, now: Instant) -> Option<Duration> {
+        self.hidden.retain(|_, until| until.is_none_or(|until| until > now));
+        self.hidden.values().flatten().map(|until| *until - now).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_and_reveal() {
+        let start = Instant::now();
+        let mut delayed = Delayed::default();
+        delayed.hold(1, start, Duration::from_secs(3));
+        delayed.hold(2, start, Duration::from_secs(5));
+        delayed.hold(3, start, Duration::ZERO);
+        assert_eq!(delayed.tick(start + Duration::from_secs(1)), Some(Duration::from_secs(2)));
+        assert!(delayed.is_hidden(1));
+
+        assert_eq!(delayed.tick(start + Duration::from_secs(3)), Some(Duration::from_secs(2)));
+        assert!(!delayed.is_hidden(1));
+        assert_eq!(delayed.tick(start + Duration::from_secs(60)), None);
+        assert!(delayed.is_hidden(3));
+
+        delayed.hold(4, start, Duration::ZERO);
+        delayed.reveal(3);
+        assert!(!delayed.is_hidden(3));
+        delayed.reveal_all();
+        assert!(delayed.is_empty());
+    }
+}
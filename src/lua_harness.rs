@@ -0,0 +1,473 @@
+//! Runs `subtitle-monitor.lua` in an embedded Lua against a mocked mpv API,
+//! so tests can drive it with property changes, key presses and timer ticks,
+//! and `vlc-subtitles.lua` against a mocked VLC playing a fake video.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use mlua::{IntoLua, Lua, Table, Value};
+
+/// The parts of mpv's Lua API the script uses, recording what it does in `mock`.
+/// The clock stands still at `mock.now` so timestamps can be asserted.
+const MOCK_MPV: &str = r#"
+mock = { properties = {}, observers = {}, bindings = {}, events = {}, timers = {}, commands = {}, osd = {}, script_opts = {} }
+
+local real_time, real_date = os.time, os.date
+function os.time(date)
+    if date == nil then
+        return mock.now
+    end
+    return real_time(date)
+end
+function os.date(format, time)
+    return real_date(format, time or mock.now)
+end
+
+function mock.set_property(name, value)
+    mock.properties[name] = value
+    for _, observer in ipairs(mock.observers) do
+        if observer.name == name then
+            observer.fn(name, value)
+        end
+    end
+end
+
+function mock.tick()
+    for _, fn in ipairs(mock.timers) do
+        fn()
+    end
+end
+
+mp = {}
+function mp.get_property(name, default)
+    local value = mock.properties[name]
+    if value == nil then
+        return default
+    end
+    return tostring(value)
+end
+function mp.get_property_number(name, default)
+    local value = mock.properties[name]
+    if type(value) == "number" then
+        return value
+    end
+    return default
+end
+function mp.set_property_bool(name, value) mock.properties[name] = value end
+function mp.observe_property(name, kind, fn) table.insert(mock.observers, { name = name, fn = fn }) end
+function mp.register_event(name, fn) mock.events[name] = fn end
+function mp.add_key_binding(key, name, fn) mock.bindings[name] = fn end
+function mp.add_periodic_timer(interval, fn) table.insert(mock.timers, fn) end
+function mp.osd_message(text) table.insert(mock.osd, text) end
+function mp.commandv(...) table.insert(mock.commands, { ... }) end
+
+local function ignore() end
+package.preload["mp.msg"] = function()
+    return { info = ignore, warn = ignore, error = ignore, verbose = ignore }
+end
+package.preload["mp.options"] = function()
+    return {
+        read_options = function(opts)
+            for key, value in pairs(mock.script_opts) do
+                opts[key] = value
+            end
+        end,
+    }
+end
+package.preload["mp.utils"] = function()
+    return {
+        format_json = mock_format_json,
+        parse_json = mock_parse_json,
+        join_path = function(a, b)
+            if b:sub(1, 1) == "/" then
+                return b
+            end
+            return a .. "/" .. b
+        end,
+    }
+end
+"#;
+
+/// The parts of VLC's Lua API the script uses. Each poll plays the next time
+/// from `mock_vlc.times`; VLC asks the script to stop when they run out.
+const MOCK_VLC: &str = r#"
+mock_vlc = { times = {}, polls = 0 }
+
+local function ignore() end
+local item = {
+    uri = function() return mock_vlc.uri end,
+    name = function() return mock_vlc.name end,
+}
+vlc = {
+    msg = { info = ignore, warn = ignore, err = ignore, dbg = ignore },
+    misc = {
+        version = function() return "3.0.20 Vetinari" end,
+        should_die = function() return mock_vlc.polls >= #mock_vlc.times end,
+        mdate = function() return 0 end,
+        mwait = function() mock_vlc.polls = mock_vlc.polls + 1 end,
+    },
+    object = { input = function() return {} end },
+    input = { item = function() return item end },
+    -- VLC 3 reports time in microseconds
+    var = { get = function(_, name) return mock_vlc.times[mock_vlc.polls + 1] * 1000000 end },
+    strings = { make_path = function(uri) return (uri:gsub("^file://", "")) end },
+    net = { opendir = mock_opendir },
+}
+"#;
+
+/// Lua value to JSON the way mpv does it: tables with keys 1..n (or none at
+/// all) become arrays, other tables objects.
+fn to_json(value: Value) -> mlua::Result<serde_json::Value> {
+    Ok(match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Boolean(b) => serde_json::Value::Bool(b),
+        Value::Integer(i) => serde_json::json!(i),
+        Value::Number(n) => serde_json::json!(n),
+        Value::String(s) => serde_json::Value::String(s.to_str()?.to_string()),
+        Value::Table(table) => {
+            let len = table.raw_len();
+            let pairs: Vec<(Value, Value)> = table.clone().pairs().collect::<mlua::Result<_>>()?;
+            if pairs.len() == len {
+                let items = (1..=len).map(|i| table.raw_get(i).and_then(to_json));
+                serde_json::Value::Array(items.collect::<mlua::Result<_>>()?)
+            } else {
+                let mut object = serde_json::Map::new();
+                for (key, value) in pairs {
+                    let key = match key {
+                        Value::String(s) => s.to_str()?.to_string(),
+                        other => to_json(other)?.to_string(),
+                    };
+                    object.insert(key, to_json(value)?);
+                }
+                serde_json::Value::Object(object)
+            }
+        }
+        other => return Err(mlua::Error::runtime(format!("can't format {} as JSON", other.type_name()))),
+    })
+}
+
+fn from_json<'lua>(lua: &'lua Lua, value: &serde_json::Value) -> mlua::Result<Value<'lua>> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Nil,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Number(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => s.as_str().into_lua(lua)?,
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table()?;
+            for (i, item) in items.iter().enumerate() {
+                table.raw_set(i + 1, from_json(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+        serde_json::Value::Object(object) => {
+            let table = lua.create_table()?;
+            for (key, item) in object {
+                table.raw_set(key.as_str(), from_json(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
+
+pub struct MockMpv {
+    lua: Lua,
+    dir: PathBuf,
+}
+
+impl MockMpv {
+    /// Where the mock clock starts: noon on 2024-05-01, local time.
+    pub fn start_time() -> i64 {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .and_then(|time| time.and_local_timezone(chrono::Local).single())
+            .unwrap()
+            .timestamp()
+    }
+
+    /// Load the script with its capture file in a fresh temporary directory.
+    pub fn start() -> Self {
+        Self::start_with(&[])
+    }
+
+    /// Load the script with script-opts as if from `subtitle-monitor.conf`.
+    pub fn start_with(opts: &[(&str, &str)]) -> Self {
+        let dir = temp_dir();
+        let lua = Lua::new();
+        let format_json = lua.create_function(|_, value: Value| Ok(to_json(value)?.to_string())).unwrap();
+        let parse_json = lua
+            .create_function(|lua, text: String| match serde_json::from_str::<serde_json::Value>(&text) {
+                Ok(value) => Ok((from_json(lua, &value)?, Value::Nil)),
+                Err(e) => Ok((Value::Nil, e.to_string().into_lua(lua)?)),
+            })
+            .unwrap();
+        lua.globals().set("mock_format_json", format_json).unwrap();
+        lua.globals().set("mock_parse_json", parse_json).unwrap();
+        lua.load(MOCK_MPV).exec().unwrap();
+
+        let mpv = Self { lua, dir };
+        mpv.mock().set("now", Self::start_time()).unwrap();
+        {
+            let script_opts: Table = mpv.mock().get("script_opts").unwrap();
+            script_opts.set("output_file", mpv.output_file()).unwrap();
+            for (key, value) in opts {
+                script_opts.set(*key, *value).unwrap();
+            }
+        }
+        mpv.lua.load(crate::LUA_SCRIPT).set_name("subtitle-monitor.lua").exec().unwrap();
+        mpv
+    }
+
+    fn mock(&self) -> Table<'_> {
+        self.lua.globals().get("mock").unwrap()
+    }
+
+    pub fn output_file(&self) -> String {
+        self.dir.join("subtitles.json").to_string_lossy().into_owned()
+    }
+
+    /// Change a property, notifying the script if it observes it.
+    pub fn set_property<'lua>(&'lua self, name: &str, value: impl IntoLua<'lua>) {
+        let set: mlua::Function = self.mock().get("set_property").unwrap();
+        set.call::<_, ()>((name, value)).unwrap();
+    }
+
+    pub fn property(&self, name: &str) -> Option<String> {
+        let properties: Table = self.mock().get("properties").unwrap();
+        let value: Value = properties.get(name).unwrap();
+        match value {
+            Value::Nil => None,
+            Value::String(s) => Some(s.to_str().unwrap().to_string()),
+            other => Some(to_json(other).unwrap().to_string()),
+        }
+    }
+
+    /// Press the key bound to the script binding `name`.
+    pub fn press(&self, name: &str) {
+        let bindings: Table = self.mock().get("bindings").unwrap();
+        let binding: mlua::Function = bindings.get(name).unwrap();
+        binding.call::<_, ()>(()).unwrap();
+    }
+
+    /// The capture file as the script last wrote it.
+    pub fn output(&self) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(self.output_file()).unwrap()).unwrap()
+    }
+
+    /// Send the script an mpv event such as `file-loaded` or `seek`.
+    pub fn fire(&self, event: &str) {
+        let events: Table = self.mock().get("events").unwrap();
+        let handler: mlua::Function = events.get(event).unwrap();
+        handler.call::<_, ()>(()).unwrap();
+    }
+
+    /// Move the clock on by `secs` seconds.
+    pub fn advance(&self, secs: i64) {
+        let now: i64 = self.mock().get("now").unwrap();
+        self.mock().set("now", now + secs).unwrap();
+    }
+
+    /// Run the script's periodic timers once.
+    pub fn tick(&self) {
+        let tick: mlua::Function = self.mock().get("tick").unwrap();
+        tick.call::<_, ()>(()).unwrap();
+    }
+
+    /// Commands the script ran through `mp.commandv`.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        let commands: Table = self.mock().get("commands").unwrap();
+        commands
+            .sequence_values::<Table>()
+            .map(|args| args.unwrap().sequence_values::<String>().map(Result::unwrap).collect())
+            .collect()
+    }
+}
+
+impl Drop for MockMpv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn temp_dir() -> PathBuf {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "scriptview-lua-{}-{}",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A fake `show.mkv` in a temporary directory for the VLC script to play.
+pub struct MockVlc {
+    dir: PathBuf,
+}
+
+impl MockVlc {
+    pub fn start() -> Self {
+        let vlc = Self { dir: temp_dir() };
+        std::fs::write(vlc.video_path(), "").unwrap();
+        vlc
+    }
+
+    pub fn video_path(&self) -> String {
+        self.dir.join("show.mkv").to_string_lossy().into_owned()
+    }
+
+    pub fn output_file(&self) -> String {
+        self.dir.join("subtitles.json").to_string_lossy().into_owned()
+    }
+
+    /// Put a subtitle file named `name` next to the video.
+    pub fn write_srt(&self, name: &str, content: &str) {
+        std::fs::write(self.dir.join(name), content).unwrap();
+    }
+
+    /// Run the script until it has polled at each playback time in `times`.
+    pub fn run(&self, times: &[f64]) {
+        let lua = Lua::new();
+        let opendir = lua
+            .create_function(|_, dir: String| {
+                let names: Vec<String> = std::fs::read_dir(dir)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect();
+                Ok(names)
+            })
+            .unwrap();
+        lua.globals().set("mock_opendir", opendir).unwrap();
+        lua.load(MOCK_VLC).exec().unwrap();
+
+        let mock: Table = lua.globals().get("mock_vlc").unwrap();
+        mock.set("times", times.to_vec()).unwrap();
+        mock.set("uri", format!("file://{}", self.video_path())).unwrap();
+        mock.set("name", "show.mkv").unwrap();
+        let config = lua.create_table().unwrap();
+        config.set("output_file", self.output_file()).unwrap();
+        lua.globals().set("config", config).unwrap();
+        lua.load(crate::player::VLC_SCRIPT).set_name("vlc-subtitles.lua").exec().unwrap();
+    }
+}
+
+impl Drop for MockVlc {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn show_lines(mpv: &MockMpv, lines: &[(f64, &str)]) {
+        for (time, text) in lines {
+            mpv.set_property("time-pos", *time);
+            mpv.set_property("sub-text", *text);
+        }
+    }
+
+    #[test]
+    fn test_captures_sub_text() {
+        let mpv = MockMpv::start();
+        assert_eq!(mpv.output(), json!([]));
+        mpv.set_property("path", "/videos/show.mkv");
+        mpv.set_property("media-title", "Show");
+        mpv.set_property("duration", 1420.5);
+        show_lines(&mpv, &[(12.5, "猫がいる"), (14.0, ""), (15.0, "犬\nもいる")]);
+        let now = MockMpv::start_time();
+        assert_eq!(
+            mpv.output(),
+            json!([
+                {
+                    "text": "猫がいる",
+                    "start_time": 12.5,
+                    "timestamp": now,
+                    "path": "/videos/show.mkv",
+                    "title": "Show",
+                    "duration": 1420.5,
+                },
+                {
+                    "text": "犬\nもいる",
+                    "start_time": 15.0,
+                    "timestamp": now,
+                    "path": "/videos/show.mkv",
+                    "title": "Show",
+                    "duration": 1420.5,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_media_path() {
+        let mpv = MockMpv::start();
+        mpv.set_property("working-directory", "/home/me");
+        mpv.set_property("path", "anime/show.mkv");
+        show_lines(&mpv, &[(1.0, "猫")]);
+        mpv.set_property("path", "https://example.com/show.m3u8");
+        show_lines(&mpv, &[(2.0, "犬")]);
+        mpv.set_property("secondary-sub-text", "A dog");
+        let output = mpv.output();
+        assert_eq!(output[0]["path"], "/home/me/anime/show.mkv");
+        assert_eq!(output[1]["path"], "https://example.com/show.m3u8");
+        assert_eq!(output[2]["text"], "[Secondary] A dog");
+        assert_eq!(output[2]["start_time"], 2.0);
+    }
+
+    #[test]
+    fn test_truncate_retention() {
+        let mpv = MockMpv::start();
+        for i in 0..55 {
+            show_lines(&mpv, &[(1.0, format!("line {}", i).as_str())]);
+        }
+        let output = mpv.output();
+        assert_eq!(output.as_array().unwrap().len(), 50);
+        assert_eq!(output[0]["text"], "line 5");
+        assert_eq!(output[49]["text"], "line 54");
+
+        mpv.fire("file-loaded");
+        assert_eq!(mpv.output(), json!([]));
+
+        // Playback moving on is fine, a jump of more than 5 seconds is a seek
+        show_lines(&mpv, &[(10.0, "猫"), (14.0, "犬")]);
+        assert_eq!(mpv.output().as_array().unwrap().len(), 2);
+        mpv.set_property("time-pos", 100.0);
+        assert_eq!(mpv.output(), json!([]));
+    }
+
+    #[test]
+    fn test_forever_retention() {
+        let mpv = MockMpv::start_with(&[("retention", "forever")]);
+        show_lines(&mpv, &[(10.0, "猫")]);
+        mpv.fire("file-loaded");
+        mpv.set_property("time-pos", 100.0);
+        mpv.advance(86400);
+        show_lines(&mpv, &[(2.0, "犬")]);
+        let now = MockMpv::start_time();
+        assert_eq!(
+            mpv.output(),
+            json!([
+                { "text": "猫", "start_time": 10.0, "timestamp": now },
+                { "text": "犬", "start_time": 2.0, "timestamp": now + 86400 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_daily_retention() {
+        let mpv = MockMpv::start_with(&[("retention", "daily")]);
+        show_lines(&mpv, &[(10.0, "猫")]);
+        mpv.advance(86400);
+        show_lines(&mpv, &[(11.0, "犬")]);
+        let now = MockMpv::start_time();
+        assert_eq!(mpv.output(), json!([{ "text": "犬", "start_time": 11.0, "timestamp": now + 86400 }]));
+        let archive = mpv.output_file().replace(".json", "-2024-05-01.json");
+        let archived: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(archive).unwrap()).unwrap();
+        assert_eq!(archived, json!([{ "text": "猫", "start_time": 10.0, "timestamp": now }]));
+    }
+}
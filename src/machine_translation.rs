@@ -0,0 +1,219 @@
+//! Machine translation of single lines, shown only in a tooltip when a line
+//! is hovered, for practising recall with a monolingual view and checking
+//! when stuck. Uses the LibreTranslate API, which can be self-hosted; each
+//! line is translated once and kept for the rest of the run. One worker
+//! thread makes the requests, and a line hovered only while another was
+//! being translated is passed over rather than queued.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+pub const DEFAULT_URL: &str = "http://localhost:5000/translate";
+/// How long a request may take before the line shows as not translated.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and into what lines are translated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Service {
+    pub url: String,
+    /// Empty for servers that don't need one.
+    pub api_key: String,
+    /// Language code of the lines, or "auto".
+    pub source: String,
+    pub target: String,
+}
+
+fn request_body(service: &Service, text: &str) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "q": text,
+        "source": service.source,
+        "target": service.target,
+        "format": "text",
+    });
+    if !service.api_key.is_empty() {
+        body["api_key"] = service.api_key.clone().into();
+    }
+    body
+}
+
+/// The translation in a response, or the error the server gave.
+fn parse_response(body: &str) -> Result<String, String> {
+    let response: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("Unexpected response: {}", e))?;
+    if let Some(translation) = response["translatedText"].as_str() {
+        return Ok(translation.to_string());
+    }
+    Err(response["error"].as_str().unwrap_or("No translation in the response").to_string())
+}
+
+fn translate(agent: &ureq::Agent, service: &Service, text: &str) -> Result<String, String> {
+    let body = request_body(service, text).to_string();
+    let response = agent.post(&service.url).set("Content-Type", "application/json").send_string(&body);
+    match response {
+        Ok(response) => parse_response(&response.into_string().map_err(|e| e.to_string())?),
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(parse_response(&body).err().unwrap_or_else(|| format!("HTTP {}", code)))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+type Translated = (Service, String, Result<String, String>);
+
+/// The thread translating lines, running until its requests are dropped.
+struct Worker {
+    requests: Sender<(Service, String)>,
+    results: Receiver<Translated>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let (requests, requests_rx) = channel::<(Service, String)>();
+        let (results_tx, results) = channel();
+        std::thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+            while let Ok(mut request) = requests_rx.recv() {
+                // Only the line hovered last is still wanted
+                while let Ok(newer) = requests_rx.try_recv() {
+                    request = newer;
+                }
+                let (service, text) = request;
+                let result = translate(&agent, &service, &text);
+                if results_tx.send((service, text, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
+}
+
+/// Translations by line text, for one service at a time.
+#[derive(Default)]
+pub struct Cache {
+    service: Option<Service>,
+    lines: HashMap<String, Result<String, String>>,
+    /// The line last sent to the worker, until it's back.
+    pending: Option<String>,
+    worker: Option<Worker>,
+}
+
+impl Cache {
+    /// The translation of `text`, asking the worker for it the first time;
+    /// `None` while it's on its way. Changing the service starts over.
+    pub fn get(&mut self, service: &Service, text: &str) -> Option<&Result<String, String>> {
+        if self.service.as_ref() != Some(service) {
+            self.service = Some(service.clone());
+            self.lines.clear();
+            self.pending = None;
+        }
+        let worker = self.worker.get_or_insert_with(Worker::spawn);
+        for (from, line, result) in worker.results.try_iter() {
+            if self.pending.as_ref() == Some(&line) {
+                self.pending = None;
+            }
+            if self.service.as_ref() == Some(&from) {
+                self.lines.insert(line, result);
+            }
+        }
+        if !self.lines.contains_key(text) && self.pending.as_deref() != Some(text) {
+            if worker.requests.send((service.clone(), text.to_string())).is_err() {
+                self.lines.insert(text.to_string(), Err("Translation failed".to_string()));
+            } else {
+                self.pending = Some(text.to_string());
+            }
+        }
+        self.lines.get(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_request_and_response() {
+        let mut service = Service {
+            url: DEFAULT_URL.to_string(),
+            api_key: String::new(),
+            source: "auto".to_string(),
+            target: "en".to_string(),
+        };
+        let body = request_body(&service, "猫がいる");
+        assert_eq!(body["q"], "猫がいる");
+        assert_eq!(body["target"], "en");
+        assert!(body.get("api_key").is_none());
+        service.api_key = "secret".to_string();
+        assert_eq!(request_body(&service, "猫")["api_key"], "secret");
+
+        assert_eq!(parse_response(r#"{"translatedText": "There's a cat"}"#).unwrap(), "There's a cat");
+        assert_eq!(
+            parse_response(r#"{"error": "ja is not supported"}"#).unwrap_err(),
+            "ja is not supported"
+        );
+        assert!(parse_response("<html>").is_err());
+    }
+
+    /// Answer `count` requests on `listener` with the text marked as
+    /// translated, sending each text on `asked` and answering once `answer`
+    /// says so.
+    fn serve(listener: TcpListener, count: usize, asked: Sender<String>, answer: Receiver<()>) {
+        for _ in 0..count {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).unwrap();
+            let text = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["q"].as_str().unwrap().to_string();
+            asked.send(text.clone()).unwrap();
+            answer.recv().unwrap();
+            let reply = serde_json::json!({ "translatedText": format!("{}!", text) }).to_string();
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", reply.len());
+            reader.get_mut().write_all(format!("{}{}", head, reply).as_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_lines_passed_over_are_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let service = Service {
+            url: format!("http://{}/translate", listener.local_addr().unwrap()),
+            api_key: String::new(),
+            source: "ja".to_string(),
+            target: "en".to_string(),
+        };
+        let (asked_tx, asked) = channel();
+        let (answer, answer_rx) = channel();
+        std::thread::spawn(move || serve(listener, 2, asked_tx, answer_rx));
+
+        let mut cache = Cache::default();
+        assert!(cache.get(&service, "猫").is_none());
+        assert_eq!(asked.recv().unwrap(), "猫");
+        // Hovered while 猫 is on its way; only the last of them is wanted
+        assert!(cache.get(&service, "犬").is_none());
+        assert!(cache.get(&service, "鳥").is_none());
+        answer.send(()).unwrap();
+        assert_eq!(asked.recv_timeout(Duration::from_secs(5)).unwrap(), "鳥");
+        answer.send(()).unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while cache.get(&service, "鳥").is_none() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(cache.get(&service, "鳥"), Some(&Ok("鳥!".to_string())));
+        assert_eq!(cache.get(&service, "猫"), Some(&Ok("猫!".to_string())));
+    }
+}
@@ -1,31 +1,467 @@
+mod align;
+mod anki;
+mod ass;
+mod audio;
+mod backup;
+mod bidi;
+mod browser;
+mod capture;
+mod cloze;
+mod crypt;
+mod diagnose;
+mod editor;
+mod encoding;
+mod examples;
+mod export;
+mod fonts;
+mod frequency;
+mod grammar;
+mod ids;
+mod keywords;
+mod known;
+mod language;
+mod launch;
+mod layout;
+mod lines;
+mod listening;
+#[cfg(test)]
+mod lua_harness;
+mod machine_translation;
+mod markup;
+mod mpv_config;
+mod mpv_ipc;
+mod mqtt;
+mod noise;
+mod notes;
+mod player;
+mod power;
+mod script_opts;
+mod session;
+mod presentation;
+mod protocol;
+mod qc;
+mod qr;
+mod raw;
+mod rate;
+mod remote;
+mod resync;
+mod review;
+mod runtime;
+mod search;
+mod settings;
+mod shows;
+mod shutdown;
+mod source;
+mod srt;
+mod starred;
+mod stats;
+mod storage;
+mod store;
+mod summary;
+mod sync;
+mod tail;
+mod toast;
+mod translation;
+mod trash;
+mod tray;
+mod tts;
+mod vertical;
+mod watchdog;
+mod watch_folder;
+mod watch_later;
+mod waveform;
+mod webhook;
+
 use eframe::egui;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::mpsc::{channel, Receiver};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use audio::AudioSource;
+use bidi::TextDirection;
+use encoding::TextEncoding;
+use examples::{Example, ExampleSource};
+use frequency::{Band, FrequencyList};
+use known::KnownWords;
+use launch::LaunchOptions;
+use layout::Layout;
+use markup::Marked;
+use grammar::GrammarDb;
+use ids::{EntryId, EntryIds};
+use mpv_ipc::{MpvEvent, MpvIpc, Track};
+use player::Player;
+use script_opts::{Retention, ScriptOpts};
+use presentation::{Preset, WidthUnit};
+use protocol::{EventReader, Request, ScriptEvent};
+use resync::{Anchor, Correction};
+use review::ReviewQueue;
+use search::SearchSite;
+use settings::{CollapseMode, Settings, TimestampClick};
+use shows::ShowSettings;
+use shutdown::ShutdownCoordinator;
+use starred::{StarredLine, StarredLines};
+use stats::{DayStats, Goal, GoalUnit, SessionRecord, SessionStats, StatsDb};
+use summary::Summary;
+use sync::SyncMode;
+use toast::Toasts;
+use waveform::Waveform;
+
 const LUA_SCRIPT: &str = include_str!("../subtitle-monitor.lua");
+/// How long confirmations stay on mpv's on-screen display.
+const OSD_DURATION_MS: u32 = 1500;
+/// How often to look for the player's process.
+const PLAYER_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How often to check whether the capture file was replaced.
+const REPLACE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the mouse rests on a line before it's machine-translated, so
+/// passing over lines doesn't send them all off.
+const HOVER_TRANSLATION_DELAY: f32 = 0.6;
+/// How long the capture file may stay unparseable before it's reported as
+/// broken, rather than caught halfway through a write.
+const CORRUPT_GRACE: Duration = Duration::from_secs(5);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 struct SubtitleEntry {
     text: String,
     start_time: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     end_time: Option<f64>,
     timestamp: i64,
+    /// Media file the subtitle came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// Length of that media file in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    /// mpv's `media-title`: the title tag if there is one, else the file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Styling carried over from an imported ASS file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ass: Option<ass::AssFields>,
+    /// The line in another language, from the source or paired up by `translation::attach`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translation: Option<String>,
+    /// How sure speech recognition or OCR was of the text, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    /// What produced the line; filled in from the player when read if the
+    /// source didn't say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<source::Source>,
+    /// Assigned by `EntryIds` when read; 0 until then.
+    #[serde(skip)]
+    id: EntryId,
+}
+
+/// Gathers anchors for fixing the timing of one media file's lines.
+struct ResyncAssistant {
+    media: Option<String>,
+    anchors: Vec<Anchor>,
+    /// The line waiting for the sync key: its uncorrected start and text.
+    pending: Option<(f64, String)>,
+}
+
+/// How far a merged file's cue may start from a captured line, when no cue
+/// is on screen at the line's time.
+const MERGE_OFFSET: f64 = 2.0;
+
+/// A subtitle file shown beside the live capture, each line next to the cue
+/// on screen when it was captured.
+struct MergedSubtitles {
+    path: String,
+    /// The media file whose lines it's matched to: the one playing when merged.
+    media: Option<String>,
+    cues: Vec<SubtitleEntry>,
+    /// Index into `cues` for each captured line that has one.
+    matches: HashMap<EntryId, usize>,
+}
+
+/// A subtitle file opened in place of the live capture.
+struct ImportedTranscript {
+    path: String,
+    /// Everything before `[Events]`, for writing ASS files back unchanged.
+    ass_header: Option<String>,
+    /// A day of the raw stream, filtered again whenever the filters change;
+    /// `path` is the day.
+    raw: bool,
 }
 
 struct SubtitleViewer {
     subtitles: Arc<Mutex<Vec<SubtitleEntry>>>,
     rx: Receiver<notify::Result<notify::Event>>,
+    watcher: RecommendedWatcher,
     subtitle_file: String,
     always_on_top: bool,
     file_exists: bool,
     script_installed: bool,
-    script_install_time: Option<Instant>,
-    font_size: f32,
+    settings: Settings,
+    saved_settings: Settings,
+    script_opts: ScriptOpts,
+    show_settings: bool,
+    show_stats: bool,
+    /// Session shown in the statistics window and its stats, computed on selection
+    stats_session: Option<(String, SessionStats)>,
+    font_status: String,
+    grammar: Option<GrammarDb>,
+    grammar_status: String,
+    frequency: Option<FrequencyList>,
+    frequency_status: String,
+    example_lookup: Option<ExampleLookup>,
+    resync: Option<ResyncAssistant>,
+    /// Applied to the lines of a media file on every read.
+    sync_correction: Option<(Option<String>, Correction)>,
+    /// Pronunciation or line audio being started.
+    pronunciation: Option<Receiver<Result<(), String>>>,
+    audio_error: Option<String>,
+    review: ReviewQueue,
+    stats_db: StatsDb,
+    /// The saved statistics are sealed and haven't been opened yet, so
+    /// they're not saved over until they are.
+    stats_locked: bool,
+    stats_export_status: String,
+    /// Tag the history and exports are limited to.
+    stats_tag: Option<String>,
+    /// Session (index into the stats database) whose tags are being edited, and the text so far.
+    tag_edit: Option<(usize, String)>,
+    show_review: bool,
+    /// Separate small window showing just the latest line.
+    show_overlay: bool,
+    show_presenter: bool,
+    /// Low-confidence lines checked this session.
+    verified: HashSet<EntryId>,
+    /// Lines the filters left out of the view, for the filtered lines window.
+    filtered_out: Vec<FilteredLine>,
+    show_filtered: bool,
+    /// Lines deleted from the view, restorable until their session ends.
+    trash: trash::Trash,
+    show_trash: bool,
+    /// Text changed in the external editor, and the file open there.
+    edits: editor::Edits,
+    editor_session: Option<editor::Session>,
+    /// How the timeline's cue timing was fixed, redone on every reload.
+    timing_fix: Option<qc::TimingFix>,
+    show_timing_problems: bool,
+    /// Lines pinned side by side for comparison.
+    tray: tray::Tray,
+    /// Machine translations for the hover tooltip.
+    hover_translations: machine_translation::Cache,
+    show_tray: bool,
+    /// Which side of each line is masked for self-testing, if any, and the
+    /// lines clicked to show it.
+    cloze: Option<cloze::ClozeSide>,
+    cloze_revealed: HashSet<EntryId>,
+    /// New lines masked by listening-first mode.
+    delayed: listening::Delayed,
+    /// The developer view of the capture file's last records, kept up to
+    /// date while it's open.
+    show_capture_tail: bool,
+    capture_tail: Option<Result<tail::Tail, String>>,
+    capture_tail_count: usize,
+    /// Whether the current review card has been flipped to show its source.
+    review_revealed: bool,
+    ipc: MpvIpc,
+    mpv_connected: bool,
+    mpv_paused: bool,
+    /// Watches for mpv playing while no lines arrive.
+    stall_watchdog: watchdog::StallWatchdog,
+    /// The failed attempt and wait before the next, while reconnecting after
+    /// mpv's socket dropped.
+    mpv_reconnecting: Option<(u32, Duration)>,
+    /// mpv's subtitle tracks and the primary and secondary selections.
+    sub_tracks: Vec<Track>,
+    sid: Option<i64>,
+    secondary_sid: Option<i64>,
+    audio_tracks: Vec<Track>,
+    aid: Option<i64>,
+    /// mpv's playback speed.
+    speed: f64,
+    /// mpv's subtitle delay in seconds, once it's reported.
+    sub_delay: Option<f64>,
+    /// The show the latest line is from, as `shows::show_name` gives it.
+    current_show: Option<String>,
+    /// The user's own values of the per-show settings while a show's are in
+    /// use: what's saved, and what comes back for a show with none.
+    show_base: Option<ShowSettings>,
+    /// A remembered subtitle delay waiting for mpv's IPC socket.
+    pending_sub_delay: Option<f64>,
+    /// Whether the player's process was found at the last check.
+    player_running: Option<bool>,
+    player_checked: Option<Instant>,
+    /// The capture file being watched, to notice when another is put in its
+    /// place, and when that was last checked.
+    capture_identity: Option<(u64, u64)>,
+    capture_checked: Instant,
+    /// Media files whose transcript section is folded away, by path.
+    collapsed_media: HashSet<Option<String>>,
+    /// Entries showing their details.
+    inspected: HashSet<EntryId>,
+    /// The line the mouse was over last frame, which the reading ruler keeps
+    /// lit and the hover translation translates.
+    ruler_hovered: Option<EntryId>,
+    entry_ids: EntryIds,
+    toasts: Toasts,
+    import_path: String,
+    imported: Option<ImportedTranscript>,
+    merge_path: String,
+    merged: Option<MergedSubtitles>,
+    /// Name typed in to save the current layout under.
+    layout_name: String,
+    /// The capture file as of the last read, which the next read is compared
+    /// against; `None` before the first.
+    stream: Option<Vec<SubtitleEntry>>,
+    /// Line that last triggered a watch word notification.
+    last_notified: String,
+    known: KnownWords,
+    /// Unknown words of the line mpv was paused for, until playback resumes.
+    assist_paused: Option<Vec<String>>,
+    /// Line that last paused playback, so its extensions don't pause again.
+    last_paused: String,
+    /// Arrival time of the capture file's first line, to notice new sessions.
+    session_start: Option<i64>,
+    /// Media-time ordering for the current session, starting from the setting.
+    time_ordered: bool,
+    /// Waveforms by media path and cue start in milliseconds.
+    waveforms: HashMap<waveform::Key, WaveformState>,
+    /// Started the first time a waveform is shown.
+    waveform_decoder: Option<waveform::Decoder>,
+    starred: StarredLines,
+    anki_export: Option<AnkiExport>,
+    /// Sends to note services on their way, by service name.
+    note_sends: Vec<(String, Receiver<Result<(), String>>)>,
+    /// The command palette, while it's open.
+    palette: Option<Palette>,
+    /// Show only the lines with reading problems.
+    only_problems: bool,
+    /// New lines since the viewer started, for summaries of the sessions they belong to.
+    session_lines: Vec<SubtitleEntry>,
+    /// The summary of the session that just ended and where it was saved.
+    session_summary: Option<(Summary, String)>,
+    /// Events from the Lua script, such as key presses.
+    script_events: EventReader,
+    /// Receives captions from the browser extension while it's the player.
+    browser_server: Option<browser::Server>,
+    /// Reads JSON lines while they're the player.
+    lines_server: Option<lines::Server>,
+    remote: Option<remote::Poller>,
+    /// Where the raw stream goes.
+    storage: Box<dyn storage::Storage>,
+    /// The history key once the passphrase has been entered.
+    history_key: Option<crypt::Key>,
+    /// The unlock window's passphrase field.
+    passphrase: String,
+    /// The key being derived from the passphrase.
+    unlocking: Option<Receiver<Result<crypt::Key, String>>>,
+    /// Raw stream lines that arrived before the history was unlocked.
+    locked_lines: Vec<SubtitleEntry>,
+    sync_hub: Option<sync::Hub>,
+    sync_client: Option<sync::Client>,
+    sync_connected: bool,
+    /// Status of whichever of the servers above is running.
+    capture_status: String,
+    mqtt: Option<mqtt::Publisher>,
+    /// Changes not yet sent to MQTT and the webhook.
+    held_changes: Vec<store::Change>,
+    line_rate: rate::LineRate,
+    last_auto_export: Instant,
+    watch_folder: Option<watch_folder::WatchFolder>,
+    /// Since when the capture file has failed to parse, and why.
+    corrupt: Option<(Instant, String)>,
+    /// Why installing the script or watching the capture file failed.
+    diagnosis: Option<diagnose::Diagnosis>,
+    /// Whether this session's language is still to be guessed from its
+    /// first lines.
+    language_check: bool,
+    /// The language the session looks to be in and the profile for it, when
+    /// another profile is active.
+    profile_suggestion: Option<(language::Language, usize)>,
+    /// mpv settings and scripts found to get in the way of capture.
+    mpv_conflicts: Vec<mpv_config::Conflict>,
+    /// Whether those are being held back, as low power mode does while minimized.
+    sinks_paused: bool,
+    /// Cleanup run when the window closes.
+    shutdown: ShutdownCoordinator<SubtitleViewer>,
+    webhook: Option<webhook::Webhook>,
+}
+
+/// Something the user asked to do with a subtitle or a word in it.
+enum SubtitleAction {
+    Examples(String),
+    Pronounce(String),
+    /// Play the audio of the subtitle with this ID from its media file.
+    Replay(EntryId),
+    /// Add the subtitle with this ID to the review queue.
+    Mine(EntryId),
+    /// Star or unstar the subtitle with this ID.
+    Star(EntryId),
+    /// Show or hide the details of the subtitle with this ID.
+    Inspect(EntryId),
+    /// The timestamp of the subtitle with this ID was clicked.
+    Timestamp(EntryId),
+    /// Wait for the subtitle with this ID to be spoken, to resync its media.
+    Resync(EntryId),
+    /// The low-confidence subtitle with this ID was checked and is right.
+    Verify(EntryId),
+    /// Move the subtitle with this ID to the trash.
+    Delete(EntryId),
+    /// Pin the subtitle with this ID to the comparison tray.
+    Pin(EntryId),
+    /// Pin every line in view that uses the word.
+    PinWord(String),
+    /// Send the subtitle with this ID to the enabled note services.
+    SendToNotes(EntryId),
+    /// Look the text up on the search site at this index.
+    Search(usize, String),
+    /// Look the subtitle with this ID up on the search site at this index.
+    SearchLine(usize, EntryId),
+    /// Unmask the subtitle with this ID, or its hidden side.
+    Reveal(EntryId),
+    ToggleKnown(String),
+    /// Open the command palette on the subtitle with this ID, and the word
+    /// if it was a word that was right-clicked.
+    Commands(EntryId, Option<String>),
+    /// Switch to the saved layout at this index.
+    SwitchLayout(usize),
+}
+
+enum WaveformState {
+    Loading,
+    Ready(Waveform),
+    Failed(String),
+}
+
+/// The open command palette.
+struct Palette {
+    /// What's typed to pick a command.
+    filter: String,
+    /// The line the commands act on; the latest one if `None`.
+    line: Option<EntryId>,
+    /// The word right-clicked to open it, if it was opened that way.
+    word: Option<String>,
+}
+
+/// A running or finished export of starred lines to Anki.
+struct AnkiExport {
+    total: usize,
+    /// Started from mpv: report the outcome on its OSD rather than in a window.
+    quiet: bool,
+    /// The window was closed before the export finished; its outcome is
+    /// reported in a toast instead.
+    hidden: bool,
+    results: Vec<(StarredLine, anki::Outcome)>,
+    /// Dropped once every line has an outcome.
+    rx: Option<Receiver<(StarredLine, anki::Outcome)>>,
+}
+
+struct ExampleLookup {
+    word: String,
+    rx: Receiver<Result<Vec<Example>, String>>,
+    result: Option<Result<Vec<Example>, String>>,
 }
 
 fn format_timestamp(seconds: f64) -> String {
@@ -42,87 +478,4441 @@ fn format_timestamp(seconds: f64) -> String {
     }
 }
 
-fn filter_prefix_subtitles(subtitles: Vec<SubtitleEntry>) -> Vec<SubtitleEntry> {
-    let mut filtered = Vec::new();
-    for i in 0..subtitles.len() {
-        let should_include = if i < subtitles.len() - 1 {
-            // Check if current subtitle is a prefix of the next one
-            !subtitles[i + 1].text.starts_with(&subtitles[i].text)
+/// How subtitle text is drawn.
+#[derive(Clone, Copy)]
+struct SubtitleFont {
+    size: f32,
+    letter_spacing: f32,
+}
+
+impl SubtitleFont {
+    fn text(self, text: impl Into<String>) -> egui::RichText {
+        egui::RichText::new(text).size(self.size).extra_letter_spacing(self.letter_spacing)
+    }
+    
+    /// `marked` in one piece with its emphasis.
+    fn layout(self, marked: &Marked, ui: &egui::Ui) -> egui::text::LayoutJob {
+        let format = egui::TextFormat {
+            font_id: egui::FontId::proportional(self.size),
+            extra_letter_spacing: self.letter_spacing,
+            color: ui.visuals().text_color(),
+            ..Default::default()
+        };
+        marked.layout_job(format, ui.visuals().strong_text_color())
+    }
+}
+
+/// Subtitle text, with grammar pattern matches underlined and explained on
+/// hover, words tinted by frequency band and emphasis from markup kept.
+/// Words have a context menu of lookups followed by the row's actions; the
+/// chosen one is returned.
+fn subtitle_label(
+    ui: &mut egui::Ui,
+    row: RowMenu,
+    marked: &Marked,
+    font: SubtitleFont,
+    grammar: Option<&GrammarDb>,
+    frequency: Option<&FrequencyList>,
+    known: impl Fn(&str) -> bool,
+) -> Option<SubtitleAction> {
+    let text = marked.text.as_str();
+    let spans = match grammar {
+        Some(grammar) => grammar.annotate(text),
+        None => vec![grammar::Span { range: 0..text.len(), pattern: None }],
+    };
+    // Without a list every word is "unknown", so only color when one is loaded
+    let tokens = match frequency {
+        Some(frequency) => frequency.tokenize(text),
+        None => FrequencyList::default().tokenize(text),
+    };
+    let mut action = None;
+    // Tokens sit flush against each other; keep the usual gap after the timestamp
+    let gap = ui.spacing().item_spacing.x;
+    ui.spacing_mut().item_spacing.x = 0.0;
+    ui.add_space(gap);
+    for span in spans {
+        for token in tokens.iter().filter(|t| t.range.start < span.range.end && span.range.start < t.range.end) {
+            let range = token.range.start.max(span.range.start)..token.range.end.min(span.range.end);
+            let word = token.band.map(|_| &text[token.range.clone()]);
+            let is_known = word.is_some_and(&known);
+            for (range, emphasis) in marked.pieces(range) {
+                let mut part = font.text(&text[range]);
+                if emphasis.italic {
+                    part = part.italics();
+                }
+                if emphasis.bold {
+                    part = part.strong();
+                }
+                if let Some(color) = token.band.filter(|_| frequency.is_some() && !is_known).and_then(Band::color) {
+                    part = part.color(color);
+                }
+                let response = match (span.pattern, grammar) {
+                    (Some(index), Some(grammar)) => ui
+                        .add(egui::Label::new(part.underline()).sense(egui::Sense::click()))
+                        .on_hover_text(grammar.pattern(index).tooltip()),
+                    _ => ui.add(egui::Label::new(part).sense(egui::Sense::click())),
+                };
+                response.context_menu(|ui| {
+                    if let Some(word) = word {
+                        ui.label(egui::RichText::new(word).strong());
+                        if ui.button("Example sentences").clicked() {
+                            action = Some(SubtitleAction::Examples(word.to_string()));
+                            ui.close_menu();
+                        }
+                        if ui.button("Play pronunciation").clicked() {
+                            action = Some(SubtitleAction::Pronounce(word.to_string()));
+                            ui.close_menu();
+                        }
+                        if ui.button(if is_known { "Mark as unknown" } else { "Mark as known" }).clicked() {
+                            action = Some(SubtitleAction::ToggleKnown(word.to_string()));
+                            ui.close_menu();
+                        }
+                        if ui.button("Pin lines with this word").clicked() {
+                            action = Some(SubtitleAction::PinWord(word.to_string()));
+                            ui.close_menu();
+                        }
+                        search_menu(ui, "Search word on", row.sites, &mut action, |site| {
+                            SubtitleAction::Search(site, word.to_string())
+                        });
+                        ui.separator();
+                    }
+                    row.show(ui, word, &mut action);
+                });
+            }
+        }
+    }
+    action
+}
+
+/// Right-to-left subtitles are shaped, wrapped and reordered here and drawn
+/// as whole right-aligned lines, since per-word labels flow left to right.
+fn rtl_subtitle_label(ui: &mut egui::Ui, row: RowMenu, text: &str, font: SubtitleFont) -> Option<SubtitleAction> {
+    let shaped = bidi::shape_arabic(text);
+    let font_id = egui::FontId::proportional(font.size);
+    let color = ui.visuals().text_color();
+    let measure = |line: &str| {
+        let width = ui.fonts(|fonts| fonts.layout_no_wrap(line.to_string(), font_id.clone(), color).size().x);
+        width + font.letter_spacing * line.chars().count() as f32
+    };
+    let lines = bidi::wrap_logical(&shaped, ui.available_width(), measure);
+    let mut action = None;
+    ui.with_layout(egui::Layout::top_down(egui::Align::RIGHT), |ui| {
+        for line in lines {
+            let visual = bidi::visual_order(&line, true);
+            ui.add(egui::Label::new(font.text(visual)).extend().sense(egui::Sense::click()))
+                .context_menu(|ui| row.show(ui, None, &mut action));
+        }
+    });
+    action
+}
+
+/// Draw `text` top to bottom in columns starting at the right edge, filling
+/// the available space.
+fn vertical_text(ui: &mut egui::Ui, text: &str, font: SubtitleFont) -> egui::Response {
+    let cell = font.size * 1.2 + font.letter_spacing;
+    let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click());
+    let rows = (rect.height() / cell).floor().max(1.0) as usize;
+    let font = egui::FontId::proportional(font.size);
+    let color = ui.visuals().text_color();
+    for (i, column) in vertical::columns(text, rows).iter().enumerate() {
+        let x = rect.right() - cell * (i as f32 + 0.5);
+        for (j, c) in column.iter().enumerate() {
+            let pos = egui::pos2(x, rect.top() + cell * (j as f32 + 0.5));
+            ui.painter().text(pos, egui::Align2::CENTER_CENTER, c, font.clone(), color);
+        }
+    }
+    response
+}
+
+/// An invisible live region holding the latest line, which screen readers
+/// read out whenever it changes.
+fn announce(ui: &mut egui::Ui, text: &str) {
+    let rect = egui::Rect::from_min_size(ui.cursor().min, egui::Vec2::ZERO);
+    let response = ui.interact(rect, egui::Id::new("subtitle_announcer"), egui::Sense::hover());
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, text));
+    ui.ctx().accesskit_node_builder(response.id, |node| {
+        node.set_live(egui::accesskit::Live::Polite);
+    });
+}
+
+/// A submenu with a button per search site, if there are any.
+fn search_menu(
+    ui: &mut egui::Ui,
+    title: &str,
+    sites: &[SearchSite],
+    action: &mut Option<SubtitleAction>,
+    search: impl Fn(usize) -> SubtitleAction,
+) {
+    if sites.is_empty() {
+        return;
+    }
+    ui.menu_button(title, |ui| {
+        for (index, site) in sites.iter().enumerate() {
+            if ui.button(&site.name).clicked() {
+                *action = Some(search(index));
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// Context menu entries that apply to a whole subtitle.
+#[derive(Clone, Copy)]
+struct RowMenu<'a> {
+    id: EntryId,
+    sites: &'a [SearchSite],
+}
+
+impl RowMenu<'_> {
+    /// The entries, after those for `word` if a word was right-clicked.
+    fn show(self, ui: &mut egui::Ui, word: Option<&str>, action: &mut Option<SubtitleAction>) {
+        let id = self.id;
+        if ui.button("Add to review queue").clicked() {
+            *action = Some(SubtitleAction::Mine(id));
+            ui.close_menu();
+        }
+        if ui.button("Pin to tray").clicked() {
+            *action = Some(SubtitleAction::Pin(id));
+            ui.close_menu();
+        }
+        if ui.button("Send to note services").clicked() {
+            *action = Some(SubtitleAction::SendToNotes(id));
+            ui.close_menu();
+        }
+        search_menu(ui, "Search line on", self.sites, action, |site| SubtitleAction::SearchLine(site, id));
+        if ui.button("Details").clicked() {
+            *action = Some(SubtitleAction::Inspect(id));
+            ui.close_menu();
+        }
+        if ui.button("Resync from this line").clicked() {
+            *action = Some(SubtitleAction::Resync(id));
+            ui.close_menu();
+        }
+        if ui.button("Delete").on_hover_text("Hide the line; it can be restored from the trash").clicked() {
+            *action = Some(SubtitleAction::Delete(id));
+            ui.close_menu();
+        }
+        if ui.button("Commands…").on_hover_text("The command palette, for this line").clicked() {
+            *action = Some(SubtitleAction::Commands(id, word.map(str::to_string)));
+            ui.close_menu();
+        }
+    }
+}
+
+/// Every field of an entry as read, for the details under a row.
+fn entry_details(sub: &SubtitleEntry) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("Text", format!("{:?}", sub.text)),
+        ("Start", format!("{} ({} s)", format_timestamp(sub.start_time), sub.start_time)),
+    ];
+    if let Some(end_time) = sub.end_time {
+        details.push(("End", format!("{} ({} s)", format_timestamp(end_time), end_time)));
+    }
+    let arrived = chrono::DateTime::from_timestamp(sub.timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local);
+    details.push(("Arrived", format!("{} ({})", arrived.format("%Y-%m-%d %H:%M:%S"), sub.timestamp)));
+    if let Some(translation) = &sub.translation {
+        details.push(("Translation", translation.clone()));
+    }
+    if let Some(confidence) = sub.confidence {
+        details.push(("Confidence", format!("{:.0}%", confidence * 100.0)));
+    }
+    if let Some(source) = sub.source {
+        details.push(("Source", source.label().to_string()));
+    }
+    if let Some(title) = &sub.title {
+        details.push(("Title", title.clone()));
+    }
+    if let Some(path) = &sub.path {
+        details.push(("Media", path.clone()));
+    }
+    if let Some(duration) = sub.duration {
+        details.push(("Media length", format_timestamp(duration)));
+    }
+    if let Some(ass) = &sub.ass {
+        if !ass.actor.is_empty() {
+            details.push(("Speaker", ass.actor.clone()));
+        }
+        details.push(("Style", format!("{} (layer {})", ass.style, ass.layer)));
+        if !ass.effect.is_empty() {
+            details.push(("Effect", ass.effect.clone()));
+        }
+        details.push(("ASS text", ass.raw_text.clone()));
+    }
+    details
+}
+
+/// A ring filled clockwise from the top to show progress towards a goal.
+fn progress_ring(ui: &mut egui::Ui, fraction: f32, radius: f32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(radius * 2.0, radius * 2.0), egui::Sense::hover());
+    let width = radius * 0.35;
+    let center = rect.center();
+    let ring_radius = radius - width / 2.0;
+    let painter = ui.painter();
+    painter.circle_stroke(center, ring_radius, egui::Stroke::new(width, ui.visuals().widgets.inactive.bg_fill));
+    let fraction = fraction.clamp(0.0, 1.0);
+    if fraction > 0.0 {
+        let color = if fraction >= 1.0 {
+            egui::Color32::from_rgb(0, 200, 0)
         } else {
-            // Always include the last subtitle
-            true
+            egui::Color32::from_rgb(100, 160, 255)
         };
-        
-        if should_include {
-            filtered.push(subtitles[i].clone());
+        let steps = (fraction * 48.0).ceil() as usize;
+        let points: Vec<egui::Pos2> = (0..=steps)
+            .map(|i| {
+                let angle = -std::f32::consts::FRAC_PI_2 + std::f32::consts::TAU * fraction * i as f32 / steps as f32;
+                center + ring_radius * egui::vec2(angle.cos(), angle.sin())
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(width, color)));
+    }
+    response
+}
+
+/// A strip of audio peaks around a cue, with the cue itself shaded.
+fn waveform_strip(ui: &mut egui::Ui, waveform: &Waveform, cue_start: f64, cue_end: Option<f64>) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width().min(360.0), 28.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let span = (waveform.end - waveform.start).max(0.001);
+    let x_at = |time: f64| rect.left() + rect.width() * ((time - waveform.start) / span).clamp(0.0, 1.0) as f32;
+    let cue_end = cue_end.unwrap_or(waveform.end - waveform::PADDING);
+    let cue = egui::Rect::from_x_y_ranges(x_at(cue_start)..=x_at(cue_end), rect.y_range());
+    painter.rect_filled(cue, 0.0, egui::Color32::from_rgba_unmultiplied(100, 160, 255, 40));
+    let color = ui.visuals().text_color();
+    let step = rect.width() / waveform.peaks.len().max(1) as f32;
+    for (i, peak) in waveform.peaks.iter().enumerate() {
+        let x = rect.left() + step * (i as f32 + 0.5);
+        let half = (peak * rect.height() / 2.0).max(0.5);
+        painter.line_segment(
+            [egui::pos2(x, rect.center().y - half), egui::pos2(x, rect.center().y + half)],
+            egui::Stroke::new(step.max(1.0) * 0.7, color),
+        );
+    }
+    let boundary = egui::Stroke::new(1.0, egui::Color32::from_rgb(100, 160, 255));
+    for x in [cue.left(), cue.right()] {
+        painter.vline(x, rect.y_range(), boundary);
+    }
+    response.on_hover_text(format!("{} – {}", format_timestamp(cue_start), format_timestamp(cue_end)))
+}
+
+/// GitHub-style calendar of the last year, one column per week (Monday on
+/// top), shaded by watching time.
+fn activity_heatmap(ui: &mut egui::Ui, days: &[DayStats], today: chrono::NaiveDate) {
+    const WEEKS: i64 = 53;
+    const CELL: f32 = 10.0;
+    const GAP: f32 = 2.0;
+    let shades = [
+        ui.visuals().widgets.inactive.bg_fill,
+        egui::Color32::from_rgb(14, 68, 41),
+        egui::Color32::from_rgb(0, 109, 50),
+        egui::Color32::from_rgb(38, 166, 65),
+        egui::Color32::from_rgb(57, 211, 83),
+    ];
+    let first = today - chrono::Duration::days(chrono::Datelike::weekday(&today).num_days_from_monday() as i64 + (WEEKS - 1) * 7);
+    let size = egui::vec2(WEEKS as f32 * (CELL + GAP), 7.0 * (CELL + GAP));
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let mut date = first;
+    while date <= today {
+        let offset = (date - first).num_days();
+        let min = rect.min + egui::vec2((offset / 7) as f32 * (CELL + GAP), (offset % 7) as f32 * (CELL + GAP));
+        let cell = egui::Rect::from_min_size(min, egui::vec2(CELL, CELL));
+        let day = days.iter().find(|day| day.date == date);
+        let level = day.map_or(0, |day| stats::activity_level(day.seconds));
+        ui.painter().rect_filled(cell, 2.0, shades[level]);
+        let response = ui.interact(cell, ui.id().with(("heatmap", offset)), egui::Sense::hover());
+        response.on_hover_ui(|ui| {
+            ui.label(egui::RichText::new(date.format("%a %Y-%m-%d").to_string()).strong());
+            match day {
+                Some(day) => {
+                    ui.label(format!("{} min, {} lines", day.seconds / 60, day.lines));
+                    for name in &day.media {
+                        ui.label(name);
+                    }
+                }
+                None => {
+                    ui.label("No activity");
+                }
+            }
+        });
+        date = date.succ_opt().unwrap();
+    }
+}
+
+/// Editor for an optional goal; returns true when it changed.
+fn goal_editor(ui: &mut egui::Ui, label: &str, goal: &mut Option<Goal>) -> bool {
+    let before = *goal;
+    ui.horizontal(|ui| {
+        let mut enabled = goal.is_some();
+        ui.checkbox(&mut enabled, label);
+        match (enabled, goal.as_mut()) {
+            (true, Some(goal)) => {
+                ui.add(egui::DragValue::new(&mut goal.target).range(1..=u64::MAX));
+                egui::ComboBox::from_id_source(label)
+                    .selected_text(goal.unit.label())
+                    .show_ui(ui, |ui| {
+                        for unit in GoalUnit::ALL {
+                            ui.selectable_value(&mut goal.unit, unit, unit.label());
+                        }
+                    });
+            }
+            (true, None) => *goal = Some(Goal { unit: GoalUnit::Minutes, target: 30 }),
+            (false, _) => *goal = None,
+        }
+    });
+    *goal != before
+}
+
+/// Why a line was left out of the view.
+#[derive(Debug, Clone, PartialEq)]
+enum FilterReason {
+    /// The next line starts with it, as when text is typed out bit by bit.
+    Prefix { next: String },
+    /// The next line ends with it (`CollapseMode::PrefixOrSuffix`).
+    Suffix { next: String },
+    /// The next line has it somewhere in the middle (`CollapseMode::Anywhere`).
+    Contained { next: String },
+    /// It repeats a line from the same file that arrived this much earlier.
+    Repeat { after_secs: i64 },
+    /// The source's noise filter caught it.
+    Noise(noise::Noise),
+}
+
+impl FilterReason {
+    fn label(&self) -> String {
+        match self {
+            FilterReason::Prefix { next } => format!("prefix of \"{}\"", next.replace('\n', " ")),
+            FilterReason::Suffix { next } => format!("end of \"{}\"", next.replace('\n', " ")),
+            FilterReason::Contained { next } => format!("contained in \"{}\"", next.replace('\n', " ")),
+            FilterReason::Repeat { after_secs } => format!("repeat of a line {} s earlier", after_secs),
+            FilterReason::Noise(noise) => noise.label(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FilteredLine {
+    entry: SubtitleEntry,
+    reason: FilterReason,
+}
+
+/// How `next` carries `line` on under `mode`, if it does: as a prefix
+/// first, then as a suffix, then anywhere inside.
+fn collapse_reason(line: &str, next: &str, mode: CollapseMode) -> Option<FilterReason> {
+    if next.starts_with(line) {
+        Some(FilterReason::Prefix { next: next.to_string() })
+    } else if mode != CollapseMode::Prefix && next.ends_with(line) {
+        Some(FilterReason::Suffix { next: next.to_string() })
+    } else if mode == CollapseMode::Anywhere && next.contains(line) {
+        Some(FilterReason::Contained { next: next.to_string() })
+    } else {
+        None
+    }
+}
+
+/// Drop lines the next line carries on (see [`collapse_reason`]), as long as
+/// it arrived at most `window` seconds later (0 for any distance): a sentence
+/// repeated much later that happens to extend an earlier line isn't that line
+/// typed out.
+fn split_prefix_subtitles(
+    subtitles: Vec<SubtitleEntry>,
+    window: u32,
+    mode: CollapseMode,
+) -> (Vec<SubtitleEntry>, Vec<FilteredLine>) {
+    let mut filtered = Vec::new();
+    let mut removed = Vec::new();
+    for i in 0..subtitles.len() {
+        // The last subtitle is always included
+        let reason = subtitles.get(i + 1).and_then(|next| {
+            let close = window == 0 || (next.timestamp - subtitles[i].timestamp).abs() <= window as i64;
+            close.then(|| collapse_reason(&subtitles[i].text, &next.text, mode)).flatten()
+        });
+        match reason {
+            Some(reason) => removed.push(FilteredLine {
+                entry: subtitles[i].clone(),
+                reason,
+            }),
+            None => filtered.push(subtitles[i].clone()),
+        }
+    }
+    (filtered, removed)
+}
+
+fn filter_prefix_subtitles(subtitles: Vec<SubtitleEntry>, window: u32, mode: CollapseMode) -> Vec<SubtitleEntry> {
+    split_prefix_subtitles(subtitles, window, mode).0
+}
+
+/// Drop lines that repeat a line from the same file which arrived at most
+/// `window` seconds earlier, as happens when mpv re-fires `sub-text` after a seek.
+fn split_duplicate_subtitles(subtitles: Vec<SubtitleEntry>, window: u32) -> (Vec<SubtitleEntry>, Vec<FilteredLine>) {
+    if window == 0 {
+        return (subtitles, Vec::new());
+    }
+    let mut filtered: Vec<SubtitleEntry> = Vec::new();
+    let mut removed = Vec::new();
+    for sub in subtitles {
+        let repeated = filtered
+            .iter()
+            .rev()
+            .take_while(|kept| (sub.timestamp - kept.timestamp).abs() <= window as i64)
+            .find(|kept| kept.text == sub.text && kept.path == sub.path);
+        match repeated {
+            Some(kept) => {
+                let after_secs = sub.timestamp - kept.timestamp;
+                removed.push(FilteredLine {
+                    entry: sub,
+                    reason: FilterReason::Repeat { after_secs },
+                });
+            }
+            None => filtered.push(sub),
+        }
+    }
+    (filtered, removed)
+}
+
+/// Lines the source's noise filter lets through, and those it catches.
+fn split_noise(
+    subtitles: Vec<SubtitleEntry>,
+    filter: Option<&noise::NoiseFilter>,
+) -> (Vec<SubtitleEntry>, Vec<FilteredLine>) {
+    let Some(filter) = filter.filter(|filter| filter.is_active()) else {
+        return (subtitles, Vec::new());
+    };
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+    for entry in subtitles {
+        match filter.check(&entry) {
+            Some(noise) => removed.push(FilteredLine {
+                entry,
+                reason: FilterReason::Noise(noise),
+            }),
+            None => kept.push(entry),
         }
     }
-    filtered
+    (kept, removed)
+}
+
+/// The filters applied to lines as read, before they're shown, returning
+/// the lines kept and those left out (in arrival order) with the reason.
+fn filter_pipeline(mut subtitles: Vec<SubtitleEntry>, settings: &Settings) -> (Vec<SubtitleEntry>, Vec<FilteredLine>) {
+    source::tag(&mut subtitles, settings.player);
+    // Hidden sources are a view choice, not filtering, so they aren't listed
+    let hidden = |source: &source::Source| settings.sources.get(source).is_some_and(|style| style.hidden);
+    subtitles.retain(|sub| !sub.source.as_ref().is_some_and(hidden));
+    let (subtitles, noise) = split_noise(subtitles, settings.noise_filters.get(&settings.player));
+    let (kept, mut removed) = split_prefix_subtitles(subtitles, settings.prefix_window_secs, settings.collapse_mode);
+    let (kept, repeats) = split_duplicate_subtitles(kept, settings.dedup_window_secs);
+    removed.extend(noise);
+    removed.extend(repeats);
+    removed.sort_by_key(|line| line.entry.timestamp);
+    (kept, removed)
+}
+
+/// Where the capture file is read from: the player's, or the local mirror
+/// of a remote one or of a sync hub's lines.
+fn capture_file(settings: &Settings, script_opts: &ScriptOpts) -> String {
+    if settings.sync_mode == SyncMode::Client {
+        return sync::mirror_path();
+    }
+    match remote::RemoteFile::parse(&settings.remote_capture) {
+        Ok(remote) => remote.mirror_path(),
+        Err(_) => settings.player.output_file(script_opts),
+    }
+}
+
+/// Watch a capture file and the script's events file beside it, returning
+/// what went wrong if that failed for some reason other than the file not
+/// existing yet.
+fn watch_capture_file(watcher: &mut RecommendedWatcher, subtitle_file: &str) -> Option<diagnose::Diagnosis> {
+    let _ = watcher.watch(Path::new(&protocol::events_path(subtitle_file)), RecursiveMode::NonRecursive);
+    let Err(e) = watcher.watch(Path::new(subtitle_file), RecursiveMode::NonRecursive) else {
+        return None;
+    };
+    eprintln!("Warning: Could not watch subtitle file ({}). Will attempt to read it anyway.", e);
+    let missing = matches!(&e.kind, notify::ErrorKind::PathNotFound)
+        || matches!(&e.kind, notify::ErrorKind::Io(io) if io.kind() == std::io::ErrorKind::NotFound);
+    (!missing).then(|| diagnose::watch_failed(Path::new(subtitle_file), &e))
 }
 
 impl SubtitleViewer {
-    fn new() -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, launch: LaunchOptions) -> Self {
         let (tx, rx) = channel();
-        let subtitle_file = "/tmp/mpv-subtitles.json".to_string();
+        let script_opts = ScriptOpts::load();
+        let settings = Settings::load();
+        let subtitle_file = capture_file(&settings, &script_opts);
+        
+        let watcher = RecommendedWatcher::new(tx, Config::default()).unwrap();
+        let events_file = protocol::events_path(&subtitle_file);
+        
+        cc.egui_ctx.set_zoom_factor(settings.ui_scale);
+        // Standard keeps whatever theme eframe picked from the system
+        if settings.preset != Preset::Standard {
+            cc.egui_ctx.set_visuals(settings.preset.visuals());
+        }
+        power::apply_style(&cc.egui_ctx, settings.low_power);
+        let ipc = MpvIpc::spawn(&settings.mpv_socket);
+        let time_ordered = settings.time_ordered;
+        let stats_db = StatsDb::load(None);
+        let stats_locked = stats_db.is_none();
+        
+        let mut viewer = Self {
+            subtitles: Arc::new(Mutex::new(Vec::new())),
+            rx,
+            watcher,
+            subtitle_file,
+            always_on_top: true,
+            file_exists: false,
+            script_installed: false,
+            saved_settings: settings.clone(),
+            settings,
+            script_opts,
+            show_settings: false,
+            show_stats: false,
+            stats_session: None,
+            font_status: String::new(),
+            grammar: None,
+            grammar_status: String::new(),
+            frequency: None,
+            frequency_status: String::new(),
+            example_lookup: None,
+            resync: None,
+            sync_correction: None,
+            pronunciation: None,
+            audio_error: None,
+            review: ReviewQueue::load(),
+            stats_db: stats_db.unwrap_or_default(),
+            stats_locked,
+            stats_export_status: String::new(),
+            stats_tag: None,
+            tag_edit: None,
+            show_review: false,
+            show_overlay: false,
+            show_presenter: false,
+            verified: HashSet::new(),
+            filtered_out: Vec::new(),
+            show_filtered: false,
+            trash: trash::Trash::default(),
+            show_trash: false,
+            edits: editor::Edits::default(),
+            editor_session: None,
+            timing_fix: None,
+            show_timing_problems: false,
+            tray: tray::Tray::default(),
+            hover_translations: machine_translation::Cache::default(),
+            show_tray: false,
+            cloze: None,
+            cloze_revealed: HashSet::new(),
+            delayed: listening::Delayed::default(),
+            show_capture_tail: false,
+            capture_tail: None,
+            capture_tail_count: 20,
+            review_revealed: false,
+            ipc,
+            mpv_connected: false,
+            mpv_paused: false,
+            stall_watchdog: watchdog::StallWatchdog::default(),
+            mpv_reconnecting: None,
+            sub_tracks: Vec::new(),
+            sid: None,
+            secondary_sid: None,
+            audio_tracks: Vec::new(),
+            aid: None,
+            speed: 1.0,
+            sub_delay: None,
+            current_show: None,
+            show_base: None,
+            pending_sub_delay: None,
+            player_running: None,
+            player_checked: None,
+            capture_identity: None,
+            capture_checked: Instant::now(),
+            collapsed_media: HashSet::new(),
+            inspected: HashSet::new(),
+            ruler_hovered: None,
+            entry_ids: EntryIds::default(),
+            toasts: Toasts::default(),
+            import_path: String::new(),
+            merge_path: String::new(),
+            layout_name: String::new(),
+            merged: None,
+            imported: None,
+            stream: None,
+            last_notified: String::new(),
+            known: KnownWords::load(),
+            assist_paused: None,
+            last_paused: String::new(),
+            session_start: None,
+            time_ordered,
+            waveforms: HashMap::new(),
+            waveform_decoder: None,
+            starred: StarredLines::load(),
+            anki_export: None,
+            note_sends: Vec::new(),
+            palette: None,
+            only_problems: false,
+            session_lines: Vec::new(),
+            session_summary: None,
+            script_events: EventReader::new(events_file),
+            browser_server: None,
+            lines_server: None,
+            remote: None,
+            storage: Box::new(storage::JsonlStorage::new(raw::raw_dir(), None)),
+            history_key: None,
+            passphrase: String::new(),
+            unlocking: None,
+            locked_lines: Vec::new(),
+            sync_hub: None,
+            sync_client: None,
+            sync_connected: false,
+            capture_status: String::new(),
+            mqtt: None,
+            held_changes: Vec::new(),
+            line_rate: rate::LineRate::default(),
+            last_auto_export: Instant::now(),
+            watch_folder: None,
+            corrupt: None,
+            diagnosis: None,
+            language_check: false,
+            profile_suggestion: None,
+            mpv_conflicts: Vec::new(),
+            sinks_paused: false,
+            shutdown: ShutdownCoordinator::default(),
+            webhook: None,
+        };
+        
+        // Load initial content
+        viewer.open_storage();
+        viewer.start_capture();
+        viewer.start_watch_folder();
+        viewer.start_mqtt();
+        viewer.start_webhook();
+        viewer.install_fonts(&cc.egui_ctx);
+        viewer.load_subtitles();
+        viewer.load_grammar();
+        viewer.load_frequency_list();
+        viewer.register_shutdown_steps();
+        viewer.apply_launch_options(&cc.egui_ctx, launch);
+        if viewer.stats_locked && !viewer.settings.encrypt_history {
+            viewer.warn("Statistics are encrypted; turn on encrypting the history to read them".to_string());
+        }
         
-        // Set up file watcher
-        let mut watcher = RecommendedWatcher::new(tx, Config::default()).unwrap();
-        watcher
-            .watch(Path::new(&subtitle_file), RecursiveMode::NonRecursive)
-            .unwrap_or_else(|_| {
-                eprintln!("Warning: Could not watch subtitle file. Will attempt to read it anyway.");
+        viewer
+    }
+    
+    fn apply_launch_options(&mut self, ctx: &egui::Context, launch: LaunchOptions) {
+        if let Some(name) = &launch.layout {
+            let layout = self.settings.layouts.iter().find(|layout| layout.name.eq_ignore_ascii_case(name.trim()));
+            match layout.cloned() {
+                Some(layout) => self.apply_layout(ctx, layout),
+                None => self.warn(format!("No saved layout called \"{}\"", name)),
+            }
+        }
+        if launch.overlay {
+            self.show_overlay = true;
+        }
+        if launch.minimized {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        }
+    }
+    
+    fn register_shutdown_steps(&mut self) {
+        self.shutdown.register("auto-export", |viewer| {
+            if viewer.imported.is_some() {
+                return Ok(());
+            }
+            let subtitles = viewer.subtitles.lock().unwrap().clone();
+            let settings = &viewer.settings;
+            export::export_on_close(&subtitles, &settings.auto_export, &settings.export_dir, &settings.cue_timing)
+        });
+        // Closing the viewer ends the session too; its summary is only saved
+        self.shutdown.register("session summary", |viewer| {
+            let Some(record) = viewer.stats_db.sessions.last() else {
+                return Ok(());
+            };
+            match viewer.summarize(record).filter(|_| viewer.settings.session_summary) {
+                Some(summary) => {
+                    let key = viewer.sealing_key()?;
+                    summary.save(&viewer.subtitle_file, key).map(|_| ()).map_err(|e| e.to_string())
+                }
+                None => Ok(()),
+            }
+        });
+        self.shutdown.register("statistics", |viewer| {
+            viewer.stats_db.set_paused(false, chrono::Utc::now().timestamp());
+            viewer.save_stats()
+        });
+        self.shutdown.register("held lines", |viewer| {
+            viewer.flush_sinks();
+            Ok(())
+        });
+        self.shutdown.register("webhook", |viewer| match viewer.webhook.take() {
+            Some(webhook) => webhook.finish(shutdown::FLUSH_TIMEOUT),
+            None => Ok(()),
+        });
+        self.shutdown.register("MQTT", |viewer| match viewer.mqtt.take() {
+            Some(mqtt) => mqtt.finish(shutdown::FLUSH_TIMEOUT),
+            None => Ok(()),
+        });
+        self.shutdown.register("mpv IPC", |viewer| viewer.ipc.disconnect(shutdown::FLUSH_TIMEOUT));
+        self.shutdown.register("capture servers", |viewer| {
+            viewer.browser_server = None;
+            viewer.lines_server = None;
+            Ok(())
+        });
+        self.shutdown.register("capture file", |viewer| {
+            if !viewer.settings.remove_capture_on_exit || !Path::new(&viewer.subtitle_file).exists() {
+                return Ok(());
+            }
+            std::fs::remove_file(&viewer.subtitle_file).map_err(|e| e.to_string())
+        });
+    }
+    
+    fn load_subtitles(&mut self) {
+        if self.show_capture_tail {
+            self.capture_tail = Some(tail::read(&self.subtitle_file, self.capture_tail_count));
+        }
+        self.file_exists = std::path::Path::new(&self.subtitle_file).exists();
+        self.script_installed = self.check_script_installed();
+        let read = session::try_read_entries(&self.subtitle_file);
+        match &read {
+            Err(e) if self.corrupt.is_none() => self.corrupt = Some((Instant::now(), e.clone())),
+            Err(_) => {}
+            Ok(_) => self.corrupt = None,
+        }
+        if let Ok(Some(mut subs)) = read {
+            self.entry_ids.assign(&mut subs);
+            let first_read = self.stream.is_none();
+            let stream = self.stream.get_or_insert_with(Vec::new);
+            let changes = store::diff(stream, &subs);
+            store::apply(stream, &changes);
+            if let Some(hub) = &self.sync_hub {
+                hub.publish_changes(&changes);
+            }
+            let sessions_before = self.stats_db.sessions.len();
+            // History counts lines as they arrive, so edits and lines dropping off the front leave it be
+            if self.stats_db.record(&store::appended(&changes)) {
+                self.stats_db.update_best_streak(chrono::Local::now().date_naive());
+                if let Err(e) = self.save_stats() {
+                    self.warn(format!("Could not save statistics: {}", e));
+                }
+            }
+            // Every session before the newest one has ended
+            let sessions = &self.stats_db.sessions;
+            let ended = sessions[sessions_before.saturating_sub(1)..sessions.len().saturating_sub(1)].to_vec();
+            for record in ended {
+                self.end_session(&record);
+            }
+            // What was already there on the first read isn't new
+            if !first_read {
+                self.handle_changes(&changes);
+            }
+            if self.imported.is_none() {
+                self.detect_show(subs.last());
+            }
+            let session_start = subs.first().map(|sub| sub.timestamp);
+            if session_start != self.session_start {
+                self.session_start = session_start;
+                self.time_ordered = self.settings.time_ordered;
+                self.language_check = true;
+                self.profile_suggestion = None;
+            }
+            if self.language_check && self.imported.is_none() {
+                self.check_language(&subs);
+            }
+            if let Some((media, correction)) = &self.sync_correction {
+                correction.apply(&mut subs, media);
+            }
+            let (mut filtered_subs, filtered_out) = filter_pipeline(subs, &self.settings);
+            if self.imported.is_some() {
+                return;
+            }
+            self.filtered_out = filtered_out;
+            self.trash.hide(&mut filtered_subs);
+            self.edits.apply(&mut filtered_subs);
+            // Self-testing needs each translation under its line
+            if self.cloze.is_some() {
+                filtered_subs = translation::attach(&filtered_subs);
+            }
+            if self.settings.timeline_view {
+                filtered_subs = session::timeline(&filtered_subs);
+                if let Some(fix) = self.timing_fix {
+                    qc::fix_timing(&mut filtered_subs, fix);
+                }
+            } else if self.time_ordered {
+                session::sort_by_media_time(&mut filtered_subs);
+            }
+            let mut view = self.subtitles.lock().unwrap();
+            let changes = store::diff(&view, &filtered_subs);
+            store::apply(&mut view, &changes);
+            // Lines that only moved, such as after switching to media-time order
+            if *view != filtered_subs {
+                *view = filtered_subs;
+            }
+            drop(view);
+            self.align_merged();
+        }
+    }
+    
+    /// Move the unreadable capture file aside and carry on with an empty one.
+    fn quarantine_capture_file(&mut self) {
+        match session::quarantine(&self.subtitle_file) {
+            Ok(moved) => {
+                if let Err(e) = std::fs::write(&self.subtitle_file, "[]") {
+                    self.warn(format!("Could not start a new capture file: {}", e));
+                }
+                self.toasts.success(format!("Moved the broken capture file to {}", moved.display()));
+                self.corrupt = None;
+                self.load_subtitles();
+            }
+            Err(e) => self.warn(format!("Could not move the capture file aside: {}", e)),
+        }
+    }
+    
+    /// Pass what changed in the capture file on to whatever follows it.
+    fn handle_changes(&mut self, changes: &[store::Change]) {
+        for change in changes {
+            if let store::Change::Remove(entry) = change {
+                self.inspected.remove(&entry.id);
+                self.verified.remove(&entry.id);
+                self.cloze_revealed.remove(&entry.id);
+                self.delayed.reveal(entry.id);
+            }
+        }
+        self.held_changes.extend_from_slice(changes);
+        if !self.sinks_paused {
+            self.flush_sinks();
+        }
+        // The raw stream is a record of arrivals
+        let new_lines = store::appended(changes);
+        if self.settings.keep_raw_stream {
+            if self.sealing_key().is_err() {
+                self.locked_lines.extend(new_lines.iter().cloned());
+            } else if let Err(e) = self.storage.append(&new_lines) {
+                self.warn(format!("Could not save raw stream: {}", e));
+            }
+        }
+        self.handle_new_lines(&new_lines);
+    }
+    
+    /// React to lines that just arrived.
+    fn handle_new_lines(&mut self, lines: &[SubtitleEntry]) {
+        self.line_rate.record(Instant::now(), lines.len());
+        if !lines.is_empty() {
+            self.stall_watchdog.reset(Instant::now());
+        }
+        let words = keywords::parse(&self.settings.watch_words);
+        if self.settings.notify_watch_words && !words.is_empty() {
+            for line in lines {
+                // A line typed out bit by bit only notifies once
+                if !self.last_notified.is_empty() && line.text.starts_with(&self.last_notified) {
+                    continue;
+                }
+                if let Some(word) = keywords::find(&line.text, &words) {
+                    keywords::notify(word, &line.text);
+                    self.last_notified = line.text.clone();
+                }
+            }
+        }
+        if self.settings.pause_on_unknown && self.can_control_mpv() && self.imported.is_none() {
+            self.pause_on_unknown(lines);
+        }
+        if self.settings.listening_first {
+            let delay = Duration::from_secs(u64::from(self.settings.reveal_delay_secs));
+            for line in lines {
+                self.delayed.hold(line.id, Instant::now(), delay);
+            }
+        }
+        self.session_lines.extend_from_slice(lines);
+    }
+    
+    /// Send the changes held back for MQTT and the webhook.
+    fn flush_sinks(&mut self) {
+        let changes = std::mem::take(&mut self.held_changes);
+        if let Some(mqtt) = &self.mqtt {
+            for change in &changes {
+                mqtt.publish(change);
+            }
+        }
+        if let Some(webhook) = &self.webhook {
+            for change in &changes {
+                webhook.send(change);
+            }
+        }
+    }
+    
+    /// Summarize a session from the lines seen while the viewer was running.
+    fn summarize(&self, record: &SessionRecord) -> Option<Summary> {
+        let lines: Vec<&SubtitleEntry> = self
+            .session_lines
+            .iter()
+            .filter(|line| line.path == record.path && (record.start..=record.end).contains(&line.timestamp))
+            .collect();
+        let last = lines.last()?;
+        let default_list = FrequencyList::default();
+        let frequency = self.frequency.as_ref().unwrap_or(&default_list);
+        let mut new_words: Vec<String> = Vec::new();
+        for line in &lines {
+            for word in self.known.unknown_words(&self.settings.profile().name, &line.text, frequency) {
+                if !new_words.iter().any(|w| w == word) {
+                    new_words.push(word.to_string());
+                }
+            }
+        }
+        let starred = self
+            .starred
+            .items
+            .iter()
+            .filter(|item| {
+                lines
+                    .iter()
+                    .any(|line| line.text == item.text && line.start_time == item.start_time && line.path == item.path)
+            })
+            .cloned()
+            .collect();
+        let title = last
+            .title
+            .clone()
+            .or_else(|| last.path.as_deref().map(stats::media_name))
+            .unwrap_or_else(|| "Session".to_string());
+        Some(Summary {
+            record: record.clone(),
+            title,
+            new_words,
+            starred,
+        })
+    }
+    
+    /// Save the summary of a session that has ended and show it.
+    fn end_session(&mut self, record: &SessionRecord) {
+        if self.settings.auto_export.on_session_end {
+            self.auto_export();
+        }
+        let summary = self.summarize(record).filter(|_| self.settings.session_summary);
+        self.session_lines.retain(|line| line.timestamp > record.end);
+        self.trash.purge_until(record.end);
+        let Some(summary) = summary else {
+            return;
+        };
+        let saved = match self.sealing_key() {
+            Ok(key) => match summary.save(&self.subtitle_file, key) {
+                Ok(path) => format!("Saved to {}", path),
+                Err(e) => format!("Could not save summary: {}", e),
+            },
+            Err(e) => format!("Not saved: {}", e),
+        };
+        self.session_summary = Some((summary, saved));
+    }
+    
+    fn session_summary_window(&mut self, ctx: &egui::Context) {
+        let Some((summary, saved)) = &self.session_summary else {
+            return;
+        };
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Session summary")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.heading(&summary.title);
+                let record = &summary.record;
+                ui.label(format!(
+                    "{} min, {} lines, {} characters",
+                    record.seconds / 60,
+                    record.lines,
+                    record.characters
+                ));
+                ui.separator();
+                ui.label(format!("{} new words", summary.new_words.len()));
+                if !summary.new_words.is_empty() {
+                    egui::ScrollArea::vertical().id_source("new_words").max_height(100.0).show(ui, |ui| {
+                        ui.label(summary.new_words.join(", "));
+                    });
+                }
+                ui.separator();
+                ui.label(format!("{} starred lines", summary.starred.len()));
+                egui::ScrollArea::vertical().id_source("summary_starred").max_height(120.0).show(ui, |ui| {
+                    for line in &summary.starred {
+                        ui.label(&line.text);
+                    }
+                });
+                ui.separator();
+                ui.label(egui::RichText::new(saved).small().color(egui::Color32::from_gray(128)));
+                close = ui.button("Close").clicked();
+            });
+        if !open || close {
+            self.session_summary = None;
+        }
+    }
+    
+    fn start_mqtt(&mut self) {
+        self.mqtt = self
+            .settings
+            .mqtt_enabled
+            .then(|| mqtt::Publisher::spawn(&self.settings.mqtt_broker, &self.settings.mqtt_topic));
+    }
+    
+    fn start_webhook(&mut self) {
+        let url = self.settings.webhook_url.trim();
+        self.webhook = (!url.is_empty()).then(|| webhook::Webhook::spawn(url, self.settings.webhook_batch_secs));
+    }
+    
+    /// Pause mpv when the newest line has more unknown words than the setting allows.
+    fn pause_on_unknown(&mut self, lines: &[SubtitleEntry]) {
+        let Some(line) = lines.last() else {
+            return;
+        };
+        if !self.last_paused.is_empty() && line.text.starts_with(&self.last_paused) {
+            return;
+        }
+        let default_list = FrequencyList::default();
+        let frequency = self.frequency.as_ref().unwrap_or(&default_list);
+        let unknown = self.known.unknown_words(&self.settings.profile().name, &line.text, frequency);
+        if unknown.len() > self.settings.unknown_word_limit {
+            self.mpv_request(Request::SetPause { paused: true });
+            self.osd(&format!("Paused: {} unknown words", unknown.len()));
+            self.assist_paused = Some(unknown.into_iter().map(str::to_string).collect());
+            self.last_paused = line.text.clone();
+        }
+    }
+    
+    fn resume_playback(&mut self) {
+        self.mpv_request(Request::SetPause { paused: false });
+        self.osd("Resumed");
+        self.assist_paused = None;
+    }
+    
+    /// Confirm an action taken over IPC on mpv's on-screen display.
+    fn osd(&self, message: &str) {
+        if self.settings.osd_feedback {
+            self.mpv_request(Request::ShowText {
+                text: format!("ScriptView: {}", message),
+                duration_ms: OSD_DURATION_MS,
+            });
+        }
+    }
+    
+    /// Whether mpv can be told what to do, over IPC or through the script.
+    fn can_control_mpv(&self) -> bool {
+        self.mpv_connected || self.script_events.script_running
+    }
+    
+    /// Send a request over the IPC socket when connected, else through the
+    /// Lua script's request file.
+    fn mpv_request(&self, request: Request) {
+        if self.mpv_connected {
+            self.ipc.request(&request);
+        } else if self.script_events.script_running {
+            if let Err(e) = protocol::send_request(&protocol::requests_path(&self.subtitle_file), &request) {
+                self.warn(format!("Could not send request to mpv: {}", e));
+            }
+        }
+    }
+    
+    fn load_grammar(&mut self) {
+        match GrammarDb::load(&self.settings.grammar_file) {
+            Ok(db) => {
+                self.grammar_status = format!("{} patterns loaded", db.len());
+                self.grammar = Some(db);
+            }
+            Err(e) => {
+                self.grammar_status = format!("Not loaded: {}", e);
+                self.grammar = None;
+            }
+        }
+    }
+    
+    fn load_frequency_list(&mut self) {
+        let path = &self.settings.profile().frequency_list;
+        if path.is_empty() {
+            self.frequency_status = "No list set for this profile".to_string();
+            self.frequency = None;
+            return;
+        }
+        match FrequencyList::load(path) {
+            Ok(list) => {
+                self.frequency_status = format!("{} words loaded", list.len());
+                self.frequency = Some(list);
+            }
+            Err(e) => {
+                self.frequency_status = format!("Not loaded: {}", e);
+                self.frequency = None;
+            }
+        }
+    }
+    
+    fn handle_action(&mut self, ctx: &egui::Context, action: SubtitleAction) {
+        match action {
+            SubtitleAction::Examples(word) => {
+                let rx = examples::spawn_lookup(
+                    self.settings.example_source,
+                    self.settings.tatoeba_file.clone(),
+                    self.settings.profile().tatoeba_language.clone(),
+                    word.clone(),
+                );
+                self.example_lookup = Some(ExampleLookup { word, rx, result: None });
+            }
+            SubtitleAction::Pronounce(word) => {
+                let location = match self.settings.audio_source {
+                    AudioSource::LocalDir => self.settings.audio_dir.clone(),
+                    AudioSource::Url => self.settings.audio_url.clone(),
+                };
+                self.audio_error = None;
+                self.pronunciation = Some(audio::spawn_pronounce(self.settings.audio_source, location, word));
+            }
+            SubtitleAction::Replay(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                let Some(media) = sub.path else {
+                    return;
+                };
+                self.audio_error = None;
+                self.pronunciation = Some(audio::spawn_play_clip(media, sub.start_time, sub.end_time));
+            }
+            SubtitleAction::Mine(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                let now = chrono::Utc::now().timestamp();
+                if self.review.add(&sub.text, sub.start_time, sub.path, now) {
+                    self.save_review();
+                }
+            }
+            SubtitleAction::Star(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                let unchecked = sub.confidence.filter(|_| self.needs_check(&sub));
+                let line = StarredLine {
+                    text: sub.text,
+                    start_time: sub.start_time,
+                    end_time: sub.end_time,
+                    path: sub.path,
+                    title: sub.title,
+                };
+                let starred = self.starred.toggle(line.clone());
+                if let Some(client) = &self.sync_client {
+                    client.star(line, starred);
+                }
+                self.save_starred();
+                if let Some(confidence) = unchecked.filter(|_| starred) {
+                    self.toasts.info(format!(
+                        "This line was recognised with {:.0}% confidence; check it before exporting",
+                        confidence * 100.0
+                    ));
+                }
+            }
+            SubtitleAction::Verify(id) => {
+                self.verified.insert(id);
+            }
+            SubtitleAction::Delete(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                if self.imported.is_some() {
+                    self.toasts.info("Deleting works on the live capture");
+                    return;
+                }
+                self.trash.delete(sub);
+                self.inspected.remove(&id);
+                self.subtitles.lock().unwrap().retain(|sub| sub.id != id);
+                self.toasts.info("Moved to the trash (🗑 in the toolbar)");
+            }
+            SubtitleAction::Pin(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                if !self.tray.pin(sub) {
+                    self.toasts.info("Already in the tray");
+                }
+                self.show_tray = true;
+            }
+            SubtitleAction::PinWord(word) => {
+                let pinned = self.tray.pin_with_word(&self.subtitles.lock().unwrap(), &word);
+                self.toasts.info(format!("Pinned {} more lines with {}", pinned, word));
+                self.show_tray = true;
+            }
+            SubtitleAction::SendToNotes(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                let services: Vec<_> =
+                    self.settings.note_services.iter().filter(|service| service.enabled).cloned().collect();
+                if services.is_empty() {
+                    self.toasts.info("Set up a note service in settings first");
+                }
+                for service in services {
+                    let rx = service.send(service.line_body(&sub));
+                    self.note_sends.push((service.name, rx));
+                }
+            }
+            SubtitleAction::Search(site, text) => self.search(ctx, site, &text),
+            SubtitleAction::SearchLine(site, id) => {
+                if let Some(sub) = self.entry(id) {
+                    self.search(ctx, site, &markup::strip(&sub.text).text);
+                }
+            }
+            SubtitleAction::Reveal(id) => {
+                self.delayed.reveal(id);
+                self.cloze_revealed.insert(id);
+            }
+            SubtitleAction::SwitchLayout(index) => {
+                if let Some(layout) = self.settings.layouts.get(index).cloned() {
+                    self.apply_layout(ctx, layout);
+                }
+            }
+            SubtitleAction::Commands(id, word) => {
+                self.palette = Some(Palette {
+                    filter: String::new(),
+                    line: Some(id),
+                    word,
+                });
+            }
+            SubtitleAction::Inspect(id) => {
+                if !self.inspected.remove(&id) {
+                    self.inspected.insert(id);
+                }
+            }
+            SubtitleAction::Timestamp(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                match self.settings.timestamp_click {
+                    TimestampClick::Seek if self.can_control_mpv() => {
+                        self.mpv_request(Request::Seek { time: sub.start_time });
+                    }
+                    TimestampClick::Seek => self.toasts.info("Seeking needs mpv with the script or IPC connected"),
+                    TimestampClick::CopyTime => {
+                        let time = format_timestamp(sub.start_time);
+                        self.toasts.success(format!("Copied {}", time));
+                        ctx.output_mut(|o| o.copied_text = time);
+                    }
+                    TimestampClick::CopyYoutubeLink => {
+                        match sub.path.as_deref().and_then(|url| browser::youtube_link(url, sub.start_time)) {
+                            Some(link) => {
+                                self.toasts.success(format!("Copied {}", link));
+                                ctx.output_mut(|o| o.copied_text = link);
+                            }
+                            None => self.toasts.info("This line isn't from a YouTube video"),
+                        }
+                    }
+                    TimestampClick::Nothing => {}
+                }
+            }
+            SubtitleAction::Resync(id) => {
+                let Some(sub) = self.entry(id) else {
+                    return;
+                };
+                if self.imported.is_some() {
+                    self.toasts.info("Resyncing works on the live capture");
+                    return;
+                }
+                // Anchors are kept in the file's own times
+                let line = match &self.sync_correction {
+                    Some((media, correction)) if *media == sub.path => correction.undo(sub.start_time),
+                    _ => sub.start_time,
+                };
+                let assistant = self.resync.get_or_insert_with(|| ResyncAssistant {
+                    media: sub.path.clone(),
+                    anchors: Vec::new(),
+                    pending: None,
+                });
+                if assistant.media != sub.path {
+                    *assistant = ResyncAssistant {
+                        media: sub.path.clone(),
+                        anchors: Vec::new(),
+                        pending: None,
+                    };
+                }
+                assistant.pending = Some((line, sub.text.clone()));
+                if self.can_control_mpv() {
+                    self.mpv_request(Request::Seek { time: (sub.start_time - 3.0).max(0.0) });
+                    self.osd("Press Alt+y when the line is spoken");
+                }
+            }
+            SubtitleAction::ToggleKnown(word) => {
+                self.known.toggle(&self.settings.profile().name, &word);
+                if let Err(e) = self.known.save() {
+                    self.warn(format!("Could not save known words: {}", e));
+                }
+            }
+        }
+    }
+    
+    fn examples_window(&mut self, ctx: &egui::Context) {
+        let Some(lookup) = &mut self.example_lookup else {
+            return;
+        };
+        if lookup.result.is_none() {
+            if let Ok(result) = lookup.rx.try_recv() {
+                lookup.result = Some(result);
+            }
+        }
+        let mut open = true;
+        egui::Window::new(format!("Examples: {}", lookup.word))
+            .id(egui::Id::new("examples_window"))
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| match &lookup.result {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Searching…");
+                    });
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), e);
+                }
+                Some(Ok(examples)) if examples.is_empty() => {
+                    ui.label("No examples found.");
+                }
+                Some(Ok(examples)) => {
+                    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                        for example in examples {
+                            ui.label(&example.sentence);
+                            if let Some(translation) = &example.translation {
+                                ui.label(egui::RichText::new(translation).color(egui::Color32::from_gray(160)));
+                            }
+                            if let Some(origin) = &example.origin {
+                                ui.label(
+                                    egui::RichText::new(origin)
+                                        .small()
+                                        .color(egui::Color32::from_gray(128)),
+                                );
+                            }
+                            ui.separator();
+                        }
+                    });
+                }
+            });
+        if !open {
+            self.example_lookup = None;
+        }
+    }
+    
+    fn save_review(&self) {
+        if let Err(e) = self.review.save() {
+            self.warn(format!("Could not save review queue: {}", e));
+        }
+    }
+    
+    fn subtitle_font(&self, ctx: &egui::Context) -> SubtitleFont {
+        let size = self.settings.subtitle_points(ctx.zoom_factor());
+        SubtitleFont {
+            size,
+            letter_spacing: self.settings.preset.letter_spacing(size),
+        }
+    }
+    
+    fn install_fonts(&mut self, ctx: &egui::Context) {
+        let reading_font = Some(self.settings.reading_font.as_str()).filter(|_| self.settings.preset.uses_reading_font());
+        self.font_status = fonts::install(ctx, &self.settings.fallback_font, reading_font)
+            .err()
+            .unwrap_or_default();
+    }
+    
+    /// The window and view state as a layout called `name`.
+    fn current_layout(&self, ctx: &egui::Context, name: String) -> Layout {
+        let (inner, outer) = ctx.input(|i| (i.viewport().inner_rect, i.viewport().outer_rect));
+        Layout {
+            name,
+            size: inner.map(|rect| [rect.width(), rect.height()]),
+            position: outer.map(|rect| [rect.min.x, rect.min.y]),
+            always_on_top: self.always_on_top,
+            timeline_view: self.settings.timeline_view,
+            show_overlay: self.show_overlay,
+            show_waveforms: self.settings.show_waveforms,
+            reading_ruler: self.settings.reading_ruler,
+            show_review: self.show_review,
+            show_stats: self.show_stats,
+            font_size: self.settings.font_size,
+        }
+    }
+    
+    fn apply_layout(&mut self, ctx: &egui::Context, layout: Layout) {
+        if let Some([width, height]) = layout.size {
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(width, height)));
+        }
+        if let Some([x, y]) = layout.position {
+            ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(x, y)));
+        }
+        self.always_on_top = layout.always_on_top;
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(if layout.always_on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        }));
+        self.show_overlay = layout.show_overlay;
+        self.settings.show_waveforms = layout.show_waveforms;
+        self.settings.reading_ruler = layout.reading_ruler;
+        self.show_review = layout.show_review;
+        self.show_stats = layout.show_stats;
+        self.settings.font_size = layout.font_size.clamp(self.settings.font_size_min, self.settings.font_size_max);
+        if self.settings.timeline_view != layout.timeline_view {
+            self.settings.timeline_view = layout.timeline_view;
+            self.load_subtitles();
+        }
+    }
+    
+    /// Once the session has enough lines to tell its language, suggest the
+    /// profile for it if another one is active.
+    fn check_language(&mut self, lines: &[SubtitleEntry]) {
+        let sample: Vec<&str> = lines
+            .iter()
+            .filter(|line| !line.text.starts_with(translation::SECONDARY_PREFIX))
+            .take(language::SAMPLE_LINES)
+            .map(|line| line.text.as_str())
+            .collect();
+        if sample.len() < language::SAMPLE_LINES {
+            return;
+        }
+        self.language_check = false;
+        let Some(language) = language::detect(&sample) else {
+            return;
+        };
+        if language.matches(self.settings.profile()) {
+            return;
+        }
+        if let Some(index) = self.settings.profiles.iter().position(|profile| language.matches(profile)) {
+            self.profile_suggestion = Some((language, index));
+        }
+    }
+    
+    /// The settings to remember for `show`, as they are now.
+    fn current_show_settings(&self, show: String) -> ShowSettings {
+        ShowSettings::of(show, &self.settings, self.sub_delay)
+    }
+    
+    /// Use the settings remembered for a show, keeping the user's own aside.
+    /// The filters take effect on the next read.
+    fn apply_show_settings(&mut self, saved: ShowSettings) {
+        if self.show_base.is_none() {
+            self.show_base = Some(self.current_show_settings(String::new()));
+        }
+        self.put_show_settings(&saved);
+        self.toasts.info(format!("Using the settings remembered for {}", saved.show));
+    }
+    
+    /// Go back to the user's own settings after a show's.
+    fn restore_show_base(&mut self) {
+        if let Some(base) = self.show_base.take() {
+            self.put_show_settings(&base);
+        }
+    }
+    
+    fn put_show_settings(&mut self, values: &ShowSettings) {
+        if values.apply_to(&mut self.settings) {
+            self.load_frequency_list();
+        }
+        self.pending_sub_delay = values.sub_delay;
+        self.send_sub_delay();
+    }
+    
+    /// Set mpv's subtitle delay to the one put back, once IPC is connected.
+    fn send_sub_delay(&mut self) {
+        if !self.mpv_connected {
+            return;
+        }
+        if let Some(delay) = self.pending_sub_delay.take() {
+            self.ipc.command(serde_json::json!(["set_property", "sub-delay", delay]));
+            self.osd(&format!("Subtitle delay {:+} ms", (delay * 1000.0).round()));
+        }
+    }
+    
+    /// The settings as they're kept on disk: the user's own values where a
+    /// show's are in use.
+    fn settings_to_save(&self) -> Cow<'_, Settings> {
+        match &self.show_base {
+            Some(base) => {
+                let mut settings = self.settings.clone();
+                base.apply_to(&mut settings);
+                Cow::Owned(settings)
+            }
+            None => Cow::Borrowed(&self.settings),
+        }
+    }
+    
+    /// Note which show the latest line is from, and when it's another one,
+    /// put back the settings remembered for it.
+    fn detect_show(&mut self, latest: Option<&SubtitleEntry>) {
+        let Some(title) = latest.and_then(|sub| sub.title.clone().or_else(|| sub.path.as_deref().map(stats::media_name)))
+        else {
+            return;
+        };
+        let show = shows::show_name(&title);
+        if self.current_show.as_ref() == Some(&show) {
+            return;
+        }
+        let saved = shows::find(&self.settings.shows, &show).cloned();
+        self.current_show = Some(show);
+        match saved {
+            Some(saved) => self.apply_show_settings(saved),
+            None => self.restore_show_base(),
+        }
+    }
+    
+    /// A line's text as shown outside the transcript, blanked out while
+    /// listening-first mode holds it back.
+    fn shown_text(&self, sub: &SubtitleEntry) -> markup::Marked {
+        if self.delayed.is_hidden(sub.id) {
+            markup::Marked {
+                text: cloze::mask(&markup::strip(&sub.text).text),
+                runs: Vec::new(),
+            }
+        } else if self.settings.render_emphasis {
+            markup::parse(&sub.text)
+        } else {
+            markup::strip(&sub.text)
+        }
+    }
+    
+    /// The latest line on its own in a small always-on-top window.
+    fn overlay_window(&mut self, ctx: &egui::Context) {
+        let text = self.subtitles.lock().unwrap().last().map(|sub| self.shown_text(sub)).unwrap_or_default();
+        let font = self.subtitle_font(ctx);
+        let vertical = &mut self.settings.overlay_vertical;
+        let mut close = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("overlay"),
+            egui::ViewportBuilder::default()
+                .with_title("ScriptView overlay")
+                .with_inner_size(if *vertical { [220.0, 480.0] } else { [640.0, 120.0] })
+                .with_always_on_top(),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let response = if *vertical {
+                        vertical_text(ui, &text.text, font)
+                    } else {
+                        let job = font.layout(&text, ui);
+                        ui.add_sized(ui.available_size(), egui::Label::new(job))
+                    };
+                    response.context_menu(|ui| {
+                        if ui.checkbox(vertical, "Vertical text").clicked() {
+                            ui.close_menu();
+                        }
+                    });
+                });
+                close = ctx.input(|i| i.viewport().close_requested());
+            },
+        );
+        if close {
+            self.show_overlay = false;
+        }
+    }
+    
+    /// The latest line large and centred in a window with no controls, for
+    /// projecting to a class. Esc closes it; F11 or a double-click toggles
+    /// full screen.
+    fn presenter_window(&mut self, ctx: &egui::Context) {
+        let (text, translation) = self
+            .subtitles
+            .lock()
+            .unwrap()
+            .last()
+            .map(|sub| {
+                let translation = sub.translation.clone().filter(|_| !self.delayed.is_hidden(sub.id));
+                (self.shown_text(sub), translation)
+            })
+            .unwrap_or_default();
+        let size = self.settings.presenter_font_size;
+        let font = SubtitleFont {
+            size,
+            letter_spacing: self.settings.preset.letter_spacing(size),
+        };
+        let qr_url = self.settings.presenter_qr_url.trim().to_string();
+        let mut close = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("presenter"),
+            egui::ViewportBuilder::default()
+                .with_title("ScriptView presenter")
+                .with_inner_size([1280.0, 720.0]),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let rect = ui.max_rect();
+                    let mut job = font.layout(&text, ui);
+                    job.halign = egui::Align::Center;
+                    job.wrap.max_width = rect.width() * 0.9;
+                    let mut galleys = vec![ui.fonts(|fonts| fonts.layout_job(job))];
+                    if let Some(translation) = translation.as_deref() {
+                        galleys.push(ui.fonts(|fonts| {
+                            fonts.layout(
+                                translation.to_string(),
+                                egui::FontId::proportional(size * 0.5),
+                                ui.visuals().weak_text_color(),
+                                rect.width() * 0.9,
+                            )
+                        }));
+                    }
+                    let gap = size * 0.4;
+                    let height = galleys.iter().map(|galley| galley.size().y + gap).sum::<f32>() - gap;
+                    let mut top = rect.center().y - height / 2.0;
+                    for galley in galleys {
+                        let galley_height = galley.size().y;
+                        let left = match galley.job.halign {
+                            egui::Align::Center => rect.center().x,
+                            _ => rect.center().x - galley.size().x / 2.0,
+                        };
+                        ui.painter().galley(egui::pos2(left, top), galley, ui.visuals().text_color());
+                        top += galley_height + gap;
+                    }
+                    if !qr_url.is_empty() {
+                        let side = (rect.height() / 4.0).clamp(96.0, 240.0);
+                        let corner = egui::Rect::from_min_max(rect.right_bottom() - egui::vec2(side, side), rect.right_bottom());
+                        ui.allocate_ui_at_rect(corner, |ui| qr::show(ui, &qr_url, side));
+                    }
+                });
+                let (escape, toggle, fullscreen) = ctx.input(|i| {
+                    let double_clicked = i.pointer.button_double_clicked(egui::PointerButton::Primary);
+                    (
+                        i.key_pressed(egui::Key::Escape),
+                        i.key_pressed(egui::Key::F11) || double_clicked,
+                        i.viewport().fullscreen.unwrap_or(false),
+                    )
+                });
+                if toggle {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+                }
+                close = escape || ctx.input(|i| i.viewport().close_requested());
+            },
+        );
+        if close {
+            self.show_presenter = false;
+        }
+    }
+    
+    /// Lines the filters hid from the view and why, to tune the filters or
+    /// attach to a bug report.
+    fn filtered_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_filtered;
+        egui::Window::new(format!("Filtered lines ({})", self.filtered_out.len()))
+            .id(egui::Id::new("filtered_window"))
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                if self.filtered_out.is_empty() {
+                    ui.label("Nothing was filtered out.");
+                    return;
+                }
+                let report = |line: &FilteredLine| {
+                    format!(
+                        "{} {} — {}",
+                        chrono::DateTime::from_timestamp(line.entry.timestamp, 0)
+                            .unwrap_or_default()
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M:%S"),
+                        line.entry.text.replace('\n', " "),
+                        line.reason.label()
+                    )
+                };
+                if ui.button("Copy as text").on_hover_text("For a bug report").clicked() {
+                    let text: Vec<String> = self.filtered_out.iter().map(report).collect();
+                    ui.output_mut(|o| o.copied_text = text.join("\n"));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for line in &self.filtered_out {
+                        ui.label(&line.entry.text);
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} · {}",
+                                format_timestamp(line.entry.start_time),
+                                line.reason.label()
+                            ))
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                        );
+                        ui.separator();
+                    }
+                });
+            });
+        self.show_filtered = open;
+    }
+    
+    /// Lines deleted this session, each with a button to put it back.
+    fn trash_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_trash;
+        let mut restore = None;
+        let mut restore_all = false;
+        egui::Window::new(format!("Trash ({})", self.trash.items().len()))
+            .id(egui::Id::new("trash_window"))
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                if self.trash.items().is_empty() {
+                    ui.label("Nothing deleted. Delete a line from its right-click menu.");
+                    return;
+                }
+                ui.label("Deleted lines stay here until their session ends.");
+                ui.horizontal(|ui| {
+                    restore_all = ui.button("Restore all").clicked();
+                    if ui.button("Empty trash").on_hover_text("The lines stay deleted").clicked() {
+                        self.trash.empty();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for entry in self.trash.items().iter().rev() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("↩").on_hover_text("Restore").clicked() {
+                                restore = Some(entry.id);
+                            }
+                            ui.label(&entry.text);
+                        });
+                        ui.label(
+                            egui::RichText::new(format_timestamp(entry.start_time))
+                                .small()
+                                .color(egui::Color32::from_gray(128)),
+                        );
+                        ui.separator();
+                    }
+                });
+            });
+        if restore_all {
+            self.trash.restore_all();
+        }
+        if let Some(id) = restore {
+            self.trash.restore(id);
+        }
+        if restore_all || restore.is_some() {
+            self.load_subtitles();
+        }
+        self.show_trash = open;
+    }
+    
+    /// Cue timing only means something where lines are cues: in the
+    /// timeline and in opened subtitle files.
+    fn checks_timing(&self) -> bool {
+        self.settings.timeline_view || self.imported.is_some()
+    }
+    
+    fn fix_timing(&mut self, fix: qc::TimingFix) {
+        let fixed = qc::fix_timing(&mut self.subtitles.lock().unwrap(), fix);
+        if self.imported.is_none() {
+            self.timing_fix = Some(fix);
+        }
+        self.toasts.success(format!("Fixed the timing of {} cues", fixed));
+    }
+    
+    fn timing_problems_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_timing_problems;
+        let mut fix = None;
+        let checked = self.checks_timing();
+        let problems: Vec<(f64, String, qc::TimingProblem)> = {
+            let subtitles = self.subtitles.lock().unwrap();
+            let by_id: HashMap<EntryId, &SubtitleEntry> = subtitles.iter().map(|sub| (sub.id, sub)).collect();
+            qc::timing_problems(&subtitles)
+                .into_iter()
+                .filter_map(|(id, problem)| {
+                    let sub = by_id.get(&id)?;
+                    Some((sub.start_time, markup::strip(&sub.text).text, problem))
+                })
+                .collect()
+        };
+        egui::Window::new(format!("Timing problems ({})", problems.len()))
+            .id(egui::Id::new("timing_problems_window"))
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                if !checked {
+                    ui.label("Cue timing is checked in the timeline (🕒) and in opened subtitle files.");
+                    return;
+                }
+                if problems.is_empty() {
+                    ui.label("No cues overlap or end before they start.");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Fix them all:");
+                    for timing_fix in qc::TimingFix::ALL {
+                        if ui.button(timing_fix.label()).on_hover_text(timing_fix.explain()).clicked() {
+                            fix = Some(timing_fix);
+                        }
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for (start_time, text, problem) in &problems {
+                        ui.label(text);
+                        ui.label(
+                            egui::RichText::new(format!("{} · {}", format_timestamp(*start_time), problem.describe()))
+                                .small()
+                                .color(egui::Color32::from_gray(128)),
+                        );
+                        ui.separator();
+                    }
+                });
+            });
+        if let Some(fix) = fix {
+            self.fix_timing(fix);
+        }
+        self.show_timing_problems = open;
+    }
+    
+    fn capture_tail_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_capture_tail;
+        let mut refresh = self.capture_tail.is_none();
+        egui::Window::new("Capture file records")
+            .open(&mut open)
+            .default_width(550.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Last");
+                    refresh |= ui
+                        .add(egui::DragValue::new(&mut self.capture_tail_count).range(1..=500))
+                        .changed();
+                    ui.label("records of");
+                    ui.monospace(&self.subtitle_file);
+                    refresh |= ui.button("⟳").on_hover_text("Read again").clicked();
+                });
+                let tail = match &self.capture_tail {
+                    Some(Ok(tail)) => tail,
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), e);
+                        return;
+                    }
+                    None => return,
+                };
+                let red = egui::Color32::from_rgb(255, 100, 100);
+                let green = egui::Color32::from_rgb(100, 200, 100);
+                if let Some((error, bytes)) = &tail.error {
+                    ui.colored_label(red, format!("✗ Not a JSON array ({} bytes): {}", tail.size, error));
+                    ui.label("The end of the file:");
+                    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                        ui.add(egui::Label::new(egui::RichText::new(bytes).monospace()).wrap());
+                    });
+                    return;
+                }
+                let failed = tail.records.iter().filter(|record| record.error.is_some()).count();
+                ui.label(format!(
+                    "{} bytes, {} records; {} of the last {} don't parse",
+                    tail.size,
+                    tail.total,
+                    failed,
+                    tail.records.len()
+                ));
+                if failed > 0 {
+                    ui.colored_label(red, "One record that doesn't parse keeps the whole file from being read.");
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for record in tail.records.iter().rev() {
+                        match &record.error {
+                            None => ui.colored_label(green, format!("#{} ✓", record.index)),
+                            Some(e) => ui.colored_label(red, format!("#{} ✗ {}", record.index, e)),
+                        };
+                        ui.add(egui::Label::new(egui::RichText::new(&record.json).monospace().small()).wrap());
+                        ui.separator();
+                    }
+                });
+            });
+        if refresh && open {
+            self.capture_tail = Some(tail::read(&self.subtitle_file, self.capture_tail_count));
+        }
+        if !open {
+            self.capture_tail = None;
+        }
+        self.show_capture_tail = open;
+    }
+    
+    /// Whether `sub` was recognised with low confidence and not yet checked.
+    fn needs_check(&self, sub: &SubtitleEntry) -> bool {
+        let low = sub.confidence.is_some_and(|confidence| confidence < self.settings.low_confidence);
+        low && !self.verified.contains(&sub.id)
+    }
+    
+    fn save_starred(&self) {
+        if let Some(hub) = &self.sync_hub {
+            hub.publish_starred(&self.starred.items);
+        }
+        // A client shows the hub's stars, leaving its own file alone
+        if self.sync_client.is_some() {
+            return;
+        }
+        if let Err(e) = self.starred.save() {
+            self.warn(format!("Could not save starred lines: {}", e));
+        }
+    }
+    
+    /// Open the raw stream storage chosen in the settings, keeping to JSON
+    /// lines files if it can't be.
+    fn open_storage(&mut self) {
+        match storage::open(self.settings.storage, self.sealing_key().ok().flatten().cloned()) {
+            Ok(storage) => self.storage = storage,
+            Err(e) => self.warn(format!("Keeping the raw stream in JSON lines files: {}", e)),
+        }
+    }
+    
+    /// The key to seal history with, `None` if it's kept in the clear, or an
+    /// error while it's waiting to be unlocked.
+    fn sealing_key(&self) -> Result<Option<&crypt::Key>, String> {
+        match &self.history_key {
+            _ if !self.settings.encrypt_history => Ok(None),
+            Some(key) => Ok(Some(key)),
+            None => Err("the history is locked".to_string()),
+        }
+    }
+    
+    /// Save the statistics, sealed if the history is. While it's locked
+    /// they're held in memory, so sealed ones aren't saved over.
+    fn save_stats(&self) -> Result<(), String> {
+        match self.sealing_key() {
+            Ok(key) if !self.stats_locked => self.stats_db.save(key).map_err(|e| e.to_string()),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Check the passphrase (or set it, the first time) on a worker thread,
+    /// since deriving the key takes a moment.
+    fn unlock_history(&mut self) {
+        let passphrase = self.passphrase.clone();
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(crypt::unlock(&passphrase));
+        });
+        self.unlocking = Some(rx);
+    }
+    
+    /// Once the key is ready, catch up on what was held back while locked.
+    fn poll_unlock(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.unlocking else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err("the key could not be derived".to_string()),
+        };
+        self.unlocking = None;
+        let key = match result {
+            Ok(key) => key,
+            Err(e) => {
+                self.warn(format!("Could not unlock the history: {}", e));
+                return;
+            }
+        };
+        self.passphrase.clear();
+        self.history_key = Some(key.clone());
+        self.open_storage();
+        let held = std::mem::take(&mut self.locked_lines);
+        if let Err(e) = self.storage.append(&held) {
+            self.warn(format!("Could not save raw stream: {}", e));
+        }
+        if self.stats_locked {
+            match StatsDb::load(Some(&key)) {
+                // What was counted meanwhile is counted again from the capture file
+                Some(stats_db) => {
+                    self.stats_db = stats_db;
+                    self.stats_locked = false;
+                    if let Ok(Some(subs)) = session::try_read_entries(&self.subtitle_file) {
+                        self.stats_db.record(&subs);
+                    }
+                }
+                None => self.warn("Could not open the encrypted statistics".to_string()),
+            }
+        }
+        if let Err(e) = self.save_stats() {
+            self.warn(format!("Could not save statistics: {}", e));
+        }
+        match session::seal_archives(&self.subtitle_file, &key) {
+            Ok(0) => self.toasts.success("History unlocked".to_string()),
+            Ok(sealed) => self.toasts.success(format!("History unlocked; encrypted {} capture archives", sealed)),
+            Err(e) => self.warn(format!("Could not encrypt capture archives: {}", e)),
+        }
+    }
+    
+    fn unlock_window(&mut self, ctx: &egui::Context) {
+        if self.sealing_key().is_ok() {
+            return;
+        }
+        let first_time = !Path::new(&crypt::key_path()).exists();
+        let mut unlock = false;
+        egui::Window::new("🔒 Encrypted history")
+            .collapsible(true)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if first_time {
+                    ui.label("Choose a passphrase for the history. There's no way to recover it if it's forgotten.");
+                } else {
+                    ui.label("Enter the passphrase to save and read the history.");
+                }
+                if !self.locked_lines.is_empty() {
+                    ui.weak(format!("{} lines waiting to be saved", self.locked_lines.len()));
+                }
+                let idle = self.unlocking.is_none();
+                ui.horizontal(|ui| {
+                    let response = ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+                    let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let button = egui::Button::new(if first_time { "Set" } else { "Unlock" });
+                    unlock = (ui.add_enabled(idle, button).clicked() || entered) && idle;
+                    if !idle {
+                        ui.spinner();
+                    }
+                });
+            });
+        if unlock {
+            self.unlock_history();
+        }
+    }
+    
+    /// Report a failure on the terminal and in a toast.
+    fn warn(&self, message: String) {
+        eprintln!("Warning: {}", message);
+        self.toasts.error(message);
+    }
+    
+    fn handle_script_event(&mut self, event: ScriptEvent) {
+        match event {
+            // Only tracked by the reader
+            ScriptEvent::Hello => {}
+            ScriptEvent::Star { line } => {
+                self.starred.add(line);
+                self.save_starred();
+            }
+            ScriptEvent::SendToAnki { line } => {
+                // Starred first, so a failed export leaves it for later
+                self.starred.add(line.clone());
+                self.save_starred();
+                if self.anki_export.is_some() {
+                    self.osd("Anki export busy; line starred");
+                } else {
+                    self.start_anki_export(vec![line], true);
+                }
+            }
+            ScriptEvent::SyncMark { time, path } => self.add_sync_anchor(time, path),
+            ScriptEvent::Reveal => self.delayed.reveal_all(),
+        }
+    }
+    
+    /// The line waiting in the resync assistant was heard at `time`.
+    fn add_sync_anchor(&mut self, time: f64, path: Option<String>) {
+        let Some(assistant) = &mut self.resync else {
+            return;
+        };
+        if assistant.media != path {
+            self.osd("Not the file being resynced");
+            return;
+        }
+        let Some((line, _)) = assistant.pending.take() else {
+            self.osd("Pick a line to resync in ScriptView first");
+            return;
+        };
+        // The first anchor and the latest: drift shows best far apart
+        assistant.anchors.truncate(1);
+        assistant.anchors.push(Anchor { line, actual: time });
+        if let Some(correction) = Correction::from_anchors(&assistant.anchors) {
+            self.sync_correction = Some((assistant.media.clone(), correction));
+            self.osd(&format!("Resynced: {}", correction.describe()));
+            self.load_subtitles();
+        }
+    }
+    
+    fn resync_window(&mut self, ctx: &egui::Context) {
+        let Some(assistant) = &self.resync else {
+            return;
+        };
+        let mut open = true;
+        let mut clear = false;
+        egui::Window::new("Resync")
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                let media = assistant.media.as_deref().unwrap_or("(unknown file)");
+                ui.label(egui::RichText::new(media).small());
+                match &assistant.pending {
+                    Some((_, text)) => {
+                        ui.label("Play the video and press Alt+y in mpv when this line is spoken:");
+                        ui.label(egui::RichText::new(text).strong());
+                    }
+                    None => {
+                        ui.label("Pick another line with \"Resync from this line\", ideally far from the first, to correct drift too.");
+                    }
+                }
+                ui.separator();
+                for (i, anchor) in assistant.anchors.iter().enumerate() {
+                    ui.label(format!(
+                        "Anchor {}: {} in the file, spoken at {}",
+                        i + 1,
+                        format_timestamp(anchor.line),
+                        format_timestamp(anchor.actual)
+                    ));
+                }
+                match &self.sync_correction {
+                    Some((media, correction)) if *media == assistant.media => {
+                        ui.label(format!("Correction: {}", correction.describe()));
+                    }
+                    _ => {
+                        ui.label("No correction yet.");
+                    }
+                }
+                clear = ui.button("Clear correction").clicked();
+            });
+        if clear {
+            self.sync_correction = None;
+            self.resync = None;
+            self.load_subtitles();
+        } else if !open {
+            self.resync = None;
+        }
+    }
+    
+    fn export_starred_to_anki(&mut self) {
+        self.start_anki_export(self.starred.items.clone(), false);
+    }
+    
+    /// Export `lines`; a quiet export reports on mpv's OSD instead of a window.
+    fn start_anki_export(&mut self, lines: Vec<StarredLine>, quiet: bool) {
+        let template = anki::NoteTemplate {
+            deck: self.settings.anki_deck.clone(),
+            note_type: self.settings.anki_note_type.clone(),
+            sentence_field: self.settings.anki_sentence_field.clone(),
+            source_field: self.settings.anki_source_field.clone(),
+            dedup_field: self.settings.anki_dedup_field.clone(),
+            audio_field: self.settings.anki_audio_field.clone(),
+            tts_command: Some(self.settings.tts_command.clone()).filter(|_| self.settings.tts_fallback),
+            cache_dir: anki::cache_dir(),
+        };
+        self.anki_export = Some(AnkiExport {
+            total: lines.len(),
+            quiet,
+            hidden: false,
+            results: Vec::new(),
+            rx: Some(anki::spawn_export(self.settings.anki_url.clone(), template, lines)),
+        });
+    }
+    
+    /// Progress of the Anki export, then the lines that were skipped or
+    /// failed and why. Lines that are now in Anki are unstarred.
+    /// Take in the outcomes of the running export, whether or not its
+    /// window is open, and report it if nothing will show it.
+    fn poll_anki_export(&mut self, ctx: &egui::Context) {
+        let Some(export) = &mut self.anki_export else {
+            return;
+        };
+        if let Some(rx) = &export.rx {
+            loop {
+                match rx.try_recv() {
+                    Ok((line, outcome)) => {
+                        if !matches!(outcome, anki::Outcome::Failed(_)) {
+                            self.starred.remove(&line);
+                        }
+                        export.results.push((line, outcome));
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => {
+                        ctx.request_repaint_after(Duration::from_millis(100));
+                        break;
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        export.rx = None;
+                        if let Some(hub) = &self.sync_hub {
+                            hub.publish_starred(&self.starred.items);
+                        }
+                        if let Err(e) = self.starred.save() {
+                            let message = format!("Could not save starred lines: {}", e);
+                            eprintln!("Warning: {}", message);
+                            self.toasts.error(message);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        if export.rx.is_some() {
+            return;
+        }
+        if export.quiet {
+            let message = match export.results.first() {
+                Some((_, anki::Outcome::Added(_))) => "Added to Anki".to_string(),
+                Some((_, anki::Outcome::Duplicate(_))) => "Already in Anki".to_string(),
+                Some((_, anki::Outcome::Failed(e))) => format!("Anki: {}", e),
+                None => "Nothing sent to Anki".to_string(),
+            };
+            self.anki_export = None;
+            self.osd(&message);
+        } else if export.hidden {
+            let count = |wanted: fn(&anki::Outcome) -> bool| export.results.iter().filter(|(_, o)| wanted(o)).count();
+            let added = count(|outcome| matches!(outcome, anki::Outcome::Added(_)));
+            let failed = count(|outcome| matches!(outcome, anki::Outcome::Failed(_)));
+            let message = format!("Anki export finished: added {} of {} cards", added, export.total);
+            self.anki_export = None;
+            match failed {
+                0 => self.toasts.success(message),
+                _ => self.toasts.error(format!("{}; {} failed and stay starred", message, failed)),
+            }
+        }
+    }
+    
+    fn anki_export_window(&mut self, ctx: &egui::Context) {
+        let Some(export) = &mut self.anki_export else {
+            return;
+        };
+        if export.quiet || export.hidden {
+            return;
+        }
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Export to Anki")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let done = export.results.len();
+                let added = export.results.iter().filter(|(_, o)| matches!(o, anki::Outcome::Added(_))).count();
+                if export.rx.is_some() {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / export.total.max(1) as f32)
+                            .text(format!("{} of {}", done, export.total)),
+                    );
+                } else {
+                    ui.label(format!("Added {} of {} cards.", added, export.total));
+                }
+                let duplicates: Vec<_> = export
+                    .results
+                    .iter()
+                    .filter(|(_, outcome)| matches!(outcome, anki::Outcome::Duplicate(_)))
+                    .collect();
+                if !duplicates.is_empty() {
+                    ui.separator();
+                    ui.label(format!("{} already in Anki, skipped:", duplicates.len()));
+                    egui::ScrollArea::vertical().id_source("duplicates").max_height(120.0).show(ui, |ui| {
+                        for (line, _) in duplicates {
+                            ui.label(egui::RichText::new(&line.text).color(egui::Color32::from_gray(128)));
+                        }
+                    });
+                }
+                let failures: Vec<_> = export
+                    .results
+                    .iter()
+                    .filter_map(|(line, outcome)| match outcome {
+                        anki::Outcome::Failed(e) => Some((line, e)),
+                        _ => None,
+                    })
+                    .collect();
+                if !failures.is_empty() {
+                    ui.separator();
+                    ui.label(format!("{} failed and stay starred:", failures.len()));
+                    egui::ScrollArea::vertical().id_source("failures").max_height(200.0).show(ui, |ui| {
+                        for (line, error) in failures {
+                            ui.label(&line.text);
+                            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("  {}", error));
+                        }
+                    });
+                }
+                if export.rx.is_none() && ui.button("Close").clicked() {
+                    close = true;
+                }
+            });
+        // Closing early leaves the export running, to be reported when it ends
+        if !open || close {
+            match export.rx {
+                Some(_) => export.hidden = true,
+                None => self.anki_export = None,
+            }
+        }
+    }
+    
+    fn review_window(&mut self, ctx: &egui::Context) {
+        let now = chrono::Utc::now().timestamp();
+        let mut open = self.show_review;
+        let mut answer = None;
+        let mut remove = None;
+        egui::Window::new("Review")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                match self.review.next_due(now) {
+                    Some(index) => {
+                        let item = &self.review.items[index];
+                        ui.label(
+                            egui::RichText::new(&item.text)
+                                .size(self.settings.subtitle_points(ctx.zoom_factor())),
+                        );
+                        if self.review_revealed {
+                            let source = item
+                                .path
+                                .as_deref()
+                                .and_then(|p| Path::new(p).file_name())
+                                .map(|n| n.to_string_lossy().into_owned())
+                                .unwrap_or_default();
+                            ui.label(
+                                egui::RichText::new(format!("{} [{}]", source, format_timestamp(item.start_time)))
+                                    .small()
+                                    .color(egui::Color32::from_gray(128)),
+                            );
+                            ui.horizontal(|ui| {
+                                if ui.button("Again").clicked() {
+                                    answer = Some((index, false));
+                                }
+                                if ui.button("Got it").clicked() {
+                                    answer = Some((index, true));
+                                }
+                            });
+                        } else if ui.button("Show answer").clicked() {
+                            self.review_revealed = true;
+                        }
+                    }
+                    None => {
+                        ui.label("Nothing due. Right-click a subtitle to add it to the queue.");
+                    }
+                }
+                ui.separator();
+                egui::CollapsingHeader::new(format!("All sentences ({})", self.review.items.len()))
+                    .show(ui, |ui| {
+                        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                            for (i, item) in self.review.items.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                        remove = Some(i);
+                                    }
+                                    let due = chrono::DateTime::from_timestamp(item.due, 0)
+                                        .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+                                        .unwrap_or_default();
+                                    ui.label(egui::RichText::new(due).small().color(egui::Color32::from_gray(128)));
+                                    ui.label(&item.text);
+                                });
+                            }
+                        });
+                    });
+            });
+        if let Some((index, remembered)) = answer {
+            self.review.answer(index, remembered, now);
+            self.review_revealed = false;
+            self.save_review();
+        }
+        if let Some(index) = remove {
+            self.review.items.remove(index);
+            self.review_revealed = false;
+            self.save_review();
+        }
+        self.show_review = open;
+    }
+    
+    fn export(&mut self, format: export::Format) {
+        let subtitles = self.subtitles.lock().unwrap();
+        let timing = &self.settings.cue_timing;
+        let result = match &self.imported {
+            Some(imported) => {
+                let stem = Path::new(&imported.path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "transcript".to_string());
+                let path = format!("{}/{}.{}", self.settings.export_dir, stem, format.extension());
+                std::fs::create_dir_all(&self.settings.export_dir)
+                    .and_then(|()| export::export_file(&subtitles, format, imported.ass_header.as_deref(), &path, timing))
+                    .map(|()| vec![path])
+            }
+            None => export::export_transcript(&subtitles, format, &self.settings.export_dir, timing),
+        };
+        match result {
+            Ok(paths) => self.toasts.success(format!("Exported {}", paths.join(", "))),
+            Err(e) => self.warn(format!("Export failed: {}", e)),
+        }
+    }
+    
+    /// Open the lines in view in the external editor, to merge back what's
+    /// saved there.
+    fn edit_externally(&mut self) {
+        let entries = self.subtitles.lock().unwrap().clone();
+        let title = match &self.imported {
+            Some(imported) => stats::media_name(&imported.path),
+            None => self.current_show.clone().unwrap_or_else(|| "ScriptView session".to_string()),
+        };
+        match editor::Session::start(editor::temp_path(), &title, entries, &self.settings.editor_command) {
+            Ok(session) => {
+                self.toasts.info("Editing in your editor; each save shows up here");
+                self.editor_session = Some(session);
+            }
+            Err(e) => self.warn(e),
+        }
+    }
+    
+    /// Take in the latest save from the external editor, if there's a new one.
+    fn merge_editor_saves(&mut self) {
+        let Some(session) = &mut self.editor_session else {
+            return;
+        };
+        let Some(markdown) = session.saved() else {
+            return;
+        };
+        let changes = editor::changes(&markdown, &session.written);
+        let originals: HashMap<EntryId, SubtitleEntry> =
+            session.written.iter().map(|entry| (entry.id, entry.clone())).collect();
+        let mut subtitles = self.subtitles.lock().unwrap();
+        for (id, text) in changes.texts {
+            if let Some(entry) = subtitles.iter_mut().find(|entry| entry.id == id) {
+                entry.text = text.clone();
+            }
+            self.edits.set(id, text, &originals[&id].text);
+        }
+        let deleted = changes.deleted.len();
+        subtitles.retain(|entry| !changes.deleted.contains(&entry.id));
+        drop(subtitles);
+        // Imported lines are gone for good; live ones can come back from the trash
+        if self.imported.is_none() {
+            for id in changes.deleted {
+                self.trash.delete(originals[&id].clone());
+            }
+        }
+        if deleted > 0 {
+            self.toasts.info(format!("Merged your edits; {} lines deleted", deleted));
+        } else {
+            self.toasts.info("Merged your edits");
+        }
+    }
+    
+    /// Save the transcript as a note per media file in the vault from
+    /// settings.
+    fn save_to_vault(&mut self) {
+        let subtitles = self.subtitles.lock().unwrap();
+        let format = self.settings.vault_format;
+        let result = export::save_to_vault(&subtitles, format, &self.settings.vault_dir, &self.settings.cue_timing);
+        drop(subtitles);
+        match result {
+            Ok(notes) => {
+                let names: Vec<String> = notes
+                    .into_iter()
+                    .map(|(path, renamed)| match renamed {
+                        true => format!("{} (a note by the plain name is already there)", path),
+                        false => path,
+                    })
+                    .collect();
+                self.toasts.success(format!("Saved {} to the vault", names.join(", ")))
+            }
+            Err(e) => self.warn(format!("Could not save to the vault: {}", e)),
+        }
+    }
+    
+    /// Send the lines in view to a note service as one transcript.
+    fn send_transcript_to_notes(&mut self, service: notes::NoteService) {
+        let body = service.transcript_body(&self.subtitles.lock().unwrap());
+        let rx = service.send(body);
+        self.note_sends.push((service.name, rx));
+    }
+    
+    /// Report the sends to note services that have finished.
+    fn poll_note_sends(&mut self) {
+        let mut finished = Vec::new();
+        self.note_sends.retain(|(name, rx)| match rx.try_recv() {
+            Ok(result) => {
+                finished.push((name.clone(), result));
+                false
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => true,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => false,
+        });
+        for (name, result) in finished {
+            match result {
+                Ok(()) => self.toasts.success(format!("Sent to {}", name)),
+                Err(e) => self.warn(format!("Could not send to {}: {}", name, e)),
+            }
+        }
+    }
+    
+    /// Look `text` up on the search site at index `site` in the browser.
+    fn search(&self, ctx: &egui::Context, site: usize, text: &str) {
+        if let Some(site) = self.settings.search_sites.get(site) {
+            ctx.open_url(egui::OpenUrl::new_tab(site.url(text)));
+        }
+    }
+    
+    /// Commands for the latest line, narrowed down by typing words of their
+    /// names; Enter runs the first one left.
+    fn command_palette(&mut self, ctx: &egui::Context) {
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        // The line under the mouse or right-clicked, or else the latest
+        let line = {
+            let subtitles = self.subtitles.lock().unwrap();
+            let target = palette.line.and_then(|id| subtitles.iter().find(|sub| sub.id == id)).or(subtitles.last());
+            target.map(|sub| (sub.id, markup::strip(&sub.text).text))
+        };
+        let mut commands = Vec::new();
+        for (index, site) in self.settings.search_sites.iter().enumerate() {
+            if let Some(word) = &palette.word {
+                commands.push((format!("Search word on {}", site.name), SubtitleAction::Search(index, word.clone())));
+            }
+            if let Some((id, _)) = &line {
+                commands.push((format!("Search line on {}", site.name), SubtitleAction::SearchLine(index, *id)));
+            }
+        }
+        for (index, layout) in self.settings.layouts.iter().enumerate() {
+            commands.push((format!("Switch layout: {}", layout.name.trim()), SubtitleAction::SwitchLayout(index)));
+        }
+        let filter = &mut palette.filter;
+        let words: Vec<String> = filter.split_whitespace().map(str::to_lowercase).collect();
+        commands.retain(|(name, _)| words.iter().all(|word| name.to_lowercase().contains(word)));
+        let mut chosen = None;
+        let mut close = false;
+        egui::Window::new("Commands")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                ui.add(egui::TextEdit::singleline(filter).hint_text("Type a command").desired_width(320.0))
+                    .request_focus();
+                if let Some(word) = &palette.word {
+                    ui.label(egui::RichText::new(word).strong());
+                }
+                match &line {
+                    Some((_, text)) => ui.label(egui::RichText::new(text).small().color(egui::Color32::from_gray(128))),
+                    None => ui.label("No lines yet"),
+                };
+                ui.separator();
+                for (index, (name, _)) in commands.iter().enumerate() {
+                    if ui.selectable_label(index == 0, name).clicked() {
+                        chosen = Some(index);
+                    }
+                }
+                if !commands.is_empty() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    chosen = Some(0);
+                }
+                close = ui.input(|i| i.key_pressed(egui::Key::Escape));
+            });
+        if let Some(index) = chosen {
+            self.palette = None;
+            let (_, action) = commands.swap_remove(index);
+            self.handle_action(ctx, action);
+        } else if close {
+            self.palette = None;
+        }
+    }
+    
+    /// Write the tray's lines to one file in the export folder.
+    fn export_tray(&mut self, format: export::Format) {
+        let path = tray::export_path(&self.settings.export_dir, format);
+        let result = std::fs::create_dir_all(&self.settings.export_dir).and_then(|()| {
+            export::export_file(self.tray.items(), format, None, &path, &self.settings.cue_timing)
+        });
+        match result {
+            Ok(()) => self.toasts.success(format!("Exported the tray to {}", path)),
+            Err(e) => self.warn(format!("Export failed: {}", e)),
+        }
+    }
+    
+    /// The machine translation of the line under the mouse in a tooltip,
+    /// once the mouse has rested on it.
+    fn hover_translation_tooltip(&mut self, ctx: &egui::Context) {
+        let resting = ctx.input(|i| i.pointer.time_since_last_movement()) >= HOVER_TRANSLATION_DELAY;
+        // A line listening-first mode is hiding isn't given away either
+        let Some(sub) = self
+            .ruler_hovered
+            .filter(|&id| resting && !self.delayed.is_hidden(id))
+            .and_then(|id| self.entry(id))
+        else {
+            return;
+        };
+        let service = machine_translation::Service {
+            url: self.settings.translate_url.clone(),
+            api_key: self.settings.translate_api_key.clone(),
+            source: self.settings.translate_source.clone(),
+            target: self.settings.translate_target.clone(),
+        };
+        let text = markup::strip(&sub.text).text;
+        let result = self.hover_translations.get(&service, &text).cloned();
+        if result.is_none() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        let id = egui::Id::new(("hover_translation", sub.id));
+        egui::show_tooltip_at_pointer(ctx, egui::LayerId::background(), id, |ui| {
+            match result {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Translating…");
+                    });
+                }
+                Some(Ok(translation)) => {
+                    ui.label(translation);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("Not translated: {}", e));
+                }
+            }
+        });
+    }
+    
+    /// The comparison tray beside the transcript.
+    fn tray_panel(&mut self, ctx: &egui::Context) {
+        let mut export = None;
+        let mut unpin = None;
+        egui::SidePanel::right("tray").resizable(true).default_width(260.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Tray");
+                if ui.small_button("✕").on_hover_text("Hide the tray; the lines stay pinned").clicked() {
+                    self.show_tray = false;
+                }
+            });
+            if self.tray.items().is_empty() {
+                ui.label("Pin lines from their right-click menu, or every line using a word from the word's menu.");
+                return;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Copy all").clicked() {
+                    let text = self.tray.to_text();
+                    ui.output_mut(|o| o.copied_text = text);
+                    self.toasts.success(format!("Copied {} lines", self.tray.items().len()));
+                }
+                ui.menu_button("Export", |ui| {
+                    let translated = translation::has_translations(self.tray.items());
+                    for format in export::Format::ALL {
+                        if ui
+                            .add_enabled(translated || !format.is_bilingual(), egui::Button::new(format.label()))
+                            .clicked()
+                        {
+                            export = Some(format);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                if ui.button("Clear").clicked() {
+                    self.tray.clear();
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (index, entry) in self.tray.items().iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✕").on_hover_text("Unpin").clicked() {
+                            unpin = Some(index);
+                        }
+                        ui.add(egui::Label::new(&entry.text).wrap());
+                    });
+                    ui.label(
+                        egui::RichText::new(tray::source(entry))
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                    );
+                    ui.separator();
+                }
+            });
+        });
+        if let Some(index) = unpin {
+            self.tray.unpin(index);
+        }
+        if let Some(format) = export {
+            self.export_tray(format);
+        }
+    }
+    
+    /// Save everything kept on disk to one archive in the export folder.
+    fn back_up(&mut self) {
+        // What's only in memory so far goes in too
+        let saved = self.settings_to_save().save().map_err(|e| e.to_string());
+        if let Err(e) = saved.and_then(|()| self.save_stats()) {
+            self.warn(format!("Could not save before backing up: {}", e));
+        }
+        let key = match self.sealing_key() {
+            Ok(key) => key,
+            Err(e) => {
+                self.warn(format!("Backup failed: {}", e));
+                return;
+            }
+        };
+        let path = backup::default_path(&self.settings.export_dir, key.is_some());
+        match backup::create(&path, &backup::roots(&self.subtitle_file), key) {
+            Ok(files) => self.toasts.success(format!("Backed up {} files to {}", files, path)),
+            Err(e) => self.warn(format!("Backup failed: {}", e)),
+        }
+    }
+    
+    /// Export the live transcript in the auto-export format, without a toast
+    /// unless it fails.
+    fn auto_export(&mut self) {
+        self.last_auto_export = Instant::now();
+        if self.imported.is_some() {
+            return;
+        }
+        let subtitles = self.subtitles.lock().unwrap().clone();
+        let settings = &self.settings;
+        let result = export::auto_export(&subtitles, &settings.auto_export, &settings.export_dir, &settings.cue_timing);
+        if let Err(e) = result {
+            self.warn(format!("Auto-export failed: {}", e));
+        }
+    }
+    
+    /// Follow the watch folder from settings, if there is one.
+    fn start_watch_folder(&mut self) {
+        if let Some(folder) = self.watch_folder.take() {
+            let _ = self.watcher.unwatch(&folder.dir);
+        }
+        let dir = self.settings.watch_folder.trim();
+        if dir.is_empty() {
+            return;
+        }
+        let folder = watch_folder::WatchFolder::new(dir);
+        if let Err(e) = self.watcher.watch(&folder.dir, RecursiveMode::NonRecursive) {
+            self.warn(format!("Could not watch {}: {}", dir, e));
+            return;
+        }
+        self.watch_folder = Some(folder);
+    }
+    
+    /// Open a capture another tool dropped into the watch folder, once it's
+    /// complete.
+    fn open_dropped(&mut self, path: &Path) {
+        let Some(mut entries) = self.watch_folder.as_mut().and_then(|folder| folder.take_new(path)) else {
+            return;
+        };
+        self.entry_ids.assign(&mut entries);
+        for entry in &mut entries {
+            entry.source.get_or_insert(source::Source::Imported);
+        }
+        self.toasts.info(format!(
+            "Opened {} from the watch folder ({} lines)",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            entries.len()
+        ));
+        *self.subtitles.lock().unwrap() = entries;
+        self.filtered_out.clear();
+        self.imported = Some(ImportedTranscript {
+            path: path.to_string_lossy().into_owned(),
+            ass_header: None,
+            raw: false,
+        });
+    }
+    
+    /// Show the file at `import_path` instead of the live capture.
+    fn import(&mut self) {
+        let path = self.import_path.trim().to_string();
+        let result = std::fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| {
+            let (content, encoding) = encoding::decode(&bytes, self.settings.import_encoding);
+            ass::parse(&content).map(|doc| (doc, encoding))
+        });
+        match result {
+            Ok((mut doc, encoding)) => {
+                for entry in &mut doc.entries {
+                    entry.text = encoding::decode_entities(&entry.text);
+                    entry.source = Some(source::Source::Imported);
+                }
+                self.entry_ids.assign(&mut doc.entries);
+                self.toasts.success(format!("Opened {} ({} lines, {})", path, doc.entries.len(), encoding));
+                *self.subtitles.lock().unwrap() = doc.entries;
+                self.filtered_out.clear();
+                self.imported = Some(ImportedTranscript {
+                    path,
+                    ass_header: Some(doc.header),
+                    raw: false,
+                });
+            }
+            Err(e) => self.warn(format!("Could not open {}: {}", path, e)),
+        }
+    }
+    
+    /// Show the SRT file at `merge_path` beside the lines captured from what's
+    /// playing now.
+    fn merge_srt(&mut self) {
+        let path = self.merge_path.trim().to_string();
+        let result = std::fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| {
+            let (content, _) = encoding::decode(&bytes, self.settings.import_encoding);
+            srt::parse(&content)
+        });
+        match result {
+            Ok(cues) => {
+                let media = self.subtitles.lock().unwrap().last().and_then(|sub| sub.path.clone());
+                self.toasts.success(format!("Merged {} ({} cues)", path, cues.len()));
+                self.merged = Some(MergedSubtitles {
+                    path,
+                    media,
+                    cues,
+                    matches: HashMap::new(),
+                });
+                self.align_merged();
+            }
+            Err(e) => self.warn(format!("Could not merge {}: {}", path, e)),
+        }
+    }
+    
+    /// Match the merged file's cues to the lines in view.
+    fn align_merged(&mut self) {
+        let Some(merged) = &mut self.merged else {
+            return;
+        };
+        let subtitles = self.subtitles.lock().unwrap();
+        merged.matches = subtitles
+            .iter()
+            .filter(|sub| sub.path == merged.media)
+            .filter_map(|sub| Some((sub.id, align::cue_at(sub.start_time, &merged.cues, MERGE_OFFSET)?)))
+            .collect();
+    }
+    
+    /// Show a day of the raw stream, run through the filters as they are now.
+    fn reprocess(&mut self, day: String) {
+        match self.storage.read_day(&day) {
+            Ok(mut lines) => {
+                self.entry_ids.assign(&mut lines);
+                let total = lines.len();
+                let (mut filtered, filtered_out) = filter_pipeline(lines, &self.settings);
+                self.filtered_out = filtered_out;
+                if self.settings.timeline_view {
+                    filtered = session::timeline(&filtered);
+                }
+                self.toasts.success(format!(
+                    "Reprocessed {}: {} of {} lines kept",
+                    day,
+                    filtered.len(),
+                    total
+                ));
+                *self.subtitles.lock().unwrap() = filtered;
+                self.imported = Some(ImportedTranscript {
+                    path: day,
+                    ass_header: None,
+                    raw: true,
+                });
+            }
+            Err(e) => self.warn(format!("Could not read the raw stream for {}: {}", day, e)),
+        }
+    }
+    
+    /// The entry in view with this ID.
+    fn entry(&self, id: EntryId) -> Option<SubtitleEntry> {
+        self.subtitles.lock().unwrap().iter().find(|sub| sub.id == id).cloned()
+    }
+    
+    /// What was done to the lines in view between reading them and showing
+    /// them, for the row details.
+    fn processing_steps(&self) -> Vec<String> {
+        let mut steps = Vec::new();
+        match &self.imported {
+            Some(imported) if imported.raw => steps.push(format!("Read from the raw stream of {}", imported.path)),
+            Some(imported) => {
+                steps.push(format!("Opened from {}", imported.path));
+                return steps;
+            }
+            None => match &self.remote {
+                Some(remote) => steps.push(format!(
+                    "Read from {}:{} over SSH, copied to {}",
+                    remote.remote.host, remote.remote.path, self.subtitle_file
+                )),
+                None => steps.push(format!("Read from {}", self.subtitle_file)),
+            },
+        }
+        if let Some(filter) = self
+            .settings
+            .noise_filters
+            .get(&self.settings.player)
+            .filter(|filter| filter.is_active())
+        {
+            let blocked = filter.blocklist.lines().filter(|line| !line.trim().is_empty()).count();
+            steps.push(format!(
+                "Noise filter: confidence from {:.0}%, at least {} characters, {} blocked strings",
+                filter.min_confidence * 100.0,
+                filter.min_chars,
+                blocked
+            ));
+        }
+        let part = match self.settings.collapse_mode {
+            CollapseMode::Prefix => "a prefix of",
+            CollapseMode::PrefixOrSuffix => "the start or end of",
+            CollapseMode::Anywhere => "contained in",
+        };
+        match self.settings.prefix_window_secs {
+            0 => steps.push(format!("Prefix filter: not {} the next line", part)),
+            window => steps.push(format!(
+                "Prefix filter: not {} the next line arriving within {} s",
+                part, window
+            )),
+        }
+        if self.settings.dedup_window_secs > 0 {
+            steps.push(format!(
+                "Repeat filter: no repeat within {} s",
+                self.settings.dedup_window_secs
+            ));
+        }
+        if self.settings.timeline_view {
+            steps.push("Timeline: one line per cue by media time".to_string());
+        } else if self.time_ordered && self.imported.is_none() {
+            steps.push("Ordered by media time".to_string());
+        }
+        steps
+    }
+    
+    /// Track pickers and playback speed for the connected mpv.
+    fn ipc_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            for (label, property, tracks, current) in [
+                ("Subtitles:", "sid", &self.sub_tracks, self.sid),
+                ("Secondary:", "secondary-sid", &self.sub_tracks, self.secondary_sid),
+                ("Audio:", "aid", &self.audio_tracks, self.aid),
+            ] {
+                ui.label(label);
+                let selected = current
+                    .and_then(|id| tracks.iter().find(|t| t.id == id))
+                    .map_or_else(|| "Off".to_string(), Track::label);
+                let mut choice = None;
+                egui::ComboBox::from_id_source(property).selected_text(selected).show_ui(ui, |ui| {
+                    if ui.selectable_label(current.is_none(), "Off").clicked() {
+                        choice = Some((serde_json::json!("no"), "Off".to_string()));
+                    }
+                    for track in tracks {
+                        if ui.selectable_label(current == Some(track.id), track.label()).clicked() {
+                            choice = Some((serde_json::json!(track.id), track.label()));
+                        }
+                    }
+                });
+                if let Some((value, chosen)) = choice {
+                    self.ipc.command(serde_json::json!(["set_property", property, value]));
+                    self.osd(&format!("{} {}", label, chosen));
+                }
+            }
+            ui.label("Speed:");
+            let mut speed = self.speed;
+            let slider = ui.add(egui::Slider::new(&mut speed, 0.5..=2.0).step_by(0.05).suffix("x"));
+            if slider.double_clicked() {
+                speed = 1.0;
+            }
+            if speed != self.speed {
+                self.speed = speed;
+                self.ipc.command(serde_json::json!(["set_property", "speed", speed]));
+                self.osd(&format!("Speed {:.2}x", speed));
+            }
+            slider.on_hover_text("Double-click to reset to 1x");
+        });
+    }
+    
+    /// What is known about the player, as an indicator colour and text.
+    fn player_status(&self) -> (egui::Color32, String) {
+        let player = self.settings.player;
+        let green = egui::Color32::from_rgb(0, 200, 0);
+        let gray = egui::Color32::from_gray(128);
+        if let Some(remote) = &self.remote {
+            return (green, format!("{}: on {}", player.label(), remote.remote.host));
+        }
+        if let Some(client) = &self.sync_client {
+            return match self.sync_connected {
+                true => (green, format!("Following {}", client.address)),
+                false => (egui::Color32::from_rgb(255, 165, 0), format!("Reconnecting to {}", client.address)),
+            };
+        }
+        match player {
+            Player::Mpv if self.mpv_connected => (green, "mpv: connected".to_string()),
+            Player::Mpv if self.mpv_reconnecting.is_some() && self.player_running != Some(false) => {
+                let (attempt, _) = self.mpv_reconnecting.unwrap_or_default();
+                (
+                    egui::Color32::from_rgb(255, 165, 0),
+                    format!("mpv: reconnecting (attempt {})", attempt),
+                )
+            }
+            Player::Browser | Player::Lines => match (&self.browser_server, &self.lines_server) {
+                (Some(_), _) | (_, Some(_)) => (green, format!("{}: listening", player.label())),
+                _ => (egui::Color32::from_rgb(255, 165, 0), format!("{}: not listening", player.label())),
+            },
+            _ => match self.player_running {
+                Some(true) => (egui::Color32::from_rgb(255, 165, 0), format!("{}: running", player.label())),
+                Some(false) => (gray, format!("{}: not running", player.label())),
+                None => (gray, format!("{}: unknown", player.label())),
+            },
+        }
+    }
+    
+    /// Look for the player's process every couple of seconds.
+    /// Follow the capture file when another is put in its place (written
+    /// elsewhere and renamed over it, or deleted and created again), which
+    /// ends the old watch without an event, and read it from the start.
+    fn check_capture_replaced(&mut self) {
+        self.capture_checked = Instant::now();
+        let identity = session::file_identity(&self.subtitle_file);
+        if identity == self.capture_identity {
+            return;
+        }
+        self.capture_identity = identity;
+        // While it's gone there's nothing to watch; it's picked up when it's back
+        if identity.is_some() {
+            let _ = self.watcher.unwatch(Path::new(&self.subtitle_file));
+            self.diagnosis = watch_capture_file(&mut self.watcher, &self.subtitle_file);
+            self.load_subtitles();
+        }
+    }
+    
+    fn poll_player_running(&mut self, ctx: &egui::Context) {
+        if self.player_checked.is_some_and(|checked| checked.elapsed() < PLAYER_POLL_INTERVAL) {
+            return;
+        }
+        self.player_checked = Some(Instant::now());
+        self.player_running = self.settings.player.is_running();
+        ctx.request_repaint_after(PLAYER_POLL_INTERVAL);
+    }
+    
+    fn status_bar(&mut self, ctx: &egui::Context) {
+        let today = chrono::Local::now().date_naive();
+        let (daily, weekly) = self.stats_db.goal_progress(today);
+        let now = Instant::now();
+        let total = self.subtitles.lock().unwrap().len();
+        let per_minute = self.line_rate.per_minute(now);
+        let since_last = self.line_rate.since_last(now);
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let (color, status) = self.player_status();
+                let hover = match self.settings.player {
+                    Player::Mpv if self.mpv_reconnecting.is_some() => {
+                        let (_, delay) = self.mpv_reconnecting.unwrap_or_default();
+                        format!("The IPC socket dropped; trying again every {} s", delay.as_secs())
+                    }
+                    Player::Mpv if !self.mpv_connected => {
+                        "Start mpv with --input-ipc-server to connect; the script works without it".to_string()
+                    }
+                    _ => "Player state, checked every couple of seconds".to_string(),
+                };
+                ui.colored_label(color, format!("● {}", status)).on_hover_text(hover);
+                ui.separator();
+                // Lit for a moment as each line comes in, as a heartbeat
+                let rate_color = if since_last.is_some_and(|since| since < Duration::from_secs(2)) {
+                    egui::Color32::from_rgb(0, 200, 0)
+                } else {
+                    egui::Color32::from_gray(128)
+                };
+                let last = match since_last {
+                    Some(since) => format!("last one {} s ago", since.as_secs()),
+                    None => "none since ScriptView started".to_string(),
+                };
+                ui.colored_label(rate_color, format!("{} lines · {:.0}/min", total, per_minute))
+                    .on_hover_text(format!("Lines in the list, and new lines per minute over the last two minutes; {}", last));
+                ui.separator();
+                let goals = [
+                    ("today", daily, self.stats_db.daily_goal),
+                    ("this week", weekly, self.stats_db.weekly_goal),
+                ];
+                for (period, progress, goal) in goals {
+                    let (Some(progress), Some(goal)) = (progress, goal) else {
+                        continue;
+                    };
+                    progress_ring(ui, progress.fraction(), 8.0);
+                    ui.label(format!("{}/{} {} {}", progress.done, progress.target, goal.unit.label(), period));
+                    ui.separator();
+                }
+                let streak = self.stats_db.current_streak(today);
+                if streak > 0 {
+                    ui.label(format!("🔥 {}", streak)).on_hover_text("Days in a row meeting the daily goal");
+                }
+            });
+        });
+    }
+    
+    fn check_script_installed(&self) -> bool {
+        self.settings.player.script_installed()
+    }
+    
+    fn install_script_clicked(&mut self) {
+        match self.install_lua_script() {
+            Ok(()) => {
+                self.script_installed = true;
+                let mut message = "✓ Script installed".to_string();
+                if let Some(hint) = self.settings.player.launch_hint() {
+                    message = format!("{}\n{}", message, hint);
+                }
+                self.toasts.success(message);
+                self.diagnosis = None;
+            }
+            Err(diagnosis) => {
+                self.warn(diagnosis.problem.clone());
+                self.diagnosis = Some(diagnosis);
+            }
+        }
+    }
+    
+    /// The "capture appears stalled" banner, with what's likely wrong and
+    /// ways to fix it.
+    fn stall_warning(&mut self, ui: &mut egui::Ui) {
+        let limit = Duration::from_secs(u64::from(self.settings.stall_warning_secs));
+        let Some(quiet) = self.stall_watchdog.stalled_for(limit, Instant::now()) else {
+            return;
+        };
+        let track = self.sid.and_then(|sid| self.sub_tracks.iter().find(|track| track.id == sid)).cloned();
+        let (mut reinstall, mut reload, mut dismiss) = (false, false, false);
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⚠️ Capture appears stalled");
+                dismiss = ui.small_button("✕").on_hover_text("Wait again before warning").clicked();
+            });
+            ui.label(format!(
+                "mpv has been playing for {} s with a subtitle track selected, but no lines have arrived.",
+                quiet.as_secs()
+            ));
+            match &track {
+                Some(track) if track.is_image() => ui.label(format!(
+                    "• Track {} is images of text, which the script can't read. Pick a text track, or read it with OCR.",
+                    track.label()
+                )),
+                _ => ui.label("• The track may only have lines later on; if so, dismiss this."),
+            };
+            if !self.script_installed {
+                ui.label("• The mpv script isn't installed.");
+            }
+            ui.label(format!("• The script should write to {}", self.subtitle_file));
+            ui.horizontal(|ui| {
+                reinstall = ui.button("Reinstall script").on_hover_text("Then restart mpv").clicked();
+                reload = ui.button("Reload track").on_hover_text("Select the subtitle track again").clicked();
+            });
+        });
+        if reinstall {
+            self.install_script_clicked();
+        }
+        if reload {
+            if let Some(sid) = self.sid {
+                self.ipc.command(serde_json::json!(["set_property", "sid", "no"]));
+                self.ipc.command(serde_json::json!(["set_property", "sid", sid]));
+            }
+        }
+        if dismiss || reinstall || reload {
+            self.stall_watchdog.reset(Instant::now());
+        }
+    }
+    
+    fn install_lua_script(&self) -> Result<(), diagnose::Diagnosis> {
+        self.settings.player.install_script()
+    }
+    
+    /// Start whatever writes the capture file from inside the viewer, then
+    /// watch it.
+    fn start_capture(&mut self) {
+        self.browser_server = None;
+        self.lines_server = None;
+        self.remote = None;
+        self.sync_hub = None;
+        if self.sync_client.take().is_some() {
+            // The client showed the hub's stars
+            self.starred = StarredLines::load();
+        }
+        self.sync_connected = false;
+        self.capture_status.clear();
+        match self.settings.sync_mode {
+            SyncMode::Off => {}
+            SyncMode::Hub => match sync::Hub::start(self.settings.sync_port) {
+                Ok(hub) => {
+                    if let Some(stream) = &self.stream {
+                        hub.publish_changes(&store::diff(&[], stream));
+                    }
+                    hub.publish_starred(&self.starred.items);
+                    self.sync_hub = Some(hub);
+                }
+                Err(e) => self.warn(format!("Could not start the sync hub on port {}: {}", self.settings.sync_port, e)),
+            },
+            SyncMode::Client if self.settings.sync_hub.trim().is_empty() => {
+                self.warn("Enter the address of the ScriptView to follow in settings".to_string())
+            }
+            SyncMode::Client => {
+                self.sync_client = Some(sync::Client::connect(&self.settings.sync_hub, &self.subtitle_file))
+            }
+        }
+        if self.settings.sync_mode != SyncMode::Client && !self.settings.remote_capture.trim().is_empty() {
+            match remote::RemoteFile::parse(&self.settings.remote_capture) {
+                Ok(remote) => {
+                    let interval = Duration::from_secs(self.settings.remote_poll_secs.max(1) as u64);
+                    self.capture_status =
+                        format!("Fetching {}:{} every {} s", remote.host, remote.path, interval.as_secs());
+                    self.remote = Some(remote::Poller::spawn(remote, interval));
+                }
+                Err(e) => self.warn(format!("Could not follow the remote capture file: {}", e)),
+            }
+        }
+        let started = match self.settings.player {
+            // The player runs on the other machine
+            _ if self.remote.is_some() || self.settings.sync_mode == SyncMode::Client => Ok(()),
+            Player::Browser => browser::Server::start(self.settings.browser_port, &self.subtitle_file).map(|server| {
+                self.capture_status = format!("Listening on ws://127.0.0.1:{}", server.port);
+                self.browser_server = Some(server);
+            }),
+            Player::Lines => lines::Server::start(self.settings.lines_port, &self.subtitle_file).map(|server| {
+                self.capture_status = format!("Listening on 127.0.0.1:{}", server.port);
+                if server.stdin {
+                    self.capture_status.push_str(" and reading stdin");
+                }
+                self.lines_server = Some(server);
+            }),
+            Player::Mpv | Player::Vlc => Ok(()),
+        };
+        if let Err(e) = started {
+            self.capture_status = format!("Could not start {} source: {}", self.settings.player.label(), e);
+            self.warn(self.capture_status.clone());
+        }
+        self.diagnosis = watch_capture_file(&mut self.watcher, &self.subtitle_file);
+        self.capture_identity = session::file_identity(&self.subtitle_file);
+        self.check_mpv_config();
+    }
+    
+    /// Take in stars from sync clients, or what the hub sent a client.
+    fn poll_sync(&mut self) {
+        let stars: Vec<(StarredLine, bool)> = self.sync_hub.iter().flat_map(|hub| hub.stars.try_iter()).collect();
+        if !stars.is_empty() {
+            for (line, starred) in stars {
+                match starred {
+                    true => self.starred.add(line),
+                    false => self.starred.remove(&line),
+                }
+            }
+            self.save_starred();
+        }
+        let Some(client) = &self.sync_client else {
+            return;
+        };
+        let address = client.address.clone();
+        let events: Vec<sync::ClientEvent> = client.events.try_iter().collect();
+        for event in events {
+            match event {
+                sync::ClientEvent::Connected => {
+                    self.sync_connected = true;
+                    self.toasts.success(format!("Following the transcript on {}", address));
+                }
+                sync::ClientEvent::Disconnected(reason) => {
+                    self.sync_connected = false;
+                    self.warn(format!("Not connected to {}: {}", address, reason));
+                }
+                sync::ClientEvent::Starred(items) => self.starred.items = items,
+            }
+        }
+    }
+    
+    /// Look for mpv settings and scripts that would keep the script from capturing.
+    fn check_mpv_config(&mut self) {
+        self.mpv_conflicts = match self.settings.player {
+            Player::Mpv => mpv_config::scan(&mpv_config::config_dir()),
+            _ => Vec::new(),
+        };
+    }
+    
+    /// Follow the capture file of the newly selected player instead.
+    fn switch_player(&mut self) {
+        let _ = self.watcher.unwatch(Path::new(&self.subtitle_file));
+        let _ = self.watcher.unwatch(Path::new(&protocol::events_path(&self.subtitle_file)));
+        self.subtitle_file = capture_file(&self.settings, &self.script_opts);
+        // Before the hub starts, so it isn't given the old player's lines
+        self.stream = None;
+        self.start_capture();
+        self.script_events = EventReader::new(protocol::events_path(&self.subtitle_file));
+        self.player_checked = None;
+        self.session_start = None;
+        self.stats_session = None;
+        self.subtitles.lock().unwrap().clear();
+        self.load_subtitles();
+    }
+    
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut reload_grammar = false;
+        let mut reload_subtitles = false;
+        let mut reload_fonts = false;
+        let mut reload_frequency = false;
+        let mut switch_player = false;
+        let mut restart_watch_folder = false;
+        let mut reopen_storage = false;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Player:");
+                    egui::ComboBox::from_id_source("player")
+                        .selected_text(self.settings.player.label())
+                        .show_ui(ui, |ui| {
+                            for player in Player::ALL {
+                                switch_player |= ui
+                                    .selectable_value(&mut self.settings.player, player, player.label())
+                                    .changed();
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Remote capture:");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.remote_capture)
+                            .hint_text("sftp://user@htpc/tmp/mpv-subtitles.json"),
+                    );
+                    switch_player |=
+                        response.lost_focus() && capture_file(&self.settings, &self.script_opts) != self.subtitle_file;
+                    ui.label("every");
+                    let response = ui.add(
+                        egui::DragValue::new(&mut self.settings.remote_poll_secs)
+                            .range(1..=60)
+                            .suffix(" s"),
+                    );
+                    switch_player |= (response.drag_stopped() || response.lost_focus()) && self.remote.is_some();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Sync:");
+                    egui::ComboBox::from_id_source("sync_mode")
+                        .selected_text(self.settings.sync_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in SyncMode::ALL {
+                                switch_player |=
+                                    ui.selectable_value(&mut self.settings.sync_mode, mode, mode.label()).changed();
+                            }
+                        });
+                });
+                match self.settings.sync_mode {
+                    SyncMode::Off => {}
+                    SyncMode::Hub => {
+                        ui.horizontal(|ui| {
+                            ui.label("Port:");
+                            let response =
+                                ui.add(egui::DragValue::new(&mut self.settings.sync_port).range(1024..=65535));
+                            let listening = self.sync_hub.as_ref().map(|hub| hub.port);
+                            switch_player |= (response.drag_stopped() || response.lost_focus())
+                                && listening != Some(self.settings.sync_port);
+                            if let Some(hub) = &self.sync_hub {
+                                ui.label(format!("{} connected", hub.clients()));
+                            }
+                        });
+                    }
+                    SyncMode::Client => {
+                        ui.horizontal(|ui| {
+                            ui.label("Hub:");
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.settings.sync_hub).hint_text("laptop.local:7275"),
+                            );
+                            switch_player |= response.lost_focus()
+                                && self.sync_client.as_ref().map(|client| client.address.as_str())
+                                    != Some(self.settings.sync_hub.trim());
+                        });
+                    }
+                }
+                if self.remote.is_some() {
+                    ui.label(
+                        egui::RichText::new(&self.capture_status)
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                    );
+                }
+                if let Some(hint) = self.settings.player.launch_hint() {
+                    ui.label(egui::RichText::new(hint).small().color(egui::Color32::from_gray(128)));
+                }
+                let server_port = match self.settings.player {
+                    Player::Browser => Some((
+                        "Extension port:",
+                        &mut self.settings.browser_port,
+                        self.browser_server.as_ref().map(|server| server.port),
+                    )),
+                    Player::Lines => Some((
+                        "TCP port:",
+                        &mut self.settings.lines_port,
+                        self.lines_server.as_ref().map(|server| server.port),
+                    )),
+                    Player::Mpv | Player::Vlc => None,
+                };
+                if let Some((label, port, listening)) = server_port {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        let response = ui.add(egui::DragValue::new(port).range(1024..=65535));
+                        // Restart the server once editing is done
+                        switch_player |=
+                            (response.drag_stopped() || response.lost_focus()) && listening != Some(*port);
+                    });
+                    ui.label(
+                        egui::RichText::new(&self.capture_status)
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                    );
+                }
+                if self.settings.player == Player::Mpv {
+                    ui.label("Capture file retention:");
+                    let mut retention = self.script_opts.retention;
+                    egui::ComboBox::from_id_source("retention")
+                        .selected_text(retention.label())
+                        .show_ui(ui, |ui| {
+                            for option in Retention::ALL {
+                                ui.selectable_value(&mut retention, option, option.label());
+                            }
+                        });
+                    if retention != self.script_opts.retention {
+                        match ScriptOpts::save_retention(retention) {
+                            Ok(()) => self.script_opts.retention = retention,
+                            Err(e) => self.warn(format!("Could not update script options: {}", e)),
+                        }
+                    }
+                    ui.label(
+                        egui::RichText::new("Takes effect the next time mpv starts.")
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    ui.label("mpv IPC socket:");
+                    if ui.text_edit_singleline(&mut self.settings.mpv_socket).lost_focus() {
+                        self.ipc.set_socket_path(&self.settings.mpv_socket);
+                        self.mpv_reconnecting = None;
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(if self.mpv_connected {
+                        "Connected; paused time is left out of statistics."
+                    } else {
+                        "Not connected; start mpv with --input-ipc-server to leave out paused time."
+                    })
+                    .small()
+                    .color(egui::Color32::from_gray(128)),
+                );
+                ui.checkbox(&mut self.settings.osd_feedback, "Confirm actions on mpv's on-screen display");
+                ui.horizontal(|ui| {
+                    ui.label("Warn when no lines arrive for");
+                    ui.add(egui::DragValue::new(&mut self.settings.stall_warning_secs).range(0..=3600).suffix(" s"))
+                        .on_hover_text("While mpv plays with a subtitle track selected (needs the IPC socket); 0 never warns");
+                });
+                ui.checkbox(&mut self.settings.follow_mpv, "Follow mpv")
+                    .on_hover_text("Restore the window when mpv starts, plays or gets focus, and minimize it when mpv closes (needs the IPC socket)");
+                
+                ui.horizontal(|ui| {
+                    ui.label("Hide repeated lines within");
+                    if ui
+                        .add(egui::DragValue::new(&mut self.settings.dedup_window_secs).range(0..=3600).suffix(" s"))
+                        .on_hover_text("0 shows every repeat")
+                        .changed()
+                    {
+                        reload_subtitles = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Collapse lines typed out within");
+                    if ui
+                        .add(egui::DragValue::new(&mut self.settings.prefix_window_secs).range(0..=3600).suffix(" s"))
+                        .on_hover_text(
+                            "A line is hidden when the next one extends it and arrived this soon after; \
+                             0 collapses them however far apart",
+                        )
+                        .changed()
+                    {
+                        reload_subtitles = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Collapse a line found at the");
+                    let before = self.settings.collapse_mode;
+                    egui::ComboBox::from_id_source("collapse_mode")
+                        .selected_text(self.settings.collapse_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in CollapseMode::ALL {
+                                ui.selectable_value(&mut self.settings.collapse_mode, mode, mode.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "For players that build text up in the middle or at the end rather than typing it out",
+                        );
+                    if self.settings.collapse_mode != before {
+                        reload_subtitles = true;
+                    }
+                });
+                egui::CollapsingHeader::new("Sources").id_source("sources").show(ui, |ui| {
+                    egui::Grid::new("source_styles").num_columns(3).show(ui, |ui| {
+                        for source in source::Source::ALL {
+                            let style = self.settings.sources.entry(source).or_default();
+                            ui.label(source.label());
+                            let mut shown = !style.hidden;
+                            if ui.checkbox(&mut shown, "Show").changed() {
+                                style.hidden = !shown;
+                                reload_subtitles = true;
+                            }
+                            let mut color = style.color.unwrap_or(source.default_color());
+                            if ui.color_edit_button_srgb(&mut color).changed() {
+                                style.color = Some(color);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+                let player = self.settings.player;
+                egui::CollapsingHeader::new(format!("Noise filter for {}", player.label()))
+                    .id_source("noise_filter")
+                    .show(ui, |ui| {
+                        let filter = self.settings.noise_filters.entry(player).or_default();
+                        ui.horizontal(|ui| {
+                            ui.label("Leave out lines below");
+                            let mut percent = filter.min_confidence * 100.0;
+                            let response = ui.add(
+                                egui::DragValue::new(&mut percent)
+                                    .range(0.0..=100.0)
+                                    .max_decimals(0)
+                                    .suffix("% confidence"),
+                            );
+                            filter.min_confidence = percent / 100.0;
+                            reload_subtitles |= response.changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("and shorter than");
+                            reload_subtitles |= ui
+                                .add(egui::DragValue::new(&mut filter.min_chars).range(0..=50).suffix(" characters"))
+                                .changed();
+                        });
+                        ui.label("and containing any of (one per line):");
+                        reload_subtitles |= ui
+                            .add(
+                                egui::TextEdit::multiline(&mut filter.blocklist)
+                                    .desired_rows(3)
+                                    .hint_text("Logos, watermarks, \"[Music]\"…"),
+                            )
+                            .lost_focus();
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Show filtered lines ({})", self.filtered_out.len())).clicked() {
+                        self.show_filtered = true;
+                    }
+                    if ui
+                        .button("Show raw capture records")
+                        .on_hover_text("The capture file's last records as written, for debugging player scripts")
+                        .clicked()
+                    {
+                        self.show_capture_tail = true;
+                    }
+                });
+                ui.checkbox(&mut self.settings.keep_raw_stream, "Keep the raw stream for reprocessing")
+                    .on_hover_text("Every line as read, before filtering, by day");
+                ui.label(format!("Kept in: {}", self.settings.storage.label()))
+                    .on_hover_text("Run scriptview --migrate-storage jsonl|sqlite to move it");
+                reopen_storage = ui
+                    .checkbox(&mut self.settings.encrypt_history, "Encrypt history with a passphrase")
+                    .on_hover_text("The raw stream, capture archives, statistics, summaries and backups; asked for at startup")
+                    .changed();
+                ui.checkbox(&mut self.settings.remove_capture_on_exit, "Delete the capture file when closing");
+                ui.horizontal(|ui| {
+                    ui.label("Watch folder:");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.watch_folder).hint_text("open captures dropped here"),
+                    );
+                    restart_watch_folder = response.lost_focus();
+                });
+                if ui
+                    .checkbox(&mut self.settings.low_power, "Low power mode")
+                    .on_hover_text("Repaint less often, skip animations and shadows, and hold back MQTT and webhook lines while minimized")
+                    .changed()
+                {
+                    power::apply_style(ctx, self.settings.low_power);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("On startup:");
+                    ui.checkbox(&mut self.settings.start_minimized, "Minimize");
+                    ui.checkbox(&mut self.settings.start_in_overlay, "Open the overlay");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Startup layout:");
+                    let selected = if self.settings.start_layout.is_empty() { "None" } else { &self.settings.start_layout };
+                    egui::ComboBox::from_id_source("start_layout")
+                        .selected_text(selected.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.settings.start_layout, String::new(), "None");
+                            for layout in &self.settings.layouts {
+                                ui.selectable_value(&mut self.settings.start_layout, layout.name.clone(), &layout.name);
+                            }
+                        });
+                });
+                ui.checkbox(&mut self.settings.time_ordered, "Order new sessions by media time");
+                ui.checkbox(&mut self.settings.announce_subtitles, "Announce new subtitles to screen readers");
+                ui.horizontal(|ui| {
+                    ui.label("Mark recognised lines for checking below");
+                    let mut percent = self.settings.low_confidence * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(0.0..=100.0).max_decimals(0).suffix("% confidence"));
+                    self.settings.low_confidence = percent / 100.0;
+                });
+                ui.checkbox(&mut self.settings.flag_reading_problems, "Flag lines too fast or too long to read")
+                    .on_hover_text("For checking subtitle files; ⚠ in the toolbar shows only the flagged lines");
+                if self.settings.flag_reading_problems {
+                    ui.horizontal(|ui| {
+                        ui.label("At most");
+                        ui.add(egui::DragValue::new(&mut self.settings.max_cps).range(0.0..=100.0).max_decimals(1));
+                        ui.label("characters per second and");
+                        ui.add(egui::DragValue::new(&mut self.settings.max_line_chars).range(0..=500));
+                        ui.label("per line");
+                    })
+                    .response
+                    .on_hover_text("0 doesn't check");
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Watch words:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.watch_words).hint_text("name, topic, …"));
+                });
+                ui.checkbox(&mut self.settings.notify_watch_words, "Desktop notification when a watch word comes up");
+                ui.checkbox(&mut self.settings.session_summary, "Summarize sessions when they end");
+                let mut restart_mqtt = ui
+                    .checkbox(&mut self.settings.mqtt_enabled, "Publish subtitles over MQTT")
+                    .changed();
+                if self.settings.mqtt_enabled {
+                    egui::Grid::new("mqtt").num_columns(2).show(ui, |ui| {
+                        ui.label("Broker:");
+                        restart_mqtt |= ui
+                            .add(egui::TextEdit::singleline(&mut self.settings.mqtt_broker).hint_text("user:password@host:1883"))
+                            .lost_focus();
+                        ui.end_row();
+                        ui.label("Topic:");
+                        restart_mqtt |= ui.text_edit_singleline(&mut self.settings.mqtt_topic).lost_focus();
+                        ui.end_row();
+                    });
+                }
+                if restart_mqtt {
+                    self.start_mqtt();
+                }
+                let mut restart_webhook = false;
+                ui.horizontal(|ui| {
+                    ui.label("Webhook:");
+                    restart_webhook |= ui
+                        .add(egui::TextEdit::singleline(&mut self.settings.webhook_url).hint_text("https://… (POST per line)"))
+                        .lost_focus();
+                });
+                if !self.settings.webhook_url.trim().is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label("Batch lines over");
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.settings.webhook_batch_secs).range(0..=600).suffix(" s"),
+                        );
+                        restart_webhook |= response.drag_stopped() || response.lost_focus();
+                    });
+                }
+                if restart_webhook {
+                    self.start_webhook();
+                }
+                egui::CollapsingHeader::new(format!("Note services ({})", self.settings.note_services.len()))
+                    .id_source("note_services")
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(
+                                "Bodies are JSON with {text}, {translation}, {title}, {source}, {time} and {date} \
+                                 for a line, and {transcript}, {title}, {source} and {date} for a transcript; \
+                                 headers can use {token}",
+                            )
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                        );
+                        let mut removed = None;
+                        for (index, service) in self.settings.note_services.iter_mut().enumerate() {
+                            ui.push_id(index, |ui| {
+                                ui.separator();
+                                ui.horizontal(|ui| {
+                                    ui.add(egui::TextEdit::singleline(&mut service.name).desired_width(100.0));
+                                    ui.checkbox(&mut service.enabled, "Send lines here")
+                                        .on_hover_text("From \"Send to note services\" in a line's menu");
+                                    if ui.small_button("Remove").clicked() {
+                                        removed = Some(index);
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("URL:");
+                                    ui.text_edit_singleline(&mut service.url);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Token:");
+                                    ui.add(egui::TextEdit::singleline(&mut service.token).password(true));
+                                });
+                                ui.label("Headers:");
+                                ui.add(egui::TextEdit::multiline(&mut service.headers).desired_rows(2).code_editor());
+                                ui.label("Line body:");
+                                let line_template = egui::TextEdit::multiline(&mut service.line_template);
+                                ui.add(line_template.desired_rows(3).code_editor());
+                                ui.label("Transcript body:");
+                                let transcript_template = egui::TextEdit::multiline(&mut service.transcript_template);
+                                ui.add(transcript_template.desired_rows(3).code_editor());
+                            });
+                        }
+                        if let Some(index) = removed {
+                            self.settings.note_services.remove(index);
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Add:");
+                            if ui.button("Readwise").clicked() {
+                                self.settings.note_services.push(notes::NoteService::readwise());
+                            }
+                            let notion = ui.button("Notion").on_hover_text("Then put your database's ID in the bodies");
+                            if notion.clicked() {
+                                self.settings.note_services.push(notes::NoteService::notion());
+                            }
+                            if ui.button("Other").clicked() {
+                                self.settings.note_services.push(notes::NoteService::default());
+                            }
+                        });
+                    });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Fallback font:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.fallback_font).hint_text("/usr/share/fonts/…"));
+                    if ui.button("Reload").clicked() {
+                        reload_fonts = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Text size from");
+                    ui.add(egui::DragValue::new(&mut self.settings.font_size_min).range(1.0..=400.0).max_decimals(2));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut self.settings.font_size_max).range(1.0..=400.0).max_decimals(2));
+                    ui.label("in steps of");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.font_size_step)
+                            .range(0.1..=20.0)
+                            .speed(0.05)
+                            .max_decimals(2),
+                    );
+                });
+                let range = self.settings.font_size_range();
+                self.settings.font_size = self.settings.font_size.clamp(*range.start(), *range.end());
+                ui.horizontal(|ui| {
+                    ui.label("Presentation:");
+                    let previous = self.settings.preset;
+                    egui::ComboBox::from_id_source("preset")
+                        .selected_text(self.settings.preset.label())
+                        .show_ui(ui, |ui| {
+                            for preset in Preset::ALL {
+                                ui.selectable_value(&mut self.settings.preset, preset, preset.label());
+                            }
+                        });
+                    if self.settings.preset != previous {
+                        ctx.set_visuals(self.settings.preset.visuals());
+                        power::apply_style(ctx, self.settings.low_power);
+                        reload_fonts = true;
+                    }
+                });
+                let line_width = &mut self.settings.line_width;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut line_width.limited, "Limit line width to");
+                    ui.add_enabled_ui(line_width.limited, |ui| {
+                        ui.add(egui::DragValue::new(&mut line_width.max).range(1.0..=4000.0).max_decimals(0));
+                        egui::ComboBox::from_id_source("width_unit")
+                            .selected_text(line_width.unit.label())
+                            .show_ui(ui, |ui| {
+                                for unit in WidthUnit::ALL {
+                                    ui.selectable_value(&mut line_width.unit, unit, unit.label());
+                                }
+                            });
+                        ui.checkbox(&mut line_width.centered, "Centered");
+                    });
+                });
+                if self.settings.preset.uses_reading_font() {
+                    ui.horizontal(|ui| {
+                        ui.label("Reading font:");
+                        ui.add(egui::TextEdit::singleline(&mut self.settings.reading_font).hint_text("OpenDyslexic-Regular.otf"));
+                        if ui.button("Reload").clicked() {
+                            reload_fonts = true;
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Presenter text size:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.presenter_font_size)
+                            .range(16.0..=300.0)
+                            .max_decimals(0),
+                    );
+                    ui.label("QR link:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.presenter_qr_url).hint_text("https://…"));
+                });
+                if !self.font_status.is_empty() {
+                    ui.label(
+                        egui::RichText::new(&self.font_status)
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                    );
+                }
+                
+                ui.separator();
+                ui.checkbox(&mut self.settings.show_grammar_hints, "Show grammar hints");
+                ui.horizontal(|ui| {
+                    ui.label("Clicking a timestamp:");
+                    egui::ComboBox::from_id_source("timestamp_click")
+                        .selected_text(self.settings.timestamp_click.label())
+                        .show_ui(ui, |ui| {
+                            for click in TimestampClick::ALL {
+                                ui.selectable_value(&mut self.settings.timestamp_click, click, click.label());
+                            }
+                        });
+                });
+                ui.checkbox(&mut self.settings.render_emphasis, "Show italics and bold from subtitle markup")
+                    .on_hover_text("When off, tags like <i> and {\\i1} are removed and the text shown plain");
+                ui.horizontal(|ui| {
+                    ui.label("Pattern file:");
+                    ui.text_edit_singleline(&mut self.settings.grammar_file)
+                        .on_hover_text("A JSON list of patterns, or YAML if the name ends in .yaml or .yml");
+                    if ui.button("Reload").clicked() {
+                        reload_grammar = true;
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(&self.grammar_status)
+                        .small()
+                        .color(egui::Color32::from_gray(128)),
+                );
+                
+                ui.separator();
+                match self.current_show.clone() {
+                    Some(show) => {
+                        let remembered = shows::find(&self.settings.shows, &show).is_some();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Playing: {}", show));
+                            let label = if remembered { "Update remembered settings" } else { "Remember settings for this show" };
+                            if ui
+                                .button(label)
+                                .on_hover_text("Text size, line filters, profile and mpv's subtitle delay, put back whenever it plays again")
+                                .clicked()
+                            {
+                                let saved = self.current_show_settings(show.clone());
+                                // From here on changes are this show's, not the user's own
+                                if self.show_base.is_none() {
+                                    self.show_base = Some(saved.clone());
+                                }
+                                shows::store(&mut self.settings.shows, saved);
+                                self.toasts.success(format!("Remembered the settings for {}", show));
+                            }
+                            if remembered && ui.button("Forget").clicked() {
+                                self.settings.shows.retain(|saved| !saved.show.eq_ignore_ascii_case(&show));
+                                self.restore_show_base();
+                            }
+                        });
+                    }
+                    None => {
+                        ui.label(
+                            egui::RichText::new("Settings can be remembered per show once one is playing")
+                                .small()
+                                .color(egui::Color32::from_gray(128)),
+                        );
+                    }
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Profile:");
+                    let previous = self.settings.active_profile;
+                    egui::ComboBox::from_id_source("profile")
+                        .selected_text(self.settings.profile().name.clone())
+                        .show_ui(ui, |ui| {
+                            for (i, profile) in self.settings.profiles.iter().enumerate() {
+                                ui.selectable_value(&mut self.settings.active_profile, i, &profile.name);
+                            }
+                        });
+                    if ui.button("+").on_hover_text("New profile").clicked() {
+                        self.settings.profiles.push(settings::Profile {
+                            name: format!("Profile {}", self.settings.profiles.len() + 1),
+                            ..Default::default()
+                        });
+                        self.settings.active_profile = self.settings.profiles.len() - 1;
+                    }
+                    if ui.add_enabled(self.settings.profiles.len() > 1, egui::Button::new("−"))
+                        .on_hover_text("Delete profile")
+                        .clicked()
+                    {
+                        let index = self.settings.active_profile.min(self.settings.profiles.len() - 1);
+                        self.settings.profiles.remove(index);
+                        self.settings.active_profile = index.saturating_sub(1);
+                    }
+                    if self.settings.active_profile != previous {
+                        reload_frequency = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.settings.profile_mut().name);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Text direction:");
+                    let direction = &mut self.settings.profile_mut().text_direction;
+                    egui::ComboBox::from_id_source("text_direction")
+                        .selected_text(direction.label())
+                        .show_ui(ui, |ui| {
+                            for option in TextDirection::ALL {
+                                ui.selectable_value(direction, option, option.label());
+                            }
+                        });
+                });
+                ui.checkbox(&mut self.settings.color_by_frequency, "Color words by frequency");
+                ui.horizontal(|ui| {
+                    ui.label("Frequency list:");
+                    ui.text_edit_singleline(&mut self.settings.profile_mut().frequency_list);
+                    if ui.button("Reload").clicked() {
+                        reload_frequency = true;
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(&self.frequency_status)
+                        .small()
+                        .color(egui::Color32::from_gray(128)),
+                );
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.pause_on_unknown, "Pause mpv on lines with more than");
+                    ui.add(egui::DragValue::new(&mut self.settings.unknown_word_limit).range(0..=20));
+                    ui.label("unknown words");
+                })
+                .response
+                .on_hover_text("Words count as known once marked so from their menu, or when common in the frequency list");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.listening_first, "Listening first: hide new lines for");
+                    ui.add(egui::DragValue::new(&mut self.settings.reveal_delay_secs).range(0..=60).suffix(" s"));
+                })
+                .response
+                .on_hover_text("Try to follow by ear, then check the text. Click a line, press Enter here or Alt+r in mpv to show it sooner; 0 waits for that");
+                if !self.settings.listening_first {
+                    self.delayed.reveal_all();
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Example sentences:");
+                    egui::ComboBox::from_id_source("example_source")
+                        .selected_text(self.settings.example_source.label())
+                        .show_ui(ui, |ui| {
+                            for source in ExampleSource::ALL {
+                                ui.selectable_value(&mut self.settings.example_source, source, source.label());
+                            }
+                        });
+                });
+                if self.settings.example_source == ExampleSource::Tatoeba {
+                    ui.horizontal(|ui| {
+                        ui.label("Sentence file:");
+                        ui.text_edit_singleline(&mut self.settings.tatoeba_file);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Language code:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings.profile_mut().tatoeba_language)
+                                .desired_width(60.0)
+                                .hint_text("jpn"),
+                        );
+                    });
+                }
+                
+                ui.separator();
+                ui.checkbox(&mut self.settings.hover_translation, "Machine-translate a line when hovered")
+                    .on_hover_text("Only in a tooltip, so the view stays in one language; each line is translated once");
+                if self.settings.hover_translation {
+                    ui.horizontal(|ui| {
+                        ui.label("LibreTranslate URL:");
+                        ui.text_edit_singleline(&mut self.settings.translate_url);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("API key:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings.translate_api_key)
+                                .password(true)
+                                .hint_text("if the server needs one"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("From");
+                        ui.add(egui::TextEdit::singleline(&mut self.settings.translate_source).desired_width(50.0));
+                        ui.label("to");
+                        ui.add(egui::TextEdit::singleline(&mut self.settings.translate_target).desired_width(50.0));
+                    })
+                    .response
+                    .on_hover_text("Language codes such as ja and en; \"auto\" detects the language of the lines");
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Pronunciation audio:");
+                    egui::ComboBox::from_id_source("audio_source")
+                        .selected_text(self.settings.audio_source.label())
+                        .show_ui(ui, |ui| {
+                            for source in AudioSource::ALL {
+                                ui.selectable_value(&mut self.settings.audio_source, source, source.label());
+                            }
+                        });
+                });
+                ui.horizontal(|ui| match self.settings.audio_source {
+                    AudioSource::LocalDir => {
+                        ui.label("Audio directory:");
+                        ui.text_edit_singleline(&mut self.settings.audio_dir);
+                    }
+                    AudioSource::Url => {
+                        ui.label("URL:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings.audio_url)
+                                .hint_text("https://example.com/audio?term={word}"),
+                        );
+                    }
+                });
+                
+                ui.separator();
+                egui::CollapsingHeader::new(format!("Web search ({})", self.settings.search_sites.len()))
+                    .id_source("search_sites")
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(
+                                "In the menus of words and lines and the command palette (Ctrl+Shift+P); \
+                                 {text} in the URL is what's searched for",
+                            )
+                            .small()
+                            .color(egui::Color32::from_gray(128)),
+                        );
+                        let mut removed = None;
+                        egui::Grid::new("search_sites_grid").num_columns(3).show(ui, |ui| {
+                            for (index, site) in self.settings.search_sites.iter_mut().enumerate() {
+                                ui.add(egui::TextEdit::singleline(&mut site.name).desired_width(90.0));
+                                ui.add(egui::TextEdit::singleline(&mut site.url).desired_width(280.0));
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(index) = removed {
+                            self.settings.search_sites.remove(index);
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Add").clicked() {
+                                self.settings.search_sites.push(search::SearchSite::default());
+                            }
+                            if ui.button("Restore defaults").clicked() {
+                                self.settings.search_sites = search::defaults();
+                            }
+                        });
+                    });
+                
+                ui.separator();
+                ui.label("Anki (through AnkiConnect):");
+                egui::Grid::new("anki_settings").num_columns(2).show(ui, |ui| {
+                    ui.label("URL:");
+                    ui.text_edit_singleline(&mut self.settings.anki_url);
+                    ui.end_row();
+                    ui.label("Deck:");
+                    ui.text_edit_singleline(&mut self.settings.anki_deck);
+                    ui.end_row();
+                    ui.label("Note type:");
+                    ui.text_edit_singleline(&mut self.settings.anki_note_type);
+                    ui.end_row();
+                    ui.label("Sentence field:");
+                    ui.text_edit_singleline(&mut self.settings.anki_sentence_field);
+                    ui.end_row();
+                    ui.label("Source field:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.anki_source_field).hint_text("none"));
+                    ui.end_row();
+                    ui.label("Skip if this field has the sentence:")
+                        .on_hover_text("Searched across your collection before adding; leave empty to add duplicates");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.anki_dedup_field).hint_text("no check"));
+                    ui.end_row();
+                    ui.label("Audio field:").on_hover_text("The line's audio, cut from the media file with ffmpeg");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.anki_audio_field).hint_text("no audio"));
+                    ui.end_row();
+                });
+                ui.checkbox(&mut self.settings.tts_fallback, "Use text to speech when the media can't be cut (streams)");
+                ui.add_enabled_ui(self.settings.tts_fallback, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Command:");
+                        ui.text_edit_singleline(&mut self.settings.tts_command)
+                            .on_hover_text("{text} is the sentence and {file} the audio file to write");
+                    });
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Export to:");
+                    ui.text_edit_singleline(&mut self.settings.export_dir);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("External editor:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.editor_command)
+                            .hint_text("$VISUAL or the default app"),
+                    )
+                    .on_hover_text(
+                        "A command such as \"code --wait\" or \"kitty vim {file}\"; {file} is the file. \
+                         A terminal on its own, such as \"kitty\", runs $EDITOR in it",
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Notes vault:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.vault_dir).hint_text("/path/to/vault/Immersion"));
+                    egui::ComboBox::from_id_source("vault_format")
+                        .selected_text(self.settings.vault_format.label())
+                        .show_ui(ui, |ui| {
+                            for format in export::Format::NOTES {
+                                ui.selectable_value(&mut self.settings.vault_format, format, format.label());
+                            }
+                        });
+                })
+                .response
+                .on_hover_text("Folder in an Obsidian vault or a Logseq graph's pages folder, for \"Save to vault\" in the 💾 menu");
+                let auto = &mut self.settings.auto_export;
+                ui.horizontal(|ui| {
+                    ui.label("Auto-export as");
+                    egui::ComboBox::from_id_source("auto_export_format")
+                        .selected_text(auto.format.label())
+                        .show_ui(ui, |ui| {
+                            for format in export::Format::ALL {
+                                ui.selectable_value(&mut auto.format, format, format.label());
+                            }
+                        });
+                    ui.checkbox(&mut auto.on_session_end, "when a session ends");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("and every");
+                    ui.add(egui::DragValue::new(&mut auto.every_minutes).range(0..=240).suffix(" min"))
+                        .on_hover_text("0 exports only when sessions end");
+                    ui.label("to");
+                    ui.add(egui::TextEdit::singleline(&mut auto.dir).hint_text("the export directory"));
+                });
+                let timing = &mut self.settings.cue_timing;
+                ui.checkbox(&mut timing.synthesize, "Fill in missing end times from the next line");
+                ui.add_enabled_ui(timing.synthesize, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Gap:");
+                        ui.add(egui::DragValue::new(&mut timing.gap).range(0.0..=2.0).speed(0.01).suffix(" s"));
+                        ui.label("Duration:");
+                        ui.add(
+                            egui::DragValue::new(&mut timing.min_duration)
+                                .range(0.1..=timing.max_duration)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut timing.max_duration)
+                                .range(timing.min_duration..=60.0)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        );
+                    });
+                });
+            });
+        if reload_grammar {
+            self.load_grammar();
+        }
+        if reload_fonts {
+            self.install_fonts(ctx);
+        }
+        if reload_subtitles {
+            self.load_subtitles();
+            if let Some(imported) = self.imported.as_ref().filter(|imported| imported.raw) {
+                self.reprocess(imported.path.clone());
+            }
+        }
+        if reload_frequency {
+            self.load_frequency_list();
+        }
+        if reopen_storage {
+            self.open_storage();
+        }
+        if restart_watch_folder {
+            self.start_watch_folder();
+        }
+        if switch_player {
+            self.switch_player();
+        }
+        self.show_settings = open;
+    }
+    
+    fn select_stats_session(&mut self, path: String) {
+        let entries = if path == self.subtitle_file {
+            self.subtitles.lock().unwrap().clone()
+        } else {
+            session::read_archive(&path, self.history_key.as_ref())
+                .map(|entries| {
+                    filter_prefix_subtitles(entries, self.settings.prefix_window_secs, self.settings.collapse_mode)
+                })
+                .unwrap_or_default()
+        };
+        let mut stats = stats::session_stats(&entries);
+        stats::add_watch_later(&mut stats);
+        self.stats_session = Some((path, stats));
+    }
+    
+    /// Recent sessions, newest first, with their tags for editing.
+    fn session_list(&mut self, ui: &mut egui::Ui) {
+        let tag = self.stats_tag.clone();
+        let indices: Vec<usize> = (0..self.stats_db.sessions.len())
+            .rev()
+            .filter(|&i| tag.as_ref().is_none_or(|tag| self.stats_db.sessions[i].tags.contains(tag)))
+            .take(50)
+            .collect();
+        let mut save = false;
+        egui::ScrollArea::vertical().id_source("session_list").max_height(200.0).show(ui, |ui| {
+            for i in indices {
+                let session = &mut self.stats_db.sessions[i];
+                ui.horizontal(|ui| {
+                    ui.label(session.label()).on_hover_text(format!(
+                        "{} lines, {} min",
+                        session.lines,
+                        session.seconds / 60
+                    ));
+                    match &mut self.tag_edit {
+                        Some((editing, text)) if *editing == i => {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(text).hint_text("anime, lecture").desired_width(140.0),
+                            );
+                            response.request_focus();
+                            if response.lost_focus() {
+                                if !ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+                                    session.tags = stats::parse_tags(text);
+                                    save = true;
+                                }
+                                self.tag_edit = None;
+                            }
+                        }
+                        _ => {
+                            for tag in &session.tags {
+                                ui.label(egui::RichText::new(format!("#{}", tag)).small().strong());
+                            }
+                            if ui.small_button("🏷").on_hover_text("Edit tags").clicked() {
+                                self.tag_edit = Some((i, session.tags.join(", ")));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        if save {
+            if let Err(e) = self.save_stats() {
+                self.warn(format!("Could not save statistics: {}", e));
+            }
+        }
+    }
+    
+    fn stats_window(&mut self, ctx: &egui::Context) {
+        if self.stats_session.is_none() {
+            self.select_stats_session(self.subtitle_file.clone());
+        }
+        let mut open = self.show_stats;
+        let mut selected = None;
+        egui::Window::new("Statistics")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some((current, stats)) = &self.stats_session else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label("Session:");
+                    egui::ComboBox::from_id_source("stats_session")
+                        .selected_text(session::session_label(current, &self.subtitle_file))
+                        .show_ui(ui, |ui| {
+                            for path in session::list_sessions(&self.subtitle_file) {
+                                let label = session::session_label(&path, &self.subtitle_file);
+                                if ui.selectable_label(&path == current, label).clicked() {
+                                    selected = Some(path);
+                                }
+                            }
+                        });
+                    if ui.button("⟳").on_hover_text("Recalculate").clicked() {
+                        selected = Some(current.clone());
+                    }
+                });
+                ui.separator();
+                egui::Grid::new("stats_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Lines:");
+                    ui.label(stats.lines.to_string());
+                    ui.end_row();
+                    ui.label("Characters:");
+                    ui.label(stats.characters.to_string());
+                    ui.end_row();
+                });
+                if !stats.media.is_empty() {
+                    ui.separator();
+                    for media in &stats.media {
+                        let name = stats::media_name(&media.path);
+                        ui.label(egui::RichText::new(name).strong()).on_hover_text(&media.path);
+                        ui.horizontal(|ui| {
+                            match (media.watched_fraction(), media.duration) {
+                                (Some(fraction), Some(duration)) => {
+                                    ui.add(
+                                        egui::ProgressBar::new(fraction as f32)
+                                            .desired_width(150.0)
+                                            .text(format!("{:.0}%", fraction * 100.0)),
+                                    );
+                                    let position = media.resume_position.unwrap_or(media.last_subtitle);
+                                    ui.label(format!(
+                                        "{} / {}",
+                                        format_timestamp(position),
+                                        format_timestamp(duration)
+                                    ));
+                                }
+                                _ => {
+                                    ui.label("Duration unknown");
+                                }
+                            }
+                        });
+                        let source = if media.resume_position.is_some() {
+                            "resume point from mpv watch-later data"
+                        } else {
+                            "no watch-later data; based on last subtitle"
+                        };
+                        ui.label(
+                            egui::RichText::new(format!("{} lines, {}", media.lines, source))
+                                .small()
+                                .color(egui::Color32::from_gray(128)),
+                        );
+                    }
+                }
+                
+                ui.separator();
+                egui::CollapsingHeader::new("History").show(ui, |ui| {
+                    let tags = self.stats_db.all_tags();
+                    if !tags.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("Tag:");
+                            egui::ComboBox::from_id_source("stats_tag")
+                                .selected_text(self.stats_tag.as_deref().unwrap_or("All sessions"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.stats_tag, None, "All sessions");
+                                    for tag in tags {
+                                        ui.selectable_value(&mut self.stats_tag, Some(tag.clone()), tag);
+                                    }
+                                });
+                        });
+                    }
+                    let filtered;
+                    let db = match &self.stats_tag {
+                        Some(tag) => {
+                            filtered = self.stats_db.tagged(tag);
+                            &filtered
+                        }
+                        None => &self.stats_db,
+                    };
+                    let days = db.daily();
+                    let today = chrono::Local::now().date_naive();
+                    activity_heatmap(ui, &days, today);
+                    let (today_lines, today_minutes) = days
+                        .iter()
+                        .find(|day| day.date == today)
+                        .map_or((0, 0), |day| (day.lines, day.seconds / 60));
+                    let total_minutes: i64 = days.iter().map(|day| day.seconds).sum::<i64>() / 60;
+                    egui::Grid::new("history_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Today:");
+                        ui.label(format!("{} lines, {} min", today_lines, today_minutes));
+                        ui.end_row();
+                        ui.label("All time:");
+                        ui.label(format!("{} days, {} min", days.len(), total_minutes));
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Export to:");
+                        ui.text_edit_singleline(&mut self.settings.export_dir);
+                    });
+                    ui.horizontal(|ui| {
+                        let mut export = None;
+                        if ui.button("Export CSV").clicked() {
+                            export = Some(false);
+                        }
+                        if ui.button("Export JSON").clicked() {
+                            export = Some(true);
+                        }
+                        if let Some(json) = export {
+                            self.stats_export_status = match db.export(&self.settings.export_dir, json) {
+                                Ok(path) => format!("Exported to {}", path),
+                                Err(e) => format!("Export failed: {}", e),
+                            };
+                        }
+                    });
+                    egui::CollapsingHeader::new("Sessions").show(ui, |ui| {
+                        self.session_list(ui);
+                    });
+                    ui.separator();
+                    let mut goals_changed = goal_editor(ui, "Daily goal", &mut self.stats_db.daily_goal);
+                    goals_changed |= goal_editor(ui, "Weekly goal", &mut self.stats_db.weekly_goal);
+                    ui.label(format!(
+                        "Streak: {} days (best {})",
+                        self.stats_db.current_streak(today),
+                        self.stats_db.best_streak
+                    ));
+                    if goals_changed {
+                        self.stats_db.update_best_streak(today);
+                        if let Err(e) = self.save_stats() {
+                            self.warn(format!("Could not save statistics: {}", e));
+                        }
+                    }
+                    if !self.stats_export_status.is_empty() {
+                        ui.label(
+                            egui::RichText::new(&self.stats_export_status)
+                                .small()
+                                .color(egui::Color32::from_gray(128)),
+                        );
+                    }
+                });
             });
-        
-        // Keep watcher alive
-        Box::leak(Box::new(watcher));
-        
-        let mut viewer = Self {
-            subtitles: Arc::new(Mutex::new(Vec::new())),
-            rx,
-            subtitle_file,
-            always_on_top: true,
-            file_exists: false,
-            script_installed: false,
-            script_install_time: None,
-            font_size: 14.0,
-        };
-        
-        // Load initial content
-        viewer.load_subtitles();
-        
-        viewer
-    }
-    
-    fn load_subtitles(&mut self) {
-        self.file_exists = std::path::Path::new(&self.subtitle_file).exists();
-        self.script_installed = self.check_script_installed();
-        if let Ok(content) = std::fs::read_to_string(&self.subtitle_file) {
-            if let Ok(subs) = serde_json::from_str::<Vec<SubtitleEntry>>(&content) {
-                let filtered_subs = filter_prefix_subtitles(subs);
-                let mut subtitles = self.subtitles.lock().unwrap();
-                *subtitles = filtered_subs;
-            }
+        if let Some(path) = selected {
+            self.select_stats_session(path);
         }
-    }
-    
-    fn check_script_installed(&self) -> bool {
-        let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-        let script_path = format!("{}/.config/mpv/scripts/subtitle-monitor.lua", home_dir);
-        std::path::Path::new(&script_path).exists()
-    }
-    
-    fn install_lua_script(&self) -> Result<(), std::io::Error> {
-        let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-        let mpv_scripts_dir = format!("{}/.config/mpv/scripts", home_dir);
-        
-        // Create directory if it doesn't exist
-        std::fs::create_dir_all(&mpv_scripts_dir)?;
-        
-        // Write the embedded script
-        let target_path = format!("{}/subtitle-monitor.lua", mpv_scripts_dir);
-        std::fs::write(target_path, LUA_SCRIPT)?;
-        
-        Ok(())
+        self.show_stats = open;
     }
 }
 
@@ -130,14 +4920,164 @@ impl eframe::App for SubtitleViewer {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Check for file changes
         while let Ok(event) = self.rx.try_recv() {
-            if let Ok(_) = event {
+            let Ok(event) = event else {
+                continue;
+            };
+            let dropped = event
+                .paths
+                .iter()
+                .find(|path| {
+                    **path != Path::new(&self.subtitle_file)
+                        && self.watch_folder.as_ref().is_some_and(|folder| folder.contains(path))
+                })
+                .cloned();
+            if event.paths.iter().any(|path| path.ends_with(protocol::events_path(&self.subtitle_file))) {
+                for event in self.script_events.read_new() {
+                    self.handle_script_event(event);
+                }
+            } else if let Some(path) = dropped {
+                self.open_dropped(&path);
+            } else {
                 self.load_subtitles();
             }
         }
+        if self.capture_checked.elapsed() >= REPLACE_POLL_INTERVAL {
+            self.check_capture_replaced();
+            self.merge_editor_saves();
+        }
+        
+        let remote_errors: Vec<String> = self.remote.iter().flat_map(|remote| remote.errors.try_iter()).collect();
+        for e in remote_errors {
+            self.warn(format!("Could not fetch the remote capture file: {}", e));
+        }
+        self.poll_sync();
+        self.poll_note_sends();
+        self.poll_anki_export(ctx);
+        self.poll_unlock(ctx);
+        
+        if let Some(rx) = &self.pronunciation {
+            if let Ok(result) = rx.try_recv() {
+                self.audio_error = result.err();
+                self.pronunciation = None;
+            }
+        }
+        
+        if let Some(decoder) = &self.waveform_decoder {
+            for (key, result) in decoder.results.try_iter() {
+                let state = match result {
+                    Ok(waveform) => WaveformState::Ready(waveform),
+                    Err(e) => WaveformState::Failed(e),
+                };
+                self.waveforms.insert(key, state);
+            }
+            if self.waveforms.values().any(|state| matches!(state, WaveformState::Loading)) {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+        
+        while let Ok(event) = self.ipc.events.try_recv() {
+            let now = chrono::Utc::now().timestamp();
+            if self.settings.follow_mpv {
+                if let Some(shown) = mpv_ipc::follow_visibility(&event) {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(!shown));
+                }
+            }
+            match event {
+                MpvEvent::Connected => {
+                    if self.mpv_reconnecting.is_some() {
+                        self.toasts.info("Reconnected to mpv");
+                    }
+                    self.mpv_connected = true;
+                    self.mpv_reconnecting = None;
+                    self.send_sub_delay();
+                }
+                MpvEvent::Reconnecting { attempt, delay } => self.mpv_reconnecting = Some((attempt, delay)),
+                MpvEvent::Disconnected => {
+                    if self.mpv_connected {
+                        self.toasts.error("Lost connection to mpv");
+                    }
+                    self.mpv_connected = false;
+                    self.sub_tracks.clear();
+                    self.sid = None;
+                    self.secondary_sid = None;
+                    self.audio_tracks.clear();
+                    self.aid = None;
+                    self.speed = 1.0;
+                    self.sub_delay = None;
+                    self.assist_paused = None;
+                    self.mpv_paused = false;
+                    self.stats_db.set_paused(false, now);
+                }
+                MpvEvent::PropertyChange { name, data } if name == "pause" => {
+                    let paused = data.as_bool() == Some(true);
+                    self.mpv_paused = paused;
+                    self.stats_db.set_paused(paused, now);
+                    if !paused {
+                        self.assist_paused = None;
+                    }
+                }
+                MpvEvent::PropertyChange { name, data } => match name.as_str() {
+                    "track-list" => {
+                        self.sub_tracks = mpv_ipc::parse_tracks(&data, "sub");
+                        self.audio_tracks = mpv_ipc::parse_tracks(&data, "audio");
+                    }
+                    "sid" => self.sid = mpv_ipc::parse_track_id(&data),
+                    "secondary-sid" => self.secondary_sid = mpv_ipc::parse_track_id(&data),
+                    "aid" => self.aid = mpv_ipc::parse_track_id(&data),
+                    "speed" => self.speed = data.as_f64().unwrap_or(1.0),
+                    "sub-delay" => self.sub_delay = data.as_f64(),
+                    _ => {}
+                },
+            }
+        }
+        
+        // Lines are only expected from mpv playing a subtitle track into the live capture
+        let expecting = self.settings.stall_warning_secs > 0
+            && self.settings.player == Player::Mpv
+            && self.mpv_connected
+            && !self.mpv_paused
+            && self.sid.is_some()
+            && self.imported.is_none();
+        self.stall_watchdog.expect(expecting, Instant::now());
+        let palette_shortcut =
+            egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::P);
+        if ctx.input_mut(|i| i.consume_shortcut(&palette_shortcut)) && self.palette.take().is_none() {
+            self.palette = Some(Palette {
+                filter: String::new(),
+                line: self.ruler_hovered,
+                word: None,
+            });
+        }
+        if !self.delayed.is_empty() && !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
+            self.delayed.reveal_all();
+        }
+        if let Some(wait) = self.delayed.tick(Instant::now()) {
+            ctx.request_repaint_after(wait);
+        }
         
         // Request repaint for continuous updates
-        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        let hidden = ctx.input(|i| i.viewport().minimized == Some(true));
+        ctx.request_repaint_after(power::repaint_interval(self.settings.low_power, hidden));
+        // Low power mode holds network sinks back while nobody is looking
+        self.sinks_paused = self.settings.low_power && hidden;
+        if !self.sinks_paused && !self.held_changes.is_empty() {
+            self.flush_sinks();
+        }
+        
+        // Keep the stored scale in sync with keyboard zoom (Ctrl +/-)
+        self.settings.ui_scale = ctx.zoom_factor();
+        
+        self.poll_player_running(ctx);
+        let every = self.settings.auto_export.every_minutes;
+        if every > 0 && self.last_auto_export.elapsed() >= Duration::from_secs(u64::from(every) * 60) {
+            self.auto_export();
+        }
+        self.status_bar(ctx);
+        if self.show_tray {
+            self.tray_panel(ctx);
+        }
         
+        let mut subtitle_action = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical(|ui| {
                 // Controls at the top
@@ -153,15 +5093,339 @@ impl eframe::App for SubtitleViewer {
                         ));
                     }
                     ui.separator();
-                    ui.label("Font size:");
-                    if ui.button("−").clicked() && self.font_size > 8.0 {
-                        self.font_size -= 1.0;
+                    ui.label("Text size:");
+                    let range = self.settings.font_size_range();
+                    if ui.button("−").clicked() {
+                        self.settings.step_font_size(-1.0);
+                    }
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.font_size)
+                            .range(range)
+                            .speed(self.settings.font_size_step.max(0.1) / 4.0)
+                            .max_decimals(2),
+                    );
+                    if ui.button("+").clicked() {
+                        self.settings.step_font_size(1.0);
+                    }
+                    ui.separator();
+                    ui.label("UI scale:");
+                    if ui.button("−").clicked() && self.settings.ui_scale > settings::MIN_UI_SCALE + 0.05 {
+                        self.settings.ui_scale -= 0.1;
+                        ctx.set_zoom_factor(self.settings.ui_scale);
+                    }
+                    ui.label(format!("{:.0}%", self.settings.ui_scale * 100.0));
+                    if ui.button("+").clicked() && self.settings.ui_scale < settings::MAX_UI_SCALE - 0.05 {
+                        self.settings.ui_scale += 0.1;
+                        ctx.set_zoom_factor(self.settings.ui_scale);
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(!self.settings.timeline_view, egui::SelectableLabel::new(self.time_ordered, "⇅"))
+                        .on_hover_text("Order this session's lines by media time instead of arrival")
+                        .clicked()
+                    {
+                        self.time_ordered = !self.time_ordered;
+                        self.load_subtitles();
+                    }
+                    if ui
+                        .selectable_label(self.settings.timeline_view, "🕒")
+                        .on_hover_text("Timeline: one line per cue, by media time")
+                        .clicked()
+                    {
+                        self.settings.timeline_view = !self.settings.timeline_view;
+                        self.load_subtitles();
+                    }
+                    let due = self.review.due_count(chrono::Utc::now().timestamp());
+                    let review_label = if due > 0 { format!("🔁 {}", due) } else { "🔁".to_string() };
+                    if ui
+                        .selectable_label(self.settings.show_waveforms, "〰")
+                        .on_hover_text("Show the audio waveform under each line")
+                        .clicked()
+                    {
+                        self.settings.show_waveforms = !self.settings.show_waveforms;
+                    }
+                    if ui
+                        .selectable_label(self.settings.reading_ruler, "📏")
+                        .on_hover_text("Reading ruler: dim every line but the one under the mouse, or the latest")
+                        .clicked()
+                    {
+                        self.settings.reading_ruler = !self.settings.reading_ruler;
+                    }
+                    if self.settings.flag_reading_problems {
+                        let (max_cps, max_line_chars) = (self.settings.max_cps, self.settings.max_line_chars);
+                        let flagged = self
+                            .subtitles
+                            .lock()
+                            .unwrap()
+                            .iter()
+                            .filter(|sub| !qc::reading_problems(sub, max_cps, max_line_chars).is_empty())
+                            .count();
+                        if ui
+                            .selectable_label(self.only_problems, format!("⚠ {}", flagged))
+                            .on_hover_text("Show only the lines too fast or too long to read")
+                            .clicked()
+                        {
+                            self.only_problems = !self.only_problems;
+                        }
+                    }
+                    if self.checks_timing() {
+                        let problems = qc::timing_problems(&self.subtitles.lock().unwrap()).len();
+                        if (problems > 0 || self.show_timing_problems)
+                            && ui
+                                .selectable_label(self.show_timing_problems, format!("⏱ {}", problems))
+                                .on_hover_text("Cues that overlap or end before they start")
+                                .clicked()
+                        {
+                            self.show_timing_problems = !self.show_timing_problems;
+                        }
+                    }
+                    let cloze = self.cloze;
+                    ui.menu_button(if cloze.is_some() { "🙈 On" } else { "🙈" }, |ui| {
+                        let translated = translation::has_translations(&self.subtitles.lock().unwrap());
+                        if ui.radio(cloze.is_none(), "Show everything").clicked() {
+                            self.cloze = None;
+                        }
+                        for side in cloze::ClozeSide::ALL {
+                            let radio = ui
+                                .add_enabled(translated, egui::RadioButton::new(cloze == Some(side), side.label()))
+                                .on_disabled_hover_text("Needs lines with translations, such as a secondary subtitle track in mpv");
+                            if radio.clicked() {
+                                self.cloze = Some(side);
+                            }
+                        }
+                        ui.separator();
+                        if ui
+                            .add_enabled(!self.cloze_revealed.is_empty(), egui::Button::new("Hide everything again"))
+                            .clicked()
+                        {
+                            self.cloze_revealed.clear();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Self-test: mask the original or the translation of each line until it's clicked");
+                    if self.cloze != cloze {
+                        self.cloze_revealed.clear();
+                        self.load_subtitles();
+                    }
+                    if ui.selectable_label(self.show_review, review_label).on_hover_text("Review queue").clicked() {
+                        self.show_review = !self.show_review;
+                    }
+                    if ui.selectable_label(self.show_overlay, "🗗").on_hover_text("Mini overlay").clicked() {
+                        self.show_overlay = !self.show_overlay;
+                    }
+                    if ui
+                        .selectable_label(self.show_presenter, "📽")
+                        .on_hover_text("Presenter: the latest line large with no controls, for projecting")
+                        .clicked()
+                    {
+                        self.show_presenter = !self.show_presenter;
+                    }
+                    if ui.selectable_label(self.show_stats, "📊").on_hover_text("Statistics").clicked() {
+                        self.show_stats = !self.show_stats;
+                        self.stats_session = None;
+                    }
+                    let pinned = self.tray.items().len();
+                    let tray_label = if pinned > 0 { format!("📌 {}", pinned) } else { "📌".to_string() };
+                    if ui
+                        .selectable_label(self.show_tray, tray_label)
+                        .on_hover_text("Comparison tray: lines pinned from their right-click menu")
+                        .clicked()
+                    {
+                        self.show_tray = !self.show_tray;
                     }
-                    ui.label(format!("{:.0}", self.font_size));
-                    if ui.button("+").clicked() && self.font_size < 32.0 {
-                        self.font_size += 1.0;
+                    let trashed = self.trash.items().len();
+                    if (trashed > 0 || self.show_trash)
+                        && ui
+                            .selectable_label(self.show_trash, format!("🗑 {}", trashed))
+                            .on_hover_text("Deleted lines, until their session ends")
+                            .clicked()
+                    {
+                        self.show_trash = !self.show_trash;
+                    }
+                    ui.menu_button("💾", |ui| {
+                        let translated = translation::has_translations(&self.subtitles.lock().unwrap());
+                        let timing_problems = if self.checks_timing() {
+                            qc::timing_problems(&self.subtitles.lock().unwrap()).len()
+                        } else {
+                            0
+                        };
+                        if timing_problems > 0 {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 165, 0),
+                                format!("⚠️ {} timing problems: cues overlapping or ending early", timing_problems),
+                            );
+                            ui.horizontal(|ui| {
+                                ui.label("Fix first:");
+                                for fix in qc::TimingFix::ALL {
+                                    if ui.button(fix.label()).on_hover_text(fix.explain()).clicked() {
+                                        self.fix_timing(fix);
+                                    }
+                                }
+                                if ui.button("List").clicked() {
+                                    self.show_timing_problems = true;
+                                    ui.close_menu();
+                                }
+                            });
+                            ui.separator();
+                        }
+                        for format in export::Format::ALL {
+                            let button = ui
+                                .add_enabled(
+                                    translated || !format.is_bilingual(),
+                                    egui::Button::new(format!("Export {}", format.label())),
+                                )
+                                .on_disabled_hover_text("Needs lines with translations, such as a secondary subtitle track in mpv");
+                            if button.clicked() {
+                                self.export(format);
+                                ui.close_menu();
+                            }
+                        }
+                        if ui
+                            .add_enabled(!self.settings.vault_dir.trim().is_empty(), egui::Button::new("Save to vault"))
+                            .on_hover_text(format!("As {}s", self.settings.vault_format.label()))
+                            .on_disabled_hover_text("Set the Obsidian or Logseq vault folder in settings")
+                            .clicked()
+                        {
+                            self.save_to_vault();
+                            ui.close_menu();
+                        }
+                        let mut send = None;
+                        for service in &self.settings.note_services {
+                            if ui.button(format!("Send transcript to {}", service.name)).clicked() {
+                                send = Some(service.clone());
+                                ui.close_menu();
+                            }
+                        }
+                        if let Some(service) = send {
+                            self.send_transcript_to_notes(service);
+                        }
+                        let starred = self.starred.items.len();
+                        if ui
+                            .add_enabled(
+                                starred > 0 && self.anki_export.is_none(),
+                                egui::Button::new(format!("Export {} starred to Anki", starred)),
+                            )
+                            .clicked()
+                        {
+                            self.export_starred_to_anki();
+                            ui.close_menu();
+                        }
+                        if ui
+                            .button("Edit in external editor")
+                            .on_hover_text("Fix lines in bulk in your own editor; each save is merged into the view")
+                            .clicked()
+                        {
+                            self.edit_externally();
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        ui.label("Open subtitle file:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.import_path).hint_text("/path/to/file.ass"));
+                            if ui.button("Open").clicked() {
+                                self.import();
+                                ui.close_menu();
+                            }
+                        });
+                        ui.label("Merge SRT beside the live lines:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.merge_path).hint_text("/path/to/official.srt"));
+                            if ui.button("Merge").clicked() {
+                                self.merge_srt();
+                                ui.close_menu();
+                            }
+                        });
+                        if let Some(merged) = &self.merged {
+                            let label = format!("Stop merging {}", Path::new(&merged.path).file_name().unwrap_or_default().to_string_lossy());
+                            if ui.button(label).clicked() {
+                                self.merged = None;
+                                ui.close_menu();
+                            }
+                        }
+                        ui.menu_button(format!("Encoding: {}", self.settings.import_encoding.label()), |ui| {
+                            for encoding in TextEncoding::ALL {
+                                ui.radio_value(&mut self.settings.import_encoding, encoding, encoding.label());
+                            }
+                        });
+                        if ui
+                            .button("Back up all data")
+                            .on_hover_text("Settings, stats, known words, starred lines and the raw stream in one file; \
+                                restore it with scriptview --restore FILE")
+                            .clicked()
+                        {
+                            self.back_up();
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        ui.menu_button("Reprocess raw stream", |ui| {
+                            let days = self.storage.days();
+                            if days.is_empty() {
+                                ui.label("Nothing recorded yet");
+                            }
+                            for day in days {
+                                if ui.button(&day).clicked() {
+                                    self.reprocess(day);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    })
+                    .response
+                    .on_hover_text("Import and export");
+                    ui.menu_button("🗔", |ui| {
+                        let mut chosen = None;
+                        let mut removed = None;
+                        for (index, layout) in self.settings.layouts.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.button(&layout.name).clicked() {
+                                    chosen = Some(layout.clone());
+                                }
+                                if ui.small_button("✕").on_hover_text("Delete this layout").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                        }
+                        if self.settings.layouts.is_empty() {
+                            ui.label("No saved layouts yet");
+                        }
+                        ui.separator();
+                        ui.label("Save the current layout as:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.layout_name).hint_text("review"));
+                            if ui.add_enabled(!self.layout_name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+                                let name = std::mem::take(&mut self.layout_name);
+                                let layout = self.current_layout(ctx, name);
+                                self.toasts.success(format!("Saved layout \"{}\"", layout.name.trim()));
+                                layout::store(&mut self.settings.layouts, layout);
+                                ui.close_menu();
+                            }
+                        });
+                        if let Some(index) = removed {
+                            self.settings.layouts.remove(index);
+                        }
+                        if let Some(layout) = chosen {
+                            self.apply_layout(ctx, layout);
+                            ui.close_menu();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Layouts");
+                    if ui.selectable_label(self.show_settings, "⚙").clicked() {
+                        self.show_settings = !self.show_settings;
                     }
                 });
+                if self.settings.color_by_frequency && self.frequency.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new("Frequency:").small());
+                        for band in Band::ALL {
+                            let color = band.color().unwrap_or_else(|| ui.visuals().text_color());
+                            ui.label(egui::RichText::new(band.label()).small().color(color));
+                        }
+                    });
+                }
+                if self.mpv_connected {
+                    self.ipc_controls(ui);
+                }
                 ui.separator();
                 
                 // Show script installation status
@@ -169,47 +5433,204 @@ impl eframe::App for SubtitleViewer {
                     ui.horizontal(|ui| {
                         ui.colored_label(
                             egui::Color32::from_rgb(255, 165, 0),
-                            "⚠️ Script not installed:"
+                            format!("⚠️ {} script not installed:", self.settings.player.label())
                         );
                         if ui.button("Install Script").clicked() {
-                            match self.install_lua_script() {
-                                Ok(_) => {
-                                    self.script_installed = true;
-                                    self.script_install_time = Some(Instant::now());
-                                }
-                                Err(_) => {}
-                            }
+                            self.install_script_clicked();
                         }
                     });
-                } else if let Some(install_time) = self.script_install_time {
-                    if install_time.elapsed() < Duration::from_secs(5) {
+                }
+                
+                if let Some(imported) = &self.imported {
+                    let mut close = false;
+                    ui.horizontal(|ui| {
+                        ui.label(format!("📄 {}", imported.path));
+                        close = ui.button("Back to live").clicked();
+                    });
+                    if close {
+                        self.imported = None;
+                        self.load_subtitles();
+                    }
+                }
+                
+                if let Some(error) = &self.audio_error {
+                    let mut dismiss = false;
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("⚠️ {}", error));
+                        dismiss = ui.small_button("✕").clicked();
+                    });
+                    if dismiss {
+                        self.audio_error = None;
+                    }
+                }
+                
+                if let Some((since, error)) = self.corrupt.as_ref().filter(|(since, _)| since.elapsed() >= CORRUPT_GRACE) {
+                    let mut quarantine = false;
+                    ui.horizontal_wrapped(|ui| {
                         ui.colored_label(
-                            egui::Color32::from_rgb(0, 200, 0),
-                            "✓ Script installed"
+                            egui::Color32::from_rgb(255, 165, 0),
+                            format!("⚠️ The capture file hasn't been readable for {} s: {}", since.elapsed().as_secs(), error),
                         );
+                        quarantine = ui
+                            .button("Set it aside and start fresh")
+                            .on_hover_text("Renames it with a .corrupt suffix, to keep for a bug report")
+                            .clicked();
+                    });
+                    if quarantine {
+                        self.quarantine_capture_file();
+                    }
+                }
+                
+                if let Some(unknown) = &self.assist_paused {
+                    let mut resume = false;
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(format!("⏸ Paused for {} unknown words: {}", unknown.len(), unknown.join(", ")));
+                        resume = ui.button("Resume (Space)").clicked();
+                    });
+                    if resume || (!ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::Space))) {
+                        self.resume_playback();
+                    }
+                }
+                
+                if let Some(diagnosis) = &self.diagnosis {
+                    let mut dismiss = false;
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("⚠️ {}", diagnosis.problem));
+                            dismiss = ui.small_button("✕").clicked();
+                        });
+                        for hint in &diagnosis.hints {
+                            ui.label(format!("• {}", hint));
+                        }
+                        if diagnosis.hints.is_empty() {
+                            ui.label("No likely cause was found; the error above is what the system reported.");
+                        }
+                    });
+                    if dismiss {
+                        self.diagnosis = None;
+                    }
+                }
+                
+                if let Some((language, index)) = self.profile_suggestion {
+                    let (mut switch, mut dismiss) = (false, false);
+                    let profile = self.settings.profiles.get(index).map(|profile| profile.name.clone());
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(format!(
+                                "🌐 These lines look like {}, but the {} profile is active.",
+                                language.name,
+                                self.settings.profile().name
+                            ));
+                            if let Some(profile) = &profile {
+                                switch = ui.button(format!("Switch to {}", profile)).clicked();
+                            }
+                            dismiss = ui.small_button("✕").clicked();
+                        });
+                    });
+                    if switch {
+                        self.settings.active_profile = index;
+                        self.load_frequency_list();
+                    }
+                    if switch || dismiss || profile.is_none() {
+                        self.profile_suggestion = None;
+                    }
+                }
+                
+                self.stall_warning(ui);
+                
+                if !self.mpv_conflicts.is_empty() {
+                    let (mut recheck, mut dismiss) = (false, false);
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 165, 0),
+                                "⚠️ Your mpv setup may keep subtitles from being captured:",
+                            );
+                            recheck = ui.small_button("Check again").clicked();
+                            dismiss = ui.small_button("✕").clicked();
+                        });
+                        for conflict in &self.mpv_conflicts {
+                            let place = match conflict.line {
+                                Some(line) => format!("{}:{}", conflict.file.display(), line),
+                                None => conflict.file.display().to_string(),
+                            };
+                            ui.label(format!("• {} — {}", place, conflict.message));
+                        }
+                    });
+                    if recheck {
+                        self.check_mpv_config();
+                    }
+                    if dismiss {
+                        self.mpv_conflicts.clear();
                     }
                 }
                 
                 // Show file status warning
                 if !self.file_exists {
-                    ui.colored_label(
-                        egui::Color32::from_rgb(255, 165, 0),
-                        "⚠️ No subtitle data (maybe mpv isn't running?)"
-                    );
+                    let player = self.settings.player.label();
+                    let warning = match self.player_running {
+                        Some(false) => format!("⚠️ No subtitle data: {} isn't running", player),
+                        Some(true) => format!("⚠️ No subtitle data: {} is running, but nothing wrote {}", player, self.subtitle_file),
+                        None => format!("⚠️ No subtitle data yet in {}", self.subtitle_file),
+                    };
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), warning);
                     ui.separator();
                 }
                 
                 // Subtitle area with automatic scrolling
                 let subtitles = self.subtitles.lock().unwrap();
+                let font = self.subtitle_font(ctx);
+                let grammar = self.grammar.as_ref().filter(|_| self.settings.show_grammar_hints);
+                let frequency = self.frequency.as_ref().filter(|_| self.settings.color_by_frequency);
+                let direction = self.settings.profile().text_direction;
+                let known_words = &self.known;
+                let profile_name = &self.settings.profile().name;
+                let watch_words = keywords::parse(&self.settings.watch_words);
+                let show_waveforms = self.settings.show_waveforms;
+                // Without the mouse over a line, the ruler rests on the latest
+                let ruler_line = self
+                    .ruler_hovered
+                    .or_else(|| subtitles.last().map(|sub| sub.id))
+                    .filter(|_| self.settings.reading_ruler);
+                let mut hovered_line = None;
+                let render_emphasis = self.settings.render_emphasis;
+                let merged = self.merged.as_ref();
+                let timestamp_click = self.settings.timestamp_click;
+                let line_width = self.settings.line_width;
+                let char_width = ui.fonts(|fonts| fonts.glyph_width(&egui::FontId::proportional(font.size), '0'))
+                    + font.letter_spacing;
+                let steps = if self.inspected.is_empty() { Vec::new() } else { self.processing_steps() };
+                let starred = &self.starred;
+                let low_confidence = self.settings.low_confidence;
+                let source_styles = &self.settings.sources;
+                let search_sites = self.settings.search_sites.as_slice();
+                let flag_problems = self.settings.flag_reading_problems;
+                let only_problems = flag_problems && self.only_problems;
+                let (max_cps, max_line_chars) = (self.settings.max_cps, self.settings.max_line_chars);
+                // Tags only mean something once sources are mixed
+                let mixed_sources = subtitles.iter().map(|sub| sub.source).collect::<HashSet<_>>().len() > 1;
+                let verified = &self.verified;
+                let waveforms = &mut self.waveforms;
+                let waveform_decoder =
+                    show_waveforms.then(|| &*self.waveform_decoder.get_or_insert_with(waveform::Decoder::spawn));
+                // Only the latest and expanded lines get one, so a long session isn't decoded whole
+                let latest_line = subtitles.last().map(|sub| sub.id);
+                let inspected = &self.inspected;
+                let cloze = self.cloze;
+                let cloze_revealed = &self.cloze_revealed;
+                let delayed = &self.delayed;
                 
                 if subtitles.is_empty() {
                     ui.centered_and_justified(|ui| {
                         if self.file_exists {
                             ui.label("No subtitles yet...");
                         } else if self.script_installed {
-                            ui.label("Start mpv to see subtitles here.");
+                            ui.label(format!("Start {} to see subtitles here.", self.settings.player.label()));
                         } else {
-                            ui.label("Install the script and start mpv to see subtitles.");
+                            ui.label(format!(
+                                "Install the script and start {} to see subtitles.",
+                                self.settings.player.label()
+                            ));
                         }
                     });
                 } else {
@@ -217,48 +5638,474 @@ impl eframe::App for SubtitleViewer {
                         .stick_to_bottom(true)
                         .show(ui, |ui| {
                             ui.set_width(ui.available_width());
-                            for sub in subtitles.iter() {
-                                ui.allocate_ui_with_layout(
-                                    egui::vec2(ui.available_width(), 0.0),
-                                    egui::Layout::top_down(egui::Align::LEFT),
-                                    |ui| {
-                                        ui.group(|ui| {
-                                            ui.set_width(ui.available_width());
-                                            ui.horizontal_wrapped(|ui| {
-                                                ui.label(
-                                                    egui::RichText::new(format!("[{}]", format_timestamp(sub.start_time)))
-                                                        .small()
-                                                        .color(egui::Color32::from_gray(128)),
-                                                );
-                                                ui.label(egui::RichText::new(&sub.text.replace('\n', " ")).size(self.font_size));
-                                            });
-                                        });
+                            let row_width = line_width.points(ui.available_width(), char_width);
+                            let align = if line_width.centered { egui::Align::Center } else { egui::Align::LEFT };
+                            ui.with_layout(egui::Layout::top_down(align), |ui| {
+                                let groups = session::media_groups(&subtitles);
+                                for group in &groups {
+                                    let collapsed = groups.len() > 1 && self.collapsed_media.contains(&group.path);
+                                    // Headers only once the capture spans several files
+                                    if groups.len() > 1 {
+                                        let header = format!(
+                                            "{} {} ({} lines)",
+                                            if collapsed { "▶" } else { "▼" },
+                                            group.title,
+                                            group.range.len()
+                                        );
+                                        let response = ui.selectable_label(false, egui::RichText::new(header).strong());
+                                        let response = match &group.path {
+                                            Some(path) => response.on_hover_text(path),
+                                            None => response,
+                                        };
+                                        if response.clicked() && !self.collapsed_media.remove(&group.path) {
+                                            self.collapsed_media.insert(group.path.clone());
+                                        }
                                     }
-                                );
-                                ui.add_space(4.0);
-                            }
+                                    if collapsed {
+                                        continue;
+                                    }
+                                    for row in group.range.clone() {
+                                        let sub = &subtitles[row];
+                                        let problems = if flag_problems {
+                                            qc::reading_problems(sub, max_cps, max_line_chars)
+                                        } else {
+                                            Vec::new()
+                                        };
+                                        if only_problems && problems.is_empty() {
+                                            continue;
+                                        }
+                                        let menu = RowMenu { id: sub.id, sites: search_sites };
+                                        ui.allocate_ui_with_layout(
+                                            egui::vec2(row_width, 0.0),
+                                            egui::Layout::top_down(egui::Align::LEFT),
+                                            |ui| {
+                                                let source = sub.text.replace('\n', " ");
+                                                let marked = if render_emphasis {
+                                                    markup::parse(&source)
+                                                } else {
+                                                    markup::strip(&source)
+                                                };
+                                                let text = &marked.text;
+                                                let rtl = direction.is_rtl(text);
+                                                let hidden = cloze.filter(|_| !cloze_revealed.contains(&sub.id));
+                                                // Held back for listening first: the translation would give it away too
+                                                let masked = hidden == Some(cloze::ClozeSide::Original) || delayed.is_hidden(sub.id);
+                                                let translation_masked = hidden == Some(cloze::ClozeSide::Translation) || delayed.is_hidden(sub.id);
+                                                let mut frame = egui::Frame::group(ui.style());
+                                                if keywords::find(text, &watch_words).is_some() {
+                                                    frame = frame
+                                                        .stroke(egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 200, 0)))
+                                                        .fill(egui::Color32::from_rgba_unmultiplied(255, 200, 0, 24));
+                                                }
+                                                let unchecked = sub
+                                                    .confidence
+                                                    .filter(|&confidence| confidence < low_confidence && !verified.contains(&sub.id));
+                                                if unchecked.is_some() {
+                                                    frame = frame
+                                                        .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(200, 120, 255)))
+                                                        .fill(egui::Color32::from_rgba_unmultiplied(200, 120, 255, 16));
+                                                }
+                                                let row_response = frame.show(ui, |ui| {
+                                                    ui.set_width(ui.available_width());
+                                                    let mut line = |ui: &mut egui::Ui| {
+                                                        ui.horizontal_wrapped(|ui| {
+                                                            let is_starred = starred.contains(&sub.text, sub.start_time, sub.path.as_deref());
+                                                            let star = if is_starred {
+                                                                egui::RichText::new("★").color(egui::Color32::from_rgb(255, 200, 0))
+                                                            } else {
+                                                                egui::RichText::new("☆").color(egui::Color32::from_gray(96))
+                                                            };
+                                                            if ui
+                                                                .add(egui::Label::new(star.small()).sense(egui::Sense::click()))
+                                                                .on_hover_text("Star for Anki export")
+                                                                .clicked()
+                                                            {
+                                                                subtitle_action = Some(SubtitleAction::Star(sub.id));
+                                                            }
+                                                            let mut timestamp = ui.add(
+                                                                egui::Label::new(
+                                                                    egui::RichText::new(format!("[{}]", format_timestamp(sub.start_time)))
+                                                                        .small()
+                                                                        .color(egui::Color32::from_gray(128)),
+                                                                )
+                                                                .sense(egui::Sense::click()),
+                                                            );
+                                                            if timestamp_click != TimestampClick::Nothing {
+                                                                timestamp = timestamp.on_hover_text(timestamp_click.label());
+                                                            }
+                                                            if timestamp.clicked() {
+                                                                subtitle_action = Some(SubtitleAction::Timestamp(sub.id));
+                                                            }
+                                                            timestamp.context_menu(|ui| menu.show(ui, None, &mut subtitle_action));
+                                                            if let Some(source) = sub.source.filter(|_| mixed_sources) {
+                                                                let style = source_styles.get(&source).copied().unwrap_or_default();
+                                                                let [r, g, b] = style.color.unwrap_or(source.default_color());
+                                                                ui.label(
+                                                                    egui::RichText::new(source.short_label())
+                                                                        .small()
+                                                                        .color(egui::Color32::from_rgb(r, g, b)),
+                                                                )
+                                                                .on_hover_text(source.label());
+                                                            }
+                                                            if sub.path.is_some()
+                                                                && ui
+                                                                    .add(
+                                                                        egui::Label::new(egui::RichText::new("▶").small().color(egui::Color32::from_gray(128)))
+                                                                            .sense(egui::Sense::click()),
+                                                                    )
+                                                                    .on_hover_text("Play this line's audio")
+                                                                    .clicked()
+                                                            {
+                                                                subtitle_action = Some(SubtitleAction::Replay(sub.id));
+                                                            }
+                                                            if let Some(confidence) = unchecked {
+                                                                let badge = egui::RichText::new(format!("? {:.0}%", confidence * 100.0))
+                                                                    .small()
+                                                                    .color(egui::Color32::from_rgb(200, 120, 255));
+                                                                if ui
+                                                                    .add(egui::Label::new(badge).sense(egui::Sense::click()))
+                                                                    .on_hover_text("Recognised with low confidence; click once you've checked it")
+                                                                    .clicked()
+                                                                {
+                                                                    subtitle_action = Some(SubtitleAction::Verify(sub.id));
+                                                                }
+                                                            }
+                                                            for problem in &problems {
+                                                                ui.label(
+                                                                    egui::RichText::new(problem.badge())
+                                                                        .small()
+                                                                        .color(egui::Color32::from_rgb(255, 165, 0)),
+                                                                )
+                                                                .on_hover_text(problem.describe());
+                                                            }
+                                                            if masked {
+                                                                if ui
+                                                                    .add(
+                                                                        egui::Label::new(font.text(cloze::mask(text)).color(egui::Color32::from_gray(128)))
+                                                                            .sense(egui::Sense::click()),
+                                                                    )
+                                                                    .on_hover_text("Click to reveal")
+                                                                    .clicked()
+                                                                {
+                                                                    subtitle_action = Some(SubtitleAction::Reveal(sub.id));
+                                                                }
+                                                                return;
+                                                            }
+                                                            if rtl {
+                                                                return;
+                                                            }
+                                                            let known = |word: &str| known_words.is_known(profile_name, word);
+                                                            if let Some(action) = subtitle_label(ui, menu, &marked, font, grammar, frequency, known) {
+                                                                subtitle_action = Some(action);
+                                                            }
+                                                        });
+                                                        if rtl && !masked {
+                                                            if let Some(action) = rtl_subtitle_label(ui, menu, text, font) {
+                                                                subtitle_action = Some(action);
+                                                            }
+                                                        }
+                                                    };
+                                                    match merged.filter(|merged| merged.media == sub.path) {
+                                                        Some(merged) => {
+                                                            ui.columns(2, |columns| {
+                                                                line(&mut columns[0]);
+                                                                let cue = merged
+                                                                    .matches
+                                                                    .get(&sub.id)
+                                                                    .map_or_else(|| "—".to_string(), |&index| markup::strip(&merged.cues[index].text).text);
+                                                                columns[1].label(font.text(cue).color(egui::Color32::from_gray(160)));
+                                                            });
+                                                        }
+                                                        None => line(ui),
+                                                    }
+                                                    if let Some(translation) = sub.translation.as_deref().filter(|_| cloze.is_some()) {
+                                                        if translation_masked {
+                                                            if ui
+                                                                .add(
+                                                                    egui::Label::new(
+                                                                        egui::RichText::new(cloze::mask(translation))
+                                                                            .color(egui::Color32::from_gray(128)),
+                                                                    )
+                                                                    .sense(egui::Sense::click()),
+                                                                )
+                                                                .on_hover_text("Click to reveal")
+                                                                .clicked()
+                                                            {
+                                                                subtitle_action = Some(SubtitleAction::Reveal(sub.id));
+                                                            }
+                                                        } else {
+                                                            ui.label(egui::RichText::new(translation).color(egui::Color32::from_gray(160)));
+                                                        }
+                                                    }
+                                                    if let (Some(path), Some(decoder)) = (
+                                                        sub.path.as_ref().filter(|_| {
+                                                            latest_line == Some(sub.id) || inspected.contains(&sub.id)
+                                                        }),
+                                                        waveform_decoder,
+                                                    ) {
+                                                        let key = waveform::key(path, sub.start_time);
+                                                        if !waveforms.contains_key(&key) {
+                                                            let strip = egui::Rect::from_min_size(
+                                                                ui.cursor().min,
+                                                                egui::vec2(ui.available_width(), 28.0),
+                                                            );
+                                                            // A full queue is asked again on a later frame
+                                                            if ui.is_rect_visible(strip)
+                                                                && decoder.request(path, sub.start_time, sub.end_time)
+                                                            {
+                                                                waveforms.insert(key.clone(), WaveformState::Loading);
+                                                            }
+                                                        }
+                                                        match waveforms.get(&key) {
+                                                            None | Some(WaveformState::Loading) => {
+                                                                ui.spinner();
+                                                            }
+                                                            Some(WaveformState::Ready(waveform)) => {
+                                                                waveform_strip(ui, waveform, sub.start_time, sub.end_time);
+                                                            }
+                                                            Some(WaveformState::Failed(error)) => {
+                                                                ui.label(
+                                                                    egui::RichText::new(format!("No waveform: {}", error))
+                                                                        .small()
+                                                                        .color(egui::Color32::from_gray(128)),
+                                                                );
+                                                            }
+                                                        }
+                                                    }
+                                                    if inspected.contains(&sub.id) {
+                                                        ui.separator();
+                                                        egui::Grid::new(("details", sub.id)).num_columns(2).show(ui, |ui| {
+                                                            for (field, value) in entry_details(sub) {
+                                                                ui.label(egui::RichText::new(field).small().strong());
+                                                                ui.label(egui::RichText::new(value).small());
+                                                                ui.end_row();
+                                                            }
+                                                            ui.label(egui::RichText::new("Steps").small().strong());
+                                                            ui.label(egui::RichText::new(steps.join("\n")).small());
+                                                            ui.end_row();
+                                                        });
+                                                        if ui.small_button("Hide details").clicked() {
+                                                            subtitle_action = Some(SubtitleAction::Inspect(sub.id));
+                                                        }
+                                                    }
+                                                })
+                                                .response;
+                                                if row_response.contains_pointer() {
+                                                    hovered_line = Some(sub.id);
+                                                }
+                                                if ruler_line.is_some_and(|line| line != sub.id) {
+                                                    let dim = ui.visuals().panel_fill.gamma_multiply(0.7);
+                                                    ui.painter().rect_filled(row_response.rect, 0.0, dim);
+                                                }
+                                                // Words are separate labels; give screen readers the whole line
+                                                row_response.widget_info(|| {
+                                                    let text = if masked { "hidden".to_string() } else { text.clone() };
+                                                    egui::WidgetInfo::labeled(
+                                                        egui::WidgetType::Label,
+                                                        true,
+                                                        format!("{}: {}", format_timestamp(sub.start_time), text),
+                                                    )
+                                                });
+                                            }
+                                        );
+                                        ui.add_space(4.0);
+                                    }
+                                }
+                            });
                         });
                 }
+                self.ruler_hovered = hovered_line;
+                
+                if self.settings.announce_subtitles {
+                    // Read out once listening-first mode shows it
+                    let latest = subtitles.last().filter(|sub| !delayed.is_hidden(sub.id));
+                    announce(ui, latest.map_or("", |sub| sub.text.as_str()));
+                }
                 
                 ui.separator();
             });
         });
+        
+        if let Some(action) = subtitle_action {
+            self.handle_action(ctx, action);
+        }
+        if self.settings.hover_translation {
+            self.hover_translation_tooltip(ctx);
+        }
+        
+        self.settings_window(ctx);
+        self.examples_window(ctx);
+        if self.show_review {
+            self.review_window(ctx);
+        }
+        if self.show_overlay {
+            self.overlay_window(ctx);
+        }
+        if self.show_presenter {
+            self.presenter_window(ctx);
+        }
+        if self.show_filtered {
+            self.filtered_window(ctx);
+        }
+        if self.show_capture_tail {
+            self.capture_tail_window(ctx);
+        }
+        if self.show_trash {
+            self.trash_window(ctx);
+        }
+        if self.show_timing_problems {
+            self.timing_problems_window(ctx);
+        }
+        self.anki_export_window(ctx);
+        self.session_summary_window(ctx);
+        self.unlock_window(ctx);
+        self.resync_window(ctx);
+        self.command_palette(ctx);
+        if self.show_stats {
+            self.stats_window(ctx);
+        }
+        
+        self.toasts.show(ctx);
+        
+        let settings = self.settings_to_save();
+        if *settings != self.saved_settings {
+            let saved = settings.into_owned();
+            if let Err(e) = saved.save() {
+                self.warn(format!("Could not save settings: {}", e));
+            }
+            self.saved_settings = saved;
+        }
+    }
+    
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        let shutdown = std::mem::take(&mut self.shutdown);
+        shutdown.run(self);
+    }
+}
+
+/// The history key for commands run from the terminal, asking for the
+/// passphrase if the history is encrypted.
+fn command_key(settings: &Settings) -> Result<Option<crypt::Key>, String> {
+    if !settings.encrypt_history {
+        return Ok(None);
+    }
+    crypt::unlock(&crypt::read_passphrase()?).map(Some)
+}
+
+/// Copy the raw stream into `kind` of storage and use that from now on.
+fn migrate_storage(kind: storage::StorageKind) -> Result<(), String> {
+    let mut settings = Settings::load();
+    if settings.storage == kind {
+        return Err(format!("Already using {} for the raw stream", kind.label()));
+    }
+    let key = command_key(&settings)?;
+    let from = storage::open(settings.storage, key.clone())?;
+    let mut to = storage::open(kind, key)?;
+    println!("Copying the raw stream from {} to {}", settings.storage.label(), kind.label());
+    let copied = storage::migrate(from.as_ref(), to.as_mut(), |day, lines| println!("  {}: {} lines", day, lines))?;
+    settings.storage = kind;
+    settings.save().map_err(|e| format!("Could not save settings: {}", e))?;
+    println!("Copied {} days; new lines now go to {}", copied, kind.label());
+    Ok(())
+}
+
+fn backup_command(path: &str) -> Result<(), String> {
+    let settings = Settings::load();
+    let key = command_key(&settings)?;
+    let roots = backup::roots(&capture_file(&settings, &ScriptOpts::load()));
+    let files = backup::create(path, &roots, key.as_ref())?;
+    println!("Backed up {} files to {}", files, path);
+    Ok(())
+}
+
+fn restore_command(path: &str) -> Result<(), String> {
+    let passphrase = if backup::is_encrypted(path) { Some(crypt::read_passphrase()?) } else { None };
+    let roots = backup::roots(&capture_file(&Settings::load(), &ScriptOpts::load()));
+    let files = backup::restore(path, &roots, passphrase.as_deref())?;
+    for file in &files {
+        println!("  {}", file);
+    }
+    println!("Restored {} files from {}", files.len(), path);
+    Ok(())
+}
+
+/// Print an encrypted session summary or capture archive.
+fn decrypt_command(path: &str) -> Result<(), String> {
+    use std::io::Write;
+    let data = std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let plain = crypt::open_file(&data, &crypt::read_passphrase()?)?;
+    std::io::stdout().write_all(&plain).map_err(|e| e.to_string())
+}
+
+/// Carry out the command given on the command line in place of opening the
+/// window, if there is one.
+fn run_command(launch: &LaunchOptions) -> Option<Result<(), String>> {
+    if let Some(path) = &launch.backup {
+        return Some(backup_command(path));
     }
+    if let Some(path) = &launch.restore {
+        return Some(restore_command(path));
+    }
+    if let Some(path) = &launch.decrypt {
+        return Some(decrypt_command(path));
+    }
+    launch.migrate_storage.map(migrate_storage)
 }
 
 fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([500.0, 600.0])
-            .with_always_on_top(),
-        ..Default::default()
+    let launch = match LaunchOptions::parse(std::env::args().skip(1)) {
+        Ok(launch) => launch.with_settings(&Settings::load()),
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, launch::USAGE);
+            std::process::exit(2);
+        }
     };
+    if launch.help {
+        println!("{}", launch::USAGE);
+        return Ok(());
+    }
+    if let Some(result) = run_command(&launch) {
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([500.0, 600.0])
+        .with_always_on_top();
+    if launch.no_focus || launch.minimized {
+        viewport = viewport.with_active(false);
+    }
     
-    eframe::run_native(
-        "ScriptView",
-        options,
-        Box::new(|_cc| Ok(Box::new(SubtitleViewer::new()))),
-    )
+    // Remote desktops often can't give wgpu or even OpenGL a GPU, so each
+    // renderer that fails to start hands over to the next
+    let renderers = launch.renderer.fallbacks();
+    let mut result = Ok(());
+    for (index, renderer) in renderers.iter().enumerate() {
+        if let (Err(e), Some(next)) = (renderer.probe(), renderers.get(index + 1)) {
+            eprintln!("Warning: Could not start the {} renderer ({}), trying {}", renderer.label(), e, next.label());
+            continue;
+        }
+        let mut options = eframe::NativeOptions {
+            viewport: viewport.clone(),
+            ..Default::default()
+        };
+        renderer.configure(&mut options);
+        let launch = launch.clone();
+        result = eframe::run_native(
+            "ScriptView",
+            options,
+            Box::new(|cc| Ok(Box::new(SubtitleViewer::new(cc, launch)))),
+        );
+        match (&result, renderers.get(index + 1)) {
+            (Err(e), Some(next)) => {
+                eprintln!("Warning: Could not start the {} renderer ({}), trying {}", renderer.label(), e, next.label());
+            }
+            _ => break,
+        }
+    }
+    result
 }
 
 #[cfg(test)]
@@ -269,11 +6116,57 @@ mod tests {
         SubtitleEntry {
             text: text.to_string(),
             start_time,
-            end_time: None,
-            timestamp: 0,
+            ..Default::default()
         }
     }
 
+    fn arrived(text: &str, timestamp: i64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_duplicates_within_window_dropped() {
+        let subtitles = vec![arrived("A", 0), arrived("B", 5), arrived("A", 20), arrived("B", 100)];
+        let (filtered, _) = split_duplicate_subtitles(subtitles, 60);
+        let texts: Vec<&str> = filtered.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["A", "B", "B"]);
+    }
+
+    #[test]
+    fn test_duplicates_kept_when_disabled_or_other_file() {
+        let mut other = arrived("A", 10);
+        other.path = Some("/other.mkv".to_string());
+        assert_eq!(split_duplicate_subtitles(vec![arrived("A", 0), other], 60).0.len(), 2);
+        assert_eq!(split_duplicate_subtitles(vec![arrived("A", 0), arrived("A", 1)], 0).0.len(), 2);
+    }
+
+    #[test]
+    fn test_entry_details_list_fields_present() {
+        let mut sub = create_subtitle("猫\n犬", 75.0);
+        sub.path = Some("/videos/show.mkv".to_string());
+        let details = entry_details(&sub);
+        let fields: Vec<&str> = details.iter().map(|(field, _)| *field).collect();
+        assert_eq!(fields, vec!["Text", "Start", "Arrived", "Media"]);
+        assert_eq!(details[0].1, "\"猫\\n犬\"");
+        assert_eq!(details[1].1, "1:15.0 (75 s)");
+    }
+
+    #[test]
+    fn test_pipeline_reports_what_it_removed() {
+        let subtitles = vec![arrived("猫", 0), arrived("猫が", 1), arrived("犬", 2), arrived("猫が", 30)];
+        let (kept, removed) = filter_pipeline(subtitles, &Settings::default());
+        let texts: Vec<&str> = kept.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["猫が", "犬"]);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].reason, FilterReason::Prefix { next: "猫が".to_string() });
+        assert_eq!(removed[1].reason, FilterReason::Repeat { after_secs: 29 });
+        assert_eq!(removed[1].reason.label(), "repeat of a line 29 s earlier");
+    }
+
     #[test]
     fn test_filter_no_prefixes() {
         let subtitles = vec![
@@ -282,7 +6175,7 @@ mod tests {
             create_subtitle("Another subtitle", 3.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles.clone());
+        let filtered = filter_prefix_subtitles(subtitles.clone(), 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 3);
         assert_eq!(filtered[0].text, "Hello world");
         assert_eq!(filtered[1].text, "Goodbye world");
@@ -297,7 +6190,7 @@ mod tests {
             create_subtitle("Goodbye", 3.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 2);
         assert_eq!(filtered[0].text, "Hello world");
         assert_eq!(filtered[1].text, "Goodbye");
@@ -315,7 +6208,7 @@ mod tests {
             create_subtitle("Next subtitle", 4.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 2);
         assert_eq!(filtered[0].text, "Hello world");
         assert_eq!(filtered[1].text, "Next subtitle");
@@ -328,7 +6221,7 @@ mod tests {
             create_subtitle("World", 2.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 2);
         assert_eq!(filtered[1].text, "World");
     }
@@ -336,14 +6229,14 @@ mod tests {
     #[test]
     fn test_filter_empty_list() {
         let subtitles = vec![];
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 0);
     }
 
     #[test]
     fn test_filter_single_subtitle() {
         let subtitles = vec![create_subtitle("Only one", 1.0)];
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0].text, "Only one");
     }
@@ -366,12 +6259,46 @@ mod tests {
             create_subtitle("Next subtitle", 3.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 2);
         assert_eq!(filtered[0].text, "I am typing this message");
         assert_eq!(filtered[1].text, "Next subtitle");
     }
 
+    #[test]
+    fn test_filter_prefix_far_apart() {
+        // Said again a few minutes later, extended: both are real lines
+        let subtitles = vec![arrived("そうか", 0), arrived("そうか。分かった", 200)];
+        let (kept, removed) = split_prefix_subtitles(subtitles.clone(), 10, CollapseMode::Prefix);
+        let texts: Vec<&str> = kept.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["そうか", "そうか。分かった"]);
+        assert!(removed.is_empty());
+        // Right at the edge of the window still counts as typed out
+        let (kept, _) = split_prefix_subtitles(vec![arrived("猫", 0), arrived("猫が", 10)], 10, CollapseMode::Prefix);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix).len(), 1);
+    }
+
+    #[test]
+    fn test_collapse_modes() {
+        // A player that puts the newest words in front, then one that builds around the middle
+        let subtitles = vec![arrived("分かった", 0), arrived("そうか。分かった", 1), arrived("犬", 2), arrived("「犬だ」", 3)];
+        let texts = |mode| -> Vec<String> {
+            filter_prefix_subtitles(subtitles.clone(), 10, mode).into_iter().map(|s| s.text).collect()
+        };
+        assert_eq!(texts(CollapseMode::Prefix).len(), 4);
+        assert_eq!(texts(CollapseMode::PrefixOrSuffix), vec!["そうか。分かった", "犬", "「犬だ」"]);
+        assert_eq!(texts(CollapseMode::Anywhere), vec!["そうか。分かった", "「犬だ」"]);
+        let (_, removed) = split_prefix_subtitles(subtitles, 10, CollapseMode::Anywhere);
+        assert_eq!(removed[0].reason.label(), "end of \"そうか。分かった\"");
+        assert_eq!(removed[1].reason, FilterReason::Contained { next: "「犬だ」".to_string() });
+        // A prefix is reported as one whatever else it also is
+        assert_eq!(
+            collapse_reason("猫", "猫と猫", CollapseMode::Anywhere),
+            Some(FilterReason::Prefix { next: "猫と猫".to_string() })
+        );
+    }
+
     #[test]
     fn test_filter_non_prefix_similar_start() {
         // These start similarly but aren't prefixes
@@ -381,7 +6308,7 @@ mod tests {
             create_subtitle("Helicopter", 3.0),
         ];
         
-        let filtered = filter_prefix_subtitles(subtitles);
+        let filtered = filter_prefix_subtitles(subtitles, 0, CollapseMode::Prefix);
         assert_eq!(filtered.len(), 3);
         assert_eq!(filtered[0].text, "Hello world");
         assert_eq!(filtered[1].text, "Hello there");
@@ -0,0 +1,173 @@
+//! Inline emphasis some subtitle sources leave in the text: HTML-style
+//! `<i>`/`<b>` from SRT and `{\i1}`/`{\b1}` ASS overrides. `parse` removes
+//! the tags and records which stretches of what's left were italic or bold,
+//! so they can be drawn that way instead of showing the tags.
+
+use std::ops::Range;
+
+use eframe::egui;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Emphasis {
+    pub italic: bool,
+    pub bold: bool,
+}
+
+/// Text with the markup taken out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Marked {
+    pub text: String,
+    /// Byte ranges of `text` and how they're emphasised, in order and
+    /// covering all of it. Empty when nothing is.
+    pub runs: Vec<(Range<usize>, Emphasis)>,
+}
+
+/// Tags that are removed without changing the emphasis.
+const IGNORED_TAGS: [&str; 3] = ["u", "s", "font"];
+
+impl Marked {
+    /// The pieces of `range` and their emphasis.
+    pub fn pieces(&self, range: Range<usize>) -> Vec<(Range<usize>, Emphasis)> {
+        if self.runs.is_empty() {
+            return vec![(range, Emphasis::default())];
+        }
+        self.runs
+            .iter()
+            .filter(|(run, _)| run.start < range.end && range.start < run.end)
+            .map(|(run, emphasis)| (run.start.max(range.start)..run.end.min(range.end), *emphasis))
+            .collect()
+    }
+
+    /// The whole text as one layout, for places that draw it in one piece.
+    pub fn layout_job(&self, format: egui::TextFormat, strong_color: egui::Color32) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        for (range, emphasis) in self.pieces(0..self.text.len()) {
+            let mut format = format.clone();
+            format.italics = emphasis.italic;
+            if emphasis.bold {
+                // egui has no bold weight, so bold is drawn in the strong text color
+                format.color = strong_color;
+            }
+            job.append(&self.text[range], 0.0, format);
+        }
+        job
+    }
+}
+
+/// Whether the HTML tag `name` (without `/`) is one that's understood, and
+/// what it switches.
+fn html_tag(name: &str, emphasis: &mut Emphasis) -> bool {
+    let (closing, name) = match name.strip_prefix('/') {
+        Some(name) => (true, name),
+        None => (false, name),
+    };
+    let name = name.trim().to_ascii_lowercase();
+    let tag = name.split_whitespace().next().unwrap_or_default();
+    match tag {
+        "i" => emphasis.italic = !closing,
+        "b" => emphasis.bold = !closing,
+        _ if IGNORED_TAGS.contains(&tag) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Apply the `\i` and `\b` overrides in an ASS override block; the rest of
+/// it is dropped.
+fn ass_overrides(block: &str, emphasis: &mut Emphasis) {
+    let switch = |value: &str| value.parse::<u32>().ok().is_none_or(|n| n != 0);
+    for tag in block.split('\\').skip(1) {
+        // `\r` or `\rStyle` goes back to the style's defaults
+        if tag.starts_with('r') {
+            *emphasis = Emphasis::default();
+        } else if let Some(value) = tag.strip_prefix('i').filter(|v| v.chars().all(|c| c.is_ascii_digit())) {
+            emphasis.italic = switch(value);
+        } else if let Some(value) = tag.strip_prefix('b').filter(|v| v.chars().all(|c| c.is_ascii_digit())) {
+            emphasis.bold = switch(value);
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Marked {
+    let mut marked = Marked::default();
+    let mut emphasis = Emphasis::default();
+    let mut run_start = 0;
+    let mut rest = source;
+    let mut styled = false;
+    while !rest.is_empty() {
+        let before = emphasis;
+        let tag_len = if rest.starts_with('<') {
+            rest.find('>').filter(|&end| html_tag(&rest[1..end], &mut emphasis)).map(|end| end + 1)
+        } else if rest.starts_with("{\\") {
+            rest.find('}').inspect(|&end| ass_overrides(&rest[1..end], &mut emphasis)).map(|end| end + 1)
+        } else {
+            None
+        };
+        match tag_len {
+            Some(len) => {
+                rest = &rest[len..];
+                if emphasis != before {
+                    if marked.text.len() > run_start {
+                        marked.runs.push((run_start..marked.text.len(), before));
+                    }
+                    run_start = marked.text.len();
+                    styled |= emphasis != Emphasis::default();
+                }
+            }
+            None => {
+                let c = rest.chars().next().unwrap();
+                marked.text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if marked.text.len() > run_start {
+        marked.runs.push((run_start..marked.text.len(), emphasis));
+    }
+    if !styled {
+        marked.runs.clear();
+    }
+    marked
+}
+
+/// The text without markup or emphasis.
+pub fn strip(source: &str) -> Marked {
+    Marked {
+        text: parse(source).text,
+        runs: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITALIC: Emphasis = Emphasis { italic: true, bold: false };
+    const BOLD: Emphasis = Emphasis { italic: false, bold: true };
+
+    #[test]
+    fn test_html_tags() {
+        let marked = parse("<i>猫だ</i>よ <B>本当</B>");
+        assert_eq!(marked.text, "猫だよ 本当");
+        assert_eq!(
+            marked.runs,
+            vec![(0..6, ITALIC), (6..10, Emphasis::default()), (10..16, BOLD)]
+        );
+        assert_eq!(marked.pieces(3..12), vec![(3..6, ITALIC), (6..10, Emphasis::default()), (10..12, BOLD)]);
+    }
+
+    #[test]
+    fn test_ass_overrides() {
+        let marked = parse("{\\an8\\i1}Hello, {\\i0\\b1}world{\\r}!");
+        assert_eq!(marked.text, "Hello, world!");
+        assert_eq!(marked.runs, vec![(0..7, ITALIC), (7..12, BOLD), (12..13, Emphasis::default())]);
+    }
+
+    #[test]
+    fn test_plain_text_is_left_alone() {
+        let marked = parse("a <3 b {not a tag} <font color=\"red\">c</font>");
+        assert_eq!(marked.text, "a <3 b {not a tag} c");
+        assert!(marked.runs.is_empty());
+        assert_eq!(marked.pieces(0..3), vec![(0..3, Emphasis::default())]);
+    }
+}
@@ -0,0 +1,171 @@
+//! Spotting mpv settings and other scripts that keep the Lua script from
+//! capturing anything: hidden or disabled subtitles, scripts not loading, a
+//! second copy of the script, or another script fighting over subtitles.
+
+use std::path::{Path, PathBuf};
+
+/// The installed script's name, which isn't a conflict with itself.
+const SCRIPT_NAME: &str = "subtitle-monitor.lua";
+/// Logged by every copy of the script, to recognise renamed ones.
+const SCRIPT_MARKER: &str = "Subtitle monitor started";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub file: PathBuf,
+    /// 1-based line in `file`, for settings.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// mpv's configuration directory.
+pub fn config_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("MPV_HOME") {
+        return PathBuf::from(home);
+    }
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(format!("{}/.config/mpv", home_dir))
+}
+
+/// What's wrong with the option `key` (without `--`) set to `value`, if
+/// anything.
+fn check_option(key: &str, value: Option<&str>) -> Option<&'static str> {
+    let off = |value: Option<&str>| matches!(value, Some("no" | "false"));
+    match key {
+        "sub-visibility" if off(value) => Some("subtitles are hidden, so the script sees no text"),
+        "no-sub-visibility" => Some("subtitles are hidden, so the script sees no text"),
+        "sid" | "sub" if off(value) => Some("no subtitle track is picked, so there's nothing to capture"),
+        "no-sub" => Some("no subtitle track is picked, so there's nothing to capture"),
+        "load-scripts" if off(value) => Some("scripts in the scripts directory aren't loaded, including ScriptView's"),
+        "no-load-scripts" => Some("scripts in the scripts directory aren't loaded, including ScriptView's"),
+        _ => None,
+    }
+}
+
+/// Conflicting options in the contents of an `mpv.conf`, by line number.
+/// Options inside `[profile]` sections only apply when that profile is, so
+/// they're mentioned as such.
+pub fn check_conf(content: &str) -> Vec<(usize, String)> {
+    let mut conflicts = Vec::new();
+    let mut profile = None;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            profile = Some(name.to_string()).filter(|name| name != "default");
+            continue;
+        }
+        let option = line.trim_start_matches("--");
+        let (key, value) = match option.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim().trim_matches('"'))),
+            None => (option, None),
+        };
+        if let Some(problem) = check_option(key, value) {
+            let message = match &profile {
+                Some(profile) => format!("`{}` in profile [{}]: {}", line, profile, problem),
+                None => format!("`{}`: {}", line, problem),
+            };
+            conflicts.push((index + 1, message));
+        }
+    }
+    conflicts
+}
+
+/// What's wrong with having the script `content` loaded alongside ours.
+fn check_script(content: &str) -> Option<&'static str> {
+    if content.contains(SCRIPT_MARKER) {
+        Some("another copy of the ScriptView script; both write the capture file")
+    } else if content.contains("sub-visibility") {
+        Some("changes subtitle visibility, which can hide subtitles from the capture")
+    } else if content.contains("sub-text") {
+        Some("also captures subtitles; if lines go missing, try without it")
+    } else {
+        None
+    }
+}
+
+/// The script files mpv loads from `dir`: `*.lua` and `*.js`, and
+/// `main.lua`/`main.js` in subdirectories.
+fn scripts_in(dir: &Path) -> Vec<PathBuf> {
+    let is_script = |path: &Path| path.extension().is_some_and(|ext| ext == "lua" || ext == "js");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .flat_map(|path| {
+            if path.is_dir() {
+                ["main.lua", "main.js"].iter().map(|main| path.join(main)).filter(|main| main.is_file()).collect()
+            } else if is_script(&path) {
+                vec![path]
+            } else {
+                Vec::new()
+            }
+        })
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// Everything in the mpv configuration at `dir` that gets in the way.
+pub fn scan(dir: &Path) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let conf = dir.join("mpv.conf");
+    if let Ok(content) = std::fs::read_to_string(&conf) {
+        for (line, message) in check_conf(&content) {
+            conflicts.push(Conflict {
+                file: conf.clone(),
+                line: Some(line),
+                message,
+            });
+        }
+    }
+    for script in scripts_in(&dir.join("scripts")) {
+        if script.file_name().is_some_and(|name| name == SCRIPT_NAME) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&script) else {
+            continue;
+        };
+        if let Some(problem) = check_script(&content) {
+            conflicts.push(Conflict {
+                file: script,
+                line: None,
+                message: problem.to_string(),
+            });
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_conf() {
+        let conf = "# sub-visibility=no\nfullscreen=yes\nsub-visibility=no\n--no-sub\nsid=auto\n[anime]\nload-scripts=no\n";
+        let conflicts = check_conf(conf);
+        let lines: Vec<usize> = conflicts.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4, 7]);
+        assert!(conflicts[0].1.starts_with("`sub-visibility=no`"));
+        assert!(conflicts[2].1.contains("profile [anime]"));
+    }
+
+    #[test]
+    fn test_scan_finds_other_scripts() {
+        let dir = std::env::temp_dir().join(format!("scriptview-mpv-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("scripts/exporter")).unwrap();
+        std::fs::write(dir.join("mpv.conf"), "sub-visibility=no\n").unwrap();
+        std::fs::write(dir.join("scripts/subtitle-monitor.lua"), crate::LUA_SCRIPT).unwrap();
+        std::fs::write(dir.join("scripts/subtitle-monitor-old.lua"), crate::LUA_SCRIPT).unwrap();
+        std::fs::write(dir.join("scripts/exporter/main.js"), "mp.observe_property('sub-text', 'string', f)").unwrap();
+        std::fs::write(dir.join("scripts/notes.txt"), "sub-text").unwrap();
+        let conflicts = scan(&dir);
+        let files: Vec<String> = conflicts
+            .iter()
+            .map(|conflict| conflict.file.strip_prefix(&dir).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(files, vec!["mpv.conf", "scripts/exporter/main.js", "scripts/subtitle-monitor-old.lua"]);
+        assert!(conflicts[2].message.contains("another copy"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
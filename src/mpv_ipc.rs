@@ -0,0 +1,425 @@
+//! Client for mpv's JSON IPC socket (`--input-ipc-server`).
+//!
+//! mpv accepts any number of clients on one socket, so this works alongside
+//! Syncplay. A task on the network runtime keeps trying to connect, observes the
+//! properties the viewer cares about, and forwards changes as events. When
+//! the socket drops (mpv quit or restarted) it reconnects right away, then
+//! backs off up to `MAX_RETRY_DELAY`, and observes everything again on the
+//! new connection. Commands sent while disconnected are dropped rather than
+//! replayed later.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::protocol::Request;
+use crate::runtime::{StopSignal, Stopped};
+
+pub const DEFAULT_SOCKET: &str = "/tmp/mpv-socket";
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Properties observed on every connection.
+const OBSERVED: [&str; 8] = [
+    "pause",
+    "track-list",
+    "sid",
+    "secondary-sid",
+    "aid",
+    "speed",
+    "sub-delay",
+    "focused",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MpvEvent {
+    Connected,
+    Disconnected,
+    /// A connection attempt after a drop failed; the next comes after `delay`.
+    Reconnecting { attempt: u32, delay: Duration },
+    PropertyChange { name: String, data: serde_json::Value },
+}
+
+/// One entry of mpv's `track-list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub id: i64,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub external: bool,
+    /// mpv's name for the format, such as `subrip` or `hdmv_pgs_subtitle`.
+    pub codec: Option<String>,
+}
+
+impl Track {
+    /// Whether the track is pictures of text (Blu-ray, DVD or DVB
+    /// subtitles), which gives the script nothing to read.
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self.codec.as_deref(),
+            Some("hdmv_pgs_subtitle" | "dvd_subtitle" | "dvb_subtitle" | "xsub")
+        )
+    }
+
+    /// Like mpv's OSD: `2: Signs (eng) [external]`.
+    pub fn label(&self) -> String {
+        let mut label = format!("{}:", self.id);
+        if let Some(title) = &self.title {
+            label.push_str(&format!(" {}", title));
+        }
+        if let Some(lang) = &self.lang {
+            label.push_str(&format!(" ({})", lang));
+        }
+        if self.external {
+            label.push_str(" [external]");
+        }
+        label
+    }
+}
+
+/// The tracks of `kind` ("sub", "audio" or "video") in a `track-list` value.
+pub fn parse_tracks(data: &serde_json::Value, kind: &str) -> Vec<Track> {
+    let Some(tracks) = data.as_array() else {
+        return Vec::new();
+    };
+    tracks
+        .iter()
+        .filter(|track| track["type"] == kind)
+        .filter_map(|track| {
+            Some(Track {
+                id: track["id"].as_i64()?,
+                title: track["title"].as_str().map(str::to_string),
+                lang: track["lang"].as_str().map(str::to_string),
+                external: track["external"].as_bool() == Some(true),
+                codec: track["codec"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Whether the viewer should come up (`true`) or get out of the way
+/// (`false`) when following mpv: it comes up when mpv starts, plays or gets
+/// focus, and goes away when mpv closes.
+pub fn follow_visibility(event: &MpvEvent) -> Option<bool> {
+    match event {
+        MpvEvent::Connected => Some(true),
+        MpvEvent::Disconnected => Some(false),
+        MpvEvent::PropertyChange { name, data } if name == "pause" && data.as_bool() == Some(false) => Some(true),
+        MpvEvent::PropertyChange { name, data } if name == "focused" && data.as_bool() == Some(true) => Some(true),
+        _ => None,
+    }
+}
+
+/// A track id property such as `sid`, which mpv reports as `false` (or
+/// "no") when no track is selected.
+pub fn parse_track_id(data: &serde_json::Value) -> Option<i64> {
+    data.as_i64()
+}
+
+pub struct MpvIpc {
+    socket_path: Arc<Mutex<String>>,
+    commands: UnboundedSender<serde_json::Value>,
+    pub events: Receiver<MpvEvent>,
+    stop: Option<StopSignal>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl MpvIpc {
+    pub fn spawn(socket_path: &str) -> Self {
+        let socket_path = Arc::new(Mutex::new(socket_path.to_string()));
+        let (event_tx, event_rx) = channel();
+        let (command_tx, command_rx) = unbounded_channel();
+        let path = socket_path.clone();
+        let (stop, stopped) = crate::runtime::stop_signal();
+        let task = crate::runtime::spawn(connection_loop(path, command_rx, event_tx, stopped));
+        Self {
+            socket_path,
+            commands: command_tx,
+            events: event_rx,
+            stop: Some(stop),
+            task: Some(task),
+        }
+    }
+
+    /// Close the connection and stop reconnecting, waiting at most `timeout`.
+    pub fn disconnect(&mut self, timeout: Duration) -> Result<(), String> {
+        self.stop = None;
+        let Some(task) = self.task.take() else {
+            return Ok(());
+        };
+        if !crate::runtime::join_within(task, timeout) {
+            return Err("connection didn't close in time".to_string());
+        }
+        Ok(())
+    }
+
+    /// Switch to another socket; the current connection is dropped.
+    pub fn set_socket_path(&self, path: &str) {
+        *self.socket_path.lock().unwrap() = path.to_string();
+    }
+
+    /// Send a command such as `["set_property", "pause", true]`.
+    pub fn command(&self, args: serde_json::Value) {
+        let _ = self.commands.send(args);
+    }
+
+    /// Carry out a request that could also go through the Lua script.
+    pub fn request(&self, request: &Request) {
+        self.command(request_command(request));
+    }
+}
+
+fn request_command(request: &Request) -> serde_json::Value {
+    match request {
+        Request::ShowText { text, duration_ms } => serde_json::json!(["show-text", text, duration_ms]),
+        Request::SetPause { paused } => serde_json::json!(["set_property", "pause", paused]),
+        Request::Seek { time } => serde_json::json!(["seek", time, "absolute"]),
+    }
+}
+
+/// Wait before connection attempt `attempt` (1 after the first failure),
+/// doubling each time.
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Sleep for `delay`, returning early (with true) if the socket path changes.
+async fn wait_unless_moved(socket_path: &Mutex<String>, path: &str, delay: Duration) -> bool {
+    let until = tokio::time::Instant::now() + delay;
+    while tokio::time::Instant::now() < until {
+        if *socket_path.lock().unwrap() != path {
+            return true;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    false
+}
+
+/// Turn one line from mpv into an event, ignoring replies and other events.
+fn parse_event(line: &str) -> Option<MpvEvent> {
+    let message: serde_json::Value = serde_json::from_str(line).ok()?;
+    if message["event"] != "property-change" {
+        return None;
+    }
+    Some(MpvEvent::PropertyChange {
+        name: message["name"].as_str()?.to_string(),
+        data: message["data"].clone(),
+    })
+}
+
+#[cfg(unix)]
+async fn connection_loop(
+    socket_path: Arc<Mutex<String>>,
+    mut commands: UnboundedReceiver<serde_json::Value>,
+    events: Sender<MpvEvent>,
+    mut stopped: Stopped,
+) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let mut failures = 0;
+    let mut was_connected = false;
+    loop {
+        let path = socket_path.lock().unwrap().clone();
+        let Ok(stream) = UnixStream::connect(&path).await else {
+            failures += 1;
+            let delay = retry_delay(failures);
+            if was_connected && events.send(MpvEvent::Reconnecting { attempt: failures, delay }).is_err() {
+                return;
+            }
+            let moved = tokio::select! {
+                moved = wait_unless_moved(&socket_path, &path, delay) => moved,
+                _ = stopped.wait() => return,
+            };
+            if moved {
+                // A new socket is a fresh start
+                failures = 0;
+                was_connected = false;
+            }
+            continue;
+        };
+        failures = 0;
+        was_connected = true;
+        // Whatever was asked for while disconnected is stale by now
+        while commands.try_recv().is_ok() {}
+        if events.send(MpvEvent::Connected).is_err() {
+            return;
+        }
+        let (reader, mut writer) = stream.into_split();
+        for (id, name) in OBSERVED.iter().enumerate() {
+            let observe = serde_json::json!({ "command": ["observe_property", id + 1, name] });
+            let _ = writer.write_all(format!("{}\n", observe).as_bytes()).await;
+        }
+
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            tokio::select! {
+                // A partial line stays in `line` if another branch wins
+                read = reader.read_until(b'\n', &mut line) => match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if let Some(event) = parse_event(&String::from_utf8_lossy(&line)) {
+                            if events.send(event).is_err() {
+                                return;
+                            }
+                        }
+                        line.clear();
+                    }
+                },
+                args = commands.recv() => match args {
+                    Some(args) => {
+                        let command = serde_json::json!({ "command": args });
+                        let _ = writer.write_all(format!("{}\n", command).as_bytes()).await;
+                    }
+                    // The viewer is gone
+                    None => return,
+                },
+                _ = tokio::time::sleep(POLL_INTERVAL) => {
+                    if *socket_path.lock().unwrap() != path {
+                        break;
+                    }
+                }
+                // Dropping the stream closes the socket
+                _ = stopped.wait() => return,
+            }
+        }
+        if events.send(MpvEvent::Disconnected).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn connection_loop(
+    _socket_path: Arc<Mutex<String>>,
+    _commands: UnboundedReceiver<serde_json::Value>,
+    _events: Sender<MpvEvent>,
+    _stopped: Stopped,
+) {
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_parse_property_change() {
+        let event = parse_event(r#"{"event":"property-change","id":1,"name":"pause","data":true}"#);
+        assert_eq!(
+            event,
+            Some(MpvEvent::PropertyChange {
+                name: "pause".to_string(),
+                data: serde_json::Value::Bool(true),
+            })
+        );
+    }
+
+    #[test]
+    fn test_request_command() {
+        let show = Request::ShowText { text: "Resumed".to_string(), duration_ms: 1500 };
+        assert_eq!(request_command(&show), serde_json::json!(["show-text", "Resumed", 1500]));
+        let pause = Request::SetPause { paused: true };
+        assert_eq!(request_command(&pause), serde_json::json!(["set_property", "pause", true]));
+        let seek = Request::Seek { time: 83.5 };
+        assert_eq!(request_command(&seek), serde_json::json!(["seek", 83.5, "absolute"]));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(100), MAX_RETRY_DELAY);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reconnects_and_observes_again() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("scriptview-ipc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let ipc = MpvIpc::spawn(&path.to_string_lossy());
+        let wait = Duration::from_secs(5);
+
+        for _ in 0..2 {
+            let (stream, _) = listener.accept().unwrap();
+            assert_eq!(ipc.events.recv_timeout(wait), Ok(MpvEvent::Connected));
+            let mut observe = String::new();
+            BufReader::new(&stream).read_line(&mut observe).unwrap();
+            assert!(observe.contains("observe_property"));
+            // mpv quits: the connection drops
+            drop(stream);
+            assert_eq!(ipc.events.recv_timeout(wait), Ok(MpvEvent::Disconnected));
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_tracks() {
+        let data = serde_json::json!([
+            {"id": 1, "type": "video", "selected": true},
+            {"id": 1, "type": "sub", "lang": "jpn", "title": "Full"},
+            {"id": 2, "type": "sub", "external": true, "title": "show.en.srt"},
+            {"id": 3, "type": "sub", "codec": "hdmv_pgs_subtitle"},
+        ]);
+        let tracks = parse_tracks(&data, "sub");
+        assert_eq!(tracks.len(), 3);
+        assert!(tracks[2].is_image() && !tracks[0].is_image());
+        assert_eq!(tracks[0].label(), "1: Full (jpn)");
+        assert_eq!(tracks[1].label(), "2: show.en.srt [external]");
+        assert!(parse_tracks(&serde_json::Value::Null, "sub").is_empty());
+        assert_eq!(parse_track_id(&serde_json::json!(2)), Some(2));
+        assert_eq!(parse_track_id(&serde_json::json!(false)), None);
+    }
+
+    #[test]
+    fn test_follow_visibility() {
+        let change = |name: &str, data| MpvEvent::PropertyChange {
+            name: name.to_string(),
+            data,
+        };
+        assert_eq!(follow_visibility(&MpvEvent::Connected), Some(true));
+        assert_eq!(follow_visibility(&MpvEvent::Disconnected), Some(false));
+        assert_eq!(follow_visibility(&change("pause", serde_json::json!(false))), Some(true));
+        assert_eq!(follow_visibility(&change("pause", serde_json::json!(true))), None);
+        assert_eq!(follow_visibility(&change("focused", serde_json::json!(true))), Some(true));
+        assert_eq!(follow_visibility(&change("speed", serde_json::json!(1.5))), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disconnect_closes_the_socket() {
+        use std::io::Read;
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("scriptview-ipc-close-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut ipc = MpvIpc::spawn(&path.to_string_lossy());
+        let (mut stream, _) = listener.accept().unwrap();
+        assert_eq!(ipc.events.recv_timeout(Duration::from_secs(5)), Ok(MpvEvent::Connected));
+
+        assert!(ipc.disconnect(Duration::from_secs(5)).is_ok());
+        // Everything up to end of file: the observe commands, then nothing more
+        let mut sent = String::new();
+        stream.read_to_string(&mut sent).unwrap();
+        assert!(sent.contains("observe_property"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_ignores_replies_and_other_events() {
+        assert_eq!(parse_event(r#"{"request_id":0,"error":"success"}"#), None);
+        assert_eq!(parse_event(r#"{"event":"seek"}"#), None);
+        assert_eq!(parse_event("not json"), None);
+    }
+}
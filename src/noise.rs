@@ -0,0 +1,97 @@
+//! Filters for the junk speech recognition and OCR produce: guesses the
+//! recogniser wasn't sure of, stray fragments, and text that keeps coming
+//! back, such as a channel logo or watermark OCR reads off every frame.
+//! Each source has its own, since what's noise from OCR is a fine line from
+//! mpv.
+
+use serde::{Deserialize, Serialize};
+
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseFilter {
+    /// Lines with a confidence below this are left out; 0 keeps them all.
+    /// Lines with no confidence are kept.
+    pub min_confidence: f32,
+    /// Lines with fewer letters and digits than this are left out.
+    pub min_chars: usize,
+    /// Text that marks a line as junk wherever it appears in it, one per
+    /// line, ignoring case.
+    pub blocklist: String,
+}
+
+/// Why the filter left a line out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Noise {
+    LowConfidence(f32),
+    TooShort(usize),
+    Blocked(String),
+}
+
+impl Noise {
+    pub fn label(&self) -> String {
+        match self {
+            Noise::LowConfidence(confidence) => format!("recognised with only {:.0}% confidence", confidence * 100.0),
+            Noise::TooShort(chars) => format!("only {} characters", chars),
+            Noise::Blocked(pattern) => format!("contains blocked \"{}\"", pattern),
+        }
+    }
+}
+
+impl NoiseFilter {
+    pub fn is_active(&self) -> bool {
+        self.min_confidence > 0.0 || self.min_chars > 0 || self.blocked().next().is_some()
+    }
+
+    fn blocked(&self) -> impl Iterator<Item = &str> {
+        self.blocklist.lines().map(str::trim).filter(|pattern| !pattern.is_empty())
+    }
+
+    /// Why `entry` is noise, if it is.
+    pub fn check(&self, entry: &SubtitleEntry) -> Option<Noise> {
+        if let Some(confidence) = entry.confidence.filter(|&confidence| confidence < self.min_confidence) {
+            return Some(Noise::LowConfidence(confidence));
+        }
+        let chars = entry.text.chars().filter(|c| c.is_alphanumeric()).count();
+        if chars < self.min_chars {
+            return Some(Noise::TooShort(chars));
+        }
+        let text = entry.text.to_lowercase();
+        self.blocked()
+            .find(|pattern| text.contains(&pattern.to_lowercase()))
+            .map(|pattern| Noise::Blocked(pattern.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, confidence: Option<f32>) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            confidence,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let filter = NoiseFilter {
+            min_confidence: 0.5,
+            min_chars: 2,
+            blocklist: "  \nSubscribe!\n".to_string(),
+        };
+        assert!(filter.is_active());
+        assert_eq!(filter.check(&entry("猫がいる", Some(0.9))), None);
+        assert_eq!(filter.check(&entry("猫がいる", None)), None);
+        assert_eq!(filter.check(&entry("猫がいる", Some(0.3))), Some(Noise::LowConfidence(0.3)));
+        assert_eq!(filter.check(&entry("…あ。", None)), Some(Noise::TooShort(1)));
+        assert_eq!(
+            filter.check(&entry("Please SUBSCRIBE! now", None)),
+            Some(Noise::Blocked("Subscribe!".to_string()))
+        );
+        assert!(!NoiseFilter::default().is_active());
+    }
+}
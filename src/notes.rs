@@ -0,0 +1,325 @@
+//! Sending a line or a whole transcript to a note-taking service's HTTP API,
+//! such as Readwise or Notion. Each service is a URL, a token, headers and a
+//! JSON body template per kind of send, so any API that takes a POST can be
+//! set up without code; the presets are starting points to adjust.
+
+use std::sync::mpsc::{channel, Receiver};
+
+use serde::{Deserialize, Serialize};
+
+use crate::SubtitleEntry;
+
+/// The most characters Notion takes in one piece of rich text.
+const NOTION_TEXT_LIMIT: usize = 2000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteService {
+    pub name: String,
+    /// Whether "Send to note services" in a line's menu sends here.
+    pub enabled: bool,
+    pub url: String,
+    pub token: String,
+    /// `Name: value` per line; `{token}` is replaced with the token.
+    pub headers: String,
+    /// Body for one line, with `{text}`, `{translation}`, `{title}`,
+    /// `{source}`, `{time}` and `{date}` filled in.
+    pub line_template: String,
+    /// Body for a whole transcript: `{transcript}`, `{title}`, `{source}`
+    /// and `{date}`, and `{paragraphs}`, the transcript as a JSON array of
+    /// Notion paragraph blocks.
+    pub transcript_template: String,
+}
+
+impl Default for NoteService {
+    fn default() -> Self {
+        Self {
+            name: "Notes".to_string(),
+            enabled: true,
+            url: String::new(),
+            token: String::new(),
+            headers: "Authorization: Bearer {token}".to_string(),
+            line_template: r#"{"text": "{text}", "translation": "{translation}", "title": "{title}", "time": "{time}"}"#
+                .to_string(),
+            transcript_template: r#"{"title": "{title}", "date": "{date}", "text": "{transcript}"}"#.to_string(),
+        }
+    }
+}
+
+impl NoteService {
+    /// Readwise's highlights API; the token is from readwise.io/access_token.
+    pub fn readwise() -> Self {
+        Self {
+            name: "Readwise".to_string(),
+            url: "https://readwise.io/api/v2/highlights/".to_string(),
+            headers: "Authorization: Token {token}".to_string(),
+            line_template: concat!(
+                r#"{"highlights": [{"text": "{text}", "title": "{title}", "#,
+                r#""note": "{translation}", "source_type": "scriptview"}]}"#,
+            )
+            .to_string(),
+            transcript_template: concat!(
+                r#"{"highlights": [{"text": "{transcript}", "title": "{title}", "#,
+                r#""source_type": "scriptview"}]}"#,
+            )
+            .to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// A page per send in a Notion database shared with an integration;
+    /// replace DATABASE_ID with the database's ID.
+    pub fn notion() -> Self {
+        Self {
+            name: "Notion".to_string(),
+            url: "https://api.notion.com/v1/pages".to_string(),
+            headers: "Authorization: Bearer {token}\nNotion-Version: 2022-06-28".to_string(),
+            line_template: concat!(
+                r#"{"parent": {"database_id": "DATABASE_ID"}, "#,
+                r#""properties": {"Name": {"title": [{"text": {"content": "{text}"}}]}}, "#,
+                r#""children": [{"paragraph": {"rich_text": [{"text": {"content": "{translation}\n{title} {time}"}}]}}]}"#,
+            )
+            .to_string(),
+            transcript_template: concat!(
+                r#"{"parent": {"database_id": "DATABASE_ID"}, "#,
+                r#""properties": {"Name": {"title": [{"text": {"content": "{title} {date}"}}]}}, "#,
+                r#""children": {paragraphs}}"#,
+            )
+            .to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn header_lines(&self) -> Vec<(String, String)> {
+        self.headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().replace("{token}", &self.token)))
+            .filter(|(name, _)| !name.is_empty())
+            .collect()
+    }
+
+    pub fn line_body(&self, entry: &SubtitleEntry) -> String {
+        let (title, source, date) = about(std::slice::from_ref(entry));
+        fill(
+            &self.line_template,
+            &[
+                ("text", entry.text.trim().into()),
+                ("translation", entry.translation.as_deref().unwrap_or_default().into()),
+                ("title", title.into()),
+                ("source", source.into()),
+                ("time", crate::format_timestamp(entry.start_time).into()),
+                ("date", date.into()),
+            ],
+        )
+    }
+
+    pub fn transcript_body(&self, entries: &[SubtitleEntry]) -> String {
+        let (title, source, date) = about(entries);
+        let transcript = entries
+            .iter()
+            .map(|entry| {
+                let text = entry.text.trim().replace('\n', " ");
+                format!("[{}] {}", crate::format_timestamp(entry.start_time), text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        fill(
+            &self.transcript_template,
+            &[
+                ("paragraphs", paragraphs(&transcript)),
+                ("transcript", transcript.into()),
+                ("title", title.into()),
+                ("source", source.into()),
+                ("date", date.into()),
+            ],
+        )
+    }
+
+    /// POST `body` on a background thread.
+    pub fn send(&self, body: String) -> Receiver<Result<(), String>> {
+        let (tx, rx) = channel();
+        let url = self.url.trim().to_string();
+        let headers = self.header_lines();
+        std::thread::spawn(move || {
+            let _ = tx.send(post(&url, &headers, &body));
+        });
+        rx
+    }
+}
+
+/// The media title (or file), media file and date of the first entry.
+fn about(entries: &[SubtitleEntry]) -> (String, String, String) {
+    let first = entries.first();
+    let source = first.and_then(|entry| entry.path.clone()).unwrap_or_default();
+    let title = first
+        .and_then(|entry| entry.title.clone())
+        .or_else(|| first.and_then(|entry| entry.path.as_deref()).map(crate::stats::media_name))
+        .unwrap_or_default();
+    let date = first
+        .and_then(|entry| chrono::DateTime::from_timestamp(entry.timestamp, 0))
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    (title, source, date)
+}
+
+/// Notion paragraph blocks holding `text`, each as many whole lines as fit
+/// in `NOTION_TEXT_LIMIT` characters; a longer line is cut up.
+fn paragraphs(text: &str) -> serde_json::Value {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut length = 0;
+    for line in text.lines() {
+        let mut chars: Vec<char> = line.chars().collect();
+        // What's left of a line too long for a paragraph of its own
+        while chars.len() > NOTION_TEXT_LIMIT {
+            chunks.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            chunks.push(chars.drain(..NOTION_TEXT_LIMIT).collect());
+            length = 0;
+        }
+        let separator = usize::from(!current.is_empty());
+        if length + separator + chars.len() > NOTION_TEXT_LIMIT {
+            chunks.push(std::mem::take(&mut current));
+            length = 0;
+        } else if separator == 1 {
+            current.push('\n');
+            length += 1;
+        }
+        current.extend(&chars);
+        length += chars.len();
+    }
+    chunks.extend((!current.is_empty()).then_some(current));
+    chunks
+        .into_iter()
+        .map(|chunk| serde_json::json!({"paragraph": {"rich_text": [{"text": {"content": chunk}}]}}))
+        .collect()
+}
+
+/// `template` with each `{name}` of `fields` replaced by its value, in one
+/// pass so values can't fill each other in: strings escaped to go inside a
+/// JSON string, anything else as JSON. Unknown `{…}` are left as they are.
+pub fn fill(template: &str, fields: &[(&str, serde_json::Value)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let field = rest[1..]
+            .find('}')
+            .and_then(|close| fields.iter().find(|(name, _)| *name == &rest[1..=close]).map(|field| (close, &field.1)));
+        match field {
+            Some((close, value)) => {
+                let json = value.to_string();
+                filled.push_str(if value.is_string() { &json[1..json.len() - 1] } else { &json });
+                rest = &rest[close + 2..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+fn post(url: &str, headers: &[(String, String)], body: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Err("No URL set".to_string());
+    }
+    let mut request = ureq::post(url);
+    if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type")) {
+        request = request.set("Content-Type", "application/json");
+    }
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    match request.send_string(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, response)) => {
+            let message = response.into_string().unwrap_or_default();
+            Err(format!("HTTP {}: {}", code, message.chars().take(200).collect::<String>()))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let filled = fill(
+            r#"{"text": "{text}", "title": "{title}", "keep": "{unknown}"}"#,
+            &[("text", "\"{title}\"\nは猫".into()), ("title", "Show".into())],
+        );
+        assert_eq!(filled, r#"{"text": "\"{title}\"\nは猫", "title": "Show", "keep": "{unknown}"}"#);
+        let body: serde_json::Value = serde_json::from_str(&filled).unwrap();
+        assert_eq!(body["text"], "\"{title}\"\nは猫");
+    }
+
+    #[test]
+    fn test_presets_make_json() {
+        let entry = SubtitleEntry {
+            text: "猫が\"いる\"".to_string(),
+            translation: Some("There's a cat".to_string()),
+            start_time: 62.0,
+            title: Some("Show".to_string()),
+            ..Default::default()
+        };
+        for service in [NoteService::default(), NoteService::readwise(), NoteService::notion()] {
+            let line: serde_json::Value = serde_json::from_str(&service.line_body(&entry)).unwrap();
+            assert!(line.to_string().contains("猫が\\\"いる\\\""), "{}", service.name);
+            let transcript = service.transcript_body(&[entry.clone(), entry.clone()]);
+            let transcript: serde_json::Value = serde_json::from_str(&transcript).unwrap();
+            assert!(transcript.to_string().contains("[1:02.0]"), "{}", service.name);
+        }
+        let mut service = NoteService::notion();
+        service.token = "secret".to_string();
+        assert_eq!(
+            service.header_lines(),
+            vec![
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("Notion-Version".to_string(), "2022-06-28".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notion_transcript_fits_its_limit() {
+        let mut entries: Vec<SubtitleEntry> = (0..400)
+            .map(|i| SubtitleEntry {
+                text: format!("猫がいる、{}番目の行です", i),
+                start_time: i as f64,
+                ..Default::default()
+            })
+            .collect();
+        entries.push(SubtitleEntry {
+            text: "長".repeat(4500),
+            ..Default::default()
+        });
+        let body: serde_json::Value =
+            serde_json::from_str(&NoteService::notion().transcript_body(&entries)).unwrap();
+        let contents: Vec<String> = body["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["paragraph"]["rich_text"][0]["text"]["content"].as_str().unwrap().to_string())
+            .collect();
+        assert!(contents.len() > 3);
+        assert!(contents.iter().all(|content| content.chars().count() <= NOTION_TEXT_LIMIT));
+        // Nothing lost, and lines are only cut when they're too long
+        assert_eq!(contents.concat().replace('\n', ""), paragraphs_source(&entries).replace('\n', ""));
+        assert!(contents[0].ends_with("番目の行です"));
+    }
+
+    fn paragraphs_source(entries: &[SubtitleEntry]) -> String {
+        let service = NoteService {
+            transcript_template: r#"{"text": "{transcript}"}"#.to_string(),
+            ..Default::default()
+        };
+        let body: serde_json::Value = serde_json::from_str(&service.transcript_body(entries)).unwrap();
+        body["text"].as_str().unwrap().to_string()
+    }
+}
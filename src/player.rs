@@ -0,0 +1,183 @@
+//! The media players ScriptView can follow, each through its own bundled
+//! Lua script (or, for streaming sites, browser extension) writing the same
+//! capture file format. Tools without a player can pipe lines in instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnose::{self, Diagnosis};
+use crate::script_opts::ScriptOpts;
+
+pub const VLC_SCRIPT: &str = include_str!("../vlc-subtitles.lua");
+/// Where the VLC script writes unless `--lua-config` says otherwise.
+pub const VLC_OUTPUT_FILE: &str = "/tmp/vlc-subtitles.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Player {
+    #[default]
+    Mpv,
+    Vlc,
+    /// Streaming sites through the browser extension.
+    Browser,
+    /// JSON lines on stdin or a TCP port.
+    Lines,
+}
+
+impl Player {
+    pub const ALL: [Player; 4] = [Player::Mpv, Player::Vlc, Player::Browser, Player::Lines];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Player::Mpv => "mpv",
+            Player::Vlc => "VLC",
+            Player::Browser => "Browser",
+            Player::Lines => "JSON lines (stdin/TCP)",
+        }
+    }
+
+    /// Where the player looks for the script, if it has one the viewer can
+    /// install.
+    pub fn script_path(self) -> Option<String> {
+        let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+        match self {
+            Player::Mpv => Some(format!("{}/.config/mpv/scripts/subtitle-monitor.lua", home_dir)),
+            Player::Vlc => Some(format!("{}/.local/share/vlc/lua/intf/scriptview.lua", home_dir)),
+            Player::Browser | Player::Lines => None,
+        }
+    }
+
+    pub fn script_source(self) -> Option<&'static str> {
+        match self {
+            Player::Mpv => Some(crate::LUA_SCRIPT),
+            Player::Vlc => Some(VLC_SCRIPT),
+            Player::Browser | Player::Lines => None,
+        }
+    }
+
+    /// Whether the script is in place, or there is nothing to install.
+    pub fn script_installed(self) -> bool {
+        self.script_path().is_none_or(|path| std::path::Path::new(&path).exists())
+    }
+
+    /// The file the script writes subtitles to.
+    pub fn output_file(self, script_opts: &ScriptOpts) -> String {
+        match self {
+            Player::Mpv => script_opts.output_file.clone(),
+            Player::Vlc => VLC_OUTPUT_FILE.to_string(),
+            Player::Browser => crate::browser::OUTPUT_FILE.to_string(),
+            Player::Lines => crate::lines::OUTPUT_FILE.to_string(),
+        }
+    }
+
+    /// What to do after installing, since VLC doesn't load interface scripts
+    /// on its own.
+    pub fn launch_hint(self) -> Option<&'static str> {
+        match self {
+            Player::Mpv => None,
+            Player::Vlc => Some("Start VLC with --extraintf=luaintf --lua-intf=scriptview"),
+            Player::Browser => Some("Load browser-extension/ as an unpacked extension in Chrome or Firefox"),
+            Player::Lines => Some("Pipe lines into scriptview, or send them to the TCP port"),
+        }
+    }
+
+    /// Process names to look for, for players that run as their own program.
+    fn process_names(self) -> &'static [&'static str] {
+        match self {
+            Player::Mpv => &["mpv"],
+            Player::Vlc => &["vlc"],
+            Player::Browser | Player::Lines => &[],
+        }
+    }
+
+    /// Whether the player is running, or None if that can't be told (no
+    /// `/proc`, or no player program to look for).
+    pub fn is_running(self) -> Option<bool> {
+        let names = self.process_names();
+        if names.is_empty() {
+            return None;
+        }
+        process_running(names)
+    }
+
+    pub fn install_script(self) -> Result<(), Diagnosis> {
+        let (Some(path), Some(source)) = (self.script_path(), self.script_source()) else {
+            return Ok(());
+        };
+        let path = std::path::Path::new(&path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| diagnose::write_failed(path, &e))?;
+        }
+        std::fs::write(path, source).map_err(|e| diagnose::write_failed(path, &e))
+    }
+}
+
+/// Whether a process with one of `names` as its command name is running,
+/// going by `/proc/<pid>/comm`.
+fn process_running(names: &[&str]) -> Option<bool> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+    Some(entries.filter_map(Result::ok).any(|entry| {
+        let is_pid = entry.file_name().to_str().is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        is_pid
+            && std::fs::read_to_string(entry.path().join("comm")).is_ok_and(|comm| names.contains(&comm.trim()))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_harness::MockVlc;
+
+    #[test]
+    fn test_output_file() {
+        let opts = ScriptOpts {
+            output_file: "/tmp/custom.json".to_string(),
+            ..ScriptOpts::default()
+        };
+        assert_eq!(Player::Mpv.output_file(&opts), "/tmp/custom.json");
+        assert_eq!(Player::Vlc.output_file(&opts), VLC_OUTPUT_FILE);
+    }
+
+    #[test]
+    fn test_process_running() {
+        let Ok(own) = std::fs::read_to_string("/proc/self/comm") else {
+            return;
+        };
+        assert_eq!(process_running(&[own.trim()]), Some(true));
+        assert_eq!(process_running(&["no-such-player"]), Some(false));
+        assert_eq!(Player::Browser.is_running(), None);
+    }
+
+    #[test]
+    fn test_vlc_script_follows_srt() {
+        let vlc = MockVlc::start();
+        vlc.write_srt(
+            "show.srt",
+            "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>猫だ</i>\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n犬\r\nもいる\r\n",
+        );
+        vlc.run(&[0.5, 1.2, 1.8, 3.5]);
+        let entries = crate::session::read_entries(&vlc.output_file()).unwrap();
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["猫だ", "犬\nもいる"]);
+        assert_eq!(entries[0].start_time, 1.0);
+        assert_eq!(entries[1].end_time, Some(4.0));
+        assert_eq!(entries[0].path.as_deref(), Some(vlc.video_path().as_str()));
+        assert_eq!(entries[0].title.as_deref(), Some("show.mkv"));
+    }
+
+    #[test]
+    fn test_vlc_script_finds_language_srt() {
+        let vlc = MockVlc::start();
+        vlc.write_srt("show.ja.srt", "1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n");
+        vlc.run(&[1.0]);
+        let entries = crate::session::read_entries(&vlc.output_file()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, "こんにちは");
+    }
+
+    #[test]
+    fn test_vlc_script_without_srt() {
+        let vlc = MockVlc::start();
+        vlc.run(&[1.0]);
+        assert!(crate::session::read_entries(&vlc.output_file()).unwrap().is_empty());
+    }
+}
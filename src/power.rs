@@ -0,0 +1,50 @@
+//! Low power mode, for leaving the viewer running all day on a laptop:
+//! fewer repaints, no animations or shadows, and the MQTT and webhook sinks
+//! held back while the window is minimized.
+
+use std::time::Duration;
+
+use eframe::egui;
+
+/// How often the window repaints to pick up new lines normally.
+const REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+const LOW_POWER_REPAINT_INTERVAL: Duration = Duration::from_secs(1);
+/// While minimized in low power mode nothing is seen, so lines only need
+/// picking up often enough for the sinks to catch up when it's restored.
+const HIDDEN_REPAINT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn repaint_interval(low_power: bool, hidden: bool) -> Duration {
+    match (low_power, hidden) {
+        (false, _) => REPAINT_INTERVAL,
+        (true, false) => LOW_POWER_REPAINT_INTERVAL,
+        (true, true) => HIDDEN_REPAINT_INTERVAL,
+    }
+}
+
+/// Turn animations and window shadows off, or back to egui's defaults.
+pub fn apply_style(ctx: &egui::Context, low_power: bool) {
+    ctx.style_mut(|style| {
+        let defaults = if style.visuals.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() };
+        if low_power {
+            style.animation_time = 0.0;
+            style.visuals.window_shadow = egui::epaint::Shadow::NONE;
+            style.visuals.popup_shadow = egui::epaint::Shadow::NONE;
+        } else {
+            style.animation_time = egui::Style::default().animation_time;
+            style.visuals.window_shadow = defaults.window_shadow;
+            style.visuals.popup_shadow = defaults.popup_shadow;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repaint_interval() {
+        assert_eq!(repaint_interval(false, true), REPAINT_INTERVAL);
+        assert!(repaint_interval(true, false) > REPAINT_INTERVAL);
+        assert!(repaint_interval(true, true) > repaint_interval(true, false));
+    }
+}
@@ -0,0 +1,154 @@
+//! Presentation presets for readability: colors, letter spacing and font,
+//! and how wide subtitle lines may get.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    #[default]
+    Standard,
+    /// White on black with strong outlines.
+    HighContrast,
+    /// Wider letter spacing and the reading font from settings, such as
+    /// OpenDyslexic.
+    EasyReading,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Standard, Preset::HighContrast, Preset::EasyReading];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Preset::Standard => "Standard",
+            Preset::HighContrast => "High contrast",
+            Preset::EasyReading => "Easy reading",
+        }
+    }
+
+    pub fn visuals(self) -> egui::Visuals {
+        let mut visuals = egui::Visuals::dark();
+        if self == Preset::HighContrast {
+            let white = egui::Stroke::new(1.5, egui::Color32::WHITE);
+            visuals.override_text_color = Some(egui::Color32::WHITE);
+            visuals.panel_fill = egui::Color32::BLACK;
+            visuals.window_fill = egui::Color32::BLACK;
+            visuals.extreme_bg_color = egui::Color32::BLACK;
+            visuals.faint_bg_color = egui::Color32::BLACK;
+            visuals.window_stroke = white;
+            visuals.widgets.noninteractive.bg_stroke = white;
+            visuals.widgets.inactive.bg_stroke = white;
+            visuals.widgets.inactive.bg_fill = egui::Color32::BLACK;
+            visuals.widgets.inactive.weak_bg_fill = egui::Color32::BLACK;
+            visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+            visuals.selection.bg_fill = egui::Color32::YELLOW;
+            visuals.selection.stroke = egui::Stroke::new(2.0, egui::Color32::BLACK);
+            visuals.hyperlink_color = egui::Color32::from_rgb(0, 255, 255);
+        }
+        visuals
+    }
+
+    /// Extra space between letters of subtitle text, in points.
+    pub fn letter_spacing(self, font_size: f32) -> f32 {
+        match self {
+            Preset::EasyReading => font_size * 0.12,
+            Preset::Standard | Preset::HighContrast => 0.0,
+        }
+    }
+
+    pub fn uses_reading_font(self) -> bool {
+        self == Preset::EasyReading
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WidthUnit {
+    /// Widths of the digit 0 at the subtitle size, like CSS's `ch`.
+    #[default]
+    Characters,
+    Pixels,
+}
+
+impl WidthUnit {
+    pub const ALL: [WidthUnit; 2] = [WidthUnit::Characters, WidthUnit::Pixels];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WidthUnit::Characters => "characters",
+            WidthUnit::Pixels => "pixels",
+        }
+    }
+}
+
+/// A cap on how wide subtitle lines get, so they stay easy to read in a
+/// wide window instead of running edge to edge.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineWidth {
+    pub limited: bool,
+    /// Widest a line may be, in `unit`s.
+    pub max: f32,
+    pub unit: WidthUnit,
+    /// Put the column of lines in the middle of the window rather than on the left.
+    pub centered: bool,
+}
+
+impl Default for LineWidth {
+    fn default() -> Self {
+        Self {
+            limited: false,
+            max: 40.0,
+            unit: WidthUnit::default(),
+            centered: false,
+        }
+    }
+}
+
+impl LineWidth {
+    /// Width in points for lines given `available` space, where one character
+    /// is `char_width` wide.
+    pub fn points(&self, available: f32, char_width: f32) -> f32 {
+        if !self.limited {
+            return available;
+        }
+        let max = match self.unit {
+            WidthUnit::Characters => self.max * char_width,
+            WidthUnit::Pixels => self.max,
+        };
+        max.clamp(char_width.min(available), available)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_easy_reading_spaces_letters() {
+        assert_eq!(Preset::Standard.letter_spacing(20.0), 0.0);
+        assert!(Preset::EasyReading.letter_spacing(20.0) > 0.0);
+    }
+
+    #[test]
+    fn test_line_width() {
+        let mut width = LineWidth::default();
+        assert_eq!(width.points(1000.0, 10.0), 1000.0);
+        width.limited = true;
+        assert_eq!(width.points(1000.0, 10.0), 400.0);
+        assert_eq!(width.points(300.0, 10.0), 300.0);
+        width.unit = WidthUnit::Pixels;
+        width.max = 600.0;
+        assert_eq!(width.points(1000.0, 10.0), 600.0);
+        width.max = 0.0;
+        assert_eq!(width.points(1000.0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn test_high_contrast_is_black_and_white() {
+        let visuals = Preset::HighContrast.visuals();
+        assert_eq!(visuals.panel_fill, egui::Color32::BLACK);
+        assert_eq!(visuals.override_text_color, Some(egui::Color32::WHITE));
+    }
+}
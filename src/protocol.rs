@@ -0,0 +1,259 @@
+//! The protocol between the Lua script and the viewer, on top of the capture
+//! file the script writes. It's an extra channel rather than a replacement:
+//! subtitles still come only through the capture file, and the protocol
+//! carries what that can't, such as key presses and requests for mpv.
+//!
+//! Two append-only files of JSON lines sit next to the capture file: the
+//! script writes events (such as key presses) to `<stem>-events.jsonl` and
+//! the viewer writes requests for mpv to `<stem>-requests.jsonl`, which the
+//! script polls. Every record carries the protocol version `v`; records from
+//! a newer version are skipped. The script truncates both files when it
+//! starts and then sends `hello`.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::starred::StarredLine;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ScriptEvent {
+    /// The script (re)started.
+    Hello,
+    /// Star the line on screen.
+    Star {
+        #[serde(flatten)]
+        line: StarredLine,
+    },
+    /// Make a card of the line on screen right away.
+    SendToAnki {
+        #[serde(flatten)]
+        line: StarredLine,
+    },
+    /// The line being resynced is spoken now, `time` seconds into `path`.
+    SyncMark {
+        time: f64,
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// Unmask the lines held back by listening-first mode.
+    Reveal,
+}
+
+/// Something for the script to do in mpv.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    ShowText { text: String, duration_ms: u32 },
+    SetPause { paused: bool },
+    /// Jump to this many seconds into the file that's playing.
+    Seek { time: f64 },
+}
+
+#[derive(Deserialize)]
+struct EventRecord {
+    v: u32,
+    #[serde(flatten)]
+    event: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct RequestRecord<'a> {
+    v: u32,
+    #[serde(flatten)]
+    request: &'a Request,
+}
+
+fn sibling_path(output_file: &str, suffix: &str) -> String {
+    format!("{}-{}", output_file.strip_suffix(".json").unwrap_or(output_file), suffix)
+}
+
+/// `/tmp/mpv-subtitles.json` → `/tmp/mpv-subtitles-events.jsonl`
+pub fn events_path(output_file: &str) -> String {
+    sibling_path(output_file, "events.jsonl")
+}
+
+/// `/tmp/mpv-subtitles.json` → `/tmp/mpv-subtitles-requests.jsonl`
+pub fn requests_path(output_file: &str) -> String {
+    sibling_path(output_file, "requests.jsonl")
+}
+
+/// Parse complete lines, skipping records from a newer protocol and events
+/// we don't know.
+fn parse_events(content: &str) -> Vec<ScriptEvent> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let record: EventRecord = serde_json::from_str(line)
+                .map_err(|e| eprintln!("Warning: Ignoring event from mpv: {}", e))
+                .ok()?;
+            if record.v > PROTOCOL_VERSION {
+                eprintln!("Warning: Ignoring event from a newer script (protocol {})", record.v);
+                return None;
+            }
+            serde_json::from_value(record.event)
+                .map_err(|e| eprintln!("Warning: Ignoring event from mpv: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Follows the events file from where it ended when the viewer started, so
+/// key presses from earlier runs aren't replayed.
+pub struct EventReader {
+    path: String,
+    offset: u64,
+    /// Whether the script has said hello since its file was last truncated.
+    pub script_running: bool,
+}
+
+impl EventReader {
+    pub fn new(path: String) -> Self {
+        let mut reader = Self {
+            path,
+            offset: 0,
+            script_running: false,
+        };
+        // Only the greeting matters from before we started
+        reader.read_new();
+        reader
+    }
+
+    /// Events appended since the last call. A truncated file means the script
+    /// restarted, so reading starts over from the top.
+    pub fn read_new(&mut self) -> Vec<ScriptEvent> {
+        let Ok(mut file) = std::fs::File::open(&self.path) else {
+            return Vec::new();
+        };
+        let len = file.metadata().map_or(0, |m| m.len());
+        if len < self.offset {
+            self.offset = 0;
+            self.script_running = false;
+        }
+        let mut content = String::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err() || file.read_to_string(&mut content).is_err() {
+            return Vec::new();
+        }
+        // Leave a half-written last line for next time
+        let complete = content.rfind('\n').map_or(0, |i| i + 1);
+        self.offset += complete as u64;
+        let events = parse_events(&content[..complete]);
+        if events.contains(&ScriptEvent::Hello) {
+            self.script_running = true;
+        }
+        events.into_iter().filter(|event| *event != ScriptEvent::Hello).collect()
+    }
+}
+
+/// Append a request for the script to pick up.
+pub fn send_request(path: &str, request: &Request) -> Result<(), std::io::Error> {
+    let record = RequestRecord {
+        v: PROTOCOL_VERSION,
+        request,
+    };
+    let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_harness::MockMpv;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(events_path("/tmp/mpv-subtitles.json"), "/tmp/mpv-subtitles-events.jsonl");
+        assert_eq!(requests_path("/tmp/subs"), "/tmp/subs-requests.jsonl");
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let events = parse_events(concat!(
+            r#"{"v":1,"event":"star","text":"猫だ","start_time":1.5,"end_time":3.0,"path":"/v.mkv"}"#,
+            "\n",
+            r#"{"v":1,"event":"dance"}"#,
+            "\n",
+            r#"{"v":2,"event":"star","text":"from the future","start_time":0}"#,
+            "\n",
+            r#"{"v":1,"event":"send_to_anki","text":"犬","start_time":4}"#,
+            "\n",
+        ));
+        assert_eq!(events.len(), 2);
+        let ScriptEvent::Star { line } = &events[0] else {
+            panic!("expected a star event");
+        };
+        assert_eq!(line.text, "猫だ");
+        assert_eq!(line.end_time, Some(3.0));
+        assert!(matches!(&events[1], ScriptEvent::SendToAnki { line } if line.path.is_none()));
+    }
+
+    #[test]
+    fn test_script_says_hello() {
+        let mpv = MockMpv::start();
+        let reader = EventReader::new(events_path(&mpv.output_file()));
+        assert!(reader.script_running);
+    }
+
+    #[test]
+    fn test_key_binding_sends_event() {
+        let mpv = MockMpv::start();
+        let mut reader = EventReader::new(events_path(&mpv.output_file()));
+        mpv.set_property("sub-text", "猫がいる");
+        mpv.set_property("sub-start", 12.5);
+        mpv.set_property("path", "/videos/show.mkv");
+        mpv.press("star-line");
+        mpv.press("send-to-anki");
+        mpv.set_property("time-pos", 14.25);
+        mpv.press("sync-mark");
+        mpv.press("reveal-lines");
+
+        let events = reader.read_new();
+        assert_eq!(events.len(), 4);
+        let ScriptEvent::Star { line } = &events[0] else {
+            panic!("expected a star event");
+        };
+        assert_eq!(line.text, "猫がいる");
+        assert_eq!(line.start_time, 12.5);
+        assert_eq!(line.path.as_deref(), Some("/videos/show.mkv"));
+        assert!(matches!(events[1], ScriptEvent::SendToAnki { .. }));
+        assert_eq!(
+            events[2],
+            ScriptEvent::SyncMark { time: 14.25, path: Some("/videos/show.mkv".to_string()) }
+        );
+        assert_eq!(events[3], ScriptEvent::Reveal);
+        assert!(reader.read_new().is_empty());
+    }
+
+    #[test]
+    fn test_script_carries_out_requests() {
+        let mpv = MockMpv::start();
+        let path = requests_path(&mpv.output_file());
+        send_request(&path, &Request::ShowText { text: "Resumed".to_string(), duration_ms: 1500 }).unwrap();
+        send_request(&path, &Request::SetPause { paused: true }).unwrap();
+        send_request(&path, &Request::Seek { time: 83.5 }).unwrap();
+        mpv.tick();
+        assert_eq!(
+            mpv.commands(),
+            vec![vec!["show-text", "Resumed", "1500"], vec!["seek", "83.5", "absolute"]]
+        );
+        assert_eq!(mpv.property("pause"), Some("true".to_string()));
+
+        // Requests are only carried out once
+        mpv.tick();
+        assert_eq!(mpv.commands().len(), 2);
+    }
+
+    #[test]
+    fn test_script_skips_newer_requests() {
+        let mpv = MockMpv::start();
+        let path = requests_path(&mpv.output_file());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, r#"{{"v":2,"request":"show_text","text":"hi","duration_ms":1}}"#).unwrap();
+        mpv.tick();
+        assert!(mpv.commands().is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Checks for people checking a subtitle file rather than watching: lines
+//! shown too briefly for their length to be read, lines too long to fit, and
+//! cues whose timing is broken: overlapping the next cue of the same file,
+//! or ending before they start. Lengths count characters of the text
+//! without markup; a limit of 0 is off.
+
+use crate::ids::EntryId;
+use crate::markup;
+use crate::SubtitleEntry;
+
+/// How long a cue that ends before it starts is shown once fixed, if the
+/// next cue leaves room.
+const FIXED_DURATION: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Problem {
+    /// Characters per second over `max`.
+    TooFast { cps: f64, max: f64 },
+    /// The longest line's characters over `max`.
+    TooLong { chars: usize, max: usize },
+}
+
+impl Problem {
+    pub fn badge(&self) -> String {
+        match self {
+            Problem::TooFast { cps, .. } => format!("⚠ {:.0} cps", cps),
+            Problem::TooLong { chars, .. } => format!("⚠ {} ch", chars),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::TooFast { cps, max } => {
+                format!("{:.1} characters per second, over the {:.0} that can be read", cps, max)
+            }
+            Problem::TooLong { chars, max } => format!("A line of {} characters, over the limit of {}", chars, max),
+        }
+    }
+}
+
+/// What's wrong with `entry` for the limits. Reading speed needs an end
+/// time after the start.
+pub fn reading_problems(entry: &SubtitleEntry, max_cps: f64, max_line_chars: usize) -> Vec<Problem> {
+    let text = markup::strip(&entry.text).text;
+    let mut problems = Vec::new();
+    let duration = entry.end_time.map(|end| end - entry.start_time).filter(|&duration| duration > 0.0);
+    if let Some(duration) = duration.filter(|_| max_cps > 0.0) {
+        let chars = text.chars().filter(|&c| c != '\n').count();
+        let cps = chars as f64 / duration;
+        if cps > max_cps {
+            problems.push(Problem::TooFast { cps, max: max_cps });
+        }
+    }
+    let longest = text.lines().map(|line| line.trim().chars().count()).max().unwrap_or(0);
+    if max_line_chars > 0 && longest > max_line_chars {
+        problems.push(Problem::TooLong { chars: longest, max: max_line_chars });
+    }
+    problems
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingProblem {
+    /// Still showing `by` seconds after the cue with ID `next` starts.
+    Overlap { next: EntryId, by: f64 },
+    /// Ends at or before its start.
+    NoDuration { duration: f64 },
+}
+
+impl TimingProblem {
+    pub fn describe(&self) -> String {
+        match self {
+            TimingProblem::Overlap { by, .. } => format!("Overlaps the next cue by {:.3} s", by),
+            TimingProblem::NoDuration { duration } if *duration == 0.0 => "Ends as it starts".to_string(),
+            TimingProblem::NoDuration { duration } => format!("Ends {:.3} s before it starts", -duration),
+        }
+    }
+}
+
+/// Indices of `entries` by file, each file's in order of start time.
+fn cue_order(entries: &[SubtitleEntry]) -> Vec<Vec<usize>> {
+    let mut files: Vec<(Option<&str>, Vec<usize>)> = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match files.iter_mut().find(|(path, _)| *path == entry.path.as_deref()) {
+            Some((_, indices)) => indices.push(index),
+            None => files.push((entry.path.as_deref(), vec![index])),
+        }
+    }
+    files
+        .into_iter()
+        .map(|(_, mut indices)| {
+            indices.sort_by(|&a, &b| entries[a].start_time.total_cmp(&entries[b].start_time));
+            indices
+        })
+        .collect()
+}
+
+/// Every cue with broken timing, in order of media time per file. Cues
+/// without an end time can't be checked.
+pub fn timing_problems(entries: &[SubtitleEntry]) -> Vec<(EntryId, TimingProblem)> {
+    let mut problems = Vec::new();
+    for order in cue_order(entries) {
+        for (k, &index) in order.iter().enumerate() {
+            let entry = &entries[index];
+            let Some(end) = entry.end_time else {
+                continue;
+            };
+            if end <= entry.start_time {
+                problems.push((entry.id, TimingProblem::NoDuration { duration: end - entry.start_time }));
+            }
+            if let Some(next) = order.get(k + 1).map(|&next| &entries[next]) {
+                if end > next.start_time {
+                    problems.push((entry.id, TimingProblem::Overlap { next: next.id, by: end - next.start_time }));
+                }
+            }
+        }
+    }
+    problems
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingFix {
+    /// End each overlapping cue when the next starts.
+    Trim,
+    /// Start each overlapped cue when the one before ends, keeping its
+    /// duration, and so on down the file.
+    Shift,
+}
+
+impl TimingFix {
+    pub const ALL: [TimingFix; 2] = [TimingFix::Trim, TimingFix::Shift];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimingFix::Trim => "Trim",
+            TimingFix::Shift => "Shift",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            TimingFix::Trim => "End each overlapping cue when the next one starts",
+            TimingFix::Shift => "Move each overlapped cue to start when the one before ends",
+        }
+    }
+}
+
+/// Fix every timing problem, returning how many cues changed. Cues that end
+/// before they start are given a second, or the time until the next cue.
+/// Cues starting together can't be trimmed apart, so they're shifted.
+pub fn fix_timing(entries: &mut [SubtitleEntry], fix: TimingFix) -> usize {
+    let mut changed = std::collections::HashSet::new();
+    for order in cue_order(entries) {
+        for (k, &index) in order.iter().enumerate() {
+            let next = order.get(k + 1).copied();
+            let start = entries[index].start_time;
+            let Some(mut end) = entries[index].end_time else {
+                continue;
+            };
+            if end <= start {
+                end = start + FIXED_DURATION;
+                if let Some(next_start) = next.map(|next| entries[next].start_time).filter(|&next| next > start) {
+                    end = end.min(next_start);
+                }
+                entries[index].end_time = Some(end);
+                changed.insert(index);
+            }
+            let Some(next) = next.filter(|&next| end > entries[next].start_time) else {
+                continue;
+            };
+            let next_start = entries[next].start_time;
+            if fix == TimingFix::Trim && next_start > start {
+                entries[index].end_time = Some(next_start);
+                changed.insert(index);
+            } else {
+                let by = end - next_start;
+                entries[next].start_time += by;
+                entries[next].end_time = entries[next].end_time.map(|end| end + by);
+                changed.insert(next);
+            }
+        }
+    }
+    changed.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(text: &str, start_time: f64, end_time: Option<f64>) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            end_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reading_problems() {
+        // 20 characters in one second
+        let fast = cue("<i>abcdefghij</i>\nabcdefghij", 10.0, Some(11.0));
+        assert_eq!(reading_problems(&fast, 17.0, 42), vec![Problem::TooFast { cps: 20.0, max: 17.0 }]);
+        assert!(reading_problems(&fast, 0.0, 42).is_empty());
+        assert_eq!(reading_problems(&fast, 0.0, 8), vec![Problem::TooLong { chars: 10, max: 8 }]);
+
+        assert!(reading_problems(&cue("abcdefghij", 10.0, Some(12.0)), 17.0, 42).is_empty());
+        assert!(reading_problems(&cue("abcdefghij", 10.0, None), 1.0, 42).is_empty());
+        assert!(reading_problems(&cue("abcdefghij", 10.0, Some(10.0)), 1.0, 42).is_empty());
+    }
+
+    #[test]
+    fn test_timing_problems_and_fixes() {
+        let mut entries = vec![
+            cue("A", 0.0, Some(3.0)),
+            cue("B", 2.0, Some(4.0)),
+            cue("C", 5.0, Some(4.5)),
+            cue("D", 6.0, None),
+            SubtitleEntry {
+                path: Some("other.mkv".to_string()),
+                ..cue("E", 1.0, Some(2.0))
+            },
+        ];
+        for (id, entry) in entries.iter_mut().enumerate() {
+            entry.id = id as EntryId;
+        }
+        assert_eq!(
+            timing_problems(&entries),
+            vec![
+                (0, TimingProblem::Overlap { next: 1, by: 1.0 }),
+                (2, TimingProblem::NoDuration { duration: -0.5 }),
+            ]
+        );
+
+        let mut trimmed = entries.clone();
+        assert_eq!(fix_timing(&mut trimmed, TimingFix::Trim), 2);
+        assert_eq!(trimmed[0].end_time, Some(2.0));
+        assert_eq!(trimmed[2].end_time, Some(6.0));
+        assert!(timing_problems(&trimmed).is_empty());
+
+        // B moves to A's end and pushes C along
+        let mut shifted = entries.clone();
+        shifted[2].start_time = 4.5;
+        shifted[2].end_time = Some(6.0);
+        assert_eq!(fix_timing(&mut shifted, TimingFix::Shift), 3);
+        assert_eq!((shifted[1].start_time, shifted[1].end_time), (3.0, Some(5.0)));
+        assert_eq!((shifted[2].start_time, shifted[2].end_time), (5.0, Some(6.5)));
+        assert_eq!(shifted[3].start_time, 6.5);
+        assert_eq!(shifted[4].start_time, 1.0);
+        assert!(timing_problems(&shifted).is_empty());
+
+        let mut together = vec![cue("A", 1.0, Some(2.0)), cue("B", 1.0, Some(2.0))];
+        fix_timing(&mut together, TimingFix::Trim);
+        assert_eq!((together[1].start_time, together[1].end_time), (2.0, Some(3.0)));
+    }
+}
@@ -0,0 +1,50 @@
+//! QR codes for the presenter view, so a class can open a link from their
+//! seats instead of copying it off the projector.
+
+use eframe::egui;
+use qrcodegen::{QrCode, QrCodeEcc};
+
+/// Light modules the standard asks for around the code.
+const QUIET_ZONE: usize = 4;
+
+/// The modules of the code for `text` row by row, `true` for dark, or
+/// `None` if it's too long to encode.
+pub fn modules(text: &str) -> Option<Vec<Vec<bool>>> {
+    let code = QrCode::encode_text(text, QrCodeEcc::Medium).ok()?;
+    let size = code.size();
+    Some((0..size).map(|y| (0..size).map(|x| code.get_module(x, y)).collect()).collect())
+}
+
+/// Paint the code for `text`, `side` points square, black on white whatever
+/// the theme so phones can read it.
+pub fn show(ui: &mut egui::Ui, text: &str, side: f32) {
+    let Some(modules) = modules(text) else {
+        return;
+    };
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    let cell = side / (modules.len() + 2 * QUIET_ZONE) as f32;
+    for (y, row) in modules.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, &dark)| dark) {
+            let min = rect.min + egui::vec2((x + QUIET_ZONE) as f32, (y + QUIET_ZONE) as f32) * cell;
+            painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(cell, cell)), 0.0, egui::Color32::BLACK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modules() {
+        let modules = modules("https://example.com/class").unwrap();
+        // Version 2 at medium error correction
+        assert_eq!(modules.len(), 25);
+        assert!(modules.iter().all(|row| row.len() == 25));
+        // The top left finder pattern and the light separator beside it
+        assert!(modules[0][0] && modules[0][6] && !modules[0][7]);
+        assert_eq!(super::modules(&"x".repeat(3000)), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! How fast lines are coming in, for the status bar. A steady rate says the
+//! capture pipeline is working; a long silence during playback says it isn't.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Lines are counted over this much recent time.
+const WINDOW: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Default)]
+pub struct LineRate {
+    arrivals: VecDeque<Instant>,
+    last: Option<Instant>,
+}
+
+impl LineRate {
+    pub fn record(&mut self, now: Instant, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.arrivals.extend(std::iter::repeat_n(now, count));
+        self.last = Some(now);
+        self.expire(now);
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self.arrivals.front().is_some_and(|&arrival| now.duration_since(arrival) > WINDOW) {
+            self.arrivals.pop_front();
+        }
+    }
+
+    /// Lines per minute over the last couple of minutes.
+    pub fn per_minute(&mut self, now: Instant) -> f64 {
+        self.expire(now);
+        self.arrivals.len() as f64 * 60.0 / WINDOW.as_secs_f64()
+    }
+
+    /// Time since the latest line, if any came in.
+    pub fn since_last(&self, now: Instant) -> Option<Duration> {
+        self.last.map(|last| now.duration_since(last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_counts_recent_lines() {
+        let start = Instant::now();
+        let mut rate = LineRate::default();
+        assert_eq!(rate.per_minute(start), 0.0);
+        assert_eq!(rate.since_last(start), None);
+        rate.record(start, 10);
+        rate.record(start + Duration::from_secs(60), 10);
+        assert_eq!(rate.per_minute(start + Duration::from_secs(60)), 10.0);
+        // The first batch has left the window
+        assert_eq!(rate.per_minute(start + Duration::from_secs(150)), 5.0);
+        assert_eq!(rate.since_last(start + Duration::from_secs(150)), Some(Duration::from_secs(90)));
+    }
+}
@@ -0,0 +1,120 @@
+//! The raw stream: every line read from the capture file, before the prefix
+//! and repeat filters, kept as JSON lines in one file per day under
+//! `~/.local/share/scriptview/raw/`. The capture file only holds the last
+//! few lines, so this is what a day can be reprocessed from after changing
+//! the filters.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::crypt::Key;
+use crate::SubtitleEntry;
+
+pub fn raw_dir() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/raw", home_dir)
+}
+
+/// "2024-05-01" for the local day of `timestamp`.
+pub fn day_of(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// `<dir>/YYYY-MM-DD.jsonl` for the local day of `timestamp`.
+fn day_path(dir: &str, timestamp: i64) -> String {
+    format!("{}/{}.jsonl", dir, day_of(timestamp))
+}
+
+/// Append lines to the files of the days they arrived on, sealed if there's
+/// a key.
+pub fn append(dir: &str, entries: &[SubtitleEntry], key: Option<&Key>) -> Result<(), std::io::Error> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir)?;
+    for day in entries.chunk_by(|a, b| day_path(dir, a.timestamp) == day_path(dir, b.timestamp)) {
+        let mut content = String::new();
+        for entry in day {
+            let json = serde_json::to_string(entry)?;
+            content.push_str(&key.map(|key| key.seal_line(&json)).unwrap_or(json));
+            content.push('\n');
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(day_path(dir, day[0].timestamp))?
+            .write_all(content.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// The day files in `dir`, newest first.
+pub fn list_days(dir: &str) -> Vec<String> {
+    let mut days: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    days.sort();
+    days.reverse();
+    days
+}
+
+/// "2024-05-01" for a day file.
+pub fn day_label(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// The lines of a day file, skipping any that don't parse (such as one cut
+/// short by a crash) or are sealed without the key.
+pub fn read(path: &str, key: Option<&Key>) -> Result<Vec<SubtitleEntry>, std::io::Error> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| crate::crypt::open_line(key, line))
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read() {
+        let dir = std::env::temp_dir().join(format!("scriptview-raw-{}", std::process::id()));
+        let dir = dir.to_string_lossy().into_owned();
+        let line = |text: &str, timestamp: i64| SubtitleEntry {
+            text: text.to_string(),
+            timestamp,
+            ..Default::default()
+        };
+        let noon = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .and_then(|t| t.and_local_timezone(chrono::Local).single())
+            .unwrap()
+            .timestamp();
+        append(&dir, &[line("猫", noon), line("猫が", noon + 1)], None).unwrap();
+        append(&dir, &[line("猫が歩く", noon + 2), line("昨日", noon - 3 * 86400)], None).unwrap();
+
+        let days = list_days(&dir);
+        assert_eq!(days.len(), 2);
+        let texts: Vec<String> = read(&days[0], None).unwrap().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["猫", "猫が", "猫が歩く"]);
+        assert_eq!(read(&days[1], None).unwrap().len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! Following a capture file on another machine, for when mpv plays on an
+//! HTPC and the reading happens on a laptop. The file is fetched over SSH
+//! every few seconds (so the usual keys and `~/.ssh/config` apply) into a
+//! local mirror, which the viewer then watches like any capture file.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// A file on another machine: `sftp://[user@]host[:port]/path`, the same
+/// with `scp://`, or scp's `[user@]host:path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteFile {
+    /// `user@host` or `host`, as ssh takes it.
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl RemoteFile {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let url = url.trim();
+        if let Some(rest) = url.strip_prefix("sftp://").or_else(|| url.strip_prefix("scp://")) {
+            let (authority, path) = rest.split_once('/').ok_or("The URL has no file path")?;
+            // sftp://host/~/file is relative to the home directory
+            let path = match path.strip_prefix("~/") {
+                Some(home) => format!("~/{}", home),
+                None => format!("/{}", path),
+            };
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port.parse().map_err(|_| format!("Bad port: {}", port))?)),
+                None => (authority, None),
+            };
+            return Self::new(host, port, path);
+        }
+        match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') => Self::new(host, None, path.to_string()),
+            _ => Err(format!("Not a remote file: {} (expected sftp://host/path or host:path)", url)),
+        }
+    }
+
+    fn new(host: &str, port: Option<u16>, path: String) -> Result<Self, String> {
+        if host.is_empty() || host.ends_with('@') {
+            return Err("The URL has no host".to_string());
+        }
+        if path.is_empty() || path.ends_with('/') {
+            return Err("The URL has no file name".to_string());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// `path` quoted for the remote shell, leaving a leading `~/` for it to
+    /// expand.
+    fn quoted_path(&self) -> String {
+        let (home, path) = match self.path.strip_prefix("~/") {
+            Some(path) => ("~/", path),
+            None => ("", self.path.as_str()),
+        };
+        format!("{}'{}'", home, path.replace('\'', "'\\''"))
+    }
+
+    fn ssh_args(&self) -> Vec<String> {
+        let mut args = vec![
+            // Never stop to ask for a password; there's no terminal to ask in
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+            "-o".to_string(),
+            "ConnectTimeout=10".to_string(),
+        ];
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        args.extend([self.host.clone(), format!("cat -- {}", self.quoted_path())]);
+        args
+    }
+
+    /// The local copy, named after the remote file so that switching between
+    /// machines doesn't mix their captures.
+    pub fn mirror_path(&self) -> String {
+        let id = format!("{}:{}:{}", self.host, self.port.unwrap_or(22), self.path);
+        let name = format!("scriptview-remote-{:x}.json", md5::compute(id));
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+
+    fn fetch(&self) -> Result<Vec<u8>, String> {
+        let output = Command::new("ssh")
+            .args(self.ssh_args())
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Could not run ssh: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().last().unwrap_or("ssh failed").trim();
+            return Err(format!("{}: {}", self.host, reason));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Fetches a remote file into its mirror until dropped.
+pub struct Poller {
+    pub remote: RemoteFile,
+    /// Dropping this stops the thread.
+    _stop: Sender<()>,
+    /// Why fetching failed, once per run of failures.
+    pub errors: Receiver<String>,
+}
+
+impl Poller {
+    pub fn spawn(remote: RemoteFile, interval: Duration) -> Self {
+        let (stop_tx, stop) = channel::<()>();
+        let (errors_tx, errors) = channel();
+        let thread_remote = remote.clone();
+        let mirror = PathBuf::from(remote.mirror_path());
+        // Something to watch until the first fetch
+        if !mirror.exists() {
+            let _ = std::fs::write(&mirror, "[]");
+        }
+        std::thread::spawn(move || {
+            let mut last = std::fs::read(&mirror).ok();
+            let mut failing = false;
+            loop {
+                match thread_remote.fetch() {
+                    // Only write when something changed, so the watcher only
+                    // reloads for new lines
+                    Ok(content) if last.as_ref() != Some(&content) => match replace(&mirror, &content) {
+                        Ok(()) => {
+                            last = Some(content);
+                            failing = false;
+                        }
+                        Err(e) => {
+                            let _ = errors_tx.send(format!("Could not write {}: {}", mirror.display(), e));
+                            failing = true;
+                        }
+                    },
+                    Ok(_) => failing = false,
+                    Err(e) if !failing => {
+                        let _ = errors_tx.send(e);
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+                match stop.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+            }
+        });
+        Self {
+            remote,
+            _stop: stop_tx,
+            errors,
+        }
+    }
+}
+
+/// Write `content` to a file beside `path` and rename it over `path`, so
+/// the viewer never reads a half-written mirror.
+fn replace(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.part", name));
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace() {
+        let dir = std::env::temp_dir().join(format!("scriptview-remote-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mirror = dir.join("mirror.json");
+        std::fs::write(&mirror, "[]").unwrap();
+        replace(&mirror, "[{\"text\": \"猫\"}]".as_bytes()).unwrap();
+        assert_eq!(std::fs::read_to_string(&mirror).unwrap(), "[{\"text\": \"猫\"}]");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse() {
+        let remote = RemoteFile::parse("sftp://me@htpc:2222/tmp/mpv-subtitles.json").unwrap();
+        assert_eq!(remote.host, "me@htpc");
+        assert_eq!(remote.port, Some(2222));
+        assert_eq!(remote.path, "/tmp/mpv-subtitles.json");
+        let remote = RemoteFile::parse("scp://htpc/~/subs/it's.json").unwrap();
+        assert_eq!(remote.path, "~/subs/it's.json");
+        assert_eq!(remote.quoted_path(), "~/'subs/it'\\''s.json'");
+        let remote = RemoteFile::parse("htpc:/tmp/mpv-subtitles.json").unwrap();
+        assert_eq!((remote.host.as_str(), remote.port), ("htpc", None));
+        assert!(RemoteFile::parse("/tmp/mpv-subtitles.json").is_err());
+        assert!(RemoteFile::parse("sftp://htpc/tmp/").is_err());
+        assert!(RemoteFile::parse("sftp://htpc:ssh/tmp/a.json").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Fixing the timing of a session whose subtitles are out of sync with the
+//! audio. The user picks a line, plays the video and presses the script's
+//! sync key when the line is actually spoken; each such anchor pairs the
+//! line's time with the true one. One anchor gives an offset, two also
+//! correct a steady drift (subtitles timed for a different frame rate).
+
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anchor {
+    /// When the line starts according to the subtitles.
+    pub line: f64,
+    /// When it's spoken.
+    pub actual: f64,
+}
+
+/// `time * scale + offset` turns subtitle times into true ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correction {
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl Correction {
+    /// The correction the anchors call for: an offset from the first, and
+    /// drift as well once there's a second one far enough from it.
+    pub fn from_anchors(anchors: &[Anchor]) -> Option<Correction> {
+        let first = anchors.first()?;
+        let offset_only = Correction {
+            scale: 1.0,
+            offset: first.actual - first.line,
+        };
+        let Some(second) = anchors.get(1).filter(|second| (second.line - first.line).abs() >= 1.0) else {
+            return Some(offset_only);
+        };
+        let scale = (second.actual - first.actual) / (second.line - first.line);
+        // A wild scale means a mistimed key press rather than drift
+        if !(0.8..=1.25).contains(&scale) {
+            return Some(offset_only);
+        }
+        Some(Correction {
+            scale,
+            offset: first.actual - scale * first.line,
+        })
+    }
+
+    pub fn time(&self, time: f64) -> f64 {
+        (time * self.scale + self.offset).max(0.0)
+    }
+
+    /// The subtitle time that `time` was corrected from.
+    pub fn undo(&self, time: f64) -> f64 {
+        (time - self.offset) / self.scale
+    }
+
+    /// Correct the times of the entries from `media`.
+    pub fn apply(&self, entries: &mut [SubtitleEntry], media: &Option<String>) {
+        for entry in entries.iter_mut().filter(|entry| entry.path == *media) {
+            entry.start_time = self.time(entry.start_time);
+            entry.end_time = entry.end_time.map(|end| self.time(end));
+        }
+    }
+
+    /// `+1.25 s`, with the drift as a percentage if there is any.
+    pub fn describe(&self) -> String {
+        let mut text = format!("{:+.2} s", self.offset);
+        if (self.scale - 1.0).abs() > 1e-6 {
+            text.push_str(&format!(", drift {:+.3}%", (self.scale - 1.0) * 100.0));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_anchor_is_an_offset() {
+        let correction = Correction::from_anchors(&[Anchor { line: 10.0, actual: 11.5 }]).unwrap();
+        assert_eq!(correction, Correction { scale: 1.0, offset: 1.5 });
+        assert_eq!(correction.time(100.0), 101.5);
+        assert_eq!(correction.undo(101.5), 100.0);
+        assert_eq!(correction.describe(), "+1.50 s");
+        assert_eq!(Correction::from_anchors(&[]), None);
+    }
+
+    #[test]
+    fn test_two_anchors_correct_drift() {
+        // Timed for 25 fps, played at 23.976
+        let scale = 25.0 / 23.976;
+        let anchors = [
+            Anchor { line: 60.0, actual: 60.0 * scale + 2.0 },
+            Anchor { line: 1200.0, actual: 1200.0 * scale + 2.0 },
+        ];
+        let correction = Correction::from_anchors(&anchors).unwrap();
+        assert!((correction.scale - scale).abs() < 1e-9);
+        assert!((correction.time(600.0) - (600.0 * scale + 2.0)).abs() < 1e-6);
+
+        let mut entries = vec![
+            SubtitleEntry {
+                start_time: 600.0,
+                end_time: Some(602.0),
+                path: Some("/videos/show.mkv".to_string()),
+                ..Default::default()
+            },
+            SubtitleEntry {
+                start_time: 600.0,
+                ..Default::default()
+            },
+        ];
+        correction.apply(&mut entries, &Some("/videos/show.mkv".to_string()));
+        assert!((entries[0].start_time - correction.time(600.0)).abs() < 1e-9);
+        assert_eq!(entries[0].end_time, Some(correction.time(602.0)));
+        assert_eq!(entries[1].start_time, 600.0);
+    }
+
+    #[test]
+    fn test_implausible_drift_is_ignored() {
+        let anchors = [Anchor { line: 10.0, actual: 12.0 }, Anchor { line: 20.0, actual: 40.0 }];
+        assert_eq!(Correction::from_anchors(&anchors).unwrap().scale, 1.0);
+    }
+}
@@ -0,0 +1,143 @@
+//! A lightweight review queue for mined sentences, for people who don't use
+//! Anki. Sentences come back at growing intervals while they are remembered
+//! and soon after when they are not.
+
+use serde::{Deserialize, Serialize};
+
+const DAY: i64 = 24 * 60 * 60;
+/// How soon a forgotten sentence comes back.
+const RELEARN_DELAY: i64 = 10 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinedSentence {
+    pub text: String,
+    pub start_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub added: i64,
+    /// Unix time when the sentence is next shown.
+    pub due: i64,
+    /// Current interval in days; 0 while (re)learning.
+    pub interval_days: u32,
+    pub reviews: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReviewQueue {
+    pub items: Vec<MinedSentence>,
+}
+
+pub fn review_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/review.json", home_dir)
+}
+
+impl ReviewQueue {
+    pub fn load() -> Self {
+        std::fs::read_to_string(review_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let path = review_path();
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Queue a sentence for review, due right away. Returns false if it is
+    /// already queued.
+    pub fn add(&mut self, text: &str, start_time: f64, path: Option<String>, now: i64) -> bool {
+        if self.items.iter().any(|item| item.text == text) {
+            return false;
+        }
+        self.items.push(MinedSentence {
+            text: text.to_string(),
+            start_time,
+            path,
+            added: now,
+            due: now,
+            interval_days: 0,
+            reviews: 0,
+        });
+        true
+    }
+
+    pub fn due_count(&self, now: i64) -> usize {
+        self.items.iter().filter(|item| item.due <= now).count()
+    }
+
+    /// The most overdue sentence.
+    pub fn next_due(&self, now: i64) -> Option<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.due <= now)
+            .min_by_key(|(_, item)| item.due)
+            .map(|(i, _)| i)
+    }
+
+    /// Reschedule after a review: remembered sentences double their interval
+    /// (starting at one day), forgotten ones start over.
+    pub fn answer(&mut self, index: usize, remembered: bool, now: i64) {
+        let item = &mut self.items[index];
+        item.reviews += 1;
+        if remembered {
+            item.interval_days = (item.interval_days * 2).max(1);
+            item.due = now + item.interval_days as i64 * DAY;
+        } else {
+            item.interval_days = 0;
+            item.due = now + RELEARN_DELAY;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_skips_duplicates() {
+        let mut queue = ReviewQueue::default();
+        assert!(queue.add("猫が好き", 1.0, None, 0));
+        assert!(!queue.add("猫が好き", 5.0, None, 10));
+        assert_eq!(queue.items.len(), 1);
+        assert_eq!(queue.due_count(0), 1);
+    }
+
+    #[test]
+    fn test_remembered_intervals_grow() {
+        let mut queue = ReviewQueue::default();
+        queue.add("a", 0.0, None, 0);
+        queue.answer(0, true, 0);
+        assert_eq!(queue.items[0].interval_days, 1);
+        assert_eq!(queue.items[0].due, DAY);
+        queue.answer(0, true, DAY);
+        assert_eq!(queue.items[0].interval_days, 2);
+        assert_eq!(queue.items[0].due, 3 * DAY);
+        assert_eq!(queue.due_count(2 * DAY), 0);
+    }
+
+    #[test]
+    fn test_forgotten_comes_back_soon() {
+        let mut queue = ReviewQueue::default();
+        queue.add("a", 0.0, None, 0);
+        queue.answer(0, true, 0);
+        queue.answer(0, false, DAY);
+        assert_eq!(queue.items[0].interval_days, 0);
+        assert_eq!(queue.items[0].due, DAY + RELEARN_DELAY);
+        assert_eq!(queue.items[0].reviews, 2);
+    }
+
+    #[test]
+    fn test_next_due_is_most_overdue() {
+        let mut queue = ReviewQueue::default();
+        queue.add("later", 0.0, None, 50);
+        queue.add("earlier", 0.0, None, 10);
+        assert_eq!(queue.next_due(100), Some(1));
+        assert_eq!(queue.next_due(5), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! The tokio runtime the network features share: the mpv IPC client and the
+//! browser and JSON lines servers run as tasks on it rather than on threads
+//! of their own.
+//!
+//! The bridge to the egui thread is plain channels. Tasks report to the GUI
+//! through a `std::sync::mpsc::Sender`, whose `send` never blocks, and the
+//! GUI drains the receiver with `try_recv` each frame; the GUI talks to a
+//! task through a `tokio::sync::mpsc::UnboundedSender`, which it can send
+//! on without awaiting. A task that should end with its owner waits on a
+//! `Stopped` from `stop_signal`, which fires when the `StopSignal` is dropped.
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("scriptview-net")
+            .enable_all()
+            .build()
+            .expect("could not start the network runtime")
+    })
+}
+
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    runtime().spawn(future)
+}
+
+/// How long a server's accept loop gets to end, and let go of its port,
+/// once stopped.
+pub const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Wait from outside the runtime for `task` to end, at most `timeout`.
+/// Returns whether it did.
+pub fn join_within<T>(task: JoinHandle<T>, timeout: std::time::Duration) -> bool {
+    runtime().block_on(async { tokio::time::timeout(timeout, task).await.is_ok() })
+}
+
+/// A listener on `port` on localhost (0 picks a free port). Binding happens
+/// right away, so a port in use is reported to the caller.
+pub fn bind(port: u16) -> Result<tokio::net::TcpListener, std::io::Error> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    let _guard = runtime().enter();
+    tokio::net::TcpListener::from_std(listener)
+}
+
+/// A listener on `port` on every interface, for other machines on the
+/// network to reach.
+pub fn bind_lan(port: u16) -> Result<tokio::net::TcpListener, std::io::Error> {
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    let _guard = runtime().enter();
+    tokio::net::TcpListener::from_std(listener)
+}
+
+/// Held by whatever owns the tasks; dropping it stops them.
+pub struct StopSignal {
+    _tx: watch::Sender<()>,
+}
+
+#[derive(Clone)]
+pub struct Stopped(watch::Receiver<()>);
+
+impl Stopped {
+    /// Wait until the `StopSignal` is dropped.
+    pub async fn wait(&mut self) {
+        while self.0.changed().await.is_ok() {}
+    }
+}
+
+pub fn stop_signal() -> (StopSignal, Stopped) {
+    let (tx, rx) = watch::channel(());
+    (StopSignal { _tx: tx }, Stopped(rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_dropping_the_signal_stops_tasks() {
+        let (signal, mut stopped) = stop_signal();
+        let task = spawn(async move { stopped.wait().await });
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!task.is_finished());
+        drop(signal);
+        runtime().block_on(task).unwrap();
+    }
+}
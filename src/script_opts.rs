@@ -0,0 +1,164 @@
+//! The `subtitle-monitor.conf` script-opts file shared with the Lua script.
+//!
+//! mpv reads it when the script starts; the viewer reads it to find where the
+//! script writes, and edits it when retention is changed from the GUI.
+
+pub const DEFAULT_OUTPUT_FILE: &str = "/tmp/mpv-subtitles.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    /// Start over on each new media file and on seeks.
+    Truncate,
+    /// Keep history across files, rotating the file when the date changes.
+    Daily,
+    /// Keep history across files and days.
+    Forever,
+}
+
+impl Retention {
+    pub const ALL: [Retention; 3] = [Retention::Truncate, Retention::Daily, Retention::Forever];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Retention::Truncate => "truncate",
+            Retention::Daily => "daily",
+            Retention::Forever => "forever",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Retention::Truncate => "Clear on each new file",
+            Retention::Daily => "Rotate daily",
+            Retention::Forever => "Keep forever",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptOpts {
+    pub output_file: String,
+    pub retention: Retention,
+}
+
+impl Default for ScriptOpts {
+    fn default() -> Self {
+        Self {
+            output_file: DEFAULT_OUTPUT_FILE.to_string(),
+            retention: Retention::Truncate,
+        }
+    }
+}
+
+pub fn script_opts_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.config/mpv/script-opts/subtitle-monitor.conf", home_dir)
+}
+
+/// Parse `key=value` lines the way mpv's `read_options` does, ignoring
+/// comments and keys we don't know about.
+fn parse_script_opts(content: &str) -> ScriptOpts {
+    let mut opts = ScriptOpts::default();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "output_file" => opts.output_file = value.trim().to_string(),
+            "retention" => {
+                if let Some(retention) = Retention::parse(value.trim()) {
+                    opts.retention = retention;
+                }
+            }
+            _ => {}
+        }
+    }
+    opts
+}
+
+/// Replace (or append) `key=value` in `content`, keeping every other line.
+fn set_option(content: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let is_key = line
+                .split_once('=')
+                .is_some_and(|(k, _)| !line.trim_start().starts_with('#') && k.trim() == key);
+            if is_key {
+                found = true;
+                format!("{}={}", key, value)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{}={}", key, value));
+    }
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+impl ScriptOpts {
+    pub fn load() -> Self {
+        std::fs::read_to_string(script_opts_path())
+            .map(|content| parse_script_opts(&content))
+            .unwrap_or_default()
+    }
+
+    pub fn save_retention(retention: Retention) -> Result<(), std::io::Error> {
+        let path = script_opts_path();
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, set_option(&content, "retention", retention.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults_when_empty() {
+        assert_eq!(parse_script_opts(""), ScriptOpts::default());
+    }
+
+    #[test]
+    fn test_parse_values_and_comments() {
+        let opts = parse_script_opts(
+            "# comment\noutput_file=/home/me/subs.json\nretention = daily\nunknown=1\n",
+        );
+        assert_eq!(opts.output_file, "/home/me/subs.json");
+        assert_eq!(opts.retention, Retention::Daily);
+    }
+
+    #[test]
+    fn test_parse_ignores_unknown_retention() {
+        let opts = parse_script_opts("retention=sometimes\n");
+        assert_eq!(opts.retention, Retention::Truncate);
+    }
+
+    #[test]
+    fn test_set_option_replaces_existing_line() {
+        let updated = set_option("# keep me\nretention=truncate\noutput_file=/x.json\n", "retention", "forever");
+        assert_eq!(updated, "# keep me\nretention=forever\noutput_file=/x.json\n");
+    }
+
+    #[test]
+    fn test_set_option_appends_missing_key() {
+        let updated = set_option("#retention=daily\n", "retention", "daily");
+        assert_eq!(updated, "#retention=daily\nretention=daily\n");
+    }
+}
@@ -0,0 +1,60 @@
+//! Looking a word or a line up on a website: each site is a URL template
+//! with `{text}` where the search goes, opened in the browser.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::percent_encode;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSite {
+    pub name: String,
+    pub url: String,
+}
+
+impl Default for SearchSite {
+    fn default() -> Self {
+        Self {
+            name: "Search".to_string(),
+            url: "https://example.com/search?q={text}".to_string(),
+        }
+    }
+}
+
+fn site(name: &str, url: &str) -> SearchSite {
+    SearchSite {
+        name: name.to_string(),
+        url: url.to_string(),
+    }
+}
+
+/// Jisho, Wiktionary, DeepL and Google.
+pub fn defaults() -> Vec<SearchSite> {
+    vec![
+        site("Jisho", "https://jisho.org/search/{text}"),
+        site("Wiktionary", "https://en.wiktionary.org/w/index.php?search={text}"),
+        site("DeepL", "https://www.deepl.com/translator#ja/en/{text}"),
+        site("Google", "https://www.google.com/search?q={text}"),
+    ]
+}
+
+impl SearchSite {
+    /// The URL searching for `text`, with line breaks as spaces.
+    pub fn url(&self, text: &str) -> String {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.url.replace("{text}", &percent_encode(&text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let sites = defaults();
+        assert_eq!(sites[0].url("猫"), "https://jisho.org/search/%E7%8C%AB");
+        assert_eq!(sites[3].url(" a b\nc "), "https://www.google.com/search?q=a%20b%20c");
+        assert_eq!(site("Plain", "https://example.com/").url("猫"), "https://example.com/");
+    }
+}
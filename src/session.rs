@@ -0,0 +1,330 @@
+//! Past sessions: capture files rotated aside by the Lua script's daily retention.
+//! With the history encrypted they're sealed as `<name>-YYYY-MM-DD.json.enc`.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::crypt::Key;
+use crate::stats::media_name;
+use crate::SubtitleEntry;
+
+/// Lines starting closer together than this (in seconds) are the same cue.
+const SAME_CUE: f64 = 0.05;
+
+/// A run of consecutive entries from the same media file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaGroup {
+    pub path: Option<String>,
+    pub title: String,
+    pub range: Range<usize>,
+}
+
+pub fn read_entries(path: &str) -> Option<Vec<SubtitleEntry>> {
+    try_read_entries(path).ok().flatten()
+}
+
+/// The entries of a capture file or archive, opening it with `key` if it's
+/// sealed.
+pub fn read_archive(path: &str, key: Option<&Key>) -> Option<Vec<SubtitleEntry>> {
+    if !path.ends_with(".enc") {
+        return read_entries(path);
+    }
+    let plain = key?.open_file(&std::fs::read(path).ok()?)?;
+    serde_json::from_slice(&plain).ok()
+}
+
+/// Seal the archives of `capture_file` that aren't yet, returning how many
+/// there were. The live capture file is left as it is, since the player
+/// writes to it.
+pub fn seal_archives(capture_file: &str, key: &Key) -> Result<usize, std::io::Error> {
+    let mut sealed = 0;
+    for path in list_sessions(capture_file).iter().skip(1).filter(|path| !path.ends_with(".enc")) {
+        std::fs::write(format!("{}.enc", path), key.seal_file(&std::fs::read(path)?))?;
+        std::fs::remove_file(path)?;
+        sealed += 1;
+    }
+    Ok(sealed)
+}
+
+/// Like `read_entries`, but telling a file that's missing (or caught empty
+/// halfway through being rewritten) apart from one that doesn't parse.
+pub fn try_read_entries(path: &str) -> Result<Option<Vec<SubtitleEntry>>, String> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+}
+
+/// What tells one file at `path` from another put there in its place: the
+/// device and inode, where there are such things.
+pub fn file_identity(path: &str) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        // Watches there follow the name rather than the file
+        let _ = path;
+        None
+    }
+}
+
+/// Move a capture file that won't parse aside as `<file>.corrupt` (or
+/// `<file>.2.corrupt`, … if that's taken) and return where it went.
+pub fn quarantine(path: &str) -> Result<PathBuf, std::io::Error> {
+    let target = (1..)
+        .map(|n| match n {
+            1 => PathBuf::from(format!("{}.corrupt", path)),
+            n => PathBuf::from(format!("{}.{}.corrupt", path, n)),
+        })
+        .find(|target| !target.exists())
+        .unwrap();
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// The live capture file followed by its `<name>-YYYY-MM-DD.json` archives,
+/// newest first.
+pub fn list_sessions(capture_file: &str) -> Vec<String> {
+    let capture = Path::new(capture_file);
+    let stem = capture
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = capture.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut archives: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| archive_date(&stem, path).is_some())
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    archives.sort();
+    archives.reverse();
+
+    let mut sessions = vec![capture_file.to_string()];
+    sessions.extend(archives);
+    sessions
+}
+
+fn archive_date(stem: &str, path: &Path) -> Option<chrono::NaiveDate> {
+    let name = path.file_name()?.to_string_lossy();
+    let name = name.strip_suffix(".enc").unwrap_or(&name);
+    let date = name.strip_suffix(".json")?.strip_prefix(stem)?.strip_prefix('-')?;
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+pub fn session_label(path: &str, capture_file: &str) -> String {
+    if path == capture_file {
+        return "Current".to_string();
+    }
+    let capture = Path::new(capture_file);
+    let stem = capture
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    archive_date(&stem, Path::new(path))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Split a transcript wherever the media file changes.
+pub fn media_groups(entries: &[SubtitleEntry]) -> Vec<MediaGroup> {
+    let mut groups: Vec<MediaGroup> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if group.path == entry.path => group.range.end = i + 1,
+            _ => groups.push(MediaGroup {
+                path: entry.path.clone(),
+                title: entry
+                    .title
+                    .clone()
+                    .or_else(|| entry.path.as_deref().map(media_name))
+                    .unwrap_or_else(|| "Unknown media".to_string()),
+                range: i..i + 1,
+            }),
+        }
+    }
+    groups
+}
+
+/// Reorder each file's lines by media time, so lines seen again after seeking
+/// backwards slot in where they belong. Lines with equal times keep their
+/// arrival order.
+pub fn sort_by_media_time(entries: &mut [SubtitleEntry]) {
+    for group in media_groups(entries) {
+        entries[group.range].sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    }
+}
+
+/// A clean transcript: each file's lines (files in order of first appearance)
+/// sorted by media time, with a cue seen more than once kept only once, in its
+/// last-arrived form.
+pub fn timeline(entries: &[SubtitleEntry]) -> Vec<SubtitleEntry> {
+    let mut files: Vec<Option<&str>> = Vec::new();
+    for entry in entries {
+        if !files.contains(&entry.path.as_deref()) {
+            files.push(entry.path.as_deref());
+        }
+    }
+    let mut timeline = Vec::with_capacity(entries.len());
+    for file in files {
+        let mut lines: Vec<&SubtitleEntry> = entries.iter().filter(|e| e.path.as_deref() == file).collect();
+        lines.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+        for (i, line) in lines.iter().enumerate() {
+            let repeated_later = lines[i + 1..]
+                .iter()
+                .take_while(|next| next.start_time - line.start_time < SAME_CUE)
+                .any(|next| next.text == line.text || next.text.starts_with(&line.text));
+            if !repeated_later {
+                timeline.push((*line).clone());
+            }
+        }
+    }
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarantine_keeps_every_broken_copy() {
+        let dir = std::env::temp_dir().join(format!("scriptview-quarantine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("capture.json").to_string_lossy().into_owned();
+        for _ in 0..2 {
+            std::fs::write(&file, "[{\"text\": ").unwrap();
+            assert!(try_read_entries(&file).is_err());
+            quarantine(&file).unwrap();
+        }
+        assert_eq!(try_read_entries(&file), Ok(None));
+        assert!(dir.join("capture.json.corrupt").exists());
+        assert!(dir.join("capture.json.2.corrupt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_identity_changes_when_replaced() {
+        let dir = std::env::temp_dir().join(format!("scriptview-identity-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.json").to_string_lossy().into_owned();
+        std::fs::write(&path, "[]").unwrap();
+        let before = file_identity(&path);
+        std::fs::write(&path, "[{}]").unwrap();
+        assert_eq!(file_identity(&path), before);
+        let replacement = dir.join("capture.json.tmp");
+        std::fs::write(&replacement, "[]").unwrap();
+        std::fs::rename(&replacement, &path).unwrap();
+        assert!(file_identity(&path).is_some());
+        assert_ne!(file_identity(&path), before);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(file_identity(&path), None);
+    }
+
+    #[test]
+    fn test_archive_date_matches_rotated_name() {
+        let date = archive_date("mpv-subtitles", Path::new("/tmp/mpv-subtitles-2024-03-01.json"));
+        assert_eq!(date, chrono::NaiveDate::from_ymd_opt(2024, 3, 1));
+        let sealed = archive_date("mpv-subtitles", Path::new("/tmp/mpv-subtitles-2024-03-01.json.enc"));
+        assert_eq!(sealed, date);
+    }
+
+    #[test]
+    fn test_archive_date_rejects_other_files() {
+        assert_eq!(archive_date("mpv-subtitles", Path::new("/tmp/mpv-subtitles.json")), None);
+        assert_eq!(archive_date("mpv-subtitles", Path::new("/tmp/other-2024-03-01.json")), None);
+        assert_eq!(archive_date("mpv-subtitles", Path::new("/tmp/mpv-subtitles-2024-03-01.txt")), None);
+    }
+
+    fn from(path: &str) -> SubtitleEntry {
+        SubtitleEntry {
+            path: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_media_groups_split_on_file_change() {
+        let mut titled = from("/b.mkv");
+        titled.title = Some("Episode 2".to_string());
+        let entries = [from("/shows/a.mkv"), from("/shows/a.mkv"), titled, from("/shows/a.mkv")];
+        let groups = media_groups(&entries);
+        let summary: Vec<(&str, Range<usize>)> = groups.iter().map(|g| (g.title.as_str(), g.range.clone())).collect();
+        assert_eq!(summary, vec![("a.mkv", 0..2), ("Episode 2", 2..3), ("a.mkv", 3..4)]);
+    }
+
+    #[test]
+    fn test_media_groups_without_metadata() {
+        let groups = media_groups(&[SubtitleEntry::default(), SubtitleEntry::default()]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].title, "Unknown media");
+        assert!(media_groups(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_sort_by_media_time_within_each_file() {
+        let at = |path: &str, start_time: f64, text: &str| SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        let mut entries = vec![
+            at("/a.mkv", 10.0, "a10"),
+            at("/a.mkv", 20.0, "a20"),
+            at("/a.mkv", 5.0, "a5"),
+            at("/b.mkv", 3.0, "b3"),
+            at("/b.mkv", 1.0, "b1"),
+        ];
+        sort_by_media_time(&mut entries);
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["a5", "a10", "a20", "b1", "b3"]);
+    }
+
+    #[test]
+    fn test_timeline_merges_files_and_repeats() {
+        let at = |path: &str, start_time: f64, text: &str| SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        let entries = vec![
+            at("/a.mkv", 10.0, "ten"),
+            at("/a.mkv", 20.0, "twenty"),
+            at("/b.mkv", 1.0, "b"),
+            at("/a.mkv", 10.0, "ten"),
+            at("/a.mkv", 15.0, "fifteen"),
+            at("/a.mkv", 15.0, "fifteen, longer"),
+        ];
+        let texts: Vec<String> = timeline(&entries).into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["ten", "fifteen, longer", "twenty", "b"]);
+    }
+
+    #[test]
+    fn test_timeline_keeps_different_lines_at_same_time() {
+        let entries = vec![
+            SubtitleEntry { text: "top".to_string(), ..Default::default() },
+            SubtitleEntry { text: "bottom".to_string(), ..Default::default() },
+        ];
+        assert_eq!(timeline(&entries).len(), 2);
+    }
+
+    #[test]
+    fn test_session_label() {
+        let capture = "/tmp/mpv-subtitles.json";
+        assert_eq!(session_label(capture, capture), "Current");
+        assert_eq!(session_label("/tmp/mpv-subtitles-2024-03-01.json", capture), "2024-03-01");
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioSource;
+use crate::bidi::TextDirection;
+use crate::encoding::TextEncoding;
+use crate::examples::ExampleSource;
+use crate::export::{AutoExport, CueTiming, Format};
+use crate::layout::Layout;
+use crate::noise::NoiseFilter;
+use crate::notes::NoteService;
+use crate::player::Player;
+use crate::shows::ShowSettings;
+use crate::presentation::{LineWidth, Preset};
+use crate::search::SearchSite;
+use crate::source::{Source, SourceStyle};
+use crate::storage::StorageKind;
+use crate::sync::SyncMode;
+
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+
+/// What clicking a line's timestamp does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampClick {
+    /// Seek the player to the line.
+    #[default]
+    Seek,
+    /// Copy the time, as shown.
+    CopyTime,
+    /// Copy a link to the moment in the YouTube video, for browser captures.
+    CopyYoutubeLink,
+    Nothing,
+}
+
+impl TimestampClick {
+    pub const ALL: [TimestampClick; 4] = [
+        TimestampClick::Seek,
+        TimestampClick::CopyTime,
+        TimestampClick::CopyYoutubeLink,
+        TimestampClick::Nothing,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimestampClick::Seek => "Seek the player",
+            TimestampClick::CopyTime => "Copy the time",
+            TimestampClick::CopyYoutubeLink => "Copy a YouTube link",
+            TimestampClick::Nothing => "Nothing",
+        }
+    }
+}
+
+/// Which lines count as earlier states of the next one, for players that
+/// build text up rather than only typing it out at the end. Each takes in
+/// the ones before it; a line is matched as a prefix first, then a suffix,
+/// then anywhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollapseMode {
+    /// The next line starts with it.
+    #[default]
+    Prefix,
+    /// The next line starts or ends with it.
+    PrefixOrSuffix,
+    /// The next line contains it anywhere.
+    Anywhere,
+}
+
+impl CollapseMode {
+    pub const ALL: [CollapseMode; 3] = [CollapseMode::Prefix, CollapseMode::PrefixOrSuffix, CollapseMode::Anywhere];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CollapseMode::Prefix => "Start of the next line",
+            CollapseMode::PrefixOrSuffix => "Start or end of the next line",
+            CollapseMode::Anywhere => "Anywhere in the next line",
+        }
+    }
+}
+
+/// Per-language settings, switched as a group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Word list, most frequent first, used to tint words by frequency.
+    pub frequency_list: String,
+    /// Tatoeba language code (`jpn`, `deu`, ...); empty searches all languages.
+    pub tatoeba_language: String,
+    pub text_direction: TextDirection,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_string(),
+            frequency_list: String::new(),
+            tatoeba_language: String::new(),
+            text_direction: TextDirection::default(),
+        }
+    }
+}
+
+/// User preferences that survive restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Subtitle text size in points, unaffected by `ui_scale`.
+    pub font_size: f32,
+    /// Smallest and largest `font_size` the size buttons go to.
+    pub font_size_min: f32,
+    pub font_size_max: f32,
+    /// How much one press of a size button changes `font_size`; may be fractional.
+    pub font_size_step: f32,
+    /// Zoom factor for the controls and everything else that isn't subtitle text.
+    pub ui_scale: f32,
+    /// Comma-separated words to watch for in new subtitles.
+    pub watch_words: String,
+    /// Show a desktop notification when a watch word comes up.
+    pub notify_watch_words: bool,
+    /// Show and save a summary when a viewing session ends.
+    pub session_summary: bool,
+    /// Keep every line as read, before filtering, so days can be reprocessed.
+    pub keep_raw_stream: bool,
+    /// Where the raw stream is kept (switch with `--migrate-storage`).
+    pub storage: StorageKind,
+    /// Seal the raw stream, capture archives, session summaries and backups
+    /// with a key from a passphrase asked for at startup.
+    pub encrypt_history: bool,
+    /// Delete the capture file when the viewer closes.
+    pub remove_capture_on_exit: bool,
+    /// Publish each new subtitle to `mqtt_topic` on `mqtt_broker`.
+    pub mqtt_enabled: bool,
+    /// `[user:password@]host[:port]`
+    pub mqtt_broker: String,
+    pub mqtt_topic: String,
+    /// URL each new subtitle is POSTed to; empty for none.
+    pub webhook_url: String,
+    /// Send the lines of this many seconds together; 0 sends each at once.
+    pub webhook_batch_secs: u32,
+    /// HTTP APIs of note-taking services lines can be sent to.
+    pub note_services: Vec<NoteService>,
+    /// Show a short confirmation on mpv's OSD for actions taken over IPC.
+    pub osd_feedback: bool,
+    /// Pause mpv when a new line has more than `unknown_word_limit` unknown words.
+    pub pause_on_unknown: bool,
+    pub unknown_word_limit: usize,
+    /// Mask each new line for `reveal_delay_secs`, to try it by ear first.
+    pub listening_first: bool,
+    /// 0 keeps new lines masked until they're revealed.
+    pub reveal_delay_secs: u32,
+    /// Have screen readers read out each new subtitle.
+    pub announce_subtitles: bool,
+    /// Lines recognised (speech or OCR) with less confidence than this are
+    /// marked for checking.
+    pub low_confidence: f32,
+    /// Badge lines too fast or too long to read, for checking subtitle files.
+    pub flag_reading_problems: bool,
+    /// Characters per second a line can be read at; 0 doesn't check.
+    pub max_cps: f64,
+    /// Characters that fit on one line; 0 doesn't check.
+    pub max_line_chars: usize,
+    /// Lay the mini overlay out in vertical columns (tategaki).
+    pub overlay_vertical: bool,
+    /// Text size in the presenter window.
+    pub presenter_font_size: f32,
+    /// Link shown as a QR code in the presenter window; empty for none.
+    pub presenter_qr_url: String,
+    pub preset: Preset,
+    /// Font used for subtitles and the interface by the easy reading preset.
+    pub reading_font: String,
+    /// Font file used for characters egui's own fonts don't have.
+    pub fallback_font: String,
+    /// Underline grammar patterns from `grammar_file` in subtitles.
+    pub show_grammar_hints: bool,
+    /// How wide subtitle lines may get and whether they're centered.
+    pub line_width: LineWidth,
+    /// What clicking a line's timestamp does.
+    pub timestamp_click: TimestampClick,
+    /// Draw `<i>`/`<b>` and `{\i1}`/`{\b1}` markup as italics and bold
+    /// rather than just removing it.
+    pub render_emphasis: bool,
+    /// JSON list of `{"pattern", "meaning", "example"}` objects.
+    pub grammar_file: String,
+    /// Tint words by the active profile's frequency list.
+    pub color_by_frequency: bool,
+    pub profiles: Vec<Profile>,
+    pub active_profile: usize,
+    pub example_source: ExampleSource,
+    pub tatoeba_file: String,
+    /// Machine-translate a line when it's hovered, shown only in a tooltip.
+    pub hover_translation: bool,
+    /// LibreTranslate endpoint for hover translations.
+    pub translate_url: String,
+    pub translate_api_key: String,
+    /// Language code of the lines, or "auto".
+    pub translate_source: String,
+    pub translate_target: String,
+    pub audio_source: AudioSource,
+    pub audio_dir: String,
+    /// Download URL with a `{word}` placeholder.
+    pub audio_url: String,
+    /// Sites that words and lines can be looked up on.
+    pub search_sites: Vec<SearchSite>,
+    /// Directory that statistics and transcripts are exported to.
+    pub export_dir: String,
+    /// Editor for "Edit in external editor", with `{file}` for the file;
+    /// empty uses `$VISUAL`, `$EDITOR` or the system's default app.
+    pub editor_command: String,
+    /// Obsidian or Logseq folder that "Save to vault" writes notes into.
+    pub vault_dir: String,
+    /// Obsidian or Logseq.
+    pub vault_format: Format,
+    /// Which player's script writes the capture file.
+    pub player: Player,
+    /// mpv's `--input-ipc-server` socket.
+    pub mpv_socket: String,
+    /// Localhost port the browser extension connects to.
+    pub browser_port: u16,
+    /// Localhost TCP port for the JSON lines source.
+    pub lines_port: u16,
+    /// Hide a line repeated within this many seconds of arrival (re-fired
+    /// after a seek); 0 keeps every repeat.
+    pub dedup_window_secs: u32,
+    /// Only collapse a line into the next one that extends it if that
+    /// arrived within this many seconds; 0 collapses at any distance.
+    pub prefix_window_secs: u32,
+    /// Where in the next line an earlier line may turn up to be collapsed.
+    pub collapse_mode: CollapseMode,
+    /// Junk to leave out of each source's lines.
+    pub noise_filters: HashMap<Player, NoiseFilter>,
+    /// Whether each source's lines are shown, and in what colour.
+    pub sources: HashMap<Source, SourceStyle>,
+    /// Whether new sessions place lines from backward seeks by media time
+    /// rather than appending them in arrival order.
+    pub time_ordered: bool,
+    /// Show one line per cue, by file and media time, however playback jumped around.
+    pub timeline_view: bool,
+    /// Draw the audio around each line from its media file (needs ffmpeg).
+    pub show_waveforms: bool,
+    /// Dim every line but the one under the mouse, or the latest when the
+    /// mouse isn't over one.
+    pub reading_ruler: bool,
+    /// AnkiConnect endpoint that starred lines are exported to.
+    pub anki_url: String,
+    pub anki_deck: String,
+    pub anki_note_type: String,
+    /// Note field that receives the subtitle text.
+    pub anki_sentence_field: String,
+    /// Note field for the media title and timestamp; empty to leave it out.
+    pub anki_source_field: String,
+    /// Field searched for the sentence before adding a card, skipping lines
+    /// already in the collection; empty turns the check off.
+    pub anki_dedup_field: String,
+    /// Note field for the line's audio; empty adds no audio.
+    pub anki_audio_field: String,
+    /// Speak the sentence for cards whose audio can't be cut from the media.
+    pub tts_fallback: bool,
+    /// Text to speech command, with `{text}` and `{file}` filled in.
+    pub tts_command: String,
+    /// End times given to exported cues that lack one.
+    pub cue_timing: CueTiming,
+    pub auto_export: AutoExport,
+    /// Folder where other tools drop captures to be opened; empty for none.
+    pub watch_folder: String,
+    /// Capture file on another machine to follow instead of the local one,
+    /// as `sftp://user@host/path`; empty for none.
+    pub remote_capture: String,
+    /// Seconds between fetches of the remote capture file.
+    pub remote_poll_secs: u32,
+    /// Warn when mpv has played this many seconds with a subtitle track
+    /// selected but no lines arrived; 0 never warns.
+    pub stall_warning_secs: u32,
+    /// Sharing the transcript with another instance on the network.
+    pub sync_mode: SyncMode,
+    /// Port the hub listens on, on every interface.
+    pub sync_port: u16,
+    /// `host[:port]` of the hub to follow.
+    pub sync_hub: String,
+    /// Character encoding of opened subtitle files.
+    pub import_encoding: TextEncoding,
+    /// Saved window layouts, in menu order.
+    pub layouts: Vec<Layout>,
+    /// Settings put back whenever a show plays again.
+    pub shows: Vec<ShowSettings>,
+    /// Start with the window minimized (also `--minimized`).
+    pub start_minimized: bool,
+    /// Start with the mini overlay open (also `--overlay`).
+    pub start_in_overlay: bool,
+    /// Layout to start in, empty for none (also `--layout`).
+    pub start_layout: String,
+    /// Restore the window when mpv starts, plays or gets focus, and minimize
+    /// it when mpv closes, going by the IPC connection.
+    pub follow_mpv: bool,
+    /// Repaint less often, drop animations and shadows, and hold network
+    /// sinks back while the window is minimized.
+    pub low_power: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            font_size_min: 8.0,
+            font_size_max: 72.0,
+            font_size_step: 1.0,
+            ui_scale: 1.0,
+            watch_words: String::new(),
+            notify_watch_words: true,
+            session_summary: true,
+            keep_raw_stream: true,
+            storage: StorageKind::Jsonl,
+            encrypt_history: false,
+            remove_capture_on_exit: false,
+            mqtt_enabled: false,
+            mqtt_broker: crate::mqtt::DEFAULT_BROKER.to_string(),
+            mqtt_topic: crate::mqtt::DEFAULT_TOPIC.to_string(),
+            webhook_url: String::new(),
+            webhook_batch_secs: 0,
+            note_services: Vec::new(),
+            osd_feedback: true,
+            pause_on_unknown: false,
+            unknown_word_limit: 2,
+            listening_first: false,
+            reveal_delay_secs: 3,
+            announce_subtitles: false,
+            overlay_vertical: false,
+            low_confidence: 0.6,
+            flag_reading_problems: false,
+            max_cps: 20.0,
+            max_line_chars: 42,
+            presenter_font_size: 72.0,
+            presenter_qr_url: String::new(),
+            preset: Preset::default(),
+            reading_font: String::new(),
+            fallback_font: String::new(),
+            show_grammar_hints: true,
+            render_emphasis: true,
+            timestamp_click: TimestampClick::default(),
+            line_width: LineWidth::default(),
+            grammar_file: format!("{}/grammar.json", config_dir()),
+            color_by_frequency: true,
+            profiles: vec![Profile::default()],
+            active_profile: 0,
+            example_source: ExampleSource::default(),
+            tatoeba_file: format!("{}/sentences.csv", config_dir()),
+            hover_translation: false,
+            translate_url: crate::machine_translation::DEFAULT_URL.to_string(),
+            translate_api_key: String::new(),
+            translate_source: "auto".to_string(),
+            translate_target: "en".to_string(),
+            audio_source: AudioSource::default(),
+            audio_dir: format!("{}/audio", config_dir()),
+            audio_url: String::new(),
+            search_sites: crate::search::defaults(),
+            editor_command: String::new(),
+            vault_dir: String::new(),
+            vault_format: Format::Obsidian,
+            export_dir: std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string()),
+            player: Player::default(),
+            mpv_socket: crate::mpv_ipc::DEFAULT_SOCKET.to_string(),
+            browser_port: crate::browser::DEFAULT_PORT,
+            lines_port: crate::lines::DEFAULT_PORT,
+            noise_filters: HashMap::new(),
+            sources: HashMap::new(),
+            dedup_window_secs: 60,
+            prefix_window_secs: 10,
+            collapse_mode: CollapseMode::Prefix,
+            time_ordered: false,
+            timeline_view: false,
+            show_waveforms: false,
+            reading_ruler: false,
+            anki_url: crate::anki::DEFAULT_URL.to_string(),
+            anki_deck: "Default".to_string(),
+            anki_note_type: "Basic".to_string(),
+            anki_sentence_field: "Front".to_string(),
+            anki_source_field: "Back".to_string(),
+            anki_dedup_field: "Front".to_string(),
+            anki_audio_field: String::new(),
+            tts_fallback: false,
+            tts_command: crate::tts::DEFAULT_COMMAND.to_string(),
+            cue_timing: CueTiming::default(),
+            auto_export: AutoExport::default(),
+            watch_folder: String::new(),
+            remote_capture: String::new(),
+            remote_poll_secs: 2,
+            stall_warning_secs: 90,
+            sync_mode: SyncMode::Off,
+            sync_port: crate::sync::DEFAULT_PORT,
+            sync_hub: String::new(),
+            import_encoding: TextEncoding::default(),
+            layouts: Vec::new(),
+            shows: Vec::new(),
+            start_minimized: false,
+            start_in_overlay: false,
+            start_layout: String::new(),
+            follow_mpv: false,
+            low_power: false,
+        }
+    }
+}
+
+pub fn config_dir() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.config/scriptview", home_dir)
+}
+
+pub fn settings_path() -> String {
+    format!("{}/settings.json", config_dir())
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings: Self = std::fs::read_to_string(settings_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        if settings.profiles.is_empty() {
+            settings.profiles.push(Profile::default());
+        }
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let path = settings_path();
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profiles[self.active_profile.min(self.profiles.len() - 1)]
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        let index = self.active_profile.min(self.profiles.len() - 1);
+        &mut self.profiles[index]
+    }
+
+    /// Point size to render subtitles at so they keep `font_size` on screen
+    /// whatever the current UI zoom is.
+    pub fn subtitle_points(&self, zoom_factor: f32) -> f32 {
+        self.font_size / zoom_factor
+    }
+
+    /// The sizes `font_size` may take, made sensible whatever was typed in.
+    pub fn font_size_range(&self) -> std::ops::RangeInclusive<f32> {
+        let min = self.font_size_min.max(1.0);
+        min..=self.font_size_max.max(min)
+    }
+
+    /// Change `font_size` by `steps` of `font_size_step`, staying in range.
+    pub fn step_font_size(&mut self, steps: f32) {
+        let range = self.font_size_range();
+        let size = self.font_size + steps * self.font_size_step.max(0.1);
+        // Rounded so repeated fractional steps don't drift to 14.499999
+        self.font_size = ((size * 100.0).round() / 100.0).clamp(*range.start(), *range.end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let settings: Settings = serde_json::from_str(r#"{"font_size": 20.0}"#).unwrap();
+        assert_eq!(settings.font_size, 20.0);
+        assert_eq!(settings.ui_scale, 1.0);
+    }
+
+    #[test]
+    fn test_noise_filters_are_kept_per_source() {
+        let settings: Settings =
+            serde_json::from_str(r#"{"noise_filters": {"lines": {"min_chars": 3, "blocklist": "LOGO"}}}"#).unwrap();
+        let filter = &settings.noise_filters[&Player::Lines];
+        assert_eq!((filter.min_chars, filter.min_confidence), (3, 0.0));
+        let json = serde_json::to_string(&settings).unwrap();
+        let settings: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(settings.noise_filters[&Player::Lines].blocklist, "LOGO");
+    }
+
+    #[test]
+    fn test_active_profile_out_of_range_falls_back_to_last() {
+        let settings: Settings = serde_json::from_str(
+            r#"{"profiles": [{"name": "ja"}, {"name": "de"}], "active_profile": 5}"#,
+        )
+        .unwrap();
+        assert_eq!(settings.profile().name, "de");
+    }
+
+    #[test]
+    fn test_font_size_steps_stay_in_bounds() {
+        let mut settings = Settings {
+            font_size: 47.0,
+            font_size_min: 10.0,
+            font_size_max: 48.0,
+            font_size_step: 0.5,
+            ..Default::default()
+        };
+        settings.step_font_size(1.0);
+        assert_eq!(settings.font_size, 47.5);
+        settings.step_font_size(3.0);
+        assert_eq!(settings.font_size, 48.0);
+
+        // A maximum below the minimum is treated as the minimum
+        settings.font_size_max = 5.0;
+        settings.step_font_size(-1.0);
+        assert_eq!(settings.font_size, 10.0);
+    }
+
+    #[test]
+    fn test_subtitle_points_compensate_zoom() {
+        let settings = Settings {
+            font_size: 24.0,
+            ui_scale: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(settings.subtitle_points(0.5), 48.0);
+        assert_eq!(settings.subtitle_points(2.0), 12.0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Settings remembered per show: text size, line filters, language profile
+//! and mpv's subtitle delay, put back whenever a line from the same show
+//! arrives in a later session. Episodes share their show's settings, so the
+//! show is the media title with episode numbers and release tags taken off.
+//! A show's values only last while it plays; the user's own are kept aside
+//! and come back for a show with none remembered.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::noise::NoiseFilter;
+use crate::player::Player;
+use crate::settings::{CollapseMode, Settings};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShowSettings {
+    /// As `show_name` gives it.
+    pub show: String,
+    pub font_size: f32,
+    pub dedup_window_secs: u32,
+    pub prefix_window_secs: u32,
+    pub collapse_mode: CollapseMode,
+    pub noise_filters: HashMap<Player, NoiseFilter>,
+    /// Name of the language profile.
+    pub profile: String,
+    /// mpv's `sub-delay` in seconds, if it was known when saved.
+    pub sub_delay: Option<f64>,
+}
+
+impl Default for ShowSettings {
+    fn default() -> Self {
+        Self {
+            show: String::new(),
+            font_size: 14.0,
+            dedup_window_secs: 60,
+            prefix_window_secs: 10,
+            collapse_mode: CollapseMode::default(),
+            noise_filters: HashMap::new(),
+            profile: String::new(),
+            sub_delay: None,
+        }
+    }
+}
+
+impl ShowSettings {
+    /// The values of `settings` that are kept per show.
+    pub fn of(show: String, settings: &Settings, sub_delay: Option<f64>) -> Self {
+        Self {
+            show,
+            font_size: settings.font_size,
+            dedup_window_secs: settings.dedup_window_secs,
+            prefix_window_secs: settings.prefix_window_secs,
+            collapse_mode: settings.collapse_mode,
+            noise_filters: settings.noise_filters.clone(),
+            profile: settings.profile().name.clone(),
+            sub_delay,
+        }
+    }
+
+    /// Put these values into `settings`, returning whether that switched
+    /// the profile. A profile that's gone since is left as it is.
+    pub fn apply_to(&self, settings: &mut Settings) -> bool {
+        settings.font_size = self.font_size.clamp(settings.font_size_min, settings.font_size_max);
+        settings.dedup_window_secs = self.dedup_window_secs;
+        settings.prefix_window_secs = self.prefix_window_secs;
+        settings.collapse_mode = self.collapse_mode;
+        settings.noise_filters = self.noise_filters.clone();
+        let profile = settings.profiles.iter().position(|profile| profile.name == self.profile);
+        match profile.filter(|&index| index != settings.active_profile) {
+            Some(index) => {
+                settings.active_profile = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Whether a word of a title numbers the episode: "03", "S01E04", "Ep3",
+/// "#12", "第3話".
+fn is_episode_marker(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    let digits_after = |prefix: &str| {
+        lower
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    };
+    lower.chars().all(|c| c.is_ascii_digit())
+        || ["s", "e", "ep", "ep.", "#"].into_iter().any(digits_after)
+        || ["episode", "ep", "ep."].contains(&lower.as_str())
+        || (lower.starts_with('第') && lower.ends_with('話'))
+}
+
+/// The show a media title belongs to: "[Group] Show - 03 (1080p).mkv" and
+/// "Show.S01E04.WEB" are both "Show". A title with nothing left after that
+/// is its own show.
+pub fn show_name(title: &str) -> String {
+    let mut title = title.trim();
+    if let Some((stem, extension)) = title.rsplit_once('.') {
+        let is_extension = (2..=4).contains(&extension.len())
+            && extension.chars().all(|c| c.is_ascii_alphanumeric())
+            && extension.chars().any(|c| c.is_ascii_alphabetic());
+        if is_extension {
+            title = stem;
+        }
+    }
+    let mut depth = 0;
+    let unbracketed: String = title
+        .chars()
+        .filter(|&c| match c {
+            '[' | '(' | '【' => {
+                depth += 1;
+                false
+            }
+            ']' | ')' | '】' => {
+                depth = (depth - 1).max(0);
+                false
+            }
+            _ => depth == 0,
+        })
+        .map(|c| if c == '_' || c == '.' { ' ' } else { c })
+        .collect();
+    let words: Vec<&str> = unbracketed
+        .split_whitespace()
+        .take_while(|word| !is_episode_marker(word))
+        .collect();
+    let name = words.join(" ");
+    let name = name.trim_end_matches(|c: char| c == '-' || c == ':' || c == '–' || c.is_whitespace());
+    if name.is_empty() {
+        title.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Add `settings` to `shows`, replacing those of the same show (ignoring
+/// case).
+pub fn store(shows: &mut Vec<ShowSettings>, settings: ShowSettings) {
+    match shows.iter_mut().find(|saved| saved.show.eq_ignore_ascii_case(&settings.show)) {
+        Some(saved) => *saved = settings,
+        None => shows.push(settings),
+    }
+}
+
+pub fn find<'a>(shows: &'a [ShowSettings], show: &str) -> Option<&'a ShowSettings> {
+    shows.iter().find(|saved| saved.show.eq_ignore_ascii_case(show))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_show_name() {
+        assert_eq!(show_name("[SubsPlease] Sousou no Frieren - 03 (1080p) [ABCD1234].mkv"), "Sousou no Frieren");
+        assert_eq!(show_name("Show.Name.S01E04.1080p.WEB"), "Show Name");
+        assert_eq!(show_name("Show Name Episode 12"), "Show Name");
+        assert_eq!(show_name("Show Name: Ep3"), "Show Name");
+        assert_eq!(show_name("進撃の巨人 第3話"), "進撃の巨人");
+        assert_eq!(show_name("Spirited Away"), "Spirited Away");
+        assert_eq!(show_name("E05"), "E05");
+    }
+
+    #[test]
+    fn test_store_and_find() {
+        let mut shows = Vec::new();
+        store(&mut shows, ShowSettings { show: "Frieren".to_string(), ..Default::default() });
+        store(
+            &mut shows,
+            ShowSettings {
+                show: "frieren".to_string(),
+                font_size: 20.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(shows.len(), 1);
+        assert_eq!(find(&shows, "FRIEREN").map(|saved| saved.font_size), Some(20.0));
+        assert!(find(&shows, "Mushishi").is_none());
+    }
+
+    #[test]
+    fn test_apply_and_restore() {
+        let mut settings = Settings::default();
+        let base = ShowSettings::of(String::new(), &settings, None);
+        let show = ShowSettings {
+            show: "Frieren".to_string(),
+            font_size: 30.0,
+            dedup_window_secs: 5,
+            profile: "gone".to_string(),
+            ..base.clone()
+        };
+        assert!(!show.apply_to(&mut settings));
+        assert_eq!((settings.font_size, settings.dedup_window_secs), (30.0, 5));
+        base.apply_to(&mut settings);
+        assert_eq!(settings, Settings::default());
+    }
+}
@@ -0,0 +1,61 @@
+//! Cleanup when the window closes. Each subsystem registers a step with the
+//! `ShutdownCoordinator` when it's set up; closing runs them all in the order
+//! they were registered, carrying on past any that fail.
+
+use std::time::Duration;
+
+/// How long a step may wait on background work, such as a webhook batch
+/// still being sent, before giving up on it.
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+type Step<T> = Box<dyn FnOnce(&mut T) -> Result<(), String>>;
+
+pub struct ShutdownCoordinator<T> {
+    steps: Vec<(&'static str, Step<T>)>,
+}
+
+impl<T> Default for ShutdownCoordinator<T> {
+    fn default() -> Self {
+        Self { steps: Vec::new() }
+    }
+}
+
+impl<T> ShutdownCoordinator<T> {
+    pub fn register(&mut self, name: &'static str, step: impl FnOnce(&mut T) -> Result<(), String> + 'static) {
+        self.steps.push((name, Box::new(step)));
+    }
+
+    /// Run every step on `target`, returning the names of those that failed.
+    pub fn run(self, target: &mut T) -> Vec<&'static str> {
+        let mut failed = Vec::new();
+        for (name, step) in self.steps {
+            if let Err(e) = step(target) {
+                eprintln!("Warning: Shutdown step \"{}\" failed: {}", name, e);
+                failed.push(name);
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_every_step_in_order() {
+        let mut shutdown = ShutdownCoordinator::<Vec<&str>>::default();
+        shutdown.register("flush", |log| {
+            log.push("flush");
+            Ok(())
+        });
+        shutdown.register("disconnect", |_| Err("already gone".to_string()));
+        shutdown.register("remove", |log| {
+            log.push("remove");
+            Ok(())
+        });
+        let mut log = Vec::new();
+        assert_eq!(shutdown.run(&mut log), vec!["disconnect"]);
+        assert_eq!(log, vec!["flush", "remove"]);
+    }
+}
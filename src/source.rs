@@ -0,0 +1,144 @@
+//! Where each line came from, for transcripts fed by more than one thing at
+//! once: mpv's primary and secondary tracks, or OCR and speech recognition
+//! sending JSON lines side by side. Each source can be hidden or given its
+//! own colour.
+
+use serde::{Deserialize, Serialize};
+
+use crate::player::Player;
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// mpv's primary subtitle track.
+    Primary,
+    /// mpv's secondary subtitle track, as `[Secondary] ` lines.
+    Secondary,
+    Vlc,
+    Browser,
+    Ocr,
+    /// Speech recognition.
+    Asr,
+    /// JSON lines from any other tool.
+    Lines,
+    /// A file opened in place of the live capture.
+    Imported,
+}
+
+impl Source {
+    pub const ALL: [Source; 8] = [
+        Source::Primary,
+        Source::Secondary,
+        Source::Vlc,
+        Source::Browser,
+        Source::Ocr,
+        Source::Asr,
+        Source::Lines,
+        Source::Imported,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Primary => "mpv",
+            Source::Secondary => "mpv secondary track",
+            Source::Vlc => "VLC",
+            Source::Browser => "Browser",
+            Source::Ocr => "OCR",
+            Source::Asr => "Speech recognition",
+            Source::Lines => "JSON lines",
+            Source::Imported => "Opened file",
+        }
+    }
+
+    /// The tag shown on rows.
+    pub fn short_label(self) -> &'static str {
+        match self {
+            Source::Primary => "mpv",
+            Source::Secondary => "2nd",
+            Source::Vlc => "VLC",
+            Source::Browser => "web",
+            Source::Ocr => "OCR",
+            Source::Asr => "ASR",
+            Source::Lines => "lines",
+            Source::Imported => "file",
+        }
+    }
+
+    pub fn default_color(self) -> [u8; 3] {
+        match self {
+            Source::Primary => [120, 160, 220],
+            Source::Secondary => [150, 150, 150],
+            Source::Vlc => [230, 140, 40],
+            Source::Browser => [90, 180, 120],
+            Source::Ocr => [200, 120, 200],
+            Source::Asr => [80, 180, 190],
+            Source::Lines => [170, 150, 110],
+            Source::Imported => [140, 140, 190],
+        }
+    }
+
+    /// The source a JSON lines tool names itself as, if it's one with its
+    /// own kind.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ocr" => Some(Source::Ocr),
+            "asr" | "speech" | "whisper" => Some(Source::Asr),
+            _ => None,
+        }
+    }
+
+    fn of_player(player: Player) -> Self {
+        match player {
+            Player::Mpv => Source::Primary,
+            Player::Vlc => Source::Vlc,
+            Player::Browser => Source::Browser,
+            Player::Lines => Source::Lines,
+        }
+    }
+}
+
+/// How a source's lines are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceStyle {
+    pub hidden: bool,
+    /// `None` for the source's default.
+    pub color: Option<[u8; 3]>,
+}
+
+/// Fill in the source of entries read from `player`'s capture that don't
+/// name one.
+pub fn tag(entries: &mut [SubtitleEntry], player: Player) {
+    for entry in entries.iter_mut().filter(|entry| entry.source.is_none()) {
+        entry.source = Some(match player {
+            Player::Mpv if entry.text.starts_with(crate::translation::SECONDARY_PREFIX) => Source::Secondary,
+            player => Source::of_player(player),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, source: Option<Source>) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            source,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tag() {
+        let mut entries = vec![entry("猫", None), entry("[Secondary] Cat", None), entry("犬", Some(Source::Ocr))];
+        tag(&mut entries, Player::Mpv);
+        let sources: Vec<_> = entries.iter().map(|entry| entry.source).collect();
+        assert_eq!(sources, vec![Some(Source::Primary), Some(Source::Secondary), Some(Source::Ocr)]);
+        let mut entries = vec![entry("[Secondary] Cat", None)];
+        tag(&mut entries, Player::Lines);
+        assert_eq!(entries[0].source, Some(Source::Lines));
+        assert_eq!(Source::from_name("Whisper"), Some(Source::Asr));
+    }
+}
@@ -0,0 +1,73 @@
+//! Reading SubRip (`.srt`) files.
+
+use crate::SubtitleEntry;
+
+/// `00:01:02,500` (or with a `.`) in seconds.
+fn parse_time(text: &str) -> Option<f64> {
+    let (clock, millis) = text.trim().split_once([',', '.'])?;
+    let mut parts = clock.split(':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    let millis: u32 = millis.parse().ok()?;
+    Some(f64::from(hours * 3600 + minutes * 60 + seconds) + f64::from(millis) / 1000.0)
+}
+
+/// The cues of an SRT file, with their text as written (markup included).
+pub fn parse(content: &str) -> Result<Vec<SubtitleEntry>, String> {
+    let content = content.replace("\r\n", "\n");
+    let mut entries = Vec::new();
+    for block in content.split("\n\n") {
+        let mut lines = block.trim_matches('\n').lines();
+        let Some(mut timing) = lines.next() else {
+            continue;
+        };
+        // The cue number is optional in practice
+        if !timing.contains("-->") {
+            match lines.next() {
+                Some(next) => timing = next,
+                None => continue,
+            }
+        }
+        let Some((start, end)) = timing.split_once("-->") else {
+            continue;
+        };
+        // Position hints like `X1:100` may follow the end time
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let (Some(start_time), Some(end_time)) = (parse_time(start), parse_time(end)) else {
+            continue;
+        };
+        let text = lines.collect::<Vec<_>>().join("\n");
+        if text.trim().is_empty() {
+            continue;
+        }
+        entries.push(SubtitleEntry {
+            text,
+            start_time,
+            end_time: Some(end_time),
+            ..Default::default()
+        });
+    }
+    if entries.is_empty() {
+        return Err("no cues found".to_string());
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let entries = parse(
+            "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>猫だ</i>\r\n\r\n2\r\n00:01:03.250 --> 00:01:04,000 X1:10\r\n犬\r\nもいる\r\n\r\n3\r\nbroken\r\n",
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text, "<i>猫だ</i>");
+        assert_eq!(entries[0].start_time, 1.0);
+        assert_eq!(entries[0].end_time, Some(2.5));
+        assert_eq!(entries[1].text, "犬\nもいる");
+        assert_eq!(entries[1].start_time, 63.25);
+        assert!(parse("hello").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Lines starred for later, such as sentences to turn into Anki cards.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StarredLine {
+    pub text: String,
+    pub start_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl StarredLine {
+    /// Where the line is from, as "title (0:12.3)".
+    pub fn source(&self) -> String {
+        let name = self
+            .title
+            .clone()
+            .or_else(|| self.path.as_deref().map(crate::stats::media_name))
+            .unwrap_or_default();
+        format!("{} ({})", name, crate::format_timestamp(self.start_time)).trim_start().to_string()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StarredLines {
+    pub items: Vec<StarredLine>,
+}
+
+pub fn starred_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/starred.json", home_dir)
+}
+
+impl StarredLines {
+    pub fn load() -> Self {
+        std::fs::read_to_string(starred_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let path = starred_path();
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    fn position(&self, text: &str, start_time: f64, path: Option<&str>) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| item.text == text && item.start_time == start_time && item.path.as_deref() == path)
+    }
+
+    pub fn contains(&self, text: &str, start_time: f64, path: Option<&str>) -> bool {
+        self.position(text, start_time, path).is_some()
+    }
+
+    /// Star the line, or unstar it if it already was. Returns whether it is
+    /// now starred.
+    pub fn toggle(&mut self, line: StarredLine) -> bool {
+        match self.position(&line.text, line.start_time, line.path.as_deref()) {
+            Some(index) => {
+                self.items.remove(index);
+                false
+            }
+            None => {
+                self.items.push(line);
+                true
+            }
+        }
+    }
+
+    /// Star the line unless it already is.
+    pub fn add(&mut self, line: StarredLine) {
+        if self.position(&line.text, line.start_time, line.path.as_deref()).is_none() {
+            self.items.push(line);
+        }
+    }
+
+    pub fn remove(&mut self, line: &StarredLine) {
+        self.items.retain(|item| item != line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, start_time: f64) -> StarredLine {
+        StarredLine {
+            text: text.to_string(),
+            start_time,
+            end_time: None,
+            path: Some("/videos/show.mkv".to_string()),
+            title: None,
+        }
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut starred = StarredLines::default();
+        assert!(starred.toggle(line("猫だ", 1.0)));
+        assert!(starred.contains("猫だ", 1.0, Some("/videos/show.mkv")));
+        // The same words at another time are a different line
+        assert!(!starred.contains("猫だ", 2.0, Some("/videos/show.mkv")));
+        assert!(!starred.toggle(line("猫だ", 1.0)));
+        assert!(starred.items.is_empty());
+    }
+
+    #[test]
+    fn test_source() {
+        assert_eq!(line("a", 72.5).source(), "show.mkv (1:12.5)");
+        let untitled = StarredLine { path: None, ..line("a", 3.0) };
+        assert_eq!(untitled.source(), "(0:03.0)");
+    }
+}
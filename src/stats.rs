@@ -0,0 +1,773 @@
+//! Summary numbers for a transcript, shown in the statistics window, and the
+//! long-term record of immersion kept in the stats database.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypt::{self, Key};
+use crate::watch_later;
+use crate::SubtitleEntry;
+
+/// A pause longer than this between subtitles isn't counted as watching time.
+const IDLE_GAP: i64 = 5 * 60;
+/// A pause longer than this starts a new session.
+const SESSION_GAP: i64 = 30 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaStats {
+    pub path: String,
+    pub lines: usize,
+    pub duration: Option<f64>,
+    /// Latest subtitle start time seen for this file.
+    pub last_subtitle: f64,
+    /// Where mpv would resume playback, from its watch-later data.
+    pub resume_position: Option<f64>,
+}
+
+impl MediaStats {
+    /// Fraction of the file watched, preferring mpv's resume position over
+    /// the last captured subtitle.
+    pub fn watched_fraction(&self) -> Option<f64> {
+        let duration = self.duration.filter(|d| *d > 0.0)?;
+        let position = self.resume_position.unwrap_or(self.last_subtitle);
+        Some((position / duration).clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    pub lines: usize,
+    pub characters: usize,
+    pub media: Vec<MediaStats>,
+}
+
+/// File name of a media path, or the path itself for URLs and the like.
+pub fn media_name(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Heatmap shade (0 = nothing, 4 = an hour or more) for a day's watching time.
+pub fn activity_level(seconds: i64) -> usize {
+    match seconds / 60 {
+        0 => 0,
+        1..=14 => 1,
+        15..=29 => 2,
+        30..=59 => 3,
+        _ => 4,
+    }
+}
+
+fn count_characters(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+pub fn session_stats(entries: &[SubtitleEntry]) -> SessionStats {
+    let mut stats = SessionStats {
+        lines: entries.len(),
+        characters: entries.iter().map(|e| count_characters(&e.text)).sum(),
+        media: Vec::new(),
+    };
+
+    for entry in entries {
+        let Some(path) = &entry.path else {
+            continue;
+        };
+        let media = match stats.media.iter_mut().position(|m| &m.path == path) {
+            Some(i) => &mut stats.media[i],
+            None => {
+                stats.media.push(MediaStats {
+                    path: path.clone(),
+                    lines: 0,
+                    duration: None,
+                    last_subtitle: 0.0,
+                    resume_position: None,
+                });
+                stats.media.last_mut().unwrap()
+            }
+        };
+        media.lines += 1;
+        media.duration = entry.duration.or(media.duration);
+        media.last_subtitle = media.last_subtitle.max(entry.start_time);
+    }
+
+    stats
+}
+
+/// Fill in resume positions from mpv's watch-later files.
+pub fn add_watch_later(stats: &mut SessionStats) {
+    for media in &mut stats.media {
+        media.resume_position = watch_later::resume_position(&media.path);
+    }
+}
+
+/// One stretch of watching a single media file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Unix time of the first and last subtitle.
+    pub start: i64,
+    pub end: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub lines: usize,
+    pub characters: usize,
+    /// Time spent watching, excluding long pauses.
+    pub seconds: i64,
+    /// Labels such as "anime" or "podcast" given in the statistics window.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl SessionRecord {
+    /// "2024-05-01 21:30 · show.mkv"
+    pub fn label(&self) -> String {
+        let start = local_time(self.start);
+        let start = start.get(..16).unwrap_or(&start);
+        match self.path.as_deref() {
+            Some(path) => format!("{} · {}", start, media_name(path)),
+            None => start.to_string(),
+        }
+    }
+}
+
+/// Comma-separated tags, trimmed, without blanks or repeats.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// The last subtitle counted, so reloads of the capture file only count new ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SeenEntry {
+    timestamp: i64,
+    start_time: f64,
+    text: String,
+}
+
+impl SeenEntry {
+    fn matches(&self, entry: &SubtitleEntry) -> bool {
+        self.timestamp == entry.timestamp && self.start_time == entry.start_time && self.text == entry.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayStats {
+    pub date: chrono::NaiveDate,
+    pub lines: usize,
+    pub characters: usize,
+    pub seconds: i64,
+    pub sessions: usize,
+    /// Names of the files watched, in order of first session.
+    pub media: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalUnit {
+    #[default]
+    Minutes,
+    Characters,
+}
+
+impl GoalUnit {
+    pub const ALL: [GoalUnit; 2] = [GoalUnit::Minutes, GoalUnit::Characters];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GoalUnit::Minutes => "minutes",
+            GoalUnit::Characters => "characters",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub unit: GoalUnit,
+    pub target: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoalProgress {
+    pub done: u64,
+    pub target: u64,
+}
+
+impl GoalProgress {
+    pub fn fraction(&self) -> f32 {
+        self.done as f32 / self.target.max(1) as f32
+    }
+}
+
+/// Immersion history, persisted across capture file resets and restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatsDb {
+    pub sessions: Vec<SessionRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_seen: Option<SeenEntry>,
+    #[serde(default)]
+    pub daily_goal: Option<Goal>,
+    #[serde(default)]
+    pub weekly_goal: Option<Goal>,
+    /// Longest run of consecutive days meeting the daily goal.
+    #[serde(default)]
+    pub best_streak: u32,
+    /// When mpv was paused, if it still is (from IPC).
+    #[serde(skip)]
+    paused_since: Option<i64>,
+    /// Seconds spent paused since the last subtitle.
+    #[serde(skip)]
+    paused_seconds: i64,
+}
+
+pub fn stats_db_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/stats.json", home_dir)
+}
+
+fn local_date(timestamp: i64) -> chrono::NaiveDate {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local)
+        .date_naive()
+}
+
+fn local_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// `value` as one CSV field, quoted if it needs to be.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl StatsDb {
+    /// The saved statistics, opened with `key` if they're sealed. `None`
+    /// if they're sealed and `key` can't open them.
+    pub fn load(key: Option<&Key>) -> Option<Self> {
+        Self::load_from(&stats_db_path(), key)
+    }
+
+    fn load_from(path: &str, key: Option<&Key>) -> Option<Self> {
+        let Ok(data) = std::fs::read(path) else {
+            return Some(Self::default());
+        };
+        let data = match crypt::is_sealed_file(&data) {
+            true => key?.open_file(&data)?,
+            false => data,
+        };
+        Some(serde_json::from_slice(&data).unwrap_or_default())
+    }
+
+    /// Save the statistics, sealed if there's a key.
+    pub fn save(&self, key: Option<&Key>) -> Result<(), std::io::Error> {
+        self.save_to(&stats_db_path(), key)
+    }
+
+    fn save_to(&self, path: &str, key: Option<&Key>) -> Result<(), std::io::Error> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        match key {
+            Some(key) => std::fs::write(path, key.seal_file(json.as_bytes())),
+            None => std::fs::write(path, json),
+        }
+    }
+
+    /// Count the entries of a freshly read capture file that haven't been
+    /// counted yet. Returns whether anything changed.
+    pub fn record(&mut self, raw: &[SubtitleEntry]) -> bool {
+        let new = match &self.last_seen {
+            Some(seen) => match raw.iter().rposition(|e| seen.matches(e)) {
+                Some(i) => &raw[i + 1..],
+                None => raw,
+            },
+            None => raw,
+        };
+        for entry in new {
+            self.add_entry(entry);
+        }
+        !new.is_empty()
+    }
+
+    /// Note mpv's pause state so time spent paused isn't counted as watching.
+    pub fn set_paused(&mut self, paused: bool, now: i64) {
+        match (paused, self.paused_since) {
+            (true, None) => self.paused_since = Some(now),
+            (false, Some(since)) => {
+                self.paused_seconds += (now - since).max(0);
+                self.paused_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn add_entry(&mut self, entry: &SubtitleEntry) {
+        let continues = self.sessions.last().is_some_and(|session| {
+            session.path == entry.path && (0..=SESSION_GAP).contains(&(entry.timestamp - session.end))
+        });
+        if !continues {
+            self.sessions.push(SessionRecord {
+                start: entry.timestamp,
+                end: entry.timestamp,
+                path: entry.path.clone(),
+                lines: 0,
+                characters: 0,
+                seconds: 0,
+                tags: Vec::new(),
+            });
+        }
+        let extends = continues && self.last_seen.as_ref().is_some_and(|seen| entry.text.starts_with(&seen.text));
+        let previous_characters = self.last_seen.as_ref().map_or(0, |seen| count_characters(&seen.text));
+
+        let mut paused = self.paused_seconds;
+        if let Some(since) = self.paused_since {
+            paused += (entry.timestamp - since).max(0);
+            self.paused_since = Some(entry.timestamp);
+        }
+        self.paused_seconds = 0;
+
+        let session = self.sessions.last_mut().unwrap();
+        let gap = entry.timestamp - session.end - paused;
+        if (0..=IDLE_GAP).contains(&gap) {
+            session.seconds += gap;
+        }
+        session.end = session.end.max(entry.timestamp);
+        // A line growing as it is typed out counts once, at its final length
+        if extends {
+            session.characters += count_characters(&entry.text).saturating_sub(previous_characters);
+        } else {
+            session.lines += 1;
+            session.characters += count_characters(&entry.text);
+        }
+        self.last_seen = Some(SeenEntry {
+            timestamp: entry.timestamp,
+            start_time: entry.start_time,
+            text: entry.text.clone(),
+        });
+    }
+
+    /// Every tag used on a session, sorted.
+    pub fn all_tags(&self) -> Vec<String> {
+        let tags: std::collections::BTreeSet<&String> = self.sessions.iter().flat_map(|s| &s.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// The sessions tagged `tag` on their own, for history and exports
+    /// limited to that tag. Goals are kept as they are.
+    pub fn tagged(&self, tag: &str) -> StatsDb {
+        StatsDb {
+            sessions: self.sessions.iter().filter(|s| s.tags.iter().any(|t| t == tag)).cloned().collect(),
+            daily_goal: self.daily_goal,
+            weekly_goal: self.weekly_goal,
+            best_streak: self.best_streak,
+            ..StatsDb::default()
+        }
+    }
+
+    /// Totals per local calendar day, oldest first.
+    pub fn daily(&self) -> Vec<DayStats> {
+        let mut days: BTreeMap<chrono::NaiveDate, DayStats> = BTreeMap::new();
+        for session in &self.sessions {
+            let date = local_date(session.start);
+            let day = days.entry(date).or_insert(DayStats {
+                date,
+                lines: 0,
+                characters: 0,
+                seconds: 0,
+                sessions: 0,
+                media: Vec::new(),
+            });
+            day.lines += session.lines;
+            day.characters += session.characters;
+            day.seconds += session.seconds;
+            day.sessions += 1;
+            if let Some(name) = session.path.as_deref().map(media_name) {
+                if !day.media.contains(&name) {
+                    day.media.push(name);
+                }
+            }
+        }
+        days.into_values().collect()
+    }
+
+    /// Amount of `goal.unit` done on days `from..=to`.
+    pub fn goal_amount(&self, goal: &Goal, from: chrono::NaiveDate, to: chrono::NaiveDate) -> u64 {
+        let days = self.daily();
+        let in_range = days.iter().filter(|day| day.date >= from && day.date <= to);
+        match goal.unit {
+            GoalUnit::Minutes => (in_range.map(|day| day.seconds).sum::<i64>() / 60) as u64,
+            GoalUnit::Characters => in_range.map(|day| day.characters as u64).sum(),
+        }
+    }
+
+    /// Progress towards the daily and weekly goals.
+    pub fn goal_progress(&self, today: chrono::NaiveDate) -> (Option<GoalProgress>, Option<GoalProgress>) {
+        let week_start = today - chrono::Days::new(chrono::Datelike::weekday(&today).num_days_from_monday() as u64);
+        let progress = |goal: Goal, from| GoalProgress {
+            done: self.goal_amount(&goal, from, today),
+            target: goal.target,
+        };
+        (
+            self.daily_goal.map(|goal| progress(goal, today)),
+            self.weekly_goal.map(|goal| progress(goal, week_start)),
+        )
+    }
+
+    /// Consecutive days up to today meeting the daily goal. Today only
+    /// counts once it is met, so the streak isn't broken mid-day.
+    pub fn current_streak(&self, today: chrono::NaiveDate) -> u32 {
+        let Some(goal) = self.daily_goal else {
+            return 0;
+        };
+        let met = |date| self.goal_amount(&goal, date, date) >= goal.target.max(1);
+        let mut date = if met(today) { today } else { today - chrono::Days::new(1) };
+        let mut streak = 0;
+        while met(date) {
+            streak += 1;
+            date = date - chrono::Days::new(1);
+        }
+        streak
+    }
+
+    /// Raise `best_streak` if the current streak beats it. Returns whether it changed.
+    pub fn update_best_streak(&mut self, today: chrono::NaiveDate) -> bool {
+        let streak = self.current_streak(today);
+        if streak > self.best_streak {
+            self.best_streak = streak;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn daily_csv(&self) -> String {
+        let mut csv = "date,lines,characters,minutes,sessions\n".to_string();
+        for day in self.daily() {
+            csv.push_str(&format!(
+                "{},{},{},{:.1},{}\n",
+                day.date,
+                day.lines,
+                day.characters,
+                day.seconds as f64 / 60.0,
+                day.sessions
+            ));
+        }
+        csv
+    }
+
+    pub fn sessions_csv(&self) -> String {
+        let mut csv = "start,end,media,lines,characters,minutes,tags\n".to_string();
+        for session in &self.sessions {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{:.1},{}\n",
+                local_time(session.start),
+                local_time(session.end),
+                csv_field(session.path.as_deref().unwrap_or("")),
+                session.lines,
+                session.characters,
+                session.seconds as f64 / 60.0,
+                csv_field(&session.tags.join(";"))
+            ));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let days: Vec<serde_json::Value> = self
+            .daily()
+            .iter()
+            .map(|day| {
+                serde_json::json!({
+                    "date": day.date.to_string(),
+                    "lines": day.lines,
+                    "characters": day.characters,
+                    "minutes": day.seconds as f64 / 60.0,
+                    "sessions": day.sessions,
+                })
+            })
+            .collect();
+        let sessions: Vec<serde_json::Value> = self
+            .sessions
+            .iter()
+            .map(|session| {
+                serde_json::json!({
+                    "start": local_time(session.start),
+                    "end": local_time(session.end),
+                    "media": session.path,
+                    "lines": session.lines,
+                    "characters": session.characters,
+                    "minutes": session.seconds as f64 / 60.0,
+                    "tags": session.tags,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::json!({ "days": days, "sessions": sessions }))
+            .unwrap_or_default()
+    }
+
+    /// Write `scriptview-daily.csv` and `scriptview-sessions.csv`, or
+    /// `scriptview-stats.json`, into `dir`.
+    pub fn export(&self, dir: &str, json: bool) -> Result<String, std::io::Error> {
+        std::fs::create_dir_all(dir)?;
+        if json {
+            let path = format!("{}/scriptview-stats.json", dir);
+            std::fs::write(&path, self.to_json())?;
+            Ok(path)
+        } else {
+            std::fs::write(format!("{}/scriptview-daily.csv", dir), self.daily_csv())?;
+            std::fs::write(format!("{}/scriptview-sessions.csv", dir), self.sessions_csv())?;
+            Ok(format!("{}/scriptview-*.csv", dir))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, start_time: f64, path: Option<&str>) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            path: path.map(str::to_string),
+            duration: path.map(|_| 100.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_counts_lines_and_characters() {
+        let stats = session_stats(&[entry("Hello world", 1.0, None), entry("こんにちは", 2.0, None)]);
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.characters, 15);
+        assert!(stats.media.is_empty());
+    }
+
+    #[test]
+    fn test_groups_by_media() {
+        let stats = session_stats(&[
+            entry("a", 10.0, Some("/a.mkv")),
+            entry("b", 50.0, Some("/a.mkv")),
+            entry("c", 5.0, Some("/b.mkv")),
+        ]);
+        assert_eq!(stats.media.len(), 2);
+        assert_eq!(stats.media[0].lines, 2);
+        assert_eq!(stats.media[0].last_subtitle, 50.0);
+        assert_eq!(stats.media[0].watched_fraction(), Some(0.5));
+    }
+
+    #[test]
+    fn test_watched_fraction_prefers_resume_position() {
+        let mut stats = session_stats(&[entry("a", 10.0, Some("/a.mkv"))]);
+        stats.media[0].resume_position = Some(75.0);
+        assert_eq!(stats.media[0].watched_fraction(), Some(0.75));
+    }
+
+    fn timed(text: &str, timestamp: i64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            timestamp,
+            path: Some("/a.mkv".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_counts_only_new_entries() {
+        let mut db = StatsDb::default();
+        let first = vec![timed("one", 100), timed("two", 110)];
+        assert!(db.record(&first));
+        let mut second = first.clone();
+        second.push(timed("three", 130));
+        assert!(db.record(&second));
+        assert!(!db.record(&second));
+        assert_eq!(db.sessions.len(), 1);
+        assert_eq!(db.sessions[0].lines, 3);
+        assert_eq!(db.sessions[0].seconds, 30);
+    }
+
+    #[test]
+    fn test_record_after_capture_reset() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("one", 100)]);
+        db.record(&[timed("fresh", 200)]);
+        assert_eq!(db.sessions[0].lines, 2);
+    }
+
+    #[test]
+    fn test_record_progressive_line_counts_once() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("I am", 100), timed("I am here", 101)]);
+        assert_eq!(db.sessions[0].lines, 1);
+        assert_eq!(db.sessions[0].characters, 7);
+    }
+
+    #[test]
+    fn test_long_pause_not_counted_and_splits_sessions() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("a", 0), timed("b", IDLE_GAP + 1), timed("c", 2 * IDLE_GAP + SESSION_GAP)]);
+        assert_eq!(db.sessions.len(), 2);
+        assert_eq!(db.sessions[0].seconds, 0);
+        assert_eq!(db.sessions[0].lines, 2);
+    }
+
+    #[test]
+    fn test_paused_time_not_counted() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("a", 100)]);
+        db.set_paused(true, 110);
+        db.set_paused(false, 170);
+        db.record(&[timed("a", 100), timed("b", 200)]);
+        assert_eq!(db.sessions[0].seconds, 40);
+    }
+
+    #[test]
+    fn test_long_pause_within_idle_gap_not_counted() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("a", 0)]);
+        db.set_paused(true, 10);
+        db.record(&[timed("a", 0), timed("b", 250)]);
+        assert_eq!(db.sessions[0].seconds, 10);
+    }
+
+    #[test]
+    fn test_new_media_starts_session() {
+        let mut db = StatsDb::default();
+        let mut other = timed("b", 10);
+        other.path = Some("/b.mkv".to_string());
+        db.record(&[timed("a", 0), other]);
+        assert_eq!(db.sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_daily_lists_media_once() {
+        let mut other = timed("b", 10);
+        other.path = Some("/shows/b.mkv".to_string());
+        let mut db = StatsDb::default();
+        db.record(&[timed("a", 0), other, timed("c", 20)]);
+        assert_eq!(db.daily()[0].media, vec!["a.mkv".to_string(), "b.mkv".to_string()]);
+    }
+
+    #[test]
+    fn test_activity_level() {
+        assert_eq!(activity_level(59), 0);
+        assert_eq!(activity_level(60), 1);
+        assert_eq!(activity_level(20 * 60), 2);
+        assert_eq!(activity_level(45 * 60), 3);
+        assert_eq!(activity_level(3 * 60 * 60), 4);
+    }
+
+    #[test]
+    fn test_daily_csv() {
+        let mut db = StatsDb::default();
+        db.record(&[timed("hello", 1_700_000_000), timed("world", 1_700_000_060)]);
+        let csv = db.daily_csv();
+        let date = local_date(1_700_000_000);
+        assert_eq!(csv, format!("date,lines,characters,minutes,sessions\n{},2,10,1.0,1\n", date));
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(parse_tags(" anime, lecture,,anime "), vec!["anime", "lecture"]);
+        let mut db = StatsDb::default();
+        db.record(&[timed("hello", 1_700_000_000)]);
+        db.record(&[timed("world", 1_700_010_000)]);
+        db.sessions[1].tags = vec!["podcast".to_string(), "news".to_string()];
+        assert_eq!(db.all_tags(), vec!["news", "podcast"]);
+        let tagged = db.tagged("podcast");
+        assert_eq!(tagged.sessions.len(), 1);
+        assert!(tagged.sessions_csv().lines().nth(1).unwrap().ends_with(",podcast;news"));
+        assert!(db.tagged("anime").daily().is_empty());
+    }
+
+    fn on_day(date: chrono::NaiveDate, hour: u32, minutes: i64) -> Vec<SubtitleEntry> {
+        let start = date
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .timestamp();
+        (0..=minutes).map(|m| timed(&format!("line {}", m), start + m * 60)).collect()
+    }
+
+    #[test]
+    fn test_goal_progress_daily_and_weekly() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 5, 8).unwrap(); // Wednesday
+        let mut db = StatsDb::default();
+        db.record(&on_day(today - chrono::Days::new(2), 12, 10)); // Monday
+        db.record(&on_day(today - chrono::Days::new(3), 12, 10)); // previous Sunday
+        db.record(&on_day(today, 12, 5));
+        db.daily_goal = Some(Goal { unit: GoalUnit::Minutes, target: 20 });
+        db.weekly_goal = Some(Goal { unit: GoalUnit::Minutes, target: 60 });
+        let (daily, weekly) = db.goal_progress(today);
+        assert_eq!(daily, Some(GoalProgress { done: 5, target: 20 }));
+        assert_eq!(weekly, Some(GoalProgress { done: 15, target: 60 }));
+    }
+
+    #[test]
+    fn test_streak_counts_consecutive_days() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 5, 8).unwrap();
+        let mut db = StatsDb::default();
+        for days_ago in [4, 2, 1] {
+            db.record(&on_day(today - chrono::Days::new(days_ago), 12, 10));
+        }
+        db.daily_goal = Some(Goal { unit: GoalUnit::Minutes, target: 10 });
+        // Today isn't met yet, so the streak runs through yesterday
+        assert_eq!(db.current_streak(today), 2);
+        assert!(db.update_best_streak(today));
+        assert_eq!(db.best_streak, 2);
+        db.record(&on_day(today, 12, 10));
+        assert_eq!(db.current_streak(today), 3);
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("/plain/path.mkv"), "/plain/path.mkv");
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_watched_fraction_needs_duration() {
+        let mut stats = session_stats(&[entry("a", 10.0, Some("/a.mkv"))]);
+        stats.media[0].duration = None;
+        assert_eq!(stats.media[0].watched_fraction(), None);
+    }
+
+    #[test]
+    fn test_sealed_save_and_load() {
+        let path = std::env::temp_dir().join(format!("scriptview-stats-{}.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let db = StatsDb {
+            best_streak: 4,
+            ..Default::default()
+        };
+        let key = Key::for_tests("hunter2");
+        db.save_to(&path, Some(&key)).unwrap();
+        assert!(crypt::is_sealed_file(&std::fs::read(&path).unwrap()));
+        assert!(StatsDb::load_from(&path, None).is_none());
+        assert!(StatsDb::load_from(&path, Some(&Key::for_tests("hunter3"))).is_none());
+        assert_eq!(StatsDb::load_from(&path, Some(&key)).unwrap().best_streak, 4);
+
+        db.save_to(&path, None).unwrap();
+        assert_eq!(StatsDb::load_from(&path, None).unwrap().best_streak, 4);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(StatsDb::load_from(&path, None).unwrap().best_streak, 0);
+    }
+}
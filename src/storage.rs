@@ -0,0 +1,260 @@
+//! Where the raw stream is kept. JSON lines files, one per day, need nothing
+//! but the file system and can be read with any text tool; SQLite (built
+//! with the `sqlite` feature) keeps months of history in one indexed file
+//! that stays quick to open a day from. `--migrate-storage` copies the
+//! history from one to the other. Either can hold lines sealed with the
+//! history key (see [`crate::crypt`]).
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypt::Key;
+use crate::raw;
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    #[default]
+    Jsonl,
+    Sqlite,
+}
+
+impl StorageKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            StorageKind::Jsonl => "JSON lines files",
+            StorageKind::Sqlite => "SQLite database",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "jsonl" | "json" => Some(StorageKind::Jsonl),
+            "sqlite" => Some(StorageKind::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+pub trait Storage {
+    /// Add lines as they arrive.
+    fn append(&mut self, entries: &[SubtitleEntry]) -> Result<(), String>;
+    /// The days with lines, as "2024-05-01", newest first.
+    fn days(&self) -> Vec<String>;
+    /// A day's lines in the order they arrived.
+    fn read_day(&self, day: &str) -> Result<Vec<SubtitleEntry>, String>;
+}
+
+/// The storage of `kind` in its usual place, sealing new lines with `key`
+/// if there is one.
+pub fn open(kind: StorageKind, key: Option<Key>) -> Result<Box<dyn Storage>, String> {
+    match kind {
+        StorageKind::Jsonl => Ok(Box::new(JsonlStorage::new(raw::raw_dir(), key))),
+        #[cfg(feature = "sqlite")]
+        StorageKind::Sqlite => Ok(Box::new(SqliteStorage::open(&sqlite_path(), key)?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageKind::Sqlite => Err("This build has no SQLite storage; build with --features sqlite".to_string()),
+    }
+}
+
+/// Copy the lines `to` doesn't have yet from `from`, calling `progress` with
+/// each day copied to and its number of lines. A day `to` already has gets
+/// the lines whose arrival time and text it lacks, after its own. Returns how
+/// many days were copied to.
+pub fn migrate(
+    from: &dyn Storage,
+    to: &mut dyn Storage,
+    mut progress: impl FnMut(&str, usize),
+) -> Result<usize, String> {
+    let existing = to.days();
+    let mut copied = 0;
+    for day in from.days().into_iter().rev() {
+        let mut lines = from.read_day(&day)?;
+        if existing.contains(&day) {
+            let there: HashSet<(i64, String)> =
+                to.read_day(&day)?.into_iter().map(|line| (line.timestamp, line.text)).collect();
+            lines.retain(|line| !there.contains(&(line.timestamp, line.text.clone())));
+            if lines.is_empty() {
+                continue;
+            }
+        }
+        to.append(&lines)?;
+        progress(&day, lines.len());
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// One JSON lines file per day; see [`raw`].
+pub struct JsonlStorage {
+    dir: String,
+    key: Option<Key>,
+}
+
+impl JsonlStorage {
+    pub fn new(dir: String, key: Option<Key>) -> Self {
+        Self { dir, key }
+    }
+}
+
+impl Storage for JsonlStorage {
+    fn append(&mut self, entries: &[SubtitleEntry]) -> Result<(), String> {
+        raw::append(&self.dir, entries, self.key.as_ref()).map_err(|e| e.to_string())
+    }
+
+    fn days(&self) -> Vec<String> {
+        raw::list_days(&self.dir).iter().map(|path| raw::day_label(path)).collect()
+    }
+
+    fn read_day(&self, day: &str) -> Result<Vec<SubtitleEntry>, String> {
+        raw::read(&format!("{}/{}.jsonl", self.dir, day), self.key.as_ref()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_path() -> String {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/.local/share/scriptview/raw.sqlite", home_dir)
+}
+
+/// Every line in one table, indexed by day, each kept as its JSON so new
+/// fields don't need a schema change.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage {
+    connection: rusqlite::Connection,
+    key: Option<Key>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    pub fn open(path: &str, key: Option<Key>) -> Result<Self, String> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let connection = rusqlite::Connection::open(path).map_err(|e| format!("Could not open {}: {}", path, e))?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS lines (
+                     id INTEGER PRIMARY KEY,
+                     day TEXT NOT NULL,
+                     timestamp INTEGER NOT NULL,
+                     entry TEXT NOT NULL
+                 );
+                 CREATE INDEX IF NOT EXISTS lines_day ON lines (day);",
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(Self { connection, key })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn append(&mut self, entries: &[SubtitleEntry]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut insert = transaction
+                .prepare_cached("INSERT INTO lines (day, timestamp, entry) VALUES (?1, ?2, ?3)")
+                .map_err(|e| e.to_string())?;
+            for entry in entries {
+                let json = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+                let json = match &self.key {
+                    Some(key) => key.seal_line(&json),
+                    None => json,
+                };
+                insert
+                    .execute(rusqlite::params![raw::day_of(entry.timestamp), entry.timestamp, json])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn days(&self) -> Vec<String> {
+        let Ok(mut query) = self.connection.prepare("SELECT DISTINCT day FROM lines ORDER BY day DESC") else {
+            return Vec::new();
+        };
+        query
+            .query_map([], |row| row.get(0))
+            .map(|rows| rows.filter_map(|row| row.ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn read_day(&self, day: &str) -> Result<Vec<SubtitleEntry>, String> {
+        let mut query = self
+            .connection
+            .prepare("SELECT entry FROM lines WHERE day = ?1 ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        let rows = query
+            .query_map([day], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        // Like the day files, skip anything that doesn't parse or open
+        Ok(rows
+            .filter_map(|row| row.ok())
+            .filter_map(|entry| crate::crypt::open_line(self.key.as_ref(), &entry))
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, timestamp: i64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("scriptview-{}-{}", name, std::process::id()));
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn check_migrate(to: &mut dyn Storage, name: &str) {
+        let dir = temp_dir(&format!("{}-from", name));
+        let mut from = JsonlStorage::new(dir.clone(), None);
+        let noon = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .and_then(|t| t.and_local_timezone(chrono::Local).single())
+            .unwrap()
+            .timestamp();
+        from.append(&[line("昨日", noon - 86400), line("猫", noon), line("猫が", noon + 1)])
+            .unwrap();
+        let mut seen = Vec::new();
+        assert_eq!(migrate(&from, to, |day, lines| seen.push((day.to_string(), lines))).unwrap(), 2);
+        assert_eq!(seen, vec![(raw::day_of(noon - 86400), 1), (raw::day_of(noon), 2)]);
+        assert_eq!(to.days(), from.days());
+        let texts: Vec<String> = to.read_day(&raw::day_of(noon)).unwrap().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["猫", "猫が"]);
+        // Days already there aren't copied twice
+        assert_eq!(migrate(&from, to, |_, _| {}).unwrap(), 0);
+
+        // Lines that came after the last migration are
+        from.append(&[line("猫がいる", noon + 2)]).unwrap();
+        let mut seen = Vec::new();
+        assert_eq!(migrate(&from, to, |day, lines| seen.push((day.to_string(), lines))).unwrap(), 1);
+        assert_eq!(seen, vec![(raw::day_of(noon), 1)]);
+        let texts: Vec<String> = to.read_day(&raw::day_of(noon)).unwrap().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["猫", "猫が", "猫がいる"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_jsonl() {
+        let dir = temp_dir("storage-to");
+        check_migrate(&mut JsonlStorage::new(dir.clone(), None), "jsonl");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_migrate_sqlite() {
+        check_migrate(&mut SqliteStorage::open(":memory:", None).unwrap(), "sqlite");
+    }
+}
@@ -0,0 +1,169 @@
+//! Changes between two reads of the capture file, so whatever follows the
+//! stream (the sync hub, MQTT, the webhook, state kept per row) gets told
+//! what was added, modified or removed instead of comparing snapshots
+//! itself. The shown list is updated the same way. Entries are matched by
+//! the IDs `EntryIds` gave them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ids::EntryId;
+use crate::SubtitleEntry;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A line that wasn't there before, at the end.
+    Append(SubtitleEntry),
+    /// A line that wasn't there before, after the line with this ID (first
+    /// if `None`), such as one seen after seeking back in a list ordered by
+    /// media time.
+    Insert(Option<EntryId>, SubtitleEntry),
+    /// A line whose fields changed, such as the end time being filled in.
+    Modify(SubtitleEntry),
+    /// A line that's gone, dropped off the front or with the file started
+    /// afresh, as it was.
+    Remove(SubtitleEntry),
+}
+
+impl Change {
+    pub fn entry(&self) -> &SubtitleEntry {
+        match self {
+            Change::Append(entry) | Change::Insert(_, entry) | Change::Modify(entry) | Change::Remove(entry) => entry,
+        }
+    }
+
+    /// How the change is sent to MQTT and the webhook: the line's fields as
+    /// in the capture file, with `"change": "modified"` or `"removed"`
+    /// added unless the line is new.
+    pub fn payload(&self) -> serde_json::Value {
+        let mut payload = serde_json::to_value(self.entry()).unwrap_or_default();
+        let change = match self {
+            Change::Append(_) | Change::Insert(..) => None,
+            Change::Modify(_) => Some("modified"),
+            Change::Remove(_) => Some("removed"),
+        };
+        if let (Some(change), Some(fields)) = (change, payload.as_object_mut()) {
+            fields.insert("change".to_string(), change.into());
+        }
+        payload
+    }
+}
+
+/// What turns `old` into `new`: removals first, then modifications and
+/// additions in `new`'s order. Lines that only moved aren't noticed.
+pub fn diff(old: &[SubtitleEntry], new: &[SubtitleEntry]) -> Vec<Change> {
+    let old_by_id: HashMap<EntryId, &SubtitleEntry> = old.iter().map(|entry| (entry.id, entry)).collect();
+    let new_ids: HashSet<EntryId> = new.iter().map(|entry| entry.id).collect();
+    let mut changes: Vec<Change> = old
+        .iter()
+        .filter(|entry| !new_ids.contains(&entry.id))
+        .map(|entry| Change::Remove(entry.clone()))
+        .collect();
+    // Lines after the last one kept are appended; any before it are inserted
+    let tail = new.iter().rposition(|entry| old_by_id.contains_key(&entry.id)).map_or(0, |i| i + 1);
+    for (i, entry) in new.iter().enumerate() {
+        match old_by_id.get(&entry.id) {
+            None if i >= tail => changes.push(Change::Append(entry.clone())),
+            None => changes.push(Change::Insert(i.checked_sub(1).map(|before| new[before].id), entry.clone())),
+            Some(&before) if before != entry => changes.push(Change::Modify(entry.clone())),
+            Some(_) => {}
+        }
+    }
+    changes
+}
+
+pub fn apply(entries: &mut Vec<SubtitleEntry>, changes: &[Change]) {
+    for change in changes {
+        match change {
+            Change::Append(entry) => entries.push(entry.clone()),
+            Change::Insert(after, entry) => {
+                let at = after
+                    .and_then(|after| entries.iter().position(|old| old.id == after))
+                    .map_or(0, |i| i + 1);
+                entries.insert(at, entry.clone());
+            }
+            Change::Modify(entry) => {
+                if let Some(old) = entries.iter_mut().find(|old| old.id == entry.id) {
+                    *old = entry.clone();
+                }
+            }
+            Change::Remove(removed) => entries.retain(|entry| entry.id != removed.id),
+        }
+    }
+}
+
+/// The new lines among `changes`, appended or inserted.
+pub fn appended(changes: &[Change]) -> Vec<SubtitleEntry> {
+    changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Append(entry) | Change::Insert(_, entry) => Some(entry.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, id: EntryId) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![line("a", 1), line("b", 2)];
+        let mut ended = line("b", 2);
+        ended.end_time = Some(3.0);
+        let new = vec![ended.clone(), line("c", 3)];
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![Change::Remove(line("a", 1)), Change::Modify(ended), Change::Append(line("c", 3))]
+        );
+        assert_eq!(appended(&changes), vec![line("c", 3)]);
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_apply_reproduces_new() {
+        let old = vec![line("a", 1), line("b", 2), line("c", 3)];
+        // Started afresh
+        let new = vec![line("x", 4), line("y", 5)];
+        let mut entries = old.clone();
+        apply(&mut entries, &diff(&old, &new));
+        assert_eq!(entries, new);
+    }
+
+    #[test]
+    fn test_lines_slotted_in_are_inserted() {
+        let old = vec![line("a", 1), line("c", 3)];
+        let new = vec![line("z", 5), line("a", 1), line("b", 2), line("c", 3), line("d", 4)];
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Insert(None, line("z", 5)),
+                Change::Insert(Some(1), line("b", 2)),
+                Change::Append(line("d", 4)),
+            ]
+        );
+        assert_eq!(appended(&changes).len(), 3);
+        let mut entries = old.clone();
+        apply(&mut entries, &changes);
+        assert_eq!(entries, new);
+    }
+
+    #[test]
+    fn test_payload_marks_changes() {
+        let added = Change::Append(line("a", 1)).payload();
+        assert_eq!(added["text"], "a");
+        assert!(added.get("change").is_none());
+        assert_eq!(Change::Modify(line("a", 1)).payload()["change"], "modified");
+        assert_eq!(Change::Remove(line("a", 1)).payload()["change"], "removed");
+    }
+}
@@ -0,0 +1,142 @@
+//! The report shown when a viewing session ends and saved as Markdown next
+//! to the capture file and its archives.
+
+use crate::crypt::Key;
+use crate::starred::StarredLine;
+use crate::stats::SessionRecord;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub record: SessionRecord,
+    /// The media title, or its file name.
+    pub title: String,
+    /// Words not marked known, in the order they first came up.
+    pub new_words: Vec<String>,
+    pub starred: Vec<StarredLine>,
+}
+
+fn local_time(timestamp: i64, format: &str) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local)
+        .format(format)
+        .to_string()
+}
+
+impl Summary {
+    pub fn to_markdown(&self) -> String {
+        let record = &self.record;
+        let mut markdown = format!("# {}\n\n", self.title);
+        markdown.push_str(&format!(
+            "- Watched: {} – {}\n",
+            local_time(record.start, "%Y-%m-%d %H:%M"),
+            local_time(record.end, "%H:%M")
+        ));
+        markdown.push_str(&format!("- Duration: {} min\n", record.seconds / 60));
+        markdown.push_str(&format!("- Lines: {}\n", record.lines));
+        markdown.push_str(&format!("- Characters: {}\n", record.characters));
+        markdown.push_str(&format!("\n## New words ({})\n\n", self.new_words.len()));
+        if self.new_words.is_empty() {
+            markdown.push_str("None.\n");
+        } else {
+            markdown.push_str(&self.new_words.join(", "));
+            markdown.push('\n');
+        }
+        markdown.push_str(&format!("\n## Starred lines ({})\n\n", self.starred.len()));
+        for line in &self.starred {
+            let text = line.text.replace('\n', " ");
+            markdown.push_str(&format!("- {} ({})\n", text, crate::format_timestamp(line.start_time)));
+        }
+        if self.starred.is_empty() {
+            markdown.push_str("None.\n");
+        }
+        markdown
+    }
+
+    /// `/tmp/mpv-subtitles.json` → `/tmp/mpv-subtitles-2024-05-01-2130.md`,
+    /// named by the session start.
+    pub fn path(&self, capture_file: &str) -> String {
+        let stem = capture_file.strip_suffix(".json").unwrap_or(capture_file);
+        format!("{}-{}.md", stem, local_time(self.record.start, "%Y-%m-%d-%H%M"))
+    }
+
+    /// Write the report, sealed as `<path>.enc` if there's a key, returning
+    /// where it went.
+    pub fn save(&self, capture_file: &str, key: Option<&Key>) -> Result<String, std::io::Error> {
+        let path = self.path(capture_file);
+        match key {
+            Some(key) => {
+                let path = format!("{}.enc", path);
+                std::fs::write(&path, key.seal_file(self.to_markdown().as_bytes()))?;
+                Ok(path)
+            }
+            None => {
+                std::fs::write(&path, self.to_markdown())?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+/// Whether the file named `name` is a summary saved for `capture_file`,
+/// sealed or not.
+pub fn is_summary(capture_file: &str, name: &str) -> bool {
+    let stem = std::path::Path::new(capture_file.strip_suffix(".json").unwrap_or(capture_file))
+        .file_name()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = name.strip_suffix(".enc").unwrap_or(name);
+    name.strip_suffix(".md")
+        .and_then(|name| name.strip_prefix(stem.as_str()))
+        .and_then(|name| name.strip_prefix('-'))
+        .is_some_and(|time| chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d-%H%M").is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> Summary {
+        Summary {
+            record: SessionRecord {
+                start: 1_700_000_000,
+                end: 1_700_001_500,
+                path: Some("/videos/show.mkv".to_string()),
+                lines: 120,
+                characters: 1500,
+                seconds: 1440,
+                tags: Vec::new(),
+            },
+            title: "show.mkv".to_string(),
+            new_words: vec!["猫".to_string(), "散歩".to_string()],
+            starred: vec![StarredLine {
+                text: "猫が\n散歩する".to_string(),
+                start_time: 75.0,
+                end_time: None,
+                path: Some("/videos/show.mkv".to_string()),
+                title: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = summary().to_markdown();
+        assert!(markdown.starts_with("# show.mkv\n"));
+        assert!(markdown.contains("- Duration: 24 min\n- Lines: 120\n- Characters: 1500\n"));
+        assert!(markdown.contains("## New words (2)\n\n猫, 散歩\n"));
+        assert!(markdown.contains("- 猫が 散歩する (1:15.0)\n"));
+    }
+
+    #[test]
+    fn test_path() {
+        let path = summary().path("/tmp/mpv-subtitles.json");
+        assert!(path.starts_with("/tmp/mpv-subtitles-20"));
+        assert!(path.ends_with(".md"));
+        let name = path.strip_prefix("/tmp/").unwrap();
+        assert!(is_summary("/tmp/mpv-subtitles.json", name));
+        assert!(is_summary("/tmp/mpv-subtitles.json", &format!("{}.enc", name)));
+        assert!(!is_summary("/tmp/other.json", name));
+        assert!(!is_summary("/tmp/mpv-subtitles.json", "mpv-subtitles-notes.md"));
+    }
+}
@@ -0,0 +1,508 @@
+//! Mirroring the transcript to another ScriptView on the network, such as a
+//! tablet next to the TV. The instance that captures runs a hub; others
+//! connect to it as clients, show its lines, and star lines on it.
+//!
+//! The protocol is newline-delimited JSON over TCP. The hub sends the whole
+//! capture when a client connects, then each line appended, inserted,
+//! modified or removed; a client that falls behind is sent the whole capture
+//! again. The starred list is small and sent whole whenever it changes. A
+//! client sends each star it toggles. There's no authentication, so only run a hub
+//! on a network you trust.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{broadcast, watch};
+
+use crate::ids::EntryId;
+use crate::runtime::{StopSignal, Stopped};
+use crate::starred::StarredLine;
+use crate::store::Change;
+use crate::SubtitleEntry;
+
+pub const DEFAULT_PORT: u16 = 7275;
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Changes a client may fall behind by before it's sent the whole capture.
+const BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    #[default]
+    Off,
+    /// Serve this instance's transcript to others.
+    Hub,
+    /// Show another instance's transcript instead of capturing.
+    Client,
+}
+
+impl SyncMode {
+    pub const ALL: [SyncMode; 3] = [SyncMode::Off, SyncMode::Hub, SyncMode::Client];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncMode::Off => "Off",
+            SyncMode::Hub => "Share this transcript (hub)",
+            SyncMode::Client => "Follow another ScriptView",
+        }
+    }
+}
+
+/// A line with its ID, which entries don't serialize.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Line {
+    id: EntryId,
+    #[serde(flatten)]
+    entry: SubtitleEntry,
+}
+
+impl Line {
+    fn new(entry: &SubtitleEntry) -> Self {
+        Self {
+            id: entry.id,
+            entry: entry.clone(),
+        }
+    }
+
+    fn into_entry(self) -> SubtitleEntry {
+        SubtitleEntry { id: self.id, ..self.entry }
+    }
+}
+
+/// A `Change` as sent to clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+enum Update {
+    Append { line: Line },
+    Insert { after: Option<EntryId>, line: Line },
+    Modify { line: Line },
+    Remove { id: EntryId },
+}
+
+impl Update {
+    fn new(change: &Change) -> Self {
+        match change {
+            Change::Append(entry) => Update::Append { line: Line::new(entry) },
+            Change::Insert(after, entry) => Update::Insert {
+                after: *after,
+                line: Line::new(entry),
+            },
+            Change::Modify(entry) => Update::Modify { line: Line::new(entry) },
+            Change::Remove(entry) => Update::Remove { id: entry.id },
+        }
+    }
+
+    fn into_change(self) -> Change {
+        match self {
+            Update::Append { line } => Change::Append(line.into_entry()),
+            Update::Insert { after, line } => Change::Insert(after, line.into_entry()),
+            Update::Modify { line } => Change::Modify(line.into_entry()),
+            Update::Remove { id } => Change::Remove(SubtitleEntry { id, ..Default::default() }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// The whole capture, on connecting or after falling behind.
+    Lines { lines: Vec<Line> },
+    /// What changed in the capture since.
+    Changes { updates: Vec<Update> },
+    Starred { items: Vec<StarredLine> },
+    /// From a client: a line it starred or unstarred.
+    Star { line: StarredLine, starred: bool },
+}
+
+fn encode(message: &Message) -> String {
+    serde_json::to_string(message).unwrap_or_default() + "\n"
+}
+
+/// Where a client keeps the hub's lines, which it watches like a capture file.
+pub fn mirror_path() -> String {
+    std::env::temp_dir().join("scriptview-sync.json").to_string_lossy().into_owned()
+}
+
+/// The capture as clients have it, and its changes encoded for them. A
+/// client subscribes while holding the lines, so it gets every change after
+/// them exactly once.
+struct Lines {
+    entries: Mutex<Vec<SubtitleEntry>>,
+    changes: broadcast::Sender<String>,
+}
+
+impl Lines {
+    /// The whole capture, encoded, and the changes that follow it.
+    fn subscribe(&self) -> (String, broadcast::Receiver<String>) {
+        let entries = self.entries.lock().unwrap();
+        let message = encode(&Message::Lines {
+            lines: entries.iter().map(Line::new).collect(),
+        });
+        (message, self.changes.subscribe())
+    }
+}
+
+/// The hub, serving whatever it's given to publish until dropped.
+pub struct Hub {
+    pub port: u16,
+    lines: Arc<Lines>,
+    /// The latest encoded starred list; empty until something is published.
+    starred: watch::Sender<String>,
+    /// Stars toggled on clients: the line and whether it's now starred.
+    pub stars: Receiver<(StarredLine, bool)>,
+    clients: Arc<AtomicUsize>,
+    _stop: StopSignal,
+}
+
+impl Hub {
+    /// Listen on `port` on every interface (0 picks a free port).
+    pub fn start(port: u16) -> Result<Self, std::io::Error> {
+        let listener = crate::runtime::bind_lan(port)?;
+        let port = listener.local_addr()?.port();
+        let lines = Arc::new(Lines {
+            entries: Mutex::new(Vec::new()),
+            changes: broadcast::channel(BACKLOG).0,
+        });
+        let (starred, starred_rx) = watch::channel(String::new());
+        let (stars_tx, stars) = channel();
+        let clients = Arc::new(AtomicUsize::new(0));
+        let (stop, mut stopped) = crate::runtime::stop_signal();
+        let counter = clients.clone();
+        let shared = lines.clone();
+        crate::runtime::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = stopped.wait() => break,
+                };
+                match accepted {
+                    Ok((stream, _)) => {
+                        crate::runtime::spawn(serve_client(
+                            stream,
+                            shared.clone(),
+                            starred_rx.clone(),
+                            stars_tx.clone(),
+                            counter.clone(),
+                            stopped.clone(),
+                        ));
+                    }
+                    Err(e) => eprintln!("Warning: Sync connection failed: {}", e),
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            lines,
+            starred,
+            stars,
+            clients,
+            _stop: stop,
+        })
+    }
+
+    /// Pass changes to the capture on to the clients.
+    pub fn publish_changes(&self, changes: &[Change]) {
+        if changes.is_empty() {
+            return;
+        }
+        let mut entries = self.lines.entries.lock().unwrap();
+        crate::store::apply(&mut entries, changes);
+        let updates = changes.iter().map(Update::new).collect();
+        // No clients is no error
+        let _ = self.lines.changes.send(encode(&Message::Changes { updates }));
+    }
+
+    pub fn publish_starred(&self, items: &[StarredLine]) {
+        publish(&self.starred, encode(&Message::Starred { items: items.to_vec() }));
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+}
+
+/// Send `message` to the clients unless it's what they already have.
+fn publish(channel: &watch::Sender<String>, message: String) {
+    channel.send_if_modified(|current| {
+        if *current == message {
+            return false;
+        }
+        *current = message;
+        true
+    });
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    lines: Arc<Lines>,
+    mut starred: watch::Receiver<String>,
+    stars: Sender<(StarredLine, bool)>,
+    clients: Arc<AtomicUsize>,
+    mut stopped: Stopped,
+) {
+    clients.fetch_add(1, Ordering::Relaxed);
+    let (read, mut write) = stream.into_split();
+    let mut incoming = tokio::io::BufReader::new(read).lines();
+    // What there is so far, then each change
+    let (everything, mut changes) = lines.subscribe();
+    let mut pending = vec![everything, starred.borrow_and_update().clone()];
+    loop {
+        for message in pending.drain(..).filter(|message| !message.is_empty()) {
+            if write.write_all(message.as_bytes()).await.is_err() {
+                clients.fetch_sub(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        tokio::select! {
+            change = changes.recv() => match change {
+                Ok(message) => pending.push(message),
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let (everything, caught_up) = lines.subscribe();
+                    pending.push(everything);
+                    changes = caught_up;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            changed = starred.changed() => match changed {
+                Ok(()) => pending.push(starred.borrow_and_update().clone()),
+                Err(_) => break,
+            },
+            line = incoming.next_line() => match line {
+                Ok(Some(line)) => match serde_json::from_str(&line) {
+                    Ok(Message::Star { line, starred }) => {
+                        let _ = stars.send((line, starred));
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Warning: Ignoring message from sync client: {}", e),
+                },
+                _ => break,
+            },
+            _ = stopped.wait() => break,
+        }
+    }
+    clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    Connected,
+    /// Lost or couldn't reach the hub, and why; retried every few seconds.
+    Disconnected(String),
+    /// The hub's starred lines.
+    Starred(Vec<StarredLine>),
+}
+
+/// A client, following the hub until dropped.
+pub struct Client {
+    /// `host:port` of the hub.
+    pub address: String,
+    outgoing: UnboundedSender<String>,
+    pub events: Receiver<ClientEvent>,
+    _stop: StopSignal,
+}
+
+impl Client {
+    /// Follow the hub at `address` (`host` or `host:port`), writing its lines
+    /// to `mirror`.
+    pub fn connect(address: &str, mirror: &str) -> Self {
+        let address = match address.trim() {
+            address if address.contains(':') => address.to_string(),
+            host => format!("{}:{}", host, DEFAULT_PORT),
+        };
+        // Something to watch until the hub sends its lines
+        if !std::path::Path::new(mirror).exists() {
+            let _ = std::fs::write(mirror, "[]");
+        }
+        let (outgoing, outgoing_rx) = unbounded_channel();
+        let (events_tx, events) = channel();
+        let (stop, stopped) = crate::runtime::stop_signal();
+        crate::runtime::spawn(run_client(address.clone(), mirror.to_string(), outgoing_rx, events_tx, stopped));
+        Self {
+            address,
+            outgoing,
+            events,
+            _stop: stop,
+        }
+    }
+
+    /// Tell the hub a line was starred or unstarred here. Sent once
+    /// connected if the hub can't be reached right now.
+    pub fn star(&self, line: StarredLine, starred: bool) {
+        let _ = self.outgoing.send(encode(&Message::Star { line, starred }));
+    }
+}
+
+async fn run_client(
+    address: String,
+    mirror: String,
+    mut outgoing: UnboundedReceiver<String>,
+    events: Sender<ClientEvent>,
+    mut stopped: Stopped,
+) {
+    // Only changes are reported, not every failed retry
+    let mut failing = false;
+    loop {
+        let connected = tokio::select! {
+            connected = TcpStream::connect(&address) => connected,
+            _ = stopped.wait() => return,
+        };
+        let reason = match connected {
+            Ok(stream) => {
+                failing = false;
+                let _ = events.send(ClientEvent::Connected);
+                match follow_hub(stream, &mirror, &mut outgoing, &events, &mut stopped).await {
+                    Some(reason) => reason,
+                    None => return,
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+        if !failing {
+            let _ = events.send(ClientEvent::Disconnected(reason));
+            failing = true;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RETRY_DELAY) => {}
+            _ = stopped.wait() => return,
+        }
+    }
+}
+
+fn write_mirror(mirror: &str, entries: &[SubtitleEntry]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(entries).unwrap_or_default();
+    std::fs::write(mirror, content).map_err(|e| format!("Could not write {}: {}", mirror, e))
+}
+
+/// Pass messages to and from a connected hub. Returns why the connection
+/// ended, or `None` when the client is stopping.
+async fn follow_hub(
+    stream: TcpStream,
+    mirror: &str,
+    outgoing: &mut UnboundedReceiver<String>,
+    events: &Sender<ClientEvent>,
+    stopped: &mut Stopped,
+) -> Option<String> {
+    let (read, mut write) = stream.into_split();
+    let mut incoming = tokio::io::BufReader::new(read).lines();
+    let mut entries: Vec<SubtitleEntry> = Vec::new();
+    loop {
+        tokio::select! {
+            line = incoming.next_line() => match line {
+                Ok(Some(line)) => match serde_json::from_str(&line) {
+                    Ok(Message::Lines { lines }) => {
+                        entries = lines.into_iter().map(Line::into_entry).collect();
+                        if let Err(e) = write_mirror(mirror, &entries) {
+                            return Some(e);
+                        }
+                    }
+                    Ok(Message::Changes { updates }) => {
+                        let changes: Vec<Change> = updates.into_iter().map(Update::into_change).collect();
+                        crate::store::apply(&mut entries, &changes);
+                        if let Err(e) = write_mirror(mirror, &entries) {
+                            return Some(e);
+                        }
+                    }
+                    Ok(Message::Starred { items }) => {
+                        let _ = events.send(ClientEvent::Starred(items));
+                    }
+                    Ok(Message::Star { .. }) => {}
+                    Err(e) => eprintln!("Warning: Ignoring message from sync hub: {}", e),
+                },
+                Ok(None) => return Some("the hub closed the connection".to_string()),
+                Err(e) => return Some(e.to_string()),
+            },
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if let Err(e) = write.write_all(message.as_bytes()).await {
+                        return Some(e.to_string());
+                    }
+                }
+                None => return None,
+            },
+            _ = stopped.wait() => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn wait_for<T>(mut check: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(value) = check() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_client_mirrors_hub_and_stars_back() {
+        let mirror = std::env::temp_dir().join(format!("scriptview-sync-{}.json", std::process::id()));
+        let mirror = mirror.to_string_lossy().into_owned();
+        let hub = Hub::start(0).unwrap();
+        let entries = vec![SubtitleEntry {
+            text: "猫がいる".to_string(),
+            start_time: 1.5,
+            id: 1,
+            ..Default::default()
+        }];
+        hub.publish_changes(&crate::store::diff(&[], &entries));
+        let line = StarredLine {
+            text: entries[0].text.clone(),
+            start_time: entries[0].start_time,
+            end_time: None,
+            path: None,
+            title: None,
+        };
+        hub.publish_starred(std::slice::from_ref(&line));
+
+        let client = Client::connect(&format!("127.0.0.1:{}", hub.port), &mirror);
+        // IDs aren't kept in the file
+        let mirrored = |expected: &[SubtitleEntry]| {
+            let expected: Vec<SubtitleEntry> =
+                expected.iter().map(|entry| SubtitleEntry { id: 0, ..entry.clone() }).collect();
+            wait_for(|| crate::session::read_entries(&mirror).filter(|read| *read == expected));
+        };
+        mirrored(&entries);
+        let events: Vec<ClientEvent> = wait_for(|| {
+            let events: Vec<_> = client.events.try_iter().collect();
+            (!events.is_empty()).then_some(events)
+        });
+        assert_eq!(events[0], ClientEvent::Connected);
+        let starred = match events.get(1) {
+            Some(event) => event.clone(),
+            None => client.events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        };
+        assert_eq!(starred, ClientEvent::Starred(vec![line.clone()]));
+        assert_eq!(hub.clients(), 1);
+
+        client.star(line.clone(), false);
+        assert_eq!(hub.stars.recv_timeout(Duration::from_secs(5)).unwrap(), (line, false));
+
+        let mut ended = entries[0].clone();
+        ended.end_time = Some(3.0);
+        let next = SubtitleEntry {
+            text: "犬もいる".to_string(),
+            start_time: 4.0,
+            id: 2,
+            ..Default::default()
+        };
+        hub.publish_changes(&[Change::Modify(ended.clone()), Change::Append(next.clone())]);
+        mirrored(&[ended.clone(), next.clone()]);
+        hub.publish_changes(&[Change::Remove(ended)]);
+        mirrored(&[next]);
+        let _ = std::fs::remove_file(&mirror);
+    }
+}
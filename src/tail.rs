@@ -0,0 +1,102 @@
+//! The developer view of the capture file: its last records exactly as
+//! written, each with whether it parses as a `SubtitleEntry` and why not.
+//! For checking changes to the player scripts against what the viewer
+//! expects, where the main list just leaves out what it can't read.
+
+use serde_json::value::RawValue;
+
+use crate::SubtitleEntry;
+
+/// One record of the capture file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Its position in the file, from 0.
+    pub index: usize,
+    /// The JSON as it is in the file.
+    pub json: String,
+    /// Why it isn't a subtitle entry, if it isn't.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tail {
+    /// Size of the file in bytes.
+    pub size: usize,
+    /// Records in the file.
+    pub total: usize,
+    /// The last records, oldest first.
+    pub records: Vec<Record>,
+    /// Why the file as a whole didn't parse, with its last bytes to look at.
+    pub error: Option<(String, String)>,
+}
+
+/// The last `count` records of `content`, or if it isn't a JSON array, what's
+/// wrong with it and its last `count * 200` bytes or so.
+pub fn parse(content: &str, count: usize) -> Tail {
+    let size = content.len();
+    let records: Vec<&RawValue> = match serde_json::from_str(content) {
+        Ok(records) => records,
+        Err(e) => {
+            let mut start = size.saturating_sub(count * 200);
+            while !content.is_char_boundary(start) {
+                start += 1;
+            }
+            return Tail {
+                size,
+                error: Some((e.to_string(), content[start..].to_string())),
+                ..Default::default()
+            };
+        }
+    };
+    let total = records.len();
+    let records = records
+        .iter()
+        .enumerate()
+        .skip(total.saturating_sub(count))
+        .map(|(index, raw)| Record {
+            index,
+            json: raw.get().to_string(),
+            error: serde_json::from_str::<SubtitleEntry>(raw.get()).err().map(|e| e.to_string()),
+        })
+        .collect();
+    Tail {
+        size,
+        total,
+        records,
+        error: None,
+    }
+}
+
+/// The tail of the file at `path`.
+pub fn read(path: &str, count: usize) -> Result<Tail, String> {
+    std::fs::read_to_string(path)
+        .map(|content| parse(&content, count))
+        .map_err(|e| format!("Could not read {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let content = r#"[
+            {"text": "猫", "start_time": 1.0, "timestamp": 1},
+            {"text": "犬", "start_time": 2.0, "timestamp": "soon"},
+            {"text": "鳥",  "start_time": 3.0, "timestamp": 3}
+        ]"#;
+        let tail = parse(content, 2);
+        assert_eq!((tail.total, tail.records.len(), tail.error), (3, 2, None));
+        assert_eq!(tail.records[0].index, 1);
+        assert!(tail.records[0].error.as_ref().unwrap().contains("invalid type"));
+        // Kept exactly as written
+        assert_eq!(tail.records[1].json, r#"{"text": "鳥",  "start_time": 3.0, "timestamp": 3}"#);
+        assert_eq!(tail.records[1].error, None);
+
+        let tail = parse(r#"[{"text": "猫"}, {"text": "犬""#, 1);
+        let (error, bytes) = tail.error.unwrap();
+        assert!(error.contains("EOF"));
+        assert!(bytes.ends_with(r#""犬""#));
+        assert!(tail.records.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Short-lived notices in the bottom right corner for confirmations and
+//! errors that would otherwise only reach the terminal. They fade on their
+//! own and can be clicked away; nothing waits on them.
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+impl ToastKind {
+    /// Errors stay up longer, since they may need reading twice.
+    fn lifetime(self) -> Duration {
+        match self {
+            ToastKind::Info | ToastKind::Success => Duration::from_secs(4),
+            ToastKind::Error => Duration::from_secs(8),
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            ToastKind::Info => egui::Color32::from_rgb(100, 160, 255),
+            ToastKind::Success => egui::Color32::from_rgb(0, 200, 0),
+            ToastKind::Error => egui::Color32::from_rgb(255, 100, 100),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+    kind: ToastKind,
+    text: String,
+    shown: Instant,
+}
+
+/// The toasts on screen. Adding only needs `&self`, so anything holding the
+/// viewer can report.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    items: RefCell<Vec<Toast>>,
+}
+
+impl Toasts {
+    pub fn add(&self, kind: ToastKind, text: impl Into<String>) {
+        let text = text.into();
+        let mut items = self.items.borrow_mut();
+        // The same message again (a save failing every read) restarts its timer
+        items.retain(|toast| toast.text != text);
+        items.push(Toast {
+            kind,
+            text,
+            shown: Instant::now(),
+        });
+    }
+
+    pub fn info(&self, text: impl Into<String>) {
+        self.add(ToastKind::Info, text);
+    }
+
+    pub fn success(&self, text: impl Into<String>) {
+        self.add(ToastKind::Success, text);
+    }
+
+    pub fn error(&self, text: impl Into<String>) {
+        self.add(ToastKind::Error, text);
+    }
+
+    fn expire(&self, now: Instant) {
+        self.items
+            .borrow_mut()
+            .retain(|toast| now.duration_since(toast.shown) < toast.kind.lifetime());
+    }
+
+    pub fn show(&self, ctx: &egui::Context) {
+        self.expire(Instant::now());
+        let items = self.items.borrow().clone();
+        if items.is_empty() {
+            return;
+        }
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -32.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(320.0);
+                for (i, toast) in items.iter().enumerate() {
+                    let response = egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, toast.kind.color()))
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&toast.text).color(toast.kind.color()));
+                        })
+                        .response
+                        .interact(egui::Sense::click())
+                        .on_hover_text("Click to dismiss");
+                    if response.clicked() {
+                        dismissed = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = dismissed {
+            self.items.borrow_mut().remove(i);
+        }
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_replace_and_toasts_expire() {
+        let toasts = Toasts::default();
+        toasts.success("Exported");
+        toasts.error("Could not save");
+        toasts.error("Could not save");
+        assert_eq!(toasts.items.borrow().len(), 2);
+
+        toasts.expire(Instant::now() + Duration::from_secs(5));
+        let items = toasts.items.borrow();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, ToastKind::Error);
+    }
+}
@@ -0,0 +1,97 @@
+//! Translations of lines. The mpv script captures the secondary subtitle
+//! track as lines of their own marked `[Secondary] `; `attach` pairs each one
+//! with the primary line shown at the same time and moves its text into that
+//! line's `translation`. Sources that know the translation up front (JSON
+//! lines) fill the field in directly.
+
+use crate::align;
+use crate::SubtitleEntry;
+
+pub const SECONDARY_PREFIX: &str = "[Secondary] ";
+
+/// How far apart in media time a secondary line may start from the primary
+/// line it translates.
+const MAX_OFFSET: f64 = 1.5;
+
+/// Whether any of `entries` has or could be given a translation.
+pub fn has_translations(entries: &[SubtitleEntry]) -> bool {
+    entries
+        .iter()
+        .any(|entry| entry.translation.is_some() || entry.text.starts_with(SECONDARY_PREFIX))
+}
+
+/// `entries` with secondary lines folded into the primary lines they
+/// translate. Secondary lines with no primary line near them are kept as
+/// they are.
+pub fn attach(entries: &[SubtitleEntry]) -> Vec<SubtitleEntry> {
+    let mut attached: Vec<SubtitleEntry> = Vec::with_capacity(entries.len());
+    let mut unmatched = Vec::new();
+    for entry in entries {
+        if !entry.text.starts_with(SECONDARY_PREFIX) {
+            attached.push(entry.clone());
+        }
+    }
+    for entry in entries {
+        let Some(text) = entry.text.strip_prefix(SECONDARY_PREFIX) else {
+            continue;
+        };
+        let same_media = attached
+            .iter()
+            .enumerate()
+            .filter(|(_, primary)| primary.path == entry.path)
+            .map(|(index, primary)| (index, primary.start_time));
+        match align::nearest(entry.start_time, same_media, MAX_OFFSET).map(|index| &mut attached[index]) {
+            Some(primary) => match &mut primary.translation {
+                Some(translation) if translation != text => {
+                    translation.push('\n');
+                    translation.push_str(text);
+                }
+                Some(_) => {}
+                None => primary.translation = Some(text.to_string()),
+            },
+            None => unmatched.push(entry.clone()),
+        }
+    }
+    attached.extend(unmatched);
+    attached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, start_time: f64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            path: Some("/videos/show.mkv".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_secondary_lines_attach_to_the_nearest_primary() {
+        let entries = vec![
+            line("猫だ", 10.0),
+            line("[Secondary] It's a cat", 10.2),
+            line("犬もいる", 14.0),
+            line("[Secondary] There's a dog too", 13.5),
+            line("[Secondary] Stray sign", 40.0),
+        ];
+        assert!(has_translations(&entries));
+        let attached = attach(&entries);
+        let pairs: Vec<(&str, Option<&str>)> = attached
+            .iter()
+            .map(|entry| (entry.text.as_str(), entry.translation.as_deref()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("猫だ", Some("It's a cat")),
+                ("犬もいる", Some("There's a dog too")),
+                ("[Secondary] Stray sign", None),
+            ]
+        );
+        assert!(!has_translations(&[line("猫だ", 10.0)]));
+    }
+}
@@ -0,0 +1,111 @@
+//! Lines deleted from the view. Deleting only hides a line (the capture file
+//! and the raw stream still have it), so one deleted by mistake can be put
+//! back from the trash long after undo would have been any use. When its
+//! session ends a deleted line is purged from the trash and stays hidden for
+//! good.
+
+use std::collections::HashSet;
+
+use crate::ids::EntryId;
+use crate::SubtitleEntry;
+
+#[derive(Debug, Default)]
+pub struct Trash {
+    /// Every line deleted since the viewer started, purged or not.
+    hidden: HashSet<EntryId>,
+    /// The lines that can still be restored, in the order they were deleted.
+    items: Vec<SubtitleEntry>,
+}
+
+impl Trash {
+    pub fn delete(&mut self, entry: SubtitleEntry) {
+        if self.hidden.insert(entry.id) {
+            self.items.push(entry);
+        }
+    }
+
+    /// Put a line back in the view, if it's still in the trash.
+    pub fn restore(&mut self, id: EntryId) -> bool {
+        let before = self.items.len();
+        self.items.retain(|entry| entry.id != id);
+        let restored = self.items.len() < before;
+        if restored {
+            self.hidden.remove(&id);
+        }
+        restored
+    }
+
+    pub fn restore_all(&mut self) {
+        for entry in self.items.drain(..) {
+            self.hidden.remove(&entry.id);
+        }
+    }
+
+    /// Leave the deleted lines out of `entries`.
+    pub fn hide(&self, entries: &mut Vec<SubtitleEntry>) {
+        if !self.hidden.is_empty() {
+            entries.retain(|entry| !self.hidden.contains(&entry.id));
+        }
+    }
+
+    /// Purge the lines that arrived by `end`, the last line of a session that
+    /// has ended, returning how many there were.
+    pub fn purge_until(&mut self, end: i64) -> usize {
+        let before = self.items.len();
+        self.items.retain(|entry| entry.timestamp > end);
+        before - self.items.len()
+    }
+
+    /// Purge everything in the trash now.
+    pub fn empty(&mut self) {
+        self.items.clear();
+    }
+
+    /// The lines that can be restored, most recently deleted last.
+    pub fn items(&self) -> &[SubtitleEntry] {
+        &self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(id: EntryId, timestamp: i64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: format!("line {}", id),
+            timestamp,
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_delete_restore_purge() {
+        let mut trash = Trash::default();
+        trash.delete(line(2, 20));
+        trash.delete(line(3, 30));
+        trash.delete(line(2, 20));
+        assert_eq!(trash.items().len(), 2);
+
+        let mut view = vec![line(1, 10), line(2, 20), line(3, 30)];
+        trash.hide(&mut view);
+        assert_eq!(view.iter().map(|e| e.id).collect::<Vec<_>>(), vec![1]);
+
+        assert!(trash.restore(3));
+        assert!(!trash.restore(3));
+        let mut view = vec![line(1, 10), line(2, 20), line(3, 30)];
+        trash.hide(&mut view);
+        assert_eq!(view.len(), 2);
+
+        // Purged lines can't be restored but stay deleted
+        trash.delete(line(4, 40));
+        assert_eq!(trash.purge_until(25), 1);
+        assert!(!trash.restore(2));
+        trash.restore_all();
+        let mut view = vec![line(2, 20), line(4, 40)];
+        trash.hide(&mut view);
+        assert_eq!(view.iter().map(|e| e.id).collect::<Vec<_>>(), vec![4]);
+        assert!(trash.items().is_empty());
+    }
+}
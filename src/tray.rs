@@ -0,0 +1,107 @@
+//! The comparison tray: lines pinned side by side beside the transcript, to
+//! compare them or to collect every line using a word during an episode.
+//! It's a scratch space for this run of the viewer; copy or export it to
+//! keep it.
+
+use crate::SubtitleEntry;
+
+#[derive(Debug, Default)]
+pub struct Tray {
+    /// In the order they were pinned.
+    items: Vec<SubtitleEntry>,
+}
+
+/// The same line, however many times it was read since.
+fn same_line(a: &SubtitleEntry, b: &SubtitleEntry) -> bool {
+    a.text == b.text && a.start_time == b.start_time && a.path == b.path
+}
+
+/// Where a line is from, as "title 0:12.3".
+pub fn source(entry: &SubtitleEntry) -> String {
+    let name = entry
+        .title
+        .clone()
+        .or_else(|| entry.path.as_deref().map(crate::stats::media_name))
+        .unwrap_or_default();
+    format!("{} {}", name, crate::format_timestamp(entry.start_time)).trim_start().to_string()
+}
+
+impl Tray {
+    /// Pin a line, unless it's pinned already.
+    pub fn pin(&mut self, entry: SubtitleEntry) -> bool {
+        if self.items.iter().any(|pinned| same_line(pinned, &entry)) {
+            return false;
+        }
+        self.items.push(entry);
+        true
+    }
+
+    /// Pin every line of `entries` that uses `word`, returning how many were
+    /// new to the tray.
+    pub fn pin_with_word(&mut self, entries: &[SubtitleEntry], word: &str) -> usize {
+        entries
+            .iter()
+            .filter(|entry| entry.text.contains(word))
+            .filter(|entry| self.pin((*entry).clone()))
+            .count()
+    }
+
+    pub fn unpin(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.items.remove(index);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn items(&self) -> &[SubtitleEntry] {
+        &self.items
+    }
+
+    /// Every pinned line and where it's from, one per line, for the clipboard.
+    pub fn to_text(&self) -> String {
+        self.items
+            .iter()
+            .map(|entry| format!("{}\t{}", entry.text.trim().replace('\n', " "), source(entry)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `<dir>/tray-YYYY-MM-DD-HHMMSS.<extension>`
+pub fn export_path(dir: &str, format: crate::export::Format) -> String {
+    let stamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    format!("{}/tray-{}.{}", dir, stamp, format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, start_time: f64) -> SubtitleEntry {
+        SubtitleEntry {
+            text: text.to_string(),
+            start_time,
+            title: Some("Show".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut tray = Tray::default();
+        assert!(tray.pin(line("猫がいる", 1.0)));
+        assert!(!tray.pin(line("猫がいる", 1.0)));
+        let view = vec![line("猫がいる", 1.0), line("犬だ", 2.0), line("猫\nと犬", 63.5)];
+        assert_eq!(tray.pin_with_word(&view, "猫"), 1);
+        assert_eq!(tray.items().len(), 2);
+        assert_eq!(tray.to_text(), "猫がいる\tShow 0:01.0\n猫 と犬\tShow 1:03.5");
+        tray.unpin(0);
+        tray.unpin(5);
+        assert_eq!(tray.items()[0].start_time, 63.5);
+        tray.clear();
+        assert!(tray.items().is_empty());
+    }
+}
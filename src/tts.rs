@@ -0,0 +1,51 @@
+//! Text to speech through an external command such as espeak-ng, for lines
+//! whose own audio can't be cut from the media.
+
+use std::path::Path;
+use std::process::Command;
+
+pub const DEFAULT_COMMAND: &str = "espeak-ng -w {file} {text}";
+
+/// Split a command template on spaces and fill in `{text}` and `{file}` in
+/// each argument. No shell is involved, so the text needs no quoting.
+fn command_args(template: &str, text: &str, file: &Path) -> Vec<String> {
+    let file = file.to_string_lossy();
+    template
+        .split_whitespace()
+        .map(|arg| arg.replace("{text}", text).replace("{file}", &file))
+        .collect()
+}
+
+/// Speak `text` into the audio file `file` with the command `template`.
+pub fn synthesize(template: &str, text: &str, file: &Path) -> Result<(), String> {
+    let args = command_args(template, text, file);
+    let (program, args) = args.split_first().ok_or("no text to speech command set")?;
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Could not run {}: {}", program, e))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, message.lines().next().unwrap_or_default()));
+    }
+    if !file.exists() {
+        return Err(format!("{} wrote no audio (is {{file}} in the command?)", program));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_args_keep_text_whole() {
+        let args = command_args("espeak-ng -v ja -w {file} {text}", "猫 が いる", Path::new("/tmp/a.wav"));
+        assert_eq!(args, vec!["espeak-ng", "-v", "ja", "-w", "/tmp/a.wav", "猫 が いる"]);
+    }
+
+    #[test]
+    fn test_synthesize_without_command() {
+        assert!(synthesize("", "text", Path::new("/tmp/none.wav")).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Vertical (tategaki) layout for CJK text: characters run top to bottom in
+//! columns that follow each other right to left.
+
+/// The vertical presentation form of punctuation that is drawn differently
+/// in vertical text; other characters are unchanged.
+pub fn vertical_form(c: char) -> char {
+    match c {
+        '、' => '︑',
+        '。' => '︒',
+        '，' => '︐',
+        '：' => '︓',
+        '；' => '︔',
+        '！' => '︕',
+        '？' => '︖',
+        '「' => '﹁',
+        '」' => '﹂',
+        '『' => '﹃',
+        '』' => '﹄',
+        '（' | '(' => '︵',
+        '）' | ')' => '︶',
+        '｛' => '︷',
+        '｝' => '︸',
+        '〔' => '︹',
+        '〕' => '︺',
+        '【' => '︻',
+        '】' => '︼',
+        '《' => '︽',
+        '》' => '︾',
+        '〈' => '︿',
+        '〉' => '﹀',
+        '…' => '︙',
+        '‥' => '︰',
+        'ー' | '－' | '—' => '｜',
+        '～' | '〜' => '≀',
+        _ => c,
+    }
+}
+
+/// Split `text` into columns of at most `rows` characters, in reading order
+/// (the first column is drawn rightmost). Line breaks start a new column and
+/// spaces at the top of a column are dropped.
+pub fn columns(text: &str, rows: usize) -> Vec<Vec<char>> {
+    let rows = rows.max(1);
+    let mut columns = Vec::new();
+    for line in text.lines() {
+        let mut column: Vec<char> = Vec::new();
+        for c in line.chars() {
+            if column.is_empty() && c.is_whitespace() {
+                continue;
+            }
+            column.push(vertical_form(c));
+            if column.len() == rows {
+                columns.push(std::mem::take(&mut column));
+            }
+        }
+        if !column.is_empty() {
+            columns.push(column);
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_wrap_and_break() {
+        let columns = columns("あいうえお\nかき", 3);
+        assert_eq!(
+            columns,
+            vec![vec!['あ', 'い', 'う'], vec!['え', 'お'], vec!['か', 'き']]
+        );
+    }
+
+    #[test]
+    fn test_columns_use_vertical_punctuation() {
+        assert_eq!(columns("「ええ、そう」", 10), vec!["﹁ええ︑そう﹂".chars().collect::<Vec<_>>()]);
+        assert_eq!(columns("ラーメン", 10)[0][1], '｜');
+    }
+
+    #[test]
+    fn test_columns_skip_leading_spaces() {
+        assert_eq!(columns("ab cd", 3), vec![vec!['a', 'b', ' '], vec!['c', 'd']]);
+    }
+}
@@ -0,0 +1,100 @@
+//! A folder where other tools (a recording box, another machine syncing its
+//! captures over) drop subtitle files. Each new JSON, JSON lines or SRT file
+//! that turns up is opened as a session of its own.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::SubtitleEntry;
+
+const EXTENSIONS: [&str; 3] = ["json", "jsonl", "srt"];
+
+/// Whether `path` looks like a capture another tool dropped.
+fn is_capture(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// The lines of a dropped file: a capture file's JSON array, JSON lines as
+/// the JSON lines source takes them (lines without times are placed a second
+/// apart), or SRT.
+pub fn read(path: &Path) -> Result<Vec<SubtitleEntry>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+    let entries = match ext.as_str() {
+        "json" => serde_json::from_str(&content).map_err(|e| e.to_string())?,
+        "jsonl" => {
+            let mut entries = Vec::new();
+            for (index, line) in content.lines().enumerate() {
+                entries.extend(crate::lines::parse_line(line, || index as f64)?);
+            }
+            entries
+        }
+        _ => crate::srt::parse(&content)?,
+    };
+    if entries.is_empty() {
+        return Err("no lines".to_string());
+    }
+    Ok(entries)
+}
+
+pub struct WatchFolder {
+    pub dir: PathBuf,
+    /// Files already there or already opened.
+    seen: HashSet<PathBuf>,
+}
+
+impl WatchFolder {
+    /// Watch `dir`, leaving the files already in it alone.
+    pub fn new(dir: &str) -> Self {
+        let dir = PathBuf::from(dir);
+        let seen = std::fs::read_dir(&dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        Self { dir, seen }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        path.parent() == Some(self.dir.as_path())
+    }
+
+    /// The lines of `path` if it's a new capture in the folder that can be
+    /// read in full. One still being written is tried again on its next
+    /// change.
+    pub fn take_new(&mut self, path: &Path) -> Option<Vec<SubtitleEntry>> {
+        if !self.contains(path) || !is_capture(path) || self.seen.contains(path) {
+            return None;
+        }
+        let entries = read(path).ok()?;
+        self.seen.insert(path.to_path_buf());
+        Some(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_new_readable_files_are_taken() {
+        let dir = std::env::temp_dir().join(format!("scriptview-watch-folder-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("old.jsonl"), "\"already here\"\n").unwrap();
+        let mut folder = WatchFolder::new(&dir.to_string_lossy());
+        assert_eq!(folder.take_new(&dir.join("old.jsonl")), None);
+
+        let new = dir.join("new.jsonl");
+        std::fs::write(&new, "{\"text\": \"猫だ\", \"start_time\": 2.5}\n{\"te").unwrap();
+        assert_eq!(folder.take_new(&new), None);
+        std::fs::write(&new, "{\"text\": \"猫だ\", \"start_time\": 2.5}\n\"犬\"\n").unwrap();
+        let entries = folder.take_new(&new).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].start_time, entries[1].start_time), (2.5, 1.0));
+        assert_eq!(folder.take_new(&new), None);
+
+        std::fs::write(dir.join("notes.txt"), "hello").unwrap();
+        assert_eq!(folder.take_new(&dir.join("notes.txt")), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,57 @@
+//! Reads mpv's watch-later (resume) files to find where playback stopped.
+
+/// Directories mpv has used for watch-later files, newest layout first.
+fn watch_later_dirs() -> Vec<String> {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    let state_dir = std::env::var("XDG_STATE_HOME")
+        .unwrap_or_else(|_| format!("{}/.local/state", home_dir));
+    vec![
+        format!("{}/mpv/watch_later", state_dir),
+        format!("{}/.config/mpv/watch_later", home_dir),
+    ]
+}
+
+/// mpv names each file after the uppercase MD5 of the media path.
+fn watch_later_name(media_path: &str) -> String {
+    format!("{:X}", md5::compute(media_path))
+}
+
+fn parse_start(content: &str) -> Option<f64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("start="))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Resume position in seconds, if mpv saved one for this file.
+pub fn resume_position(media_path: &str) -> Option<f64> {
+    let name = watch_later_name(media_path);
+    watch_later_dirs()
+        .into_iter()
+        .filter_map(|dir| std::fs::read_to_string(format!("{}/{}", dir, name)).ok())
+        .find_map(|content| parse_start(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_later_name_is_uppercase_md5() {
+        assert_eq!(
+            watch_later_name("/home/user/video.mkv"),
+            "E008201F3C112CE64F4D570DE5B36BBB"
+        );
+    }
+
+    #[test]
+    fn test_parse_start() {
+        let content = "# /home/user/video.mkv\nstart=754.120000\nvolume=80.000000\n";
+        assert_eq!(parse_start(content), Some(754.12));
+    }
+
+    #[test]
+    fn test_parse_start_missing() {
+        assert_eq!(parse_start("volume=80.000000\n"), None);
+    }
+}
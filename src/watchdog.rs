@@ -0,0 +1,64 @@
+//! Noticing when capture has silently stopped: mpv says it's playing with a
+//! subtitle track selected, yet no lines arrive. Usually the script isn't
+//! loaded, writes somewhere else, or the track is made of images it can't
+//! read, and nothing else would tell the user.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct StallWatchdog {
+    /// Since when lines have been expected without any arriving.
+    quiet_since: Option<Instant>,
+}
+
+impl StallWatchdog {
+    /// Note whether lines are expected right now. Pausing or switching the
+    /// track off starts the wait over.
+    pub fn expect(&mut self, expecting: bool, now: Instant) {
+        if !expecting {
+            self.quiet_since = None;
+        } else if self.quiet_since.is_none() {
+            self.quiet_since = Some(now);
+        }
+    }
+
+    /// A line arrived (or the warning was dismissed): wait a full period
+    /// again before warning.
+    pub fn reset(&mut self, now: Instant) {
+        if self.quiet_since.is_some() {
+            self.quiet_since = Some(now);
+        }
+    }
+
+    /// How long lines have been expected without any arriving, once that's
+    /// at least `limit`.
+    pub fn stalled_for(&self, limit: Duration, now: Instant) -> Option<Duration> {
+        self.quiet_since
+            .map(|since| now.saturating_duration_since(since))
+            .filter(|&quiet| quiet >= limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall() {
+        let start = Instant::now();
+        let limit = Duration::from_secs(60);
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut watchdog = StallWatchdog::default();
+        watchdog.expect(true, start);
+        watchdog.expect(true, at(30));
+        assert_eq!(watchdog.stalled_for(limit, at(59)), None);
+        assert_eq!(watchdog.stalled_for(limit, at(70)), Some(Duration::from_secs(70)));
+        watchdog.reset(at(70));
+        assert_eq!(watchdog.stalled_for(limit, at(100)), None);
+        // Paused: nothing is expected, and resuming starts the wait over
+        watchdog.expect(false, at(200));
+        assert_eq!(watchdog.stalled_for(limit, at(300)), None);
+        watchdog.expect(true, at(300));
+        assert_eq!(watchdog.stalled_for(limit, at(330)), None);
+    }
+}
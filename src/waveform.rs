@@ -0,0 +1,169 @@
+//! Waveform snippets of the audio around a subtitle, decoded with ffmpeg and
+//! cached, to check clip boundaries at a glance. One worker thread decodes
+//! them in turn from a short queue, so a long session can't start an ffmpeg
+//! per line at once.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender, TrySendError};
+
+use serde::{Deserialize, Serialize};
+
+/// Audio shown either side of the cue, in seconds.
+pub const PADDING: f64 = 0.5;
+/// Assumed cue length when the end time is unknown.
+const DEFAULT_CUE_LENGTH: f64 = 3.0;
+const SAMPLE_RATE: u32 = 8000;
+const BUCKETS: usize = 120;
+/// Cues waiting to be decoded; asking for more is refused until there's room.
+const QUEUE: usize = 4;
+
+/// A cue's waveform by media path and start in milliseconds.
+pub type Key = (String, u64);
+
+pub fn key(path: &str, start: f64) -> Key {
+    (path.to_string(), (start * 1000.0) as u64)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waveform {
+    /// Media time covered by the strip, in seconds.
+    pub start: f64,
+    pub end: f64,
+    /// Peak level of each slice of the strip, 0.0 to 1.0.
+    pub peaks: Vec<f32>,
+}
+
+fn cache_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(format!("{}/.cache/scriptview/waveforms", home_dir))
+}
+
+/// The stretch of media to draw for a cue: the cue plus padding, not before 0.
+pub fn clip_range(start: f64, end: Option<f64>) -> (f64, f64) {
+    let end = end.filter(|end| *end > start).unwrap_or(start + DEFAULT_CUE_LENGTH);
+    ((start - PADDING).max(0.0), end + PADDING)
+}
+
+/// Peak absolute level of `samples` in `buckets` equal slices.
+pub fn peaks(samples: &[i16], buckets: usize) -> Vec<f32> {
+    if samples.is_empty() || buckets == 0 {
+        return Vec::new();
+    }
+    (0..buckets)
+        .map(|i| {
+            let from = i * samples.len() / buckets;
+            let to = ((i + 1) * samples.len() / buckets).max(from + 1).min(samples.len());
+            let peak = samples[from..to].iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+            peak as f32 / i16::MAX as f32
+        })
+        .map(|level| level.min(1.0))
+        .collect()
+}
+
+fn decode(path: &str, start: f64, end: f64) -> Result<Waveform, String> {
+    let cache = cache_dir().join(format!("{:x}.json", md5::compute(format!("{}:{:.3}:{:.3}", path, start, end))));
+    if let Some(waveform) = std::fs::read_to_string(&cache)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        return Ok(waveform);
+    }
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &format!("{:.3}", start), "-t", &format!("{:.3}", end - start), "-i"])
+        .arg(path)
+        .args(["-vn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "s16le", "-"])
+        .output()
+        .map_err(|e| format!("Could not run ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(message.lines().next().unwrap_or("ffmpeg failed").to_string());
+    }
+    let samples: Vec<i16> = output
+        .stdout
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let waveform = Waveform {
+        start,
+        end,
+        peaks: peaks(&samples, BUCKETS),
+    };
+    if std::fs::create_dir_all(cache_dir()).is_ok() {
+        if let Ok(json) = serde_json::to_string(&waveform) {
+            let _ = std::fs::write(cache, json);
+        }
+    }
+    Ok(waveform)
+}
+
+type Decoded = (Key, Result<Waveform, String>);
+
+/// The thread decoding waveforms, running until dropped.
+pub struct Decoder {
+    requests: SyncSender<(String, f64, Option<f64>)>,
+    pub results: Receiver<Decoded>,
+}
+
+impl Decoder {
+    pub fn spawn() -> Self {
+        let (requests, requests_rx) = sync_channel::<(String, f64, Option<f64>)>(QUEUE);
+        let (results_tx, results) = channel();
+        std::thread::spawn(move || {
+            for (path, start, end) in requests_rx {
+                let (from, to) = clip_range(start, end);
+                let result = decode(&path, from, to);
+                if results_tx.send((key(&path, start), result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
+
+    /// Queue the audio around a cue for decoding, returning whether there
+    /// was room; if not, ask again later.
+    pub fn request(&self, path: &str, start: f64, end: Option<f64>) -> bool {
+        match self.requests.try_send((path.to_string(), start, end)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_range_pads_cue() {
+        assert_eq!(clip_range(10.0, Some(12.0)), (9.5, 12.5));
+        assert_eq!(clip_range(0.2, None), (0.0, 3.7));
+        // An end before the start is treated as unknown
+        assert_eq!(clip_range(5.0, Some(4.0)), (4.5, 8.5));
+    }
+
+    #[test]
+    fn test_decoder_queue_is_bounded() {
+        let decoder = Decoder::spawn();
+        let missing = std::env::temp_dir().join(format!("scriptview-no-such-media-{}.mkv", std::process::id()));
+        let missing = missing.to_string_lossy().into_owned();
+        // The worker takes one off the queue at most before it fills
+        let queued = (0..QUEUE + 10).filter(|&i| decoder.request(&missing, i as f64 * 1000.0, None)).count();
+        assert!((QUEUE..=QUEUE + 1).contains(&queued), "{} queued", queued);
+        let (key, result) = decoder.results.recv().unwrap();
+        assert_eq!(key, super::key(&missing, 0.0));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_peaks() {
+        let samples = [0, 100, -16384, 0, i16::MIN, 5];
+        let levels = peaks(&samples, 3);
+        assert_eq!(levels.len(), 3);
+        assert!((levels[0] - 100.0 / 32767.0).abs() < 1e-6);
+        assert!((levels[1] - 0.5).abs() < 0.01);
+        assert_eq!(levels[2], 1.0);
+        assert!(peaks(&[], 10).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! Posts new subtitles to a user-configured URL for custom processing, and
+//! lines modified or removed since, marked with a `change` field.
+//!
+//! With no batching each line is POSTed as a JSON object on its own; with a
+//! batch window, the lines collected over that many seconds go out together
+//! as a JSON array. Failed deliveries are retried with exponential backoff,
+//! except for 4xx answers other than 429, which retrying won't fix.
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use crate::store::Change;
+
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+enum Failure {
+    /// Worth trying again later, such as a refused connection or a 503.
+    Temporary(String),
+    Permanent(String),
+}
+
+fn post(url: &str, body: &str) -> Result<(), Failure> {
+    match ureq::post(url).set("Content-Type", "application/json").send_string(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, _)) if (400..500).contains(&code) && code != 429 => {
+            Err(Failure::Permanent(format!("{} answered {}", url, code)))
+        }
+        Err(e) => Err(Failure::Temporary(e.to_string())),
+    }
+}
+
+/// POST `body`, retrying with the delay doubling from `retry_delay`.
+fn deliver(url: &str, body: &str, retry_delay: Duration) -> Result<(), String> {
+    let mut delay = retry_delay;
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        if attempt > 1 {
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+        match post(url, body) {
+            Ok(()) => return Ok(()),
+            Err(Failure::Permanent(e)) => return Err(e),
+            Err(Failure::Temporary(e)) => last_error = e,
+        }
+    }
+    Err(format!("{} (gave up after {} attempts)", last_error, MAX_ATTEMPTS))
+}
+
+pub struct Webhook {
+    payloads: Sender<serde_json::Value>,
+    /// Disconnects once the thread has sent everything and stopped.
+    done: Receiver<()>,
+}
+
+impl Webhook {
+    /// Start posting to `url`, batching lines over `batch_secs` (0 sends each
+    /// line as it comes). Stops when dropped.
+    pub fn spawn(url: &str, batch_secs: u32) -> Self {
+        Self::spawn_with_retry_delay(url, batch_secs, FIRST_RETRY_DELAY)
+    }
+
+    fn spawn_with_retry_delay(url: &str, batch_secs: u32, retry_delay: Duration) -> Self {
+        let url = url.to_string();
+        let window = Duration::from_secs(batch_secs as u64);
+        let (tx, rx) = channel::<serde_json::Value>();
+        let (done_tx, done) = channel::<()>();
+        std::thread::spawn(move || {
+            let _done = done_tx;
+            let mut batch = Vec::new();
+            let mut deadline: Option<Instant> = None;
+            loop {
+                let timeout = deadline.map_or(Duration::from_secs(3600), |d| d.saturating_duration_since(Instant::now()));
+                match rx.recv_timeout(timeout) {
+                    Ok(payload) => {
+                        batch.push(payload);
+                        deadline.get_or_insert_with(|| Instant::now() + window);
+                        if deadline.is_some_and(|d| Instant::now() < d) {
+                            continue;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) if batch.is_empty() => continue,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) if batch.is_empty() => break,
+                    Err(RecvTimeoutError::Disconnected) => {}
+                }
+                let body = if window.is_zero() {
+                    batch.iter().map(serde_json::to_string).collect::<Result<Vec<_>, _>>()
+                } else {
+                    serde_json::to_string(&batch).map(|body| vec![body])
+                };
+                batch.clear();
+                deadline = None;
+                for body in body.unwrap_or_default() {
+                    if let Err(e) = deliver(&url, &body, retry_delay) {
+                        eprintln!("Warning: Webhook delivery failed: {}", e);
+                    }
+                }
+            }
+        });
+        Self { payloads: tx, done }
+    }
+
+    pub fn send(&self, change: &Change) {
+        let _ = self.payloads.send(change.payload());
+    }
+
+    /// Send the batch in progress, waiting at most `timeout` for it to go out.
+    pub fn finish(self, timeout: Duration) -> Result<(), String> {
+        drop(self.payloads);
+        match self.done.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Disconnected) => Ok(()),
+            _ => Err("lines still unsent".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Answer each request on `listener` with the next status, returning the
+    /// bodies received.
+    fn serve(listener: &TcpListener, statuses: &[u16]) -> Vec<String> {
+        let mut bodies = Vec::new();
+        for status in statuses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).unwrap();
+            bodies.push(String::from_utf8(body).unwrap());
+            let reply = format!("HTTP/1.1 {} Whatever\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+        }
+        bodies
+    }
+
+    fn line(text: &str) -> Change {
+        Change::Append(crate::SubtitleEntry {
+            text: text.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_retries_server_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let webhook = Webhook::spawn_with_retry_delay(&url, 0, Duration::from_millis(10));
+        webhook.send(&line("猫"));
+
+        let bodies = serve(&listener, &[503, 200]);
+        assert_eq!(bodies[0], bodies[1]);
+        let entry: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(entry["text"], "猫");
+    }
+
+    #[test]
+    fn test_batches_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let webhook = Webhook::spawn_with_retry_delay(&url, 1, Duration::from_millis(10));
+        webhook.send(&line("猫"));
+        webhook.send(&Change::Modify(line("犬").entry().clone()));
+
+        let bodies = serve(&listener, &[200]);
+        let batch: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+        assert!(batch[0].get("change").is_none());
+        assert_eq!(batch[1]["change"], "modified");
+    }
+
+    #[test]
+    fn test_finish_sends_the_open_batch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let webhook = Webhook::spawn_with_retry_delay(&url, 3600, Duration::from_millis(10));
+        webhook.send(&line("猫"));
+        let server = std::thread::spawn(move || serve(&listener, &[200]));
+        assert!(webhook.finish(Duration::from_secs(5)).is_ok());
+        assert!(server.join().unwrap()[0].contains("猫"));
+    }
+
+    #[test]
+    fn test_client_errors_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || deliver(&url, "{}", Duration::from_millis(10)));
+        serve(&listener, &[404]);
+        assert!(handle.join().unwrap().is_err());
+    }
+}
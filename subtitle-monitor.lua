@@ -3,11 +3,34 @@
 
 local utils = require 'mp.utils'
 local msg = require 'mp.msg'
+local options = require 'mp.options'
 
--- Configuration
-local output_file = "/tmp/mpv-subtitles.json"
-local max_entries = 50  -- Keep last 50 subtitles in memory
+-- Configuration, overridable from ~/.config/mpv/script-opts/subtitle-monitor.conf
+-- (the viewer reads the same file to find the output path)
+local opts = {
+    output_file = "/tmp/mpv-subtitles.json",
+    -- truncate: start over on each new file and on seeks
+    -- daily: keep history across files, rotate the file when the date changes
+    -- forever: keep history across files and days
+    retention = "truncate",
+    -- Command that starts the viewer along with mpv, such as
+    -- "scriptview --minimized --no-focus"; empty to start it yourself
+    viewer_command = "",
+}
+options.read_options(opts, "subtitle-monitor")
+
+local output_file = opts.output_file
+-- Protocol with the viewer: append-only files of JSON lines next to the
+-- capture file, events for the viewer and requests from it
+local PROTOCOL_VERSION = 1
+local output_stem = output_file:gsub("%.json$", "")
+local events_file = output_stem .. "-events.jsonl"
+local requests_file = output_stem .. "-requests.jsonl"
+local requests_offset = 0
+local retention = opts.retention
+local max_entries = 50  -- Keep last 50 subtitles in memory (truncate mode only)
 local subtitle_history = {}
+local current_day = os.date("%Y-%m-%d")
 local last_position = 0
 local seek_threshold = 5  -- Clear if seeking more than 5 seconds
 
@@ -24,11 +47,64 @@ end
 
 -- Function to clear subtitle history
 local function clear_history(reason)
+    if retention ~= "truncate" then
+        return
+    end
     subtitle_history = {}
     write_subtitle_data()
     msg.info("Subtitle history cleared: " .. reason)
 end
 
+-- Move the current file aside as <name>-<day>.json and start a new one
+local function rotate(day)
+    local archive = output_file:gsub("%.json$", "") .. "-" .. day .. ".json"
+    os.rename(output_file, archive)
+    subtitle_history = {}
+    msg.info("Subtitle file rotated to: " .. archive)
+end
+
+local function rotate_if_new_day()
+    local today = os.date("%Y-%m-%d")
+    if retention == "daily" and today ~= current_day then
+        rotate(current_day)
+    end
+    current_day = today
+end
+
+-- Pick up where a previous mpv instance left off when history is retained
+local function load_existing_history()
+    local file = io.open(output_file, "r")
+    if not file then
+        return
+    end
+    local content = file:read("*a")
+    file:close()
+    local existing = utils.parse_json(content)
+    if type(existing) ~= "table" and content:match("%S") then
+        -- Keep the broken file for a bug report rather than writing over it
+        os.rename(output_file, output_file .. ".corrupt")
+        msg.warn("Capture file could not be parsed; moved to " .. output_file .. ".corrupt")
+        return
+    end
+    if type(existing) ~= "table" or #existing == 0 then
+        return
+    end
+    subtitle_history = existing
+    local last_day = os.date("%Y-%m-%d", existing[#existing].timestamp or os.time())
+    if retention == "daily" and last_day ~= current_day then
+        rotate(last_day)
+    end
+end
+
+-- Absolute path of the current media, the way mpv keys its watch-later files
+local function media_path()
+    local path = mp.get_property("path")
+    if not path or path:match("^%a[%w+.-]*://") then
+        return path
+    end
+    return utils.join_path(mp.get_property("working-directory", ""), path)
+end
+
 -- Function to add subtitle to history
 local function add_subtitle(text, start_time, end_time)
     if text and text ~= "" then
@@ -37,14 +113,19 @@ local function add_subtitle(text, start_time, end_time)
             text = text,
             start_time = start_time or mp.get_property_number("time-pos", 0),
             end_time = end_time,
-            timestamp = os.time()
+            timestamp = os.time(),
+            path = media_path(),
+            title = mp.get_property("media-title"),
+            duration = mp.get_property_number("duration")
         }
         
+        rotate_if_new_day()
+        
         -- Add to history
         table.insert(subtitle_history, entry)
         
         -- Keep only last max_entries
-        if #subtitle_history > max_entries then
+        if retention == "truncate" and #subtitle_history > max_entries then
             table.remove(subtitle_history, 1)
         end
         
@@ -92,6 +173,104 @@ local function on_time_pos_change(name, value)
     end
 end
 
+local function send_event(record)
+    record.v = PROTOCOL_VERSION
+    local file = io.open(events_file, "a")
+    if not file then
+        msg.error("Failed to write event file: " .. events_file)
+        return false
+    end
+    file:write(utils.format_json(record) .. "\n")
+    file:close()
+    return true
+end
+
+local function handle_request(request)
+    if request.v ~= PROTOCOL_VERSION then
+        msg.warn("Ignoring request for protocol version " .. tostring(request.v))
+    elseif request.request == "show_text" then
+        mp.commandv("show-text", request.text, tostring(request.duration_ms or 1500))
+    elseif request.request == "set_pause" then
+        mp.set_property_bool("pause", request.paused == true)
+    elseif request.request == "seek" then
+        mp.commandv("seek", tostring(request.time), "absolute")
+    else
+        msg.warn("Unknown request from viewer: " .. tostring(request.request))
+    end
+end
+
+-- Carry out requests the viewer appended since the last poll
+local function poll_requests()
+    local file = io.open(requests_file, "r")
+    if not file then
+        return
+    end
+    local size = file:seek("end")
+    if size < requests_offset then
+        requests_offset = 0
+    end
+    file:seek("set", requests_offset)
+    local content = file:read("*a") or ""
+    file:close()
+    -- Leave a half-written last line for next time
+    local complete = content:match(".*()\n")
+    if not complete then
+        return
+    end
+    requests_offset = requests_offset + complete
+    for line in content:sub(1, complete):gmatch("[^\n]+") do
+        local request = utils.parse_json(line)
+        if type(request) == "table" then
+            handle_request(request)
+        end
+    end
+end
+
+-- Tell the viewer to do something with the line on screen
+local function send_line_event(event, done_message)
+    local text = mp.get_property("sub-text")
+    local start_time = mp.get_property_number("sub-start")
+    local end_time = mp.get_property_number("sub-end")
+    if not text or text == "" then
+        local last = subtitle_history[#subtitle_history]
+        if not last then
+            mp.osd_message("No subtitle to send")
+            return
+        end
+        text, start_time, end_time = last.text, last.start_time, last.end_time
+    end
+    local record = {
+        event = event,
+        text = text,
+        start_time = start_time or mp.get_property_number("time-pos", 0),
+        end_time = end_time,
+        path = media_path(),
+        title = mp.get_property("media-title"),
+    }
+    if send_event(record) then
+        mp.osd_message(done_message)
+    end
+end
+
+-- Rebind in input.conf with e.g. `Ctrl+s script-binding subtitle-monitor/star-line`
+mp.add_key_binding("Alt+s", "star-line", function()
+    send_line_event("star", "Starred in ScriptView")
+end)
+mp.add_key_binding("Alt+a", "send-to-anki", function()
+    send_line_event("send_to_anki", "Sending to Anki…")
+end)
+-- Pressed when the line picked for resyncing in the viewer is actually spoken
+mp.add_key_binding("Alt+y", "sync-mark", function()
+    local time = mp.get_property_number("time-pos", 0)
+    if send_event({ event = "sync_mark", time = time, path = media_path() }) then
+        mp.osd_message(string.format("Sync mark at %.2f s", time))
+    end
+end)
+-- Shows the lines the viewer's listening-first mode is hiding
+mp.add_key_binding("Alt+r", "reveal-lines", function()
+    send_event({ event = "reveal" })
+end)
+
 -- Initialize: observe subtitle text property
 mp.observe_property("sub-text", "string", on_subtitle_change)
 
@@ -117,6 +296,25 @@ mp.register_event("seek", function()
     on_seek()
 end)
 
--- Write empty file on script load to signal we're running
+-- Write the (possibly empty) file on script load to signal we're running
+if retention ~= "truncate" then
+    load_existing_history()
+end
 write_subtitle_data()
-msg.info("Subtitle monitor started. Writing to: " .. output_file)
\ No newline at end of file
+-- Start both protocol files afresh, then greet the viewer
+for _, path in ipairs({ events_file, requests_file }) do
+    local file = io.open(path, "w")
+    if file then
+        file:close()
+    end
+end
+send_event({ event = "hello" })
+mp.add_periodic_timer(0.25, poll_requests)
+if opts.viewer_command ~= "" then
+    local args = {}
+    for arg in opts.viewer_command:gmatch("%S+") do
+        table.insert(args, arg)
+    end
+    mp.command_native_async({ name = "subprocess", args = args, playback_only = false, detach = true }, function() end)
+end
+msg.info("Subtitle monitor started. Writing to: " .. output_file .. " (retention: " .. retention .. ")")
\ No newline at end of file
@@ -0,0 +1,178 @@
+-- VLC Subtitle Monitor for ScriptView
+-- The VLC counterpart of subtitle-monitor.lua. VLC's Lua API can't read the
+-- subtitle on screen, so this interface script follows playback time through
+-- the SRT file next to the video and writes the same JSON the mpv script does.
+--
+-- Install to ~/.local/share/vlc/lua/intf/scriptview.lua and start VLC with
+--   vlc --extraintf=luaintf --lua-intf=scriptview video.mkv
+-- The output file can be changed with
+--   --lua-config "scriptview={output_file='/path/to/file.json'}"
+
+local output_file = (config and config.output_file) or "/tmp/vlc-subtitles.json"
+local max_entries = 50  -- Keep last 50 subtitles, like the mpv script's truncate mode
+local poll_interval = 100000  -- microseconds
+
+local history = {}
+local cues = {}
+local current_uri = nil
+local current_cue = nil
+
+local function json_string(s)
+    s = s:gsub('[%c"\\]', function(c)
+        local escapes = { ['"'] = '\\"', ['\\'] = '\\\\', ['\n'] = '\\n', ['\r'] = '\\r', ['\t'] = '\\t' }
+        return escapes[c] or string.format("\\u%04x", c:byte())
+    end)
+    return '"' .. s .. '"'
+end
+
+local function json_entry(entry)
+    local fields = {
+        '"text":' .. json_string(entry.text),
+        '"start_time":' .. string.format("%.3f", entry.start_time),
+        '"end_time":' .. string.format("%.3f", entry.end_time),
+        '"timestamp":' .. string.format("%d", entry.timestamp),
+    }
+    if entry.path then
+        table.insert(fields, '"path":' .. json_string(entry.path))
+    end
+    if entry.title then
+        table.insert(fields, '"title":' .. json_string(entry.title))
+    end
+    return "{" .. table.concat(fields, ",") .. "}"
+end
+
+local function write_history()
+    local file = io.open(output_file, "w")
+    if not file then
+        vlc.msg.err("ScriptView: Failed to write " .. output_file)
+        return
+    end
+    local entries = {}
+    for i, entry in ipairs(history) do
+        entries[i] = json_entry(entry)
+    end
+    file:write("[" .. table.concat(entries, ",") .. "]")
+    file:close()
+end
+
+local function srt_time(h, m, s, ms)
+    return tonumber(h) * 3600 + tonumber(m) * 60 + tonumber(s) + tonumber(ms) / 1000
+end
+
+-- Cues of an SRT file as { start, stop, text }, with formatting tags removed
+local function parse_srt(content)
+    local parsed = {}
+    content = content:gsub("\r\n", "\n"):gsub("^\239\187\191", "")
+    for block in (content .. "\n\n"):gmatch("(.-)\n\n+") do
+        local h1, m1, s1, ms1, h2, m2, s2, ms2, text = block:match(
+            "(%d+):(%d+):(%d+)[,.](%d+)%s*%-%->%s*(%d+):(%d+):(%d+)[,.](%d+)[^\n]*\n?(.*)")
+        if h1 and text ~= "" then
+            text = text:gsub("<[^>]+>", ""):gsub("{\\[^}]*}", "")
+            table.insert(parsed, {
+                start = srt_time(h1, m1, s1, ms1),
+                stop = srt_time(h2, m2, s2, ms2),
+                text = text,
+            })
+        end
+    end
+    return parsed
+end
+
+local function read_file(path)
+    local file = io.open(path, "r")
+    if not file then
+        return nil
+    end
+    local content = file:read("*a")
+    file:close()
+    return content
+end
+
+-- The SRT next to the video: video.srt, or else the first video.*.srt
+local function load_cues(path)
+    local stem = path:gsub("%.[^./]+$", "")
+    local content = read_file(stem .. ".srt")
+    local dir, name = stem:match("^(.*)/([^/]*)$")
+    if not content and dir and vlc.net and vlc.net.opendir then
+        local ok, files = pcall(vlc.net.opendir, dir)
+        if ok and files then
+            table.sort(files)
+            for _, file in ipairs(files) do
+                if file:sub(1, #name + 1) == name .. "." and file:match("%.srt$") then
+                    content = read_file(dir .. "/" .. file)
+                    break
+                end
+            end
+        end
+    end
+    if not content then
+        vlc.msg.info("ScriptView: No SRT file found for " .. path)
+        return {}
+    end
+    return parse_srt(content)
+end
+
+local function media_path(uri)
+    if vlc.strings.make_path then
+        return vlc.strings.make_path(uri) or uri
+    end
+    return (vlc.strings.decode_uri(uri):gsub("^file://", ""))
+end
+
+-- Playback time in seconds; VLC 3 reports microseconds
+local function playback_time(input)
+    local time = vlc.var.get(input, "time") or 0
+    if not vlc.misc.version():match("^[12]%.") then
+        time = time / 1000000
+    end
+    return time
+end
+
+local function cue_at(time)
+    for _, cue in ipairs(cues) do
+        if time >= cue.start and time < cue.stop then
+            return cue
+        end
+    end
+    return nil
+end
+
+local function poll()
+    local input = vlc.object.input()
+    local item = vlc.input.item()
+    if not input or not item then
+        return
+    end
+    local uri = item:uri()
+    if uri ~= current_uri then
+        current_uri = uri
+        current_cue = nil
+        cues = load_cues(media_path(uri))
+        history = {}
+        write_history()
+    end
+    local cue = cue_at(playback_time(input))
+    if cue and cue ~= current_cue then
+        table.insert(history, {
+            text = cue.text,
+            start_time = cue.start,
+            end_time = cue.stop,
+            timestamp = os.time(),
+            path = media_path(uri),
+            title = item:name(),
+        })
+        if #history > max_entries then
+            table.remove(history, 1)
+        end
+        write_history()
+    end
+    current_cue = cue
+end
+
+-- Write the (empty) file on start to signal we're running
+write_history()
+vlc.msg.info("ScriptView: Writing subtitles to " .. output_file)
+while not vlc.misc.should_die() do
+    poll()
+    vlc.misc.mwait(vlc.misc.mdate() + poll_interval)
+end